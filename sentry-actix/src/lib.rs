@@ -38,6 +38,40 @@
 //! }
 //! ```
 //!
+//! # Panics and Error Pages
+//!
+//! Panics that unwind out of a request handler are reported as unhandled events on the
+//! request's Hub, unless the panic handler integration already reported them, and the panic
+//! is then resumed.
+//!
+//! When a server error is captured, its event ID is inserted into the response extensions as a
+//! [`Uuid`](sentry_core::types::Uuid), so that error pages rendered further up the middleware
+//! chain can show it to the user:
+//!
+//! ```
+//! # fn test(res: &actix_web::HttpResponse) {
+//! use sentry_core::types::Uuid;
+//!
+//! if let Some(event_id) = res.extensions().get::<Uuid>() {
+//!     println!("Please reference error {} when contacting support", event_id);
+//! }
+//! # }
+//! ```
+//!
+//! # Filtering Requests and Errors
+//!
+//! Requests that should not be instrumented at all, like health checks, can be excluded
+//! with a request filter, and an error filter decides which errors are captured:
+//!
+//! ```
+//! use actix_web::http::StatusCode;
+//!
+//! let middleware = sentry_actix::Sentry::builder()
+//!     .request_filter(|req| req.path() != "/health")
+//!     .error_filter(|err| err.as_response_error().status_code() != StatusCode::NOT_FOUND)
+//!     .finish();
+//! ```
+//!
 //! # Reusing the Hub
 //!
 //! This integration will automatically update the current Hub instance. For example,
@@ -58,15 +92,16 @@
 #![allow(clippy::type_complexity)]
 
 use std::borrow::Cow;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
 use std::sync::Arc;
 
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
 use actix_web::Error;
-use futures_util::future::{ok, Future, Ready};
+use futures_util::future::{ok, poll_fn, Future, Ready};
 use futures_util::FutureExt;
 
-use sentry_core::protocol::{ClientSdkPackage, Event, Request};
+use sentry_core::protocol::{ClientSdkPackage, Event, Request, User};
 use sentry_core::{Hub, SentryFutureExt};
 
 /// A helper construct that can be used to reconfigure and build the middleware.
@@ -92,12 +127,52 @@ impl SentryBuilder {
         self
     }
 
-    /// If configured the sentry id is attached to a X-Sentry-Event header.
+    /// If configured the sentry id is attached to a X-Sentry-Event-Id header.
     pub fn emit_header(mut self, val: bool) -> Self {
         self.middleware.emit_header = val;
         self
     }
 
+    /// Sets the user of the scope from each request with the given extractor.
+    ///
+    /// The extractor is only called if `send_default_pii` is enabled.  It can
+    /// for example read the authentication state that an earlier middleware
+    /// inserted into the request extensions.
+    pub fn user_extractor<F>(mut self, extractor: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<User> + 'static,
+    {
+        self.middleware.user_extractor = Some(Arc::new(extractor));
+        self
+    }
+
+    /// Sets a filter that decides which requests are instrumented.
+    ///
+    /// Requests for which the filter returns `false`, like health checks, are passed
+    /// through to the service untouched: no transaction name or request data is set, and
+    /// their errors and panics are not captured.
+    pub fn request_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> bool + 'static,
+    {
+        self.middleware.request_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Sets a filter that decides which errors are captured.
+    ///
+    /// The filter is called for errors returned by the service, and for errors attached
+    /// to responses, regardless of their status code.  Without a filter, errors returned
+    /// by the service and errors of responses with a server error status are captured.
+    /// Nothing is captured if `capture_server_errors` is disabled.
+    pub fn error_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Error) -> bool + 'static,
+    {
+        self.middleware.error_filter = Some(Arc::new(filter));
+        self
+    }
+
     /// Enables or disables error reporting.
     ///
     /// The default is to report all errors.
@@ -105,6 +180,15 @@ impl SentryBuilder {
         self.middleware.capture_server_errors = val;
         self
     }
+
+    /// Enables or disables reporting of panics in request handlers.
+    ///
+    /// Panics that were already reported by the panic handler integration are not
+    /// reported a second time.  The default is to report panics.
+    pub fn capture_panics(mut self, val: bool) -> Self {
+        self.middleware.capture_panics = val;
+        self
+    }
 }
 
 /// Reports certain failures to Sentry.
@@ -113,6 +197,10 @@ pub struct Sentry {
     hub: Option<Arc<Hub>>,
     emit_header: bool,
     capture_server_errors: bool,
+    capture_panics: bool,
+    user_extractor: Option<Arc<dyn Fn(&ServiceRequest) -> Option<User>>>,
+    request_filter: Option<Arc<dyn Fn(&ServiceRequest) -> bool>>,
+    error_filter: Option<Arc<dyn Fn(&Error) -> bool>>,
 }
 
 impl Sentry {
@@ -122,6 +210,10 @@ impl Sentry {
             hub: None,
             emit_header: false,
             capture_server_errors: true,
+            capture_panics: true,
+            user_extractor: None,
+            request_filter: None,
+            error_filter: None,
         }
     }
 
@@ -146,6 +238,7 @@ impl<S, B> Transform<S> for Sentry
 where
     S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
+    B: 'static,
 {
     type Request = ServiceRequest;
     type Response = ServiceResponse<B>;
@@ -172,6 +265,7 @@ impl<S, B> Service for SentryMiddleware<S>
 where
     S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
+    B: 'static,
 {
     type Request = ServiceRequest;
     type Response = ServiceResponse<B>;
@@ -187,6 +281,11 @@ where
 
     fn call(&mut self, req: ServiceRequest) -> Self::Future {
         let inner = self.inner.clone();
+        if let Some(ref filter) = inner.request_filter {
+            if !filter(&req) {
+                return self.service.call(req).boxed_local();
+            }
+        }
         let hub = Arc::new(Hub::new_from_top(
             inner.hub.clone().unwrap_or_else(Hub::main),
         ));
@@ -196,19 +295,48 @@ where
             .map_or(false, |x| x.options().send_default_pii);
 
         let (tx, sentry_req) = sentry_request_from_http(&req, with_pii);
+        let user = match inner.user_extractor {
+            Some(ref extractor) if with_pii => extractor(&req),
+            _ => None,
+        };
         hub.configure_scope(|scope| {
             scope.set_transaction(tx.as_deref());
+            if user.is_some() {
+                scope.set_user(user);
+            }
             scope.add_event_processor(Box::new(move |event| process_event(event, &sentry_req)))
         });
 
-        let fut = self.service.call(req).bind_hub(hub.clone());
+        let mut fut = self.service.call(req).bind_hub(hub.clone()).boxed_local();
 
         async move {
+            // Panics
+            let result = if inner.capture_panics {
+                let poll_hub = hub.clone();
+                poll_fn(|cx| {
+                    match sentry_panic::report_unwind(
+                        &poll_hub,
+                        AssertUnwindSafe(|| fut.poll_unpin(cx)),
+                    ) {
+                        Ok(poll) => poll,
+                        Err(payload) => panic::resume_unwind(payload),
+                    }
+                })
+                .await
+            } else {
+                fut.await
+            };
+
             // Service errors
-            let mut res: Self::Response = match fut.await {
+            let mut res: Self::Response = match result {
                 Ok(res) => res,
                 Err(e) => {
-                    if inner.capture_server_errors {
+                    if inner.capture_server_errors
+                        && inner
+                            .error_filter
+                            .as_ref()
+                            .map_or(true, |filter| filter(&e))
+                    {
                         hub.capture_error(&e);
                     }
                     return Err(e);
@@ -216,15 +344,23 @@ where
             };
 
             // Response errors
-            if inner.capture_server_errors && res.response().status().is_server_error() {
-                if let Some(e) = res.response().error() {
+            if inner.capture_server_errors {
+                let error = res.response().error().filter(|e| match inner.error_filter {
+                    Some(ref filter) => filter(e),
+                    None => res.response().status().is_server_error(),
+                });
+                if let Some(e) = error {
                     let event_id = hub.capture_error(e);
 
-                    if inner.emit_header {
-                        res.response_mut().headers_mut().insert(
-                            "x-sentry-event".parse().unwrap(),
-                            event_id.to_simple_ref().to_string().parse().unwrap(),
-                        );
+                    if !event_id.is_nil() {
+                        res.response_mut().extensions_mut().insert(event_id);
+
+                        if inner.emit_header {
+                            res.response_mut().headers_mut().insert(
+                                "x-sentry-event-id".parse().unwrap(),
+                                event_id.to_simple_ref().to_string().parse().unwrap(),
+                            );
+                        }
                     }
                 }
             }
@@ -300,6 +436,7 @@ mod tests {
     use actix_web::{get, web, App, HttpRequest, HttpResponse};
     use futures::executor::block_on;
 
+    use sentry::types::Uuid;
     use sentry::Level;
 
     use super::*;
@@ -400,6 +537,35 @@ mod tests {
         }
     }
 
+    /// Ensures the event id of a captured server error is attached to the response.
+    #[actix_rt::test]
+    async fn test_event_id_in_extensions() {
+        let mut event_ids = vec![];
+        let events = sentry::test::with_captured_events(|| {
+            block_on(async {
+                #[get("/test")]
+                async fn failing(_req: HttpRequest) -> Result<String, Error> {
+                    Err(io::Error::new(io::ErrorKind::Other, "Test Error").into())
+                }
+
+                let mut app = init_service(
+                    App::new()
+                        .wrap(Sentry::builder().with_hub(Hub::current()).finish())
+                        .service(failing),
+                )
+                .await;
+
+                let req = TestRequest::get().uri("/test").to_request();
+                let res = call_service(&mut app, req).await;
+                assert!(res.status().is_server_error());
+                event_ids.extend(res.response().extensions().get::<Uuid>().copied());
+            })
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(event_ids, vec![events[0].event_id]);
+    }
+
     /// Ensures client errors (4xx) are not captured.
     #[actix_rt::test]
     async fn test_client_errors_discarded() {
@@ -423,6 +589,92 @@ mod tests {
         assert!(events.is_empty());
     }
 
+    /// Ensures panics in handlers are captured once, even if the handler captured events.
+    #[actix_rt::test]
+    async fn test_panic_captured() {
+        let run = || {
+            block_on(async {
+                let service = || -> HttpResponse {
+                    sentry::capture_message("Handling request", Level::Info);
+                    panic!("Handler panicked")
+                };
+
+                let mut app = init_service(
+                    App::new()
+                        .wrap(Sentry::builder().with_hub(Hub::current()).finish())
+                        .service(web::resource("/test").to(service)),
+                )
+                .await;
+
+                let req = TestRequest::get().uri("/test").to_request();
+                let res = AssertUnwindSafe(call_service(&mut app, req))
+                    .catch_unwind()
+                    .await;
+                assert!(res.is_err());
+            })
+        };
+
+        let events = sentry::test::with_captured_events(run);
+        let events_with_hook = sentry::test::with_captured_events_options(
+            run,
+            sentry::ClientOptions::new().add_integration(sentry_panic::PanicIntegration::new()),
+        );
+
+        for events in &[events, events_with_hook] {
+            assert_eq!(events.len(), 2);
+            assert_eq!(events[0].message.as_deref(), Some("Handling request"));
+            let event = &events[1];
+            assert_eq!(event.level, Level::Fatal);
+            assert_eq!(event.transaction.as_deref(), Some("/test"));
+            let exception = &event.exception[0];
+            assert_eq!(exception.value.as_deref(), Some("Handler panicked"));
+            assert_eq!(exception.mechanism.as_ref().unwrap().handled, Some(false));
+        }
+    }
+
+    /// Ensures the filters decide which requests and errors are captured.
+    #[actix_rt::test]
+    async fn test_filters() {
+        let events = sentry::test::with_captured_events(|| {
+            block_on(async {
+                let health = || async {
+                    Err::<String, Error>(io::Error::new(io::ErrorKind::Other, "Unhealthy").into())
+                };
+                let bad =
+                    || async { Err::<String, Error>(actix_web::error::ErrorBadRequest("Bad")) };
+                let missing =
+                    || async { Err::<String, Error>(actix_web::error::ErrorNotFound("Missing")) };
+
+                let middleware = Sentry::builder()
+                    .with_hub(Hub::current())
+                    .request_filter(|req| req.path() != "/health")
+                    .error_filter(|err| {
+                        err.as_response_error().status_code()
+                            != actix_web::http::StatusCode::NOT_FOUND
+                    })
+                    .finish();
+                let mut app = init_service(
+                    App::new()
+                        .wrap(middleware)
+                        .service(web::resource("/health").to(health))
+                        .service(web::resource("/bad").to(bad))
+                        .service(web::resource("/missing").to(missing)),
+                )
+                .await;
+
+                for uri in &["/health", "/bad", "/missing"] {
+                    let req = TestRequest::get().uri(uri).to_request();
+                    let res = call_service(&mut app, req).await;
+                    assert!(!res.status().is_success());
+                }
+            })
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].transaction, Some("/bad".into()));
+        assert_eq!(events[0].exception.values[0].value, Some("Bad".into()));
+    }
+
     /// Ensures transaction name can be overridden in handler scope.
     #[actix_rt::test]
     async fn test_override_transaction_name() {
@@ -458,4 +710,56 @@ mod tests {
         assert_eq!(event.level, Level::Error);
         assert_eq!(request.method, Some("GET".into()));
     }
+
+    /// Ensures the user extractor sets the user if PII may be sent.
+    #[actix_rt::test]
+    async fn test_user_extractor() {
+        let options = sentry::ClientOptions {
+            send_default_pii: true,
+            ..Default::default()
+        };
+        let events = sentry::test::with_captured_events_options(
+            || {
+                block_on(async {
+                    let service = || {
+                        sentry::capture_message("Message", Level::Warning);
+                        HttpResponse::Ok()
+                    };
+
+                    let middleware = Sentry::builder()
+                        .with_hub(Hub::current())
+                        .user_extractor(|req| {
+                            Some(User {
+                                id: req
+                                    .headers()
+                                    .get("x-user-id")?
+                                    .to_str()
+                                    .ok()
+                                    .map(Into::into),
+                                ..Default::default()
+                            })
+                        })
+                        .finish();
+                    let mut app = init_service(
+                        App::new()
+                            .wrap(middleware)
+                            .service(web::resource("/test").to(service)),
+                    )
+                    .await;
+
+                    let req = TestRequest::get()
+                        .uri("/test")
+                        .header("x-user-id", "42")
+                        .to_request();
+                    let res = call_service(&mut app, req).await;
+                    assert!(res.status().is_success());
+                })
+            },
+            options,
+        );
+
+        assert_eq!(events.len(), 1);
+        let user = events[0].user.as_ref().expect("User should be set.");
+        assert_eq!(user.id, Some("42".into()));
+    }
 }
@@ -0,0 +1,126 @@
+//! The instrumenting allocator and its counters.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static FREED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static HEAP_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_HEAP_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator that counts allocations, and forwards them to another allocator.
+///
+/// The counters are process wide, and only updated with relaxed atomic operations, so
+/// the overhead per allocation is small.
+///
+/// # Examples
+///
+/// ```
+/// use sentry_alloc::SentryAlloc;
+///
+/// #[global_allocator]
+/// static ALLOC: SentryAlloc = SentryAlloc::system();
+/// ```
+#[derive(Debug, Default)]
+pub struct SentryAlloc<A = System> {
+    inner: A,
+}
+
+impl SentryAlloc<System> {
+    /// Creates an allocator that forwards to the system allocator.
+    pub const fn system() -> Self {
+        SentryAlloc { inner: System }
+    }
+}
+
+impl<A> SentryAlloc<A> {
+    /// Creates an allocator that forwards to `inner`, such as `jemallocator::Jemalloc`.
+    pub const fn new(inner: A) -> Self {
+        SentryAlloc { inner }
+    }
+}
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
+    let heap = HEAP_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_HEAP_BYTES.fetch_max(heap, Ordering::Relaxed);
+}
+
+fn record_dealloc(size: usize) {
+    DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    FREED_BYTES.fetch_add(size, Ordering::Relaxed);
+    HEAP_BYTES.fetch_sub(size, Ordering::Relaxed);
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for SentryAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// The counters of the [`SentryAlloc`] allocator.
+///
+/// All counters are zero if the allocator is not installed as the global allocator.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AllocStats {
+    /// The number of allocations since the start of the process.
+    pub allocations: usize,
+    /// The number of deallocations since the start of the process.
+    pub deallocations: usize,
+    /// The number of bytes allocated since the start of the process.
+    pub allocated_bytes: usize,
+    /// The number of bytes freed since the start of the process.
+    pub freed_bytes: usize,
+    /// The number of bytes currently allocated.
+    pub heap_bytes: usize,
+    /// The highest number of bytes allocated at once.
+    pub peak_heap_bytes: usize,
+}
+
+impl AllocStats {
+    /// Returns the current counters.
+    pub fn current() -> Self {
+        AllocStats {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+            allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+            freed_bytes: FREED_BYTES.load(Ordering::Relaxed),
+            heap_bytes: HEAP_BYTES.load(Ordering::Relaxed),
+            peak_heap_bytes: PEAK_HEAP_BYTES.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the number of allocations that were not freed yet.
+    pub fn live_allocations(&self) -> usize {
+        self.allocations.saturating_sub(self.deallocations)
+    }
+}
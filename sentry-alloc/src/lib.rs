@@ -0,0 +1,168 @@
+//! Adds support for tracking memory allocations.
+//!
+//! The [`SentryAlloc`] global allocator counts allocations and the bytes allocated,
+//! and forwards them to the system allocator, or any other allocator.  With the
+//! allocator installed, the [`AllocIntegration`]:
+//!
+//! * Adds the `memory.allocations`, `memory.allocated` and `memory.heap_growth`
+//!   measurements to every transaction, which make memory regressions in production
+//!   visible in performance monitoring.
+//! * Adds a `memory` context with the current heap statistics to every event.
+//!
+//! The counters are process wide, so the measurements of a transaction include the
+//! allocations of everything that ran concurrently with it.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use sentry_alloc::{AllocIntegration, SentryAlloc};
+//!
+//! #[global_allocator]
+//! static ALLOC: SentryAlloc = SentryAlloc::system();
+//!
+//! let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(AllocIntegration));
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use sentry_core::protocol::{Context, Event, Map, Measurement, Transaction};
+use sentry_core::{ClientOptions, Integration};
+
+mod allocator;
+
+pub use crate::allocator::{AllocStats, SentryAlloc};
+
+/// The Sentry memory allocation Integration.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllocIntegration;
+
+/// The names and units of the measurements added to transactions.
+const MEASUREMENTS: &[(&str, &str)] = &[
+    ("memory.allocations", "none"),
+    ("memory.allocated", "byte"),
+    ("memory.heap_growth", "byte"),
+];
+
+/// Returns the values of the [`MEASUREMENTS`].
+fn measurement_values(stats: &AllocStats) -> [f64; 3] {
+    [
+        stats.allocations as f64,
+        stats.allocated_bytes as f64,
+        stats.heap_bytes as f64,
+    ]
+}
+
+impl Integration for AllocIntegration {
+    fn name(&self) -> &'static str {
+        "alloc"
+    }
+
+    fn start_transaction(&self, transaction: &mut Transaction<'static>) {
+        let stats = AllocStats::current();
+        if stats.allocations == 0 {
+            return;
+        }
+        // the counters at the start are kept in the measurements, and replaced by
+        // the difference once the transaction is finished
+        for (&(name, _), value) in MEASUREMENTS.iter().zip(&measurement_values(&stats)) {
+            transaction.measurements.insert(
+                name.into(),
+                Measurement {
+                    value: *value,
+                    unit: None,
+                },
+            );
+        }
+    }
+
+    fn process_transaction(
+        &self,
+        mut transaction: Transaction<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Transaction<'static>> {
+        let values = measurement_values(&AllocStats::current());
+        for (&(name, unit), value) in MEASUREMENTS.iter().zip(&values) {
+            if let Some(measurement) = transaction.measurements.get_mut(name) {
+                measurement.value = value - measurement.value;
+                measurement.unit = Some(unit.into());
+            }
+        }
+        Some(transaction)
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        let stats = AllocStats::current();
+        if stats.allocations == 0 {
+            return Some(event);
+        }
+        let mut context = Map::new();
+        context.insert("heap_bytes".into(), stats.heap_bytes.into());
+        context.insert("peak_heap_bytes".into(), stats.peak_heap_bytes.into());
+        context.insert("live_allocations".into(), stats.live_allocations().into());
+        context.insert("allocations".into(), stats.allocations.into());
+        context.insert("allocated_bytes".into(), stats.allocated_bytes.into());
+        event
+            .contexts
+            .insert("memory".into(), Context::Other(context));
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry::protocol::EnvelopeItem;
+
+    use super::*;
+
+    #[global_allocator]
+    static ALLOC: SentryAlloc = SentryAlloc::system();
+
+    #[test]
+    fn test_measurements() {
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let ctx = sentry::TransactionContext::new("job", "queue.task");
+                let transaction = sentry::start_transaction(ctx);
+                let buffer = vec![1u8; 1 << 20];
+                sentry::capture_message("Hello World!", sentry::Level::Warning);
+                drop(buffer);
+                transaction.finish();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            }
+            .add_integration(AllocIntegration),
+        );
+
+        let event = envelopes
+            .iter()
+            .find_map(|envelope| envelope.event())
+            .unwrap();
+        match event.contexts.get("memory") {
+            Some(Context::Other(context)) => {
+                assert!(context["heap_bytes"].as_u64().unwrap() >= 1 << 20);
+            }
+            context => panic!("expected memory context, got {:?}", context),
+        }
+
+        let transaction = envelopes
+            .iter()
+            .flat_map(|envelope| envelope.items())
+            .find_map(|item| match item {
+                EnvelopeItem::Transaction(transaction) => Some(transaction),
+                _ => None,
+            })
+            .unwrap();
+        let allocated = &transaction.measurements["memory.allocated"];
+        assert!(allocated.value >= (1 << 20) as f64);
+        assert_eq!(allocated.unit.as_deref(), Some("byte"));
+        assert!(transaction.measurements["memory.allocations"].value >= 1.0);
+    }
+}
@@ -0,0 +1,98 @@
+//! Adds support for detecting hanging threads and event loops.
+//!
+//! The [`AnrIntegration`] spawns a watchdog thread, which watches all threads that
+//! registered a [`Heartbeat`].  When a thread does not call [`Heartbeat::beat`] for longer
+//! than the configured timeout, the watchdog captures an `AppHang` event with the stacks of
+//! the threads of the process.  This is most useful for the main loop of GUI applications
+//! and games, or for the threads of an executor, which should never block for long.
+//!
+//! A hanging thread is only reported once, and is watched again after its next heartbeat.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(
+//!     sentry_anr::AnrIntegration::new().timeout(Duration::from_secs(5)),
+//! ));
+//!
+//! let heartbeat = sentry_anr::Heartbeat::register("main loop");
+//! loop {
+//!     heartbeat.beat();
+//!     // handle the events of this frame
+//! }
+//! ```
+//!
+//! # Blocked Runtimes
+//!
+//! With the `tokio` feature, [`spawn_runtime_monitor`] spawns a task that sends heartbeats
+//! from within a `tokio` runtime.  When the runtime is blocked for longer than the given
+//! threshold, for example by synchronous I/O in async code, the watchdog captures a
+//! `BlockedRuntime` warning with the stacks of the threads, which show what is blocking it.
+//!
+//! # Stacks
+//!
+//! On unix platforms, the watchdog collects the stacks by sending `SIGURG` to the threads,
+//! which is ignored by default.  The signal handler is only installed while the stacks are
+//! collected.  On Linux, the stacks of all threads of the process are collected, and on
+//! other unix platforms the stacks of the threads that registered a heartbeat.  On other
+//! platforms, the event does not contain any stacks.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::time::Duration;
+
+use sentry_core::{ClientOptions, Integration};
+
+#[cfg(unix)]
+mod stacks;
+#[cfg(feature = "tokio")]
+mod runtime;
+mod watchdog;
+
+#[cfg(feature = "tokio")]
+pub use crate::runtime::spawn_runtime_monitor;
+pub use crate::watchdog::Heartbeat;
+
+/// The Sentry hang detection Integration.
+#[derive(Clone, Debug)]
+pub struct AnrIntegration {
+    timeout: Duration,
+}
+
+impl AnrIntegration {
+    /// Creates a new hang detection Integration.
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Sets how long a thread may go without a heartbeat before it is reported.
+    ///
+    /// The default is five seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for AnrIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integration for AnrIntegration {
+    fn name(&self) -> &'static str {
+        "anr"
+    }
+
+    fn setup(&self, _cfg: &mut ClientOptions) {
+        watchdog::set_timeout(self.timeout);
+        watchdog::spawn();
+    }
+}
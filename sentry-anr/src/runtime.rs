@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use tokio_::task::JoinHandle;
+
+use crate::watchdog::{self, Heartbeat};
+
+/// Spawns a task on the current `tokio` runtime that reports when the runtime is blocked.
+///
+/// The task wakes up regularly to send a heartbeat.  If it is not scheduled for longer
+/// than `threshold`, because the threads of the runtime are blocked, the watchdog
+/// captures a `BlockedRuntime` warning with the stacks of the threads.  The runtime is
+/// watched until the returned task is aborted.
+///
+/// # Panics
+///
+/// This panics when called outside of a `tokio` runtime.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let monitor = sentry_anr::spawn_runtime_monitor("tokio", Duration::from_millis(500));
+/// // ...
+/// monitor.abort();
+/// # }
+/// ```
+pub fn spawn_runtime_monitor<S: Into<String>>(name: S, threshold: Duration) -> JoinHandle<()> {
+    watchdog::spawn();
+    let heartbeat = Heartbeat::register_runtime(name.into(), threshold);
+    let period = (threshold / 4).max(Duration::from_millis(10));
+    tokio_::spawn(async move {
+        let mut interval = tokio_::time::interval(period);
+        loop {
+            interval.tick().await;
+            heartbeat.beat();
+        }
+    })
+}
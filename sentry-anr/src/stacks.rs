@@ -0,0 +1,187 @@
+//! Collects the stacks of other threads by interrupting them with a signal.
+//!
+//! The signal handler walks the stack of the interrupted thread into a static buffer,
+//! which the watchdog reads once the handler has finished.  Only one stack is collected
+//! at a time, and everything that runs inside the signal handler has to be
+//! async-signal-safe.
+
+use std::ffi::c_void;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libc::{c_int, siginfo_t};
+use sentry_backtrace::addresses_to_stacktrace;
+use sentry_core::protocol::{Thread, ThreadId};
+
+use crate::watchdog::WatchedThread;
+
+/// The signal used to interrupt the threads, which is ignored by default.
+const SIGNAL: c_int = libc::SIGURG;
+/// The maximum number of frames collected per thread.
+const MAX_FRAMES: usize = 128;
+/// How long to wait for a thread to collect its stack.
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The sequence number of the last requested stack.
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+/// The sequence number of the requested stack, or `0` if none is requested.
+static REQUESTED: AtomicUsize = AtomicUsize::new(0);
+/// The sequence number of the last collected stack.
+static COLLECTED: AtomicUsize = AtomicUsize::new(0);
+/// The number of frames in `FRAMES`.
+static LEN: AtomicUsize = AtomicUsize::new(0);
+/// The instruction addresses of the last collected stack.
+static mut FRAMES: [usize; MAX_FRAMES] = [0; MAX_FRAMES];
+
+/// A thread whose stack can be collected.
+struct Target {
+    id: u64,
+    name: Option<String>,
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pthread: usize,
+}
+
+/// Collects the stacks of the threads of the process.
+///
+/// On Linux, these are all threads of the process, otherwise the watched threads.
+/// The thread calling this is skipped.
+pub(crate) fn capture_threads(watched: &[Arc<WatchedThread>]) -> Vec<Thread> {
+    let targets = targets(watched);
+    let mut previous: libc::sigaction = unsafe { mem::zeroed() };
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handle_signal as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(SIGNAL, &action, &mut previous) != 0 {
+            return Vec::new();
+        }
+    }
+
+    let threads = targets
+        .into_iter()
+        .map(|target| {
+            let addresses = collect(&target).unwrap_or_default();
+            Thread {
+                id: Some(ThreadId::Int(target.id)),
+                name: target.name,
+                stacktrace: addresses_to_stacktrace(&addresses),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    unsafe { libc::sigaction(SIGNAL, &previous, ptr::null_mut()) };
+    threads
+}
+
+/// Returns all threads of the process, except the current one.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn targets(watched: &[Arc<WatchedThread>]) -> Vec<Target> {
+    let current = unsafe { libc::syscall(libc::SYS_gettid) } as u64;
+    let tasks = match std::fs::read_dir("/proc/self/task") {
+        Ok(tasks) => tasks,
+        Err(_) => return Vec::new(),
+    };
+    tasks
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u64>().ok())
+        .filter(|&id| id != current)
+        .map(|id| {
+            let name = watched
+                .iter()
+                .find(|thread| thread.id == Some(id))
+                .map(|thread| thread.name.clone())
+                .or_else(|| {
+                    let comm = std::fs::read_to_string(format!("/proc/self/task/{}/comm", id));
+                    comm.ok().map(|comm| comm.trim_end().to_owned())
+                });
+            Target { id, name }
+        })
+        .collect()
+}
+
+/// Returns the watched threads, except the current one.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn targets(watched: &[Arc<WatchedThread>]) -> Vec<Target> {
+    let current = unsafe { libc::pthread_self() } as usize;
+    watched
+        .iter()
+        .filter(|thread| thread.pthread != current)
+        .filter_map(|thread| {
+            Some(Target {
+                id: thread.id?,
+                name: Some(thread.name.clone()),
+                pthread: thread.pthread,
+            })
+        })
+        .collect()
+}
+
+/// Sends the signal to the thread.
+fn interrupt(target: &Target) -> bool {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    unsafe {
+        libc::syscall(
+            libc::SYS_tgkill,
+            libc::getpid(),
+            target.id as libc::pid_t,
+            SIGNAL,
+        ) == 0
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    unsafe {
+        libc::pthread_kill(target.pthread as libc::pthread_t, SIGNAL) == 0
+    }
+}
+
+/// Collects the stack of a thread, returning its instruction addresses.
+fn collect(target: &Target) -> Option<Vec<usize>> {
+    let sequence = SEQUENCE
+        .fetch_add(1, Ordering::SeqCst)
+        .wrapping_add(1)
+        .max(1);
+    REQUESTED.store(sequence, Ordering::SeqCst);
+    if !interrupt(target) {
+        REQUESTED.store(0, Ordering::SeqCst);
+        return None;
+    }
+
+    let deadline = Instant::now() + TIMEOUT;
+    while COLLECTED.load(Ordering::Acquire) != sequence {
+        if Instant::now() >= deadline {
+            // if the handler did not pick up the request yet, it never will
+            if REQUESTED
+                .compare_exchange(sequence, 0, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                return None;
+            }
+        }
+        thread::yield_now();
+    }
+
+    let len = LEN.load(Ordering::Acquire);
+    Some(unsafe { FRAMES[..len].to_vec() })
+}
+
+extern "C" fn handle_signal(_signal: c_int, _info: *mut siginfo_t, _context: *mut c_void) {
+    let sequence = REQUESTED.swap(0, Ordering::SeqCst);
+    if sequence == 0 {
+        return;
+    }
+
+    let mut len = 0;
+    unsafe {
+        backtrace::trace_unsynchronized(|frame| {
+            FRAMES[len] = frame.ip() as usize;
+            len += 1;
+            len < MAX_FRAMES
+        });
+    }
+    LEN.store(len, Ordering::Release);
+    COLLECTED.store(sequence, Ordering::Release);
+}
@@ -0,0 +1,277 @@
+//! The heartbeats of the watched threads, and the watchdog thread checking them.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sentry_core::protocol::{Event, Exception, Level, Mechanism, Thread, ThreadId};
+use sentry_core::Hub;
+
+lazy_static::lazy_static! {
+    /// The point in time heartbeats are measured from.
+    static ref START: Instant = Instant::now();
+    /// The threads that registered a heartbeat.
+    static ref WATCHED: Mutex<Vec<Arc<WatchedThread>>> = Mutex::new(Vec::new());
+}
+
+/// The timeout of the watchdog, in milliseconds.
+static TIMEOUT_MS: AtomicU64 = AtomicU64::new(5000);
+
+/// Returns the milliseconds since `START`.
+fn now_ms() -> u64 {
+    START.elapsed().as_millis() as u64
+}
+
+static SPAWN: Once = Once::new();
+
+/// What sends the heartbeats of a [`WatchedThread`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Kind {
+    /// The thread that registered the heartbeat.
+    Thread,
+    /// A task of an async runtime, which may run on any of its threads.
+    #[cfg(feature = "tokio")]
+    Runtime,
+}
+
+/// A thread that is watched by the watchdog.
+#[derive(Debug)]
+pub(crate) struct WatchedThread {
+    pub name: String,
+    pub kind: Kind,
+    /// The OS specific id of the thread, if it is known.
+    pub id: Option<u64>,
+    /// The `pthread_t` of the thread, which is signaled to collect its stack on
+    /// platforms where the threads of the process cannot be listed.
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    pub pthread: usize,
+    /// Overrides the timeout of the watchdog, in milliseconds.
+    timeout: Option<u64>,
+    last_beat: AtomicU64,
+    reported: AtomicBool,
+}
+
+/// The heartbeat of a watched thread.
+///
+/// The thread that registered the heartbeat is reported as hanging, unless [`beat`] is
+/// called at least once per timeout of the [`AnrIntegration`](crate::AnrIntegration).
+/// Dropping the heartbeat stops watching the thread.
+///
+/// [`beat`]: Heartbeat::beat
+#[derive(Debug)]
+pub struct Heartbeat {
+    thread: Arc<WatchedThread>,
+}
+
+impl Heartbeat {
+    /// Starts watching the current thread under the given name.
+    pub fn register<S: Into<String>>(name: S) -> Self {
+        Self::new(WatchedThread {
+            name: name.into(),
+            kind: Kind::Thread,
+            id: current_thread_id(),
+            #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+            pthread: unsafe { libc::pthread_self() } as usize,
+            timeout: None,
+            last_beat: AtomicU64::new(now_ms()),
+            reported: AtomicBool::new(false),
+        })
+    }
+
+    /// Starts watching an async runtime, which is blocked if it misses the `threshold`.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn register_runtime(name: String, threshold: Duration) -> Self {
+        Self::new(WatchedThread {
+            name,
+            kind: Kind::Runtime,
+            id: None,
+            #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+            pthread: 0,
+            timeout: Some(threshold.as_millis() as u64),
+            last_beat: AtomicU64::new(now_ms()),
+            reported: AtomicBool::new(false),
+        })
+    }
+
+    fn new(thread: WatchedThread) -> Self {
+        let thread = Arc::new(thread);
+        watched().push(thread.clone());
+        Heartbeat { thread }
+    }
+
+    /// Signals that the watched thread is still responsive.
+    pub fn beat(&self) {
+        self.thread.last_beat.store(now_ms(), Ordering::Relaxed);
+        self.thread.reported.store(false, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        watched().retain(|thread| !Arc::ptr_eq(thread, &self.thread));
+    }
+}
+
+fn watched() -> std::sync::MutexGuard<'static, Vec<Arc<WatchedThread>>> {
+    WATCHED.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns the OS specific id of the current thread.
+fn current_thread_id() -> Option<u64> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        Some(unsafe { libc::syscall(libc::SYS_gettid) } as u64)
+    }
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    {
+        Some(unsafe { libc::pthread_self() } as u64)
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+pub(crate) fn set_timeout(timeout: Duration) {
+    TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::Relaxed);
+}
+
+/// Spawns the watchdog thread, unless it is already running.
+pub(crate) fn spawn() {
+    SPAWN.call_once(|| {
+        if let Err(err) = spawn_thread() {
+            sentry_core::sentry_debug!("failed to spawn the hang watchdog: {}", err);
+        }
+    });
+}
+
+fn spawn_thread() -> io::Result<()> {
+    thread::Builder::new()
+        .name("sentry-anr-watchdog".into())
+        .spawn(|| loop {
+            let timeout = TIMEOUT_MS.load(Ordering::Relaxed);
+            // check often enough to notice the shortest timeout in time
+            let shortest = watched()
+                .iter()
+                .filter_map(|thread| thread.timeout)
+                .fold(timeout, u64::min);
+            thread::sleep(Duration::from_millis((shortest / 4).max(10)));
+            report_hangs(&Hub::main(), timeout);
+        })?;
+    Ok(())
+}
+
+/// Captures an event for every watched thread that has not sent a heartbeat for
+/// `timeout` milliseconds, and was not reported yet.
+pub(crate) fn report_hangs(hub: &Hub, timeout: u64) {
+    let now = now_ms();
+    let (hanging, watched): (Vec<_>, Vec<_>) = {
+        let watched = watched();
+        let hanging = watched
+            .iter()
+            .filter_map(|thread| {
+                let elapsed = now.saturating_sub(thread.last_beat.load(Ordering::Relaxed));
+                if elapsed >= thread.timeout.unwrap_or(timeout)
+                    && !thread.reported.swap(true, Ordering::Relaxed)
+                {
+                    Some((thread.clone(), elapsed))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        (hanging, watched.clone())
+    };
+    if hanging.is_empty() || hub.client().is_none() {
+        return;
+    }
+
+    #[cfg(unix)]
+    let threads = crate::stacks::capture_threads(&watched);
+    #[cfg(not(unix))]
+    let threads: Vec<Thread> = {
+        let _watched = watched;
+        Vec::new()
+    };
+
+    for (thread, elapsed) in hanging {
+        hub.capture_event(event_from_hang(&thread, elapsed, &threads));
+    }
+}
+
+/// Creates an event for a thread that did not respond for `elapsed` milliseconds.
+///
+/// Hanging threads are reported as `AppHang` errors, and blocked runtimes as
+/// `BlockedRuntime` warnings.
+fn event_from_hang(thread: &WatchedThread, elapsed: u64, threads: &[Thread]) -> Event<'static> {
+    let thread_id = thread.id.map(ThreadId::Int);
+    let stacktrace = threads
+        .iter()
+        .find(|t| t.id.is_some() && t.id == thread_id)
+        .and_then(|t| t.stacktrace.clone());
+    let (ty, value, level) = match thread.kind {
+        Kind::Thread => (
+            "AppHang",
+            format!(
+                "Thread {:?} did not respond for {} ms",
+                thread.name, elapsed
+            ),
+            Level::Error,
+        ),
+        #[cfg(feature = "tokio")]
+        Kind::Runtime => (
+            "BlockedRuntime",
+            format!("Runtime {:?} was blocked for {} ms", thread.name, elapsed),
+            Level::Warning,
+        ),
+    };
+    Event {
+        exception: vec![Exception {
+            ty: ty.into(),
+            value: Some(value),
+            stacktrace,
+            thread_id,
+            mechanism: Some(Mechanism {
+                ty: ty.into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]
+        .into(),
+        threads: threads.to_vec().into(),
+        level,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_hangs() {
+        let events = sentry::test::with_captured_events(|| {
+            let heartbeat = Heartbeat::register("test loop");
+            heartbeat.thread.last_beat.store(0, Ordering::Relaxed);
+            thread::sleep(Duration::from_millis(20));
+
+            report_hangs(&Hub::current(), 10);
+            // a hanging thread is only reported once
+            report_hangs(&Hub::current(), 10);
+            heartbeat.beat();
+            report_hangs(&Hub::current(), 10_000);
+        });
+
+        assert_eq!(events.len(), 1);
+        let exception = &events[0].exception.values[0];
+        assert_eq!(exception.ty, "AppHang");
+        assert!(exception
+            .value
+            .as_ref()
+            .unwrap()
+            .starts_with("Thread \"test loop\" did not respond"));
+        assert_eq!(exception.thread_id, current_thread_id().map(ThreadId::Int));
+    }
+}
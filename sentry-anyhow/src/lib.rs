@@ -65,7 +65,7 @@ pub trait AnyhowHubExt {
 
 impl AnyhowHubExt for Hub {
     fn capture_anyhow(&self, e: &anyhow::Error) -> Uuid {
-        let e: &dyn std::error::Error = e.as_ref();
-        self.capture_error(e)
+        let e: &(dyn std::error::Error + 'static) = e.as_ref();
+        self.capture_enriched_error(e)
     }
 }
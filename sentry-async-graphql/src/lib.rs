@@ -0,0 +1,320 @@
+//! Adds support for the [`async-graphql`](https://docs.rs/async-graphql) server library.
+//!
+//! The [`SentryExtension`] instruments the execution of GraphQL operations:
+//!
+//! * Events and transactions are named after the executed operation, such as
+//!   `query GetUser`, instead of the single HTTP route all operations share.
+//! * The operation is executed in a `graphql.execute` span, which is a child of the
+//!   current span, or a new transaction if there is none.  Resolvers that take longer
+//!   than a threshold get a `graphql.resolve` child span.
+//! * Errors returned by resolvers are captured, together with the query of the
+//!   operation.  The query is sanitized by `async-graphql`, which hides the values
+//!   of arguments marked as `secret`.  Variables are never attached.
+//!
+//! The extension changes the current scope, so it should be used together with a server
+//! integration that binds a new hub per request, such as
+//! [`sentry-tower`](https://docs.rs/sentry-tower) or
+//! [`sentry-actix`](https://docs.rs/sentry-actix).
+//!
+//! # Example
+//!
+//! ```
+//! use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema};
+//! use sentry_async_graphql::SentryExtension;
+//!
+//! struct Query;
+//!
+//! #[Object]
+//! impl Query {
+//!     async fn answer(&self) -> i32 {
+//!         42
+//!     }
+//! }
+//!
+//! let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+//!     .extension(SentryExtension::new())
+//!     .finish();
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextExecute, NextParseQuery, NextResolve,
+    ResolveInfo,
+};
+use async_graphql::parser::types::{ExecutableDocument, OperationType};
+use async_graphql::{PathSegment, Response, ServerError, ServerResult, Value, Variables};
+use sentry_core::protocol::{Context, Event, Exception, Level, Map, Mechanism};
+use sentry_core::TransactionOrSpan;
+
+/// An `async-graphql` extension that names transactions after operations, creates
+/// spans for slow resolvers and captures resolver errors.
+#[derive(Clone, Debug)]
+pub struct SentryExtension {
+    span_threshold: Duration,
+    capture_errors: bool,
+}
+
+impl SentryExtension {
+    /// Creates a new extension.
+    pub fn new() -> Self {
+        Self {
+            span_threshold: Duration::from_millis(10),
+            capture_errors: true,
+        }
+    }
+
+    /// Sets how long a resolver needs to run to get its own span.
+    ///
+    /// The default is 10 milliseconds, which keeps transactions of large queries with
+    /// many trivial field resolvers small.
+    pub fn span_threshold(mut self, threshold: Duration) -> Self {
+        self.span_threshold = threshold;
+        self
+    }
+
+    /// Enables or disables capturing errors returned by resolvers.
+    ///
+    /// This is enabled by default.
+    pub fn capture_errors(mut self, enabled: bool) -> Self {
+        self.capture_errors = enabled;
+        self
+    }
+}
+
+impl Default for SentryExtension {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExtensionFactory for SentryExtension {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(SentryRequestExtension {
+            config: self.clone(),
+            state: Mutex::new(State::default()),
+        })
+    }
+}
+
+/// What is known about the request, once its query is parsed.
+#[derive(Default)]
+struct State {
+    query: Option<String>,
+    operations: Vec<(Option<String>, OperationType)>,
+    span: Option<TransactionOrSpan>,
+}
+
+/// The extension for a single request.
+struct SentryRequestExtension {
+    config: SentryExtension,
+    state: Mutex<State>,
+}
+
+impl SentryRequestExtension {
+    /// Returns the transaction name of the executed operation, such as `query GetUser`.
+    fn operation(&self, operation_name: Option<&str>) -> String {
+        let state = self.state.lock().unwrap();
+        let operation = match operation_name {
+            Some(name) => state
+                .operations
+                .iter()
+                .find(|(op_name, _)| op_name.as_deref() == Some(name)),
+            None => state.operations.first(),
+        };
+        match operation {
+            Some((Some(name), ty)) => format!("{} {}", ty, name),
+            Some((None, ty)) => ty.to_string(),
+            None => operation_name.unwrap_or("graphql").to_owned(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for SentryRequestExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        let mut state = self.state.lock().unwrap();
+        state.query = Some(ctx.stringify_execute_doc(&document, variables));
+        state.operations = document
+            .operations
+            .iter()
+            .map(|(name, operation)| (name.map(ToString::to_string), operation.node.ty))
+            .collect();
+        Ok(document)
+    }
+
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        let operation = self.operation(operation_name);
+        sentry_core::configure_scope(|scope| scope.set_transaction(Some(&operation)));
+        let span = sentry_core::start_child_or_transaction("graphql.execute", &operation);
+        self.state.lock().unwrap().span = Some(span.clone());
+        let guard = span.enter();
+
+        let response = next.run(ctx, operation_name).await;
+
+        let status = if response.errors.is_empty() {
+            "ok"
+        } else {
+            "internal_error"
+        };
+        guard.span().set_status(status);
+        if self.config.capture_errors {
+            let query = self.state.lock().unwrap().query.clone();
+            for error in &response.errors {
+                let event = event_from_error(error, &operation, query.as_deref());
+                sentry_core::capture_event(event);
+            }
+        }
+        self.state.lock().unwrap().span = None;
+        drop(guard);
+        response
+    }
+
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        let parent = self.state.lock().unwrap().span.clone();
+        let parent = match parent {
+            Some(parent) if !info.is_for_introspection => parent,
+            _ => return next.run(ctx, info).await,
+        };
+        let description = format!("{}.{}", info.parent_type, info.name);
+        let span = parent.start_child("graphql.resolve", &description);
+        span.set_data("graphql.path", info.path_node.to_string().into());
+
+        let start = Instant::now();
+        let result = next.run(ctx, info).await;
+        if start.elapsed() >= self.config.span_threshold {
+            let status = if result.is_ok() {
+                "ok"
+            } else {
+                "internal_error"
+            };
+            span.set_status(status);
+            span.finish();
+        }
+        result
+    }
+}
+
+/// Creates a Sentry [`Event`] from an error returned by a resolver.
+///
+/// The event has a `graphql` context with the operation, the path of the failed field
+/// and the query, if it is known.
+pub fn event_from_error(
+    error: &ServerError,
+    operation: &str,
+    query: Option<&str>,
+) -> Event<'static> {
+    let path = error
+        .path
+        .iter()
+        .map(|segment| match segment {
+            PathSegment::Field(name) => name.clone(),
+            PathSegment::Index(index) => index.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+
+    let mut context = Map::new();
+    context.insert("operation".into(), operation.into());
+    if !path.is_empty() {
+        context.insert("path".into(), path.into());
+    }
+    if let Some(query) = query {
+        context.insert("query".into(), query.into());
+    }
+
+    let mut event = Event {
+        exception: vec![Exception {
+            ty: "GraphQLError".into(),
+            value: Some(error.message.clone()),
+            mechanism: Some(Mechanism {
+                ty: "graphql".into(),
+                handled: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]
+        .into(),
+        level: Level::Error,
+        ..Default::default()
+    };
+    event
+        .contexts
+        .insert("graphql".into(), Context::Other(context));
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema};
+
+    use super::*;
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn answer(&self) -> i32 {
+            42
+        }
+
+        async fn broken(&self, #[graphql(secret)] _token: String) -> async_graphql::Result<i32> {
+            Err("database is down".into())
+        }
+    }
+
+    #[test]
+    fn test_resolver_errors() {
+        let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+            .extension(SentryExtension::new())
+            .finish();
+
+        let events = sentry::test::with_captured_events(|| {
+            let response = futures::executor::block_on(schema.execute("query Answer { answer }"));
+            assert!(response.errors.is_empty());
+            let response = futures::executor::block_on(
+                schema.execute(r#"query Broken { broken(token: "hunter2") }"#),
+            );
+            assert_eq!(response.errors.len(), 1);
+        });
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.transaction.as_deref(), Some("query Broken"));
+        let exception = &event.exception.values[0];
+        assert_eq!(exception.ty, "GraphQLError");
+        assert_eq!(exception.value.as_deref(), Some("database is down"));
+        match event.contexts.get("graphql") {
+            Some(Context::Other(context)) => {
+                assert_eq!(context["operation"], "query Broken");
+                assert_eq!(context["path"], "broken");
+                let query = context["query"].as_str().unwrap();
+                assert!(query.contains("broken"));
+                assert!(!query.contains("hunter2"));
+            }
+            context => panic!("expected graphql context, got {:?}", context),
+        }
+    }
+}
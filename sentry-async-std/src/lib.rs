@@ -0,0 +1,132 @@
+//! Adds support for propagating hubs to [`async-std`](https://docs.rs/async-std) tasks.
+//!
+//! Spawned tasks of `async-std` are polled on the threads of its executor, which have
+//! their own thread local hub.  The scope data of the spawning task, such as tags, the
+//! user and the current span, is therefore lost in the spawned task.
+//!
+//! The [`spawn`] and [`spawn_blocking`] functions are drop-in replacements for the
+//! functions of the same name in `async_std::task`, which run the spawned work in a
+//! new hub that starts with a copy of the current scope.  Changes to the scope in the
+//! spawned task do not affect the spawning task, or other spawned tasks.
+//!
+//! Futures that are spawned by other means can be bound to a hub with
+//! [`SentryFutureExt::bind_hub`].
+//!
+//! # Example
+//!
+//! ```
+//! # async_std::task::block_on(async {
+//! sentry::configure_scope(|scope| scope.set_tag("request", "42"));
+//!
+//! sentry_async_std::spawn(async {
+//!     // this event has the `request` tag
+//!     sentry::capture_message("Hello from a task!", sentry::Level::Info);
+//! })
+//! .await;
+//! # });
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::future::Future;
+use std::sync::Arc;
+
+use async_std::task::JoinHandle;
+use sentry_core::Hub;
+
+pub use sentry_core::{SentryFuture, SentryFutureExt};
+
+/// Spawns a task with a new hub that starts with the current scope.
+///
+/// See [`async_std::task::spawn`].
+pub fn spawn<F, T>(future: F) -> JoinHandle<T>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    async_std::task::spawn(future.bind_hub(task_hub()))
+}
+
+/// Runs a blocking function on a thread pool with a new hub that starts with the
+/// current scope.
+///
+/// See [`async_std::task::spawn_blocking`].
+pub fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let hub = task_hub();
+    async_std::task::spawn_blocking(move || Hub::run(hub, f))
+}
+
+/// Creates the hub of a spawned task.
+fn task_hub() -> Arc<Hub> {
+    Arc::new(Hub::new_from_top(Hub::current()))
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry::{capture_message, configure_scope, Level};
+
+    use super::*;
+
+    #[test]
+    fn test_spawn() {
+        let mut events = sentry::test::with_captured_events(|| {
+            async_std::task::block_on(async {
+                configure_scope(|scope| scope.set_tag("request", "42"));
+
+                let task1 = spawn(async {
+                    configure_scope(|scope| scope.set_transaction(Some("transaction1")));
+                    capture_message("oh hai from 1", Level::Info);
+                });
+                let task2 = spawn(async {
+                    configure_scope(|scope| scope.set_transaction(Some("transaction2")));
+                    capture_message("oh hai from 2", Level::Info);
+                });
+                let task3 = spawn_blocking(|| {
+                    configure_scope(|scope| scope.set_transaction(Some("transaction3")));
+                    capture_message("oh hai from 3", Level::Info);
+                });
+                task1.await;
+                task2.await;
+                task3.await;
+
+                capture_message("oh hai from outside", Level::Info);
+            });
+        });
+
+        events.sort_by(|a, b| a.transaction.cmp(&b.transaction));
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].transaction, None);
+        assert_eq!(events[1].transaction.as_deref(), Some("transaction1"));
+        assert_eq!(events[2].transaction.as_deref(), Some("transaction2"));
+        assert_eq!(events[3].transaction.as_deref(), Some("transaction3"));
+        for event in &events {
+            assert_eq!(event.tags["request"], "42");
+        }
+    }
+
+    #[test]
+    fn test_bind_hub() {
+        let events = sentry::test::with_captured_events(|| {
+            async_std::task::block_on(async {
+                let hub = Arc::new(Hub::new_from_top(Hub::current()));
+                hub.configure_scope(|scope| scope.set_tag("bound", "yes"));
+                async_std::task::spawn(
+                    async {
+                        capture_message("oh hai", Level::Info);
+                    }
+                    .bind_hub(hub),
+                )
+                .await;
+            });
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tags["bound"], "yes");
+    }
+}
@@ -0,0 +1,104 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::extract::{FromRequest, FromRequestParts};
+use http_::request::Parts;
+use http_::Request;
+use sentry_core::Hub;
+
+/// Extractor for the Sentry hub that is bound to the current request.
+///
+/// The hub is put into the request extensions by the
+/// [`SentryAxumLayer`](crate::SentryAxumLayer).  If that layer is missing, the currently
+/// active hub is returned instead.
+///
+/// This is useful to capture events from tasks that are spawned by a handler, as those are
+/// not bound to the request's hub automatically.
+#[derive(Clone, Debug)]
+pub struct SentryHubExtension(pub Arc<Hub>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SentryHubExtension
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<SentryHubExtension>()
+            .cloned()
+            .unwrap_or_else(|| SentryHubExtension(Hub::current())))
+    }
+}
+
+/// Extractor wrapper that captures the rejection of the wrapped extractor.
+///
+/// The rejection is captured as an error on the hub that is bound to the request, and then
+/// returned unchanged, so the response sent to the client is the same as without the wrapper.
+///
+/// # Example
+///
+/// ```
+/// use axum::extract::Path;
+/// use sentry_axum::CaptureRejection;
+///
+/// async fn handler(CaptureRejection(Path(id)): CaptureRejection<Path<u32>>) -> String {
+///     format!("User {}", id)
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaptureRejection<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequestParts<S> for CaptureRejection<T>
+where
+    S: Send + Sync,
+    T: FromRequestParts<S>,
+    T::Rejection: Error + 'static,
+{
+    type Rejection = T::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let hub = hub_from_extensions(&parts.extensions);
+        match T::from_request_parts(parts, state).await {
+            Ok(value) => Ok(CaptureRejection(value)),
+            Err(rejection) => Err(capture_rejection(&hub, rejection)),
+        }
+    }
+}
+
+#[async_trait]
+impl<S, B, T> FromRequest<S, B> for CaptureRejection<T>
+where
+    S: Send + Sync,
+    B: Send + 'static,
+    T: FromRequest<S, B>,
+    T::Rejection: Error + 'static,
+{
+    type Rejection = T::Rejection;
+
+    async fn from_request(request: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let hub = hub_from_extensions(request.extensions());
+        match T::from_request(request, state).await {
+            Ok(value) => Ok(CaptureRejection(value)),
+            Err(rejection) => Err(capture_rejection(&hub, rejection)),
+        }
+    }
+}
+
+/// Returns the hub bound to the request, or the current hub.
+fn hub_from_extensions(extensions: &http_::Extensions) -> Arc<Hub> {
+    extensions
+        .get::<SentryHubExtension>()
+        .map_or_else(Hub::current, |ext| ext.0.clone())
+}
+
+/// Captures the rejection as an error, and passes it on.
+fn capture_rejection<R: Error + 'static>(hub: &Hub, rejection: R) -> R {
+    hub.capture_error(&rejection);
+    rejection
+}
@@ -0,0 +1,298 @@
+//! Adds support for the [`axum`](https://docs.rs/axum) web framework.
+//!
+//! This builds on top of the [`sentry-tower`](sentry_tower) integration, which binds a hub per
+//! request and attaches the request interface to captured events.  On top of that, this crate
+//! provides:
+//!
+//! * The [`SentryAxumLayer`], which names the transaction started by the
+//!   [`SentryHttpLayer`](sentry_tower::SentryHttpLayer) after the [`MatchedPath`] of the
+//!   route that handles the request, and reports panics in handlers.
+//! * The [`SentryHubExtension`] extractor, which gives handlers access to the hub that is
+//!   bound to the request, for example to move it into spawned tasks.
+//! * The [`CaptureRejection`] extractor wrapper, which reports the rejection of the wrapped
+//!   extractor to Sentry.
+//!
+//! # Example
+//!
+//! ```
+//! use axum::routing::get;
+//! use axum::Router;
+//! use sentry_axum::{SentryAxumLayer, SentryHubExtension};
+//! use sentry_tower::{SentryHttpLayer, SentryLayer};
+//! use tower::ServiceBuilder;
+//!
+//! async fn handler(SentryHubExtension(hub): SentryHubExtension) -> &'static str {
+//!     hub.capture_message("Something is not well", sentry_core::Level::Warning);
+//!     "Hello World"
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/users/:id", get(handler))
+//!     .route_layer(SentryAxumLayer::new())
+//!     .layer(
+//!         ServiceBuilder::new()
+//!             .layer(SentryLayer::new_from_top())
+//!             .layer(SentryHttpLayer::new()),
+//!     );
+//! ```
+//!
+//! The [`MatchedPath`] is only known after routing, which is why the [`SentryAxumLayer`]
+//! needs to be added using `Router::route_layer`, whereas the `sentry-tower` layers need to
+//! wrap the whole router.
+//!
+//! [`MatchedPath`]: axum::extract::MatchedPath
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::extract::MatchedPath;
+use http_::Request;
+use sentry_core::{Hub, TransactionOrSpan};
+use tower_layer::Layer;
+use tower_service::Service;
+
+mod extract;
+
+pub use crate::extract::{CaptureRejection, SentryHubExtension};
+
+/// Axum layer that names transactions after the matched route, and reports panics.
+#[derive(Clone, Debug)]
+pub struct SentryAxumLayer {
+    capture_panics: bool,
+}
+
+impl SentryAxumLayer {
+    /// Creates a new layer.
+    pub fn new() -> Self {
+        Self {
+            capture_panics: true,
+        }
+    }
+
+    /// Enables or disables reporting of panics in handlers.
+    ///
+    /// Panics that were already reported by the panic handler integration are not
+    /// reported a second time.  The default is to report panics.
+    pub fn capture_panics(mut self, val: bool) -> Self {
+        self.capture_panics = val;
+        self
+    }
+}
+
+impl Default for SentryAxumLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for SentryAxumLayer {
+    type Service = SentryAxumService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SentryAxumService {
+            service,
+            capture_panics: self.capture_panics,
+        }
+    }
+}
+
+/// Axum service that names transactions after the matched route, and reports panics.
+#[derive(Clone, Debug)]
+pub struct SentryAxumService<S> {
+    service: S,
+    capture_panics: bool,
+}
+
+impl<S, B> Service<Request<B>> for SentryAxumService<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SentryAxumFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        let hub = Hub::current();
+
+        if let Some(path) = request.extensions().get::<MatchedPath>() {
+            let transaction = format!("{} {}", request.method(), path.as_str());
+            hub.configure_scope(|scope| {
+                scope.set_transaction(Some(&transaction));
+                // rename the transaction that the `SentryHttpLayer` started for the request
+                if let Some(TransactionOrSpan::Transaction(span)) = scope.get_span() {
+                    span.set_name(&transaction);
+                }
+            });
+        }
+        request
+            .extensions_mut()
+            .insert(SentryHubExtension(hub.clone()));
+
+        SentryAxumFuture {
+            future: self.service.call(request),
+            hub,
+            capture_panics: self.capture_panics,
+        }
+    }
+}
+
+/// The future returned by [`SentryAxumService`].
+#[derive(Debug)]
+pub struct SentryAxumFuture<F> {
+    future: F,
+    hub: Arc<Hub>,
+    capture_panics: bool,
+}
+
+impl<F> Future for SentryAxumFuture<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // https://doc.rust-lang.org/std/pin/index.html#pinning-is-structural-for-field
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+
+        if !this.capture_panics {
+            return future.poll(cx);
+        }
+        match sentry_panic::report_unwind(&this.hub, AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(poll) => poll,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use futures::executor::block_on;
+    use sentry::protocol::EnvelopeItem;
+    use sentry::Level;
+    use sentry_tower::{SentryHttpLayer, SentryLayer};
+    use tower::{ServiceBuilder, ServiceExt};
+
+    use super::*;
+
+    fn app(router: Router) -> Router {
+        router.route_layer(SentryAxumLayer::new()).layer(
+            ServiceBuilder::new()
+                .layer(SentryLayer::new_from_top())
+                .layer(SentryHttpLayer::new()),
+        )
+    }
+
+    #[test]
+    fn test_matched_path_transaction() {
+        let events = sentry::test::with_captured_events(|| {
+            let app = app(Router::new().route(
+                "/users/:id",
+                get(|SentryHubExtension(hub): SentryHubExtension| async move {
+                    hub.capture_message("Message", Level::Warning);
+                }),
+            ));
+
+            let request = Request::get("/users/42").body(Body::empty()).unwrap();
+            let response = block_on(app.oneshot(request)).unwrap();
+            assert!(response.status().is_success());
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].transaction, Some("GET /users/:id".into()));
+        assert_eq!(events[0].message, Some("Message".into()));
+    }
+
+    #[test]
+    fn test_matched_path_server_transaction() {
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let app = app(Router::new().route("/users/:id", get(|| async {})));
+
+                let request = Request::get("/users/42").body(Body::empty()).unwrap();
+                let response = block_on(app.oneshot(request)).unwrap();
+                assert!(response.status().is_success());
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(envelopes.len(), 1);
+        let transaction = match envelopes[0].items().next() {
+            Some(EnvelopeItem::Transaction(transaction)) => transaction,
+            _ => panic!("expected a transaction"),
+        };
+        assert_eq!(transaction.name.as_deref(), Some("GET /users/:id"));
+    }
+
+    #[test]
+    fn test_captured_rejection() {
+        #[derive(serde::Deserialize)]
+        struct Payload {
+            #[allow(unused)]
+            name: String,
+        }
+
+        let events = sentry::test::with_captured_events(|| {
+            let app = app(Router::new().route(
+                "/users",
+                axum::routing::post(
+                    |CaptureRejection(_): CaptureRejection<Json<Payload>>| async {},
+                ),
+            ));
+
+            let request = Request::post("/users")
+                .header("content-type", "application/json")
+                .body(Body::from("{}"))
+                .unwrap();
+            let response = block_on(app.oneshot(request)).unwrap();
+            assert!(response.status().is_client_error());
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].transaction, Some("POST /users".into()));
+        // the rejection is the outermost error of the chain
+        assert_eq!(events[0].exception.last().unwrap().ty, "JsonDataError");
+    }
+
+    #[test]
+    fn test_handler_panic() {
+        async fn handler() -> &'static str {
+            sentry::capture_message("Handling request", Level::Info);
+            panic!("Handler panicked")
+        }
+
+        let events = sentry::test::with_captured_events(|| {
+            let app = app(Router::new().route("/panic", get(handler)));
+
+            let request = Request::get("/panic").body(Body::empty()).unwrap();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| block_on(app.oneshot(request))));
+            assert!(result.is_err());
+        });
+
+        assert_eq!(events.len(), 2);
+        let event = &events[1];
+        assert_eq!(event.level, Level::Fatal);
+        assert_eq!(event.transaction, Some("GET /panic".into()));
+        assert_eq!(
+            event.exception[0].value.as_deref(),
+            Some("Handler panicked")
+        );
+    }
+}
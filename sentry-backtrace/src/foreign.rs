@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use sentry_core::protocol::{Event, Frame, Stacktrace};
+use sentry_core::{ClientOptions, Integration};
+
+thread_local! {
+    static FOREIGN_FRAMES: RefCell<Vec<Frame>> = RefCell::new(Vec::new());
+}
+
+/// Registers a frame of an embedded language for the current thread.
+///
+/// Applications that embed a scripting runtime, such as Python or JavaScript, call this
+/// when a script calls into Rust, with the file, line and function of the script.  While
+/// the returned guard is alive, the [`ForeignFramesIntegration`] adds the frame to the
+/// stacktraces of events captured on this thread, so that the script that led to an error
+/// shows up next to the Rust frames.
+///
+/// Frames are kept in the order they are registered, so nested calls between scripts and
+/// Rust are represented by registering a frame per call.  The guard has to be dropped on
+/// the same thread, and removes the frame and all frames registered after it.
+///
+/// # Examples
+///
+/// ```
+/// use sentry_backtrace::{push_foreign_frame, Frame};
+///
+/// fn call_from_script(file: &str, line: u64, function: &str) {
+///     let _frame = push_foreign_frame(Frame {
+///         function: Some(function.into()),
+///         filename: Some(file.into()),
+///         lineno: Some(line),
+///         in_app: Some(true),
+///         ..Default::default()
+///     });
+///     // errors captured here include the frame of the script
+/// }
+/// ```
+pub fn push_foreign_frame(frame: Frame) -> ForeignFrameGuard {
+    let depth = FOREIGN_FRAMES.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        frames.push(frame);
+        frames.len() - 1
+    });
+    ForeignFrameGuard {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+/// Removes a frame registered with [`push_foreign_frame`] when dropped.
+#[must_use = "the frame is removed when the guard is dropped"]
+#[derive(Debug)]
+pub struct ForeignFrameGuard {
+    depth: usize,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ForeignFrameGuard {
+    fn drop(&mut self) {
+        FOREIGN_FRAMES.with(|frames| frames.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// Returns the frames of embedded languages registered on the current thread.
+///
+/// The frames are ordered from the outermost to the innermost call, like the frames of a
+/// [`Stacktrace`].
+pub fn foreign_frames() -> Vec<Frame> {
+    FOREIGN_FRAMES.with(|frames| frames.borrow().clone())
+}
+
+/// Adds the frames registered on the current thread to a `Stacktrace`.
+///
+/// As the embedded language calls into Rust, its frames are inserted before the Rust
+/// frames, that is, as the outermost frames of the stacktrace.
+pub fn add_foreign_frames(stacktrace: &mut Stacktrace) {
+    FOREIGN_FRAMES.with(|frames| {
+        let frames = frames.borrow();
+        if !frames.is_empty() {
+            stacktrace.frames.splice(0..0, frames.iter().cloned());
+        }
+    })
+}
+
+/// Integration to add frames of embedded languages to Events.
+///
+/// This adds the frames registered with [`push_foreign_frame`] to the stacktraces of the
+/// exceptions and the current thread of events.  It does nothing unless frames are
+/// registered, and needs to run before the [`ProcessStacktraceIntegration`], so that the
+/// frames are classified as in-app and get their source context as well.
+///
+/// [`ProcessStacktraceIntegration`]: crate::ProcessStacktraceIntegration
+#[derive(Debug, Default)]
+pub struct ForeignFramesIntegration;
+
+impl ForeignFramesIntegration {
+    /// Creates a new Integration to add frames of embedded languages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Integration for ForeignFramesIntegration {
+    fn name(&self) -> &'static str {
+        "foreign-frames"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        if FOREIGN_FRAMES.with(|frames| frames.borrow().is_empty()) {
+            return Some(event);
+        }
+        let exception_stacktraces = event
+            .exception
+            .iter_mut()
+            .filter_map(|exc| exc.stacktrace.as_mut());
+        let thread_stacktraces = event
+            .threads
+            .values
+            .iter_mut()
+            .filter(|thread| thread.current)
+            .filter_map(|thread| thread.stacktrace.as_mut());
+        for stacktrace in exception_stacktraces.chain(thread_stacktraces) {
+            add_foreign_frames(stacktrace);
+        }
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(function: &str) -> Frame {
+        Frame {
+            function: Some(function.into()),
+            ..Default::default()
+        }
+    }
+
+    fn functions(stacktrace: &Stacktrace) -> Vec<&str> {
+        stacktrace
+            .frames
+            .iter()
+            .map(|frame| frame.function.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_foreign_frames() {
+        let mut stacktrace = Stacktrace {
+            frames: vec![frame("main"), frame("my_crate::callback")],
+            ..Default::default()
+        };
+
+        let outer = push_foreign_frame(frame("run_script"));
+        {
+            let _inner = push_foreign_frame(frame("on_click"));
+            add_foreign_frames(&mut stacktrace);
+        }
+        assert_eq!(
+            functions(&stacktrace),
+            ["run_script", "on_click", "main", "my_crate::callback"]
+        );
+        assert_eq!(foreign_frames().len(), 1);
+
+        let _leaked = push_foreign_frame(frame("leaked"));
+        drop(outer);
+        assert!(foreign_frames().is_empty());
+    }
+}
@@ -3,15 +3,16 @@ use std::thread;
 use sentry_core::protocol::{Event, Thread};
 use sentry_core::{ClientOptions, Integration};
 
-use crate::current_stacktrace;
 use crate::process::process_event_stacktrace;
+use crate::{capture_stacktrace, current_stacktrace};
 
 /// Integration to process Event stacktraces.
 ///
-/// This integration will trim backtraces, depending on the `trim_backtraces`
-/// and `extra_border_frames` options.
+/// This integration will trim backtraces, depending on the `trim_backtraces`,
+/// `backtrace_trimmer` and `extra_border_frames` options.
 /// It will then classify each frame according to the `in_app_include` and
-/// `in_app_exclude` options.
+/// `in_app_exclude` options, and attach the source context of in-app frames
+/// depending on the `source_context_lines` option.
 #[derive(Debug, Default)]
 pub struct ProcessStacktraceIntegration;
 
@@ -34,6 +35,13 @@ impl Integration for ProcessStacktraceIntegration {
     ) -> Option<Event<'static>> {
         for exc in &mut event.exception {
             if let Some(ref mut stacktrace) = exc.stacktrace {
+                // Unresolved stacktraces are processed once they are resolved.
+                #[cfg(feature = "backtrace")]
+                {
+                    if crate::is_unresolved(stacktrace) {
+                        continue;
+                    }
+                }
                 process_event_stacktrace(stacktrace, &options);
             }
         }
@@ -66,7 +74,8 @@ impl Integration for AttachStacktraceIntegration {
         options: &ClientOptions,
     ) -> Option<Event<'static>> {
         if options.attach_stacktrace && event.exception.is_empty() {
-            let thread = current_thread(true);
+            let mut thread = current_thread(false);
+            thread.stacktrace = capture_stacktrace(options.defer_symbolication);
             if thread.stacktrace.is_some() {
                 event.threads.values.push(thread);
             }
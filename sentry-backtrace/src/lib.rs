@@ -2,26 +2,83 @@
 //!
 //! Exposes functions to capture, process and convert/parse stacktraces, as well
 //! as integrations to process event stacktraces.
+//!
+//! Applications that embed a scripting runtime can register the frames of scripts with
+//! [`push_foreign_frame`], which are then added to the stacktraces of captured events, so
+//! that stacks mixing Rust and scripts are readable.
+//!
+//! Backtraces are captured using the `backtrace` crate by default.  With the
+//! `std-backtrace` feature and without the default `backtrace` feature,
+//! `std::backtrace::Backtrace` is used instead.
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
 #![warn(missing_docs)]
 
+mod foreign;
 mod integration;
 mod parse;
 mod process;
+#[cfg(feature = "backtrace")]
+mod resolve;
+mod source;
 mod trim;
 mod utils;
 
+pub use crate::foreign::{
+    add_foreign_frames, foreign_frames, push_foreign_frame, ForeignFrameGuard,
+    ForeignFramesIntegration,
+};
 pub use crate::integration::{
     current_thread, AttachStacktraceIntegration, ProcessStacktraceIntegration,
 };
 pub use crate::parse::parse_stacktrace;
-pub use crate::process::{backtrace_to_stacktrace, process_event_stacktrace};
+#[cfg(feature = "std-backtrace")]
+pub use crate::parse::std_backtrace_to_stacktrace;
+pub use crate::process::process_event_stacktrace;
+#[cfg(feature = "backtrace")]
+pub use crate::resolve::{
+    addresses_to_stacktrace, backtrace_to_stacktrace, current_unresolved_stacktrace, is_unresolved,
+    resolve_event_stacktraces, resolve_stacktrace,
+};
 pub use crate::trim::trim_stacktrace;
 pub use sentry_core::protocol::{Frame, Stacktrace};
 
 /// Returns the current backtrace as sentry stacktrace.
+///
+/// This uses the `backtrace` crate if the `backtrace` feature is enabled, and
+/// otherwise `std::backtrace::Backtrace` if the `std-backtrace` feature is
+/// enabled.  Returns `None` if neither is enabled.
 pub fn current_stacktrace() -> Option<Stacktrace> {
-    backtrace_to_stacktrace(&backtrace::Backtrace::new())
+    #[cfg(feature = "backtrace")]
+    {
+        let mut stacktrace = current_unresolved_stacktrace()?;
+        resolve_stacktrace(&mut stacktrace);
+        Some(stacktrace)
+    }
+    #[cfg(all(feature = "std-backtrace", not(feature = "backtrace")))]
+    {
+        std_backtrace_to_stacktrace(&std::backtrace::Backtrace::force_capture())
+    }
+    #[cfg(not(any(feature = "backtrace", feature = "std-backtrace")))]
+    {
+        None
+    }
+}
+
+/// Returns the current backtrace, optionally without resolving symbols.
+///
+/// With `defer_symbolication` set and the `backtrace` feature enabled, only the
+/// instruction addresses are captured and symbols are resolved later using
+/// [`resolve_event_stacktraces`].  Otherwise this is the same as
+/// [`current_stacktrace`].
+pub fn capture_stacktrace(defer_symbolication: bool) -> Option<Stacktrace> {
+    #[cfg(feature = "backtrace")]
+    {
+        if defer_symbolication {
+            return current_unresolved_stacktrace();
+        }
+    }
+    let _ = defer_symbolication;
+    current_stacktrace()
 }
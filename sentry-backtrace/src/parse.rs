@@ -6,7 +6,7 @@ use crate::{Frame, Stacktrace};
 lazy_static::lazy_static! {
     static ref FRAME_RE: Regex = Regex::new(r#"(?xm)
         ^
-            \s*(?:\d+:)?\s*                      # frame number (missing for inline)
+            \s*(?:(?P<num>\d+):)?\s*             # frame number (missing for inline)
 
             (?:
                 (?P<addr_old>0x[a-f0-9]+)        # old style address prefix
@@ -24,17 +24,30 @@ lazy_static::lazy_static! {
                 \s+at\s                          # padded "at" in new line
                 (?P<path>[^\r\n]+?)              # path to source file
                 (?::(?P<lineno>\d+))?            # optional source line
+                (?::(?P<colno>\d+))?             # optional source column
             )?
         $
     "#).unwrap();
 }
 
 /// Parses a backtrace string into a Sentry `Stacktrace`.
+///
+/// This understands the text format of both `std::backtrace::Backtrace` and the
+/// `backtrace` crate, so stacktraces can be recovered from log files or
+/// reports passed through FFI layers.  Any text before the first numbered
+/// frame, such as the panic message and the `stack backtrace:` header, as well
+/// as trailing notes are ignored.
 pub fn parse_stacktrace(bt: &str) -> Option<Stacktrace> {
     let mut last_address = None;
+    let mut in_frames = false;
 
     let frames = FRAME_RE
         .captures_iter(&bt)
+        .filter(|captures| {
+            // frames without a number are inlined into the previous frame
+            in_frames |= captures.name("num").is_some();
+            in_frames && !captures["symbol"].starts_with("note: ")
+        })
         .map(|captures| {
             let abs_path = captures.name("path").map(|m| m.as_str().to_string());
             let filename = abs_path.as_ref().map(|p| filename(p).to_string());
@@ -63,6 +76,9 @@ pub fn parse_stacktrace(bt: &str) -> Option<Stacktrace> {
                 lineno: captures
                     .name("lineno")
                     .map(|x| x.as_str().parse::<u64>().unwrap()),
+                colno: captures
+                    .name("colno")
+                    .map(|x| x.as_str().parse::<u64>().unwrap()),
                 ..Default::default()
             }
         })
@@ -70,3 +86,79 @@ pub fn parse_stacktrace(bt: &str) -> Option<Stacktrace> {
 
     Stacktrace::from_frames_reversed(frames)
 }
+
+/// Converts a `std::backtrace::Backtrace` into a Rust `Stacktrace`.
+///
+/// The standard library does not expose the frames of a backtrace, so this
+/// parses its textual representation.  Returns `None` if the backtrace was
+/// not captured.
+#[cfg(feature = "std-backtrace")]
+pub fn std_backtrace_to_stacktrace(bt: &std::backtrace::Backtrace) -> Option<Stacktrace> {
+    if bt.status() != std::backtrace::BacktraceStatus::Captured {
+        return None;
+    }
+    parse_stacktrace(&bt.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_std_backtrace() {
+        let bt = "   0: std::backtrace::Backtrace::force_capture
+             at /rustc/abc/library/std/src/backtrace.rs:310:9
+   1: app::main
+             at ./src/main.rs:12:5
+   2: core::ops::function::FnOnce::call_once
+             at /rustc/abc/library/core/src/ops/function.rs:250:5";
+
+        let stacktrace = parse_stacktrace(bt).unwrap();
+        assert_eq!(stacktrace.frames.len(), 3);
+        let frame = &stacktrace.frames[1];
+        assert_eq!(frame.function.as_deref(), Some("app::main"));
+        assert_eq!(frame.abs_path.as_deref(), Some("./src/main.rs"));
+        assert_eq!(frame.filename.as_deref(), Some("main.rs"));
+        assert_eq!(frame.lineno, Some(12));
+        assert_eq!(frame.colno, Some(5));
+    }
+
+    #[test]
+    fn test_parse_without_column() {
+        let bt = "   0: app::main
+             at src/main.rs:12";
+
+        let stacktrace = parse_stacktrace(bt).unwrap();
+        let frame = &stacktrace.frames[0];
+        assert_eq!(frame.abs_path.as_deref(), Some("src/main.rs"));
+        assert_eq!(frame.lineno, Some(12));
+        assert_eq!(frame.colno, None);
+    }
+
+    #[test]
+    fn test_parse_panic_output() {
+        let bt = "thread 'main' panicked at 'boom', src/main.rs:2:5
+stack backtrace:
+   0: rust_begin_unwind
+             at /rustc/abc/library/std/src/panicking.rs:493:5
+   1: app::main
+             at ./src/main.rs:2:5
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+
+        let stacktrace = parse_stacktrace(bt).unwrap();
+        let functions: Vec<_> = stacktrace
+            .frames
+            .iter()
+            .map(|frame| frame.function.as_deref().unwrap())
+            .collect();
+        assert_eq!(functions, vec!["app::main", "rust_begin_unwind"]);
+    }
+
+    #[cfg(feature = "std-backtrace")]
+    #[test]
+    fn test_std_backtrace_to_stacktrace() {
+        let bt = std::backtrace::Backtrace::force_capture();
+        let stacktrace = std_backtrace_to_stacktrace(&bt).unwrap();
+        assert!(!stacktrace.frames.is_empty());
+    }
+}
@@ -1,27 +1,26 @@
-use std::borrow::Cow;
-
-use backtrace::Backtrace;
 use sentry_core::ClientOptions;
 
-use crate::trim::{is_sys_function, trim_stacktrace};
-use crate::utils::{
-    demangle_symbol, filename, function_starts_with, parse_crate_name, strip_symbol,
-};
-use crate::{Frame, Stacktrace};
+use crate::source::add_source_context;
+use crate::trim::{is_sys_function, trim_stacktrace_at};
+use crate::utils::{function_starts_with, parse_crate_name};
+use crate::Stacktrace;
 
 /// Processes a `Stacktrace`.
 ///
-/// Trims a `Stacktrace` and marks frames as in-app based on the provided
-/// `ClientOptions`.
+/// Trims a `Stacktrace`, marks frames as in-app and attaches the source context
+/// of in-app frames based on the provided `ClientOptions`.
 pub fn process_event_stacktrace(stacktrace: &mut Stacktrace, options: &ClientOptions) {
     // automatically trim backtraces
-    if options.trim_backtraces {
-        trim_stacktrace(stacktrace, |frame, _| {
-            if let Some(ref func) = frame.function {
-                options.extra_border_frames.contains(&func.as_str())
-            } else {
-                false
+    let trimmer = &options.backtrace_trimmer;
+    if options.trim_backtraces && trimmer.is_enabled() {
+        trim_stacktrace_at(stacktrace, |frame, _| match frame.function {
+            Some(ref func) => {
+                trimmer
+                    .border_frames()
+                    .any(|border| function_starts_with(func, border))
+                    || options.extra_border_frames.contains(&func.as_str())
             }
+            None => false,
         })
     }
 
@@ -84,55 +83,48 @@ pub fn process_event_stacktrace(stacktrace: &mut Stacktrace, options: &ClientOpt
             }
         }
     }
+
+    if options.source_context_lines > 0 {
+        add_source_context(stacktrace, options.source_context_lines);
+    }
 }
 
-/// Convert a `backtrace::Backtrace` into a Rust `Stacktrace`
-pub fn backtrace_to_stacktrace(bt: &Backtrace) -> Option<Stacktrace> {
-    let frames = bt
-        .frames()
-        .iter()
-        .flat_map(|frame| {
-            // For each frame, there may be multiple symbols if a function was inlined, so
-            // add an entry for each symbol.
-            let symbols = frame.symbols();
-            symbols
-                .iter()
-                .map(move |sym| {
-                    let abs_path = sym.filename().map(|m| m.to_string_lossy().to_string());
-                    let filename = abs_path.as_ref().map(|p| filename(p).to_string());
-                    let real_symbol = sym
-                        .name()
-                        .map_or(Cow::Borrowed("<unknown>"), |n| Cow::Owned(n.to_string()));
-                    let symbol = strip_symbol(&real_symbol);
-                    let function = demangle_symbol(symbol);
-                    Frame {
-                        symbol: if symbol != function {
-                            Some(symbol.into())
-                        } else {
-                            None
-                        },
-                        function: Some(function),
-                        instruction_addr: Some(frame.ip().into()),
-                        abs_path,
-                        filename,
-                        lineno: sym.lineno().map(u64::from),
-                        colno: None,
-                        ..Default::default()
-                    }
-
-                    // If there were no symbols at all, make sure to add at least one frame, as we
-                    // may be able to symbolicate it on the server.
-                })
-                .chain(if symbols.is_empty() {
-                    Some(Frame {
-                        instruction_addr: Some(frame.ip().into()),
-                        function: Some("<unknown>".into()),
-                        ..Default::default()
-                    })
-                } else {
-                    None
-                })
-        })
-        .collect();
-    Stacktrace::from_frames_reversed(frames)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frame;
+    use sentry_core::BacktraceTrimmer;
+
+    fn make_stacktrace() -> Stacktrace {
+        let frames = ["main", "my_crate::report", "my_crate::inner"]
+            .iter()
+            .map(|&function| Frame {
+                function: Some(function.into()),
+                ..Default::default()
+            })
+            .collect();
+        Stacktrace {
+            frames,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_custom_border_frames() {
+        let options = ClientOptions {
+            backtrace_trimmer: BacktraceTrimmer::new().add_border_frame("my_crate::report"),
+            ..Default::default()
+        };
+        let mut stacktrace = make_stacktrace();
+        process_event_stacktrace(&mut stacktrace, &options);
+        assert_eq!(stacktrace.frames.len(), 1);
+
+        let options = ClientOptions {
+            backtrace_trimmer: BacktraceTrimmer::disabled().add_border_frame("my_crate::report"),
+            ..Default::default()
+        };
+        let mut stacktrace = make_stacktrace();
+        process_event_stacktrace(&mut stacktrace, &options);
+        assert_eq!(stacktrace.frames.len(), 3);
+    }
 }
@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::path::Path;
+use std::sync::Mutex;
+
+use backtrace::{Backtrace, SymbolName};
+use sentry_core::protocol::Event;
+use sentry_core::ClientOptions;
+
+use crate::process::process_event_stacktrace;
+use crate::utils::{demangle_symbol, filename, strip_symbol};
+use crate::{Frame, Stacktrace};
+
+/// Convert a `backtrace::Backtrace` into a Rust `Stacktrace`
+pub fn backtrace_to_stacktrace(bt: &Backtrace) -> Option<Stacktrace> {
+    let frames = bt
+        .frames()
+        .iter()
+        .flat_map(|frame| {
+            // For each frame, there may be multiple symbols if a function was inlined, so
+            // add an entry for each symbol.
+            let symbols = frame.symbols();
+            symbols
+                .iter()
+                .map(move |sym| symbol_frame(frame.ip(), sym.name(), sym.filename(), sym.lineno()))
+                // If there were no symbols at all, make sure to add at least one frame, as we
+                // may be able to symbolicate it on the server.
+                .chain(if symbols.is_empty() {
+                    Some(unknown_frame(frame.ip()))
+                } else {
+                    None
+                })
+        })
+        .collect();
+    Stacktrace::from_frames_reversed(frames)
+}
+
+/// Resolves a list of instruction addresses into a Rust `Stacktrace`.
+///
+/// The addresses are expected in the order they were walked, with the innermost
+/// frame first.  This is useful when the addresses were collected in a context
+/// where symbols can not be resolved, such as a signal handler.
+pub fn addresses_to_stacktrace(addresses: &[usize]) -> Option<Stacktrace> {
+    let frames = addresses
+        .iter()
+        .flat_map(|&addr| resolve_frames(addr as *mut c_void))
+        .collect();
+    Stacktrace::from_frames_reversed(frames)
+}
+
+/// The maximum number of instruction addresses kept in the symbol cache.
+const SYMBOL_CACHE_SIZE: usize = 1024;
+
+/// A least recently used cache of resolved frames, keyed by instruction address.
+#[derive(Default)]
+struct SymbolCache {
+    entries: HashMap<usize, (u64, Vec<Frame>)>,
+    tick: u64,
+}
+
+impl SymbolCache {
+    fn get(&mut self, ip: usize) -> Option<Vec<Frame>> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(&ip).map(|entry| {
+            entry.0 = tick;
+            entry.1.clone()
+        })
+    }
+
+    fn insert(&mut self, ip: usize, frames: Vec<Frame>) {
+        if self.entries.len() >= SYMBOL_CACHE_SIZE && !self.entries.contains_key(&ip) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (tick, _))| *tick)
+                .map(|(ip, _)| *ip);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(ip, (self.tick, frames));
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SYMBOL_CACHE: Mutex<SymbolCache> = Mutex::new(SymbolCache::default());
+}
+
+/// Resolves the frames of an instruction address, innermost first.
+///
+/// There may be multiple frames if functions were inlined.  Results are cached
+/// per address, so repeated captures from the same call sites are cheap.
+fn resolve_frames(ip: *mut c_void) -> Vec<Frame> {
+    let key = ip as usize;
+    if let Some(frames) = SYMBOL_CACHE
+        .lock()
+        .ok()
+        .and_then(|mut cache| cache.get(key))
+    {
+        return frames;
+    }
+
+    let mut frames = Vec::new();
+    backtrace::resolve(ip, |sym| {
+        frames.push(symbol_frame(ip, sym.name(), sym.filename(), sym.lineno()));
+    });
+    if frames.is_empty() {
+        frames.push(unknown_frame(ip));
+    }
+
+    if let Ok(mut cache) = SYMBOL_CACHE.lock() {
+        cache.insert(key, frames.clone());
+    }
+    frames
+}
+
+/// Creates a `Frame` from a resolved symbol.
+fn symbol_frame(
+    ip: *mut c_void,
+    name: Option<SymbolName<'_>>,
+    path: Option<&Path>,
+    lineno: Option<u32>,
+) -> Frame {
+    let abs_path = path.map(|m| m.to_string_lossy().to_string());
+    let filename = abs_path.as_ref().map(|p| filename(p).to_string());
+    let real_symbol = name.map_or(Cow::Borrowed("<unknown>"), |n| Cow::Owned(n.to_string()));
+    let symbol = strip_symbol(&real_symbol);
+    let function = demangle_symbol(symbol);
+    Frame {
+        symbol: if symbol != function {
+            Some(symbol.into())
+        } else {
+            None
+        },
+        function: Some(function),
+        instruction_addr: Some(ip.into()),
+        abs_path,
+        filename,
+        lineno: lineno.map(u64::from),
+        colno: None,
+        ..Default::default()
+    }
+}
+
+/// Creates a `Frame` for an address without symbols.
+fn unknown_frame(ip: *mut c_void) -> Frame {
+    Frame {
+        instruction_addr: Some(ip.into()),
+        function: Some("<unknown>".into()),
+        ..Default::default()
+    }
+}
+
+/// Captures the current backtrace without resolving any symbols.
+///
+/// The returned frames only carry an `instruction_addr`.  Use
+/// [`resolve_stacktrace`] to resolve them at a later point.
+pub fn current_unresolved_stacktrace() -> Option<Stacktrace> {
+    let bt = Backtrace::new_unresolved();
+    let frames = bt
+        .frames()
+        .iter()
+        .map(|frame| Frame {
+            instruction_addr: Some(frame.ip().into()),
+            ..Default::default()
+        })
+        .collect();
+    Stacktrace::from_frames_reversed(frames)
+}
+
+/// Returns `true` if the stacktrace contains frames that were not resolved yet.
+pub fn is_unresolved(stacktrace: &Stacktrace) -> bool {
+    stacktrace
+        .frames
+        .iter()
+        .any(|frame| frame.function.is_none() && frame.instruction_addr.is_some())
+}
+
+/// Resolves the symbols of all unresolved frames in the stacktrace.
+///
+/// Frames that already have a function name are left untouched.  Unresolved
+/// frames may expand into multiple frames if functions were inlined.
+pub fn resolve_stacktrace(stacktrace: &mut Stacktrace) {
+    let mut frames = Vec::with_capacity(stacktrace.frames.len());
+    for frame in stacktrace.frames.drain(..) {
+        let addr = match frame.instruction_addr {
+            Some(addr) if frame.function.is_none() => addr,
+            _ => {
+                frames.push(frame);
+                continue;
+            }
+        };
+        // Frames are stored with the outermost frame first, but symbols are
+        // resolved innermost first.
+        let ip = addr.0 as usize as *mut c_void;
+        frames.extend(resolve_frames(ip).into_iter().rev());
+    }
+    stacktrace.frames = frames;
+}
+
+/// Resolves all unresolved stacktraces of an event.
+///
+/// This is used when `defer_symbolication` is enabled, in which case the
+/// stacktraces of exceptions are processed here rather than by the
+/// `ProcessStacktraceIntegration`.
+pub fn resolve_event_stacktraces(event: &mut Event<'_>, options: &ClientOptions) {
+    for exc in &mut event.exception {
+        if let Some(ref mut stacktrace) = exc.stacktrace {
+            if is_unresolved(stacktrace) {
+                resolve_stacktrace(stacktrace);
+                process_event_stacktrace(stacktrace, options);
+            }
+        }
+    }
+    for thread in &mut event.threads {
+        if let Some(ref mut stacktrace) = thread.stacktrace {
+            resolve_stacktrace(stacktrace);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_unresolved_stacktrace() {
+        let mut stacktrace = current_unresolved_stacktrace().unwrap();
+        assert!(is_unresolved(&stacktrace));
+        assert!(stacktrace
+            .frames
+            .iter()
+            .all(|frame| frame.function.is_none()));
+
+        resolve_stacktrace(&mut stacktrace);
+        assert!(!is_unresolved(&stacktrace));
+        assert!(stacktrace
+            .frames
+            .iter()
+            .all(|frame| frame.function.is_some()));
+    }
+
+    #[test]
+    fn test_symbol_cache() {
+        let ip = test_symbol_cache as usize;
+        let frames = resolve_frames(ip as *mut c_void);
+        let cached = SYMBOL_CACHE.lock().unwrap().get(ip);
+        assert_eq!(cached, Some(frames));
+    }
+
+    #[test]
+    fn test_symbol_cache_eviction() {
+        let mut cache = SymbolCache::default();
+        for ip in 0..SYMBOL_CACHE_SIZE {
+            cache.insert(ip, Vec::new());
+        }
+        // touch the first entry so the second one is evicted instead
+        assert!(cache.get(0).is_some());
+        cache.insert(SYMBOL_CACHE_SIZE, Vec::new());
+        assert_eq!(cache.entries.len(), SYMBOL_CACHE_SIZE);
+        assert!(cache.get(0).is_some());
+        assert!(cache.get(1).is_none());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::Stacktrace;
+
+/// Attaches the lines surrounding the line of each in-app frame.
+///
+/// The source files are read from the `abs_path` of the frames, and frames
+/// whose source file can not be read are left untouched.
+pub fn add_source_context(stacktrace: &mut Stacktrace, context_lines: usize) {
+    let mut files: HashMap<String, Option<Vec<String>>> = HashMap::new();
+
+    for frame in &mut stacktrace.frames {
+        if frame.in_app != Some(true) || frame.context_line.is_some() {
+            continue;
+        }
+        let (abs_path, lineno) = match (&frame.abs_path, frame.lineno) {
+            (Some(abs_path), Some(lineno)) if lineno > 0 => (abs_path, lineno as usize),
+            _ => continue,
+        };
+
+        let lines = files
+            .entry(abs_path.clone())
+            .or_insert_with(|| read_lines(abs_path));
+        let lines = match lines {
+            Some(lines) if lineno <= lines.len() => lines,
+            _ => continue,
+        };
+
+        let index = lineno - 1;
+        let start = index.saturating_sub(context_lines);
+        let end = (index + 1 + context_lines).min(lines.len());
+        frame.pre_context = lines[start..index].to_vec();
+        frame.context_line = Some(lines[index].clone());
+        frame.post_context = lines[index + 1..end].to_vec();
+    }
+}
+
+/// Reads the lines of a source file.
+fn read_lines(path: &str) -> Option<Vec<String>> {
+    let source = fs::read(path).ok()?;
+    let source = String::from_utf8_lossy(&source);
+    Some(source.lines().map(Into::into).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frame;
+
+    fn frame(abs_path: &str, lineno: u64, in_app: bool) -> Frame {
+        Frame {
+            abs_path: Some(abs_path.into()),
+            lineno: Some(lineno),
+            in_app: Some(in_app),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_add_source_context() {
+        let path = std::env::temp_dir().join(format!(
+            "sentry-backtrace-{}.rs",
+            sentry_core::types::Uuid::new_v4()
+        ));
+        fs::write(&path, "fn main() {\n    let a = 1;\n    panic!();\n}\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let mut stacktrace = Stacktrace {
+            frames: vec![
+                frame(&path, 3, true),
+                frame(&path, 1, true),
+                frame(&path, 3, false),
+                frame(&path, 10, true),
+                frame("/does/not/exist.rs", 1, true),
+            ],
+            ..Default::default()
+        };
+        add_source_context(&mut stacktrace, 1);
+        fs::remove_file(&path).ok();
+
+        let frames = &stacktrace.frames;
+        assert_eq!(frames[0].pre_context, vec!["    let a = 1;"]);
+        assert_eq!(frames[0].context_line.as_deref(), Some("    panic!();"));
+        assert_eq!(frames[0].post_context, vec!["}"]);
+
+        assert!(frames[1].pre_context.is_empty());
+        assert_eq!(frames[1].context_line.as_deref(), Some("fn main() {"));
+        assert_eq!(frames[1].post_context, vec!["    let a = 1;"]);
+
+        for frame in &frames[2..] {
+            assert!(frame.context_line.is_none());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use sentry_core::protocol::{Frame, Stacktrace};
+use sentry_core::BacktraceTrimmer;
 
 use crate::utils::function_starts_with;
 
@@ -18,18 +19,25 @@ lazy_static::lazy_static! {
         "anyhow::",
         "log::",
     ];
-
-    static ref WELL_KNOWN_BORDER_FRAMES: Vec<&'static str> = vec![
-        "std::panicking::begin_panic",
-        "core::panicking::panic",
-        // well-known library frames
-        "anyhow::",
-        "<sentry_log::Logger as log::Log>::log",
-    ];
 }
 
 /// A helper function to trim a stacktrace.
+///
+/// The stacktrace is cut at the innermost frame that is either one of the
+/// `DEFAULT_BORDER_FRAMES` of the `BacktraceTrimmer`, or for which `f` returns
+/// `true`.
 pub fn trim_stacktrace<F>(stacktrace: &mut Stacktrace, f: F)
+where
+    F: Fn(&Frame, &Stacktrace) -> bool,
+{
+    trim_stacktrace_at(stacktrace, |frame, stacktrace| match frame.function {
+        Some(ref func) => is_well_known(&func) || f(frame, stacktrace),
+        None => false,
+    })
+}
+
+/// Trims the stacktrace at the innermost frame for which `is_border` returns `true`.
+pub(crate) fn trim_stacktrace_at<F>(stacktrace: &mut Stacktrace, is_border: F)
 where
     F: Fn(&Frame, &Stacktrace) -> bool,
 {
@@ -37,10 +45,7 @@ where
         .frames
         .iter()
         .rev()
-        .position(|frame| match frame.function {
-            Some(ref func) => is_well_known(&func) || f(frame, stacktrace),
-            None => false,
-        });
+        .position(|frame| is_border(frame, stacktrace));
 
     if let Some(cutoff) = known_cutoff {
         let trunc = stacktrace.frames.len() - cutoff - 1;
@@ -57,7 +62,7 @@ pub fn is_sys_function(func: &str) -> bool {
 
 /// Checks if a function is a well-known system function
 fn is_well_known(func: &str) -> bool {
-    WELL_KNOWN_BORDER_FRAMES
+    BacktraceTrimmer::DEFAULT_BORDER_FRAMES
         .iter()
         .any(|m| function_starts_with(&func, m))
 }
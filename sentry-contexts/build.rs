@@ -15,8 +15,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // `NoneError` does not implement `std::error::Error`
     // See https://github.com/rust-lang/rust/issues/46871
     let arch = target_bits.next().unwrap();
-    target_bits.next();
-    let platform = target_bits.next().unwrap();
+    let vendor = target_bits.next().unwrap();
+    // two-component targets such as `wasm32-wasi` have no vendor
+    let platform = target_bits.next().unwrap_or(vendor);
 
     writeln!(
         f,
@@ -51,6 +52,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     )?;
 
+    writeln!(f, "/// The target triple this crate was compiled for.")?;
+    writeln!(f, "pub const TARGET: &str = \"{}\";", target)?;
+    writeln!(
+        f,
+        "/// The optimization level this crate was compiled with."
+    )?;
+    writeln!(
+        f,
+        "pub const OPT_LEVEL: Option<&'static str> = {};",
+        if let Ok(opt_level) = env::var("OPT_LEVEL") {
+            format!("Some(\"{}\")", opt_level)
+        } else {
+            "None".into()
+        }
+    )?;
+
     writeln!(f, "/// The platform identifier.")?;
     writeln!(
         f,
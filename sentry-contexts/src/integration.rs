@@ -1,10 +1,14 @@
 use std::borrow::Cow;
 
 use sentry_core::protocol::map::Entry;
-use sentry_core::protocol::Event;
+use sentry_core::protocol::{Context, Event, Value};
+use sentry_core::types::{DateTime, Utc};
 use sentry_core::{ClientOptions, Integration};
 
-use crate::utils::{device_context, os_context, rust_context, server_name};
+use crate::utils::{
+    app_context, ci_context, container_context, culture_context, device_context,
+    kubernetes_context, os_context, rust_context, server_name,
+};
 
 /// Adds Contexts to Sentry Events.
 ///
@@ -16,6 +20,17 @@ pub struct ContextIntegration {
     add_os: bool,
     add_rust: bool,
     add_device: bool,
+    add_app: bool,
+    add_culture: bool,
+    add_container: bool,
+    add_kubernetes: bool,
+    add_ci: bool,
+    kubernetes: Option<Context>,
+    ci: Option<Context>,
+    #[cfg(feature = "gpu")]
+    add_gpu: bool,
+    app_build: Option<String>,
+    start_time: DateTime<Utc>,
 }
 
 impl Default for ContextIntegration {
@@ -24,6 +39,17 @@ impl Default for ContextIntegration {
             add_os: true,
             add_rust: true,
             add_device: true,
+            add_app: true,
+            add_culture: true,
+            add_container: true,
+            add_kubernetes: true,
+            add_ci: true,
+            kubernetes: kubernetes_context("POD_NAME", "POD_NAMESPACE", "NODE_NAME"),
+            ci: ci_context(),
+            #[cfg(feature = "gpu")]
+            add_gpu: true,
+            app_build: None,
+            start_time: Utc::now(),
         }
     }
 }
@@ -50,6 +76,77 @@ impl ContextIntegration {
         self.add_device = add_device;
         self
     }
+
+    /// Add `app` context, enabled by default.
+    ///
+    /// The app start time is the time this integration was created.
+    pub fn add_app(mut self, add_app: bool) -> Self {
+        self.add_app = add_app;
+        self
+    }
+
+    /// Add `culture` context, enabled by default.
+    pub fn add_culture(mut self, add_culture: bool) -> Self {
+        self.add_culture = add_culture;
+        self
+    }
+
+    /// Add `container` context, enabled by default.
+    ///
+    /// This is only available on Linux.
+    pub fn add_container(mut self, add_container: bool) -> Self {
+        self.add_container = add_container;
+        self
+    }
+
+    /// Add `kubernetes` context and tags, enabled by default.
+    ///
+    /// The context is only added when the pod name environment variable is
+    /// set, and also sets the `k8s.pod`, `k8s.namespace` and `k8s.node` tags.
+    pub fn add_kubernetes(mut self, add_kubernetes: bool) -> Self {
+        self.add_kubernetes = add_kubernetes;
+        self
+    }
+
+    /// Sets the environment variables the `kubernetes` context is read from.
+    ///
+    /// These default to `POD_NAME`, `POD_NAMESPACE` and `NODE_NAME`.  The variables
+    /// are read once, when the integration is created and when they are changed.
+    pub fn kubernetes_vars(
+        mut self,
+        pod_name: &'static str,
+        namespace: &'static str,
+        node_name: &'static str,
+    ) -> Self {
+        self.kubernetes = kubernetes_context(pod_name, namespace, node_name);
+        self
+    }
+
+    /// Add `ci` context and the `ci.provider` tag, enabled by default.
+    ///
+    /// The context is only added when running on a detected CI provider, which
+    /// allows filtering events from test runs.  The provider is detected once,
+    /// when the integration is created.
+    pub fn add_ci(mut self, add_ci: bool) -> Self {
+        self.add_ci = add_ci;
+        self
+    }
+
+    /// Add `gpu` context, enabled by default with the `gpu` feature.
+    #[cfg(feature = "gpu")]
+    pub fn add_gpu(mut self, add_gpu: bool) -> Self {
+        self.add_gpu = add_gpu;
+        self
+    }
+
+    /// Sets the build of the application reported in the `app` context.
+    ///
+    /// See [`app_build!`](macro.app_build.html) to read the build from the
+    /// environment at compile time.
+    pub fn app_build(mut self, app_build: Option<&str>) -> Self {
+        self.app_build = app_build.map(Into::into);
+        self
+    }
 }
 
 impl Integration for ContextIntegration {
@@ -87,6 +184,70 @@ impl Integration for ContextIntegration {
                 .entry("device".to_string())
                 .or_insert_with(device_context);
         }
+        if self.add_culture {
+            if let Entry::Vacant(entry) = event.contexts.entry("culture".to_string()) {
+                if let Some(culture) = culture_context() {
+                    entry.insert(culture);
+                }
+            }
+        }
+        if self.add_container {
+            if let Entry::Vacant(entry) = event.contexts.entry("container".to_string()) {
+                if let Some(container) = container_context() {
+                    entry.insert(container);
+                }
+            }
+        }
+        if self.add_kubernetes {
+            if let Some(Context::Other(ref map)) = self.kubernetes {
+                for (tag, key) in &[
+                    ("k8s.pod", "pod_name"),
+                    ("k8s.namespace", "namespace"),
+                    ("k8s.node", "node_name"),
+                ] {
+                    if let Some(value) = map.get(*key).and_then(Value::as_str) {
+                        event
+                            .tags
+                            .entry((*tag).to_string())
+                            .or_insert_with(|| value.to_string());
+                    }
+                }
+                event
+                    .contexts
+                    .entry("kubernetes".to_string())
+                    .or_insert_with(|| Context::Other(map.clone()));
+            }
+        }
+        if self.add_ci {
+            if let Some(Context::Other(ref map)) = self.ci {
+                if let Some(provider) = map.get("provider").and_then(Value::as_str) {
+                    event
+                        .tags
+                        .entry("ci.provider".to_string())
+                        .or_insert_with(|| provider.to_string());
+                }
+                event
+                    .contexts
+                    .entry("ci".to_string())
+                    .or_insert_with(|| Context::Other(map.clone()));
+            }
+        }
+        #[cfg(feature = "gpu")]
+        {
+            if self.add_gpu {
+                if let Entry::Vacant(entry) = event.contexts.entry("gpu".to_string()) {
+                    if let Some(gpu) = crate::utils::gpu_context() {
+                        entry.insert(gpu);
+                    }
+                }
+            }
+        }
+        if self.add_app {
+            event
+                .contexts
+                .entry("app".to_string())
+                .or_insert_with(|| app_context(self.start_time, self.app_build.as_deref()));
+        }
 
         Some(event)
     }
@@ -1,11 +1,18 @@
 //! Adds Contexts to Sentry Events
 //!
-//! This integration is enabled by default in `sentry` and adds `device`, `os`
-//! and `rust` contexts to Events, as well as sets a `server_name` if not
-//! already defined.
+//! This integration is enabled by default in `sentry` and adds `app`, `ci`,
+//! `container`, `culture`, `device`, `kubernetes`, `os` and `rust` contexts to
+//! Events, as well as sets a `server_name` if not already defined.
 //!
 //! See the [Contexts Interface] documentation for more info.
 //!
+//! # Features
+//!
+//! * `feature = "gpu"`: Adds a `gpu` context describing the graphics device,
+//!   which is queried once via `wgpu`.
+//! * `feature = "cloud"`: Adds the async `utils::cloud_resource_context`, which
+//!   queries the EC2, GCE and Azure instance metadata services.
+//!
 //! # Examples
 //!
 //! ```
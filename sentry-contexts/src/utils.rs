@@ -1,4 +1,13 @@
-use sentry_core::protocol::{Context, DeviceContext, Map, OsContext, RuntimeContext};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "gpu")]
+use sentry_core::protocol::GpuContext;
+#[cfg(any(feature = "cloud", target_os = "linux"))]
+use sentry_core::protocol::Value;
+use sentry_core::protocol::{
+    AppContext, Context, CultureContext, DeviceContext, Map, OsContext, RuntimeContext,
+};
+use sentry_core::types::{DateTime, TimeZone, Utc};
 
 include!(concat!(env!("OUT_DIR"), "/constants.gen.rs"));
 
@@ -62,13 +71,62 @@ mod model_support {
 }
 
 /// Returns the server name (hostname) if available.
+#[cfg(not(target_arch = "wasm32"))]
 pub fn server_name() -> Option<String> {
     hostname::get().ok().and_then(|s| s.into_string().ok())
 }
 
+/// Returns the server name (hostname) if available.
+///
+/// WebAssembly targets have no notion of a hostname.
+#[cfg(target_arch = "wasm32")]
+pub fn server_name() -> Option<String> {
+    None
+}
+
+/// Parses the distribution details from `os-release`.
+///
+/// See the [os-release documentation](https://www.freedesktop.org/software/systemd/man/os-release.html)
+/// for the format.
+#[cfg(target_os = "linux")]
+fn distribution_info() -> Map<String, Value> {
+    let contents = std::fs::read_to_string("/etc/os-release")
+        .or_else(|_| std::fs::read_to_string("/usr/lib/os-release"))
+        .unwrap_or_default();
+
+    let mut info = Map::default();
+    for line in contents.lines() {
+        let mut parts = line.trim().splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key, value),
+            _ => continue,
+        };
+        let field = match key {
+            "ID" => "distribution_name",
+            "VERSION_ID" => "distribution_version",
+            "PRETTY_NAME" => "distribution_pretty_name",
+            _ => continue,
+        };
+        let value = value
+            .trim_matches(|c| c == '"' || c == '\'')
+            .replace("\\\"", "\"")
+            .replace("\\$", "$")
+            .replace("\\`", "`")
+            .replace("\\\\", "\\");
+        if !value.is_empty() {
+            info.insert(field.to_string(), value.into());
+        }
+    }
+    info
+}
+
 /// Returns the OS context
+///
+/// On Linux, this also contains the distribution name, version and pretty
+/// name as `distribution_name`, `distribution_version` and
+/// `distribution_pretty_name`, read from `os-release`.
 pub fn os_context() -> Option<Context> {
-    #[cfg(not(windows))]
+    #[cfg(not(any(windows, target_arch = "wasm32")))]
     {
         use uname::uname;
         if let Ok(info) = uname() {
@@ -77,6 +135,8 @@ pub fn os_context() -> Option<Context> {
                     name: Some(info.sysname),
                     kernel_version: Some(info.version),
                     version: Some(info.release),
+                    #[cfg(target_os = "linux")]
+                    other: distribution_info(),
                     ..Default::default()
                 }
                 .into(),
@@ -85,7 +145,7 @@ pub fn os_context() -> Option<Context> {
             None
         }
     }
-    #[cfg(windows)]
+    #[cfg(any(windows, target_arch = "wasm32"))]
     {
         Some(
             OsContext {
@@ -98,6 +158,9 @@ pub fn os_context() -> Option<Context> {
 }
 
 /// Returns the rust info.
+///
+/// This contains the version and channel of rustc, the target triple and the
+/// optimization level this crate was compiled with.
 pub fn rust_context() -> Context {
     RuntimeContext {
         name: Some("rustc".into()),
@@ -107,19 +170,923 @@ pub fn rust_context() -> Context {
             if let Some(channel) = RUSTC_CHANNEL {
                 map.insert("channel".to_string(), channel.into());
             }
+            map.insert("target".to_string(), TARGET.into());
+            if let Some(opt_level) = OPT_LEVEL {
+                map.insert("opt_level".to_string(), opt_level.into());
+            }
             map
         },
     }
     .into()
 }
 
+/// Memory and uptime of the system.
+#[derive(Default)]
+struct SystemInfo {
+    memory_size: Option<u64>,
+    free_memory: Option<u64>,
+    uptime: Option<Duration>,
+}
+
+#[cfg(target_os = "linux")]
+fn system_info() -> SystemInfo {
+    let mut info: libc::sysinfo = unsafe { std::mem::zeroed() };
+    if unsafe { libc::sysinfo(&mut info) } != 0 {
+        return SystemInfo::default();
+    }
+    let unit = u64::from(info.mem_unit);
+    // `freeram` does not include reclaimable caches, `MemAvailable` does
+    let available = std::fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|meminfo| {
+            let line = meminfo
+                .lines()
+                .find(|line| line.starts_with("MemAvailable:"))?;
+            let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+            Some(kb * 1024)
+        });
+    SystemInfo {
+        memory_size: Some(u64::from(info.totalram) * unit),
+        free_memory: Some(available.unwrap_or(u64::from(info.freeram) * unit)),
+        uptime: Some(Duration::from_secs(info.uptime as u64)),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn system_info() -> SystemInfo {
+    use std::mem::size_of;
+    use std::ptr;
+
+    let mut memory_size = 0u64;
+    let mut size = size_of::<u64>();
+    let has_memory_size = unsafe {
+        libc::sysctlbyname(
+            "hw.memsize\x00".as_ptr() as *const i8,
+            &mut memory_size as *mut u64 as *mut libc::c_void,
+            &mut size,
+            ptr::null_mut(),
+            0,
+        ) == 0
+    };
+
+    let mut boot_time: libc::timeval = unsafe { std::mem::zeroed() };
+    let mut size = size_of::<libc::timeval>();
+    let booted = unsafe {
+        libc::sysctlbyname(
+            "kern.boottime\x00".as_ptr() as *const i8,
+            &mut boot_time as *mut libc::timeval as *mut libc::c_void,
+            &mut size,
+            ptr::null_mut(),
+            0,
+        ) == 0
+    };
+    let uptime = if booted {
+        (UNIX_EPOCH + Duration::from_secs(boot_time.tv_sec as u64))
+            .elapsed()
+            .ok()
+    } else {
+        None
+    };
+
+    SystemInfo {
+        memory_size: if has_memory_size {
+            Some(memory_size)
+        } else {
+            None
+        },
+        free_memory: None,
+        uptime,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn system_info() -> SystemInfo {
+    SystemInfo::default()
+}
+
+lazy_static::lazy_static! {
+    static ref PROCESSOR_COUNT: usize = num_cpus::get();
+}
+
 /// Returns the device context.
+///
+/// Besides the model and architecture, this contains the number of logical
+/// CPUs as `processor_count`, and the memory and boot time of the system on
+/// Linux and macOS, which are queried on every call.
 pub fn device_context() -> Context {
+    let info = system_info();
+    let boot_time = info
+        .uptime
+        .and_then(|uptime| SystemTime::now().checked_sub(uptime))
+        .and_then(|boot_time| boot_time.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| Utc.timestamp(since_epoch.as_secs() as i64, 0));
+
+    let mut other = Map::default();
+    other.insert("processor_count".to_string(), (*PROCESSOR_COUNT).into());
     DeviceContext {
         model: model_support::get_model(),
         family: model_support::get_family(),
         arch: Some(ARCH.into()),
+        memory_size: info.memory_size,
+        free_memory: info.free_memory,
+        boot_time,
+        other,
         ..Default::default()
     }
     .into()
 }
+
+/// Returns the build information of the application from the environment at
+/// compile time.
+///
+/// This expands to the value of the `SENTRY_APP_BUILD` or, if that is not set,
+/// the `GIT_SHA` environment variable at the time the calling crate is
+/// compiled, which can be passed to [`ContextIntegration::app_build`].
+///
+/// # Examples
+///
+/// ```
+/// let integration =
+///     sentry_contexts::ContextIntegration::new().app_build(sentry_contexts::app_build!());
+/// ```
+///
+/// [`ContextIntegration::app_build`]: struct.ContextIntegration.html#method.app_build
+#[macro_export]
+macro_rules! app_build {
+    () => {
+        option_env!("SENTRY_APP_BUILD").or(option_env!("GIT_SHA"))
+    };
+}
+
+/// Returns the resident and virtual memory of the current process in bytes.
+#[cfg(target_os = "linux")]
+fn process_memory() -> Option<(u64, u64)> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let mut fields = statm.split_whitespace();
+    let size: u64 = fields.next()?.parse().ok()?;
+    let resident: u64 = fields.next()?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    Some((resident * page_size as u64, size * page_size as u64))
+}
+
+/// Returns the resident and virtual memory of the current process in bytes.
+#[cfg(not(target_os = "linux"))]
+fn process_memory() -> Option<(u64, u64)> {
+    None
+}
+
+/// Returns the app context.
+///
+/// This contains the given start time and build, the build type of this
+/// crate, and the current memory usage of the process, where available.
+pub fn app_context(start_time: DateTime<Utc>, app_build: Option<&str>) -> Context {
+    let mut other = Map::default();
+    if let Some((resident, virt)) = process_memory() {
+        other.insert("app_memory".to_string(), resident.into());
+        other.insert("app_virtual_memory".to_string(), virt.into());
+    }
+    AppContext {
+        app_start_time: Some(start_time),
+        build_type: Some(
+            if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            }
+            .into(),
+        ),
+        app_build: app_build.map(Into::into),
+        other,
+        ..Default::default()
+    }
+    .into()
+}
+
+#[cfg(feature = "gpu")]
+lazy_static::lazy_static! {
+    static ref GPU_CONTEXT: Option<Context> = query_gpu_context();
+}
+
+/// Returns the name of a graphics device vendor by its PCI vendor id.
+#[cfg(feature = "gpu")]
+fn gpu_vendor_name(vendor_id: usize) -> Option<&'static str> {
+    Some(match vendor_id {
+        0x1002 => "AMD",
+        0x106b => "Apple",
+        0x10de => "NVIDIA",
+        0x13b5 => "ARM",
+        0x5143 => "Qualcomm",
+        0x8086 => "Intel",
+        _ => return None,
+    })
+}
+
+#[cfg(feature = "gpu")]
+fn query_gpu_context() -> Option<Context> {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter =
+        futures_executor::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+        }))?;
+    let info = adapter.get_info();
+
+    let mut other = Map::default();
+    other.insert(
+        "device_type".to_string(),
+        format!("{:?}", info.device_type).into(),
+    );
+    Some(
+        GpuContext {
+            name: Some(info.name),
+            id: Some(info.device as u64),
+            vendor_id: Some(info.vendor as u64),
+            vendor_name: gpu_vendor_name(info.vendor).map(Into::into),
+            api_type: Some(format!("{:?}", info.backend)),
+            other,
+            ..Default::default()
+        }
+        .into(),
+    )
+}
+
+/// Returns the GPU context.
+///
+/// The graphics device that would be picked for high performance rendering
+/// is queried via `wgpu` on the first call, which uses Vulkan, Metal or
+/// DirectX 12 depending on the platform.  This returns `None` if no device
+/// is available.
+#[cfg(feature = "gpu")]
+pub fn gpu_context() -> Option<Context> {
+    GPU_CONTEXT.clone()
+}
+
+/// Converts a POSIX locale, such as `de_AT.UTF-8@euro`, to a BCP 47 language tag.
+fn parse_locale(value: &str) -> Option<String> {
+    // strip the encoding and modifier
+    let locale = value.split(&['.', '@'][..]).next()?;
+    match locale {
+        "" | "C" | "POSIX" => None,
+        locale => Some(locale.replace('_', "-")),
+    }
+}
+
+/// Returns the locale from the environment, as a BCP 47 language tag.
+fn locale() -> Option<String> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    parse_locale(&value)
+}
+
+/// Returns the timezone name from a `TZ` value or a path into the zoneinfo database.
+fn parse_timezone(value: &str) -> Option<String> {
+    let value = value.trim().trim_start_matches(':');
+    let name = match value.find("zoneinfo/") {
+        Some(idx) => &value[idx + "zoneinfo/".len()..],
+        None => value,
+    };
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Returns the name of the local timezone.
+fn timezone() -> Option<String> {
+    if let Some(tz) = std::env::var("TZ").ok().and_then(|tz| parse_timezone(&tz)) {
+        return Some(tz);
+    }
+    #[cfg(all(unix, not(target_arch = "wasm32")))]
+    {
+        if let Some(tz) = std::fs::read_to_string("/etc/timezone")
+            .ok()
+            .and_then(|tz| parse_timezone(&tz))
+        {
+            return Some(tz);
+        }
+        if let Ok(path) = std::fs::read_link("/etc/localtime") {
+            let path = path.to_string_lossy();
+            if path.contains("zoneinfo/") {
+                return parse_timezone(&path);
+            }
+        }
+    }
+    None
+}
+
+/// Returns whether the time format of the environment locale uses a 24 hour clock.
+#[cfg(target_os = "linux")]
+fn is_24_hour_format() -> Option<bool> {
+    use std::ffi::CStr;
+
+    unsafe {
+        // query a private copy of the locale to avoid changing the global one
+        let empty = b"\0";
+        let locale = libc::newlocale(
+            libc::LC_TIME_MASK,
+            empty.as_ptr() as *const libc::c_char,
+            std::ptr::null_mut(),
+        );
+        if locale.is_null() {
+            return None;
+        }
+        let format = CStr::from_ptr(libc::nl_langinfo_l(libc::T_FMT, locale))
+            .to_string_lossy()
+            .into_owned();
+        libc::freelocale(locale);
+
+        if format.contains("%I") || format.contains("%l") || format.contains("%r") {
+            Some(false)
+        } else if format.contains("%H") || format.contains("%k") || format.contains("%T") {
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns whether the time format of the environment locale uses a 24 hour clock.
+#[cfg(not(target_os = "linux"))]
+fn is_24_hour_format() -> Option<bool> {
+    None
+}
+
+lazy_static::lazy_static! {
+    static ref CULTURE_CONTEXT: Option<Context> = query_culture_context();
+}
+
+fn query_culture_context() -> Option<Context> {
+    let context = CultureContext {
+        locale: locale(),
+        timezone: timezone(),
+        is_24_hour_format: is_24_hour_format(),
+        ..Default::default()
+    };
+    if context == CultureContext::default() {
+        None
+    } else {
+        Some(context.into())
+    }
+}
+
+/// Returns the culture context.
+///
+/// The locale is read from the `LC_ALL`, `LC_MESSAGES` and `LANG` environment
+/// variables, and the timezone from `TZ` or the system configuration, on the
+/// first call.
+pub fn culture_context() -> Option<Context> {
+    CULTURE_CONTEXT.clone()
+}
+
+#[cfg(target_os = "linux")]
+mod container_support {
+    use std::fs;
+
+    use regex::Regex;
+
+    lazy_static::lazy_static! {
+        static ref CONTAINER_ID_RE: Regex = Regex::new(r"[0-9a-f]{64}").unwrap();
+        static ref MOUNT_CONTAINER_ID_RE: Regex =
+            Regex::new(r"/containers/([0-9a-f]{64})/").unwrap();
+    }
+
+    /// Memory limits at or above this value mean there is no limit.
+    const UNLIMITED_MEMORY: u64 = 1 << 62;
+
+    fn read(path: &str) -> Option<String> {
+        fs::read_to_string(path)
+            .ok()
+            .map(|contents| contents.trim().to_string())
+    }
+
+    /// Returns the cgroup version of the current process, `2` for the unified hierarchy.
+    pub fn cgroup_version(cgroup: &str) -> Option<u8> {
+        if cgroup.lines().any(|line| line.starts_with("0::")) {
+            Some(2)
+        } else if cgroup.lines().next().is_some() {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the id of the container from the cgroup paths.
+    pub fn container_id(cgroup: &str) -> Option<String> {
+        CONTAINER_ID_RE.find(cgroup).map(|m| m.as_str().to_string())
+    }
+
+    /// Returns the id of the container from the mount paths.
+    ///
+    /// With cgroup namespaces, the id only shows up in the mounts.
+    pub fn mount_container_id(mountinfo: &str) -> Option<String> {
+        MOUNT_CONTAINER_ID_RE
+            .captures(mountinfo)
+            .and_then(|c| c.get(1))
+            .map(|m| m.as_str().to_string())
+    }
+
+    pub fn in_container() -> bool {
+        std::path::Path::new("/.dockerenv").exists()
+            || std::path::Path::new("/run/.containerenv").exists()
+            || std::env::var_os("container").is_some()
+    }
+
+    /// Returns the memory limit in bytes.
+    pub fn memory_limit(version: u8) -> Option<u64> {
+        let limit = if version == 2 {
+            read("/sys/fs/cgroup/memory.max")?
+        } else {
+            read("/sys/fs/cgroup/memory/memory.limit_in_bytes")?
+        };
+        limit.parse().ok().filter(|limit| *limit < UNLIMITED_MEMORY)
+    }
+
+    /// Returns the CPU quota as a number of CPUs.
+    pub fn cpu_quota(version: u8) -> Option<f64> {
+        let (quota, period) = if version == 2 {
+            let max = read("/sys/fs/cgroup/cpu.max")?;
+            let mut parts = max.split_whitespace();
+            (parts.next()?.to_string(), parts.next()?.to_string())
+        } else {
+            (
+                read("/sys/fs/cgroup/cpu/cpu.cfs_quota_us")?,
+                read("/sys/fs/cgroup/cpu/cpu.cfs_period_us")?,
+            )
+        };
+        // the quota is `max` or `-1` when unlimited
+        let quota: f64 = quota.parse().ok().filter(|quota| *quota > 0.0)?;
+        let period: f64 = period.parse().ok().filter(|period| *period > 0.0)?;
+        Some(quota / period)
+    }
+}
+
+#[cfg(target_os = "linux")]
+lazy_static::lazy_static! {
+    static ref CONTAINER_CONTEXT: Option<Context> = query_container_context();
+}
+
+#[cfg(target_os = "linux")]
+fn query_container_context() -> Option<Context> {
+    use container_support::*;
+
+    let cgroup = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    let version = cgroup_version(&cgroup)?;
+    let container_id = container_id(&cgroup).or_else(|| {
+        let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+        mount_container_id(&mountinfo)
+    });
+
+    let mut map = Map::default();
+    map.insert(
+        "in_container".into(),
+        (container_id.is_some() || in_container()).into(),
+    );
+    map.insert("cgroup_version".into(), version.into());
+    if let Some(id) = container_id {
+        map.insert("container_id".into(), id.into());
+    }
+    if let Some(limit) = memory_limit(version) {
+        map.insert("memory_limit".into(), limit.into());
+    }
+    if let Some(quota) = cpu_quota(version) {
+        map.insert("cpu_quota".into(), Value::from(quota));
+    }
+    Some(Context::Other(map))
+}
+
+/// Returns the container context.
+///
+/// This contains the cgroup version, the container id, and the memory limit
+/// and CPU quota of the cgroup of the process, which are queried on the first
+/// call.  Only available on Linux.
+#[cfg(target_os = "linux")]
+pub fn container_context() -> Option<Context> {
+    CONTAINER_CONTEXT.clone()
+}
+
+/// Returns the container context.
+///
+/// This contains the cgroup version, the container id, and the memory limit
+/// and CPU quota of the cgroup of the process, which are queried on the first
+/// call.  Only available on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn container_context() -> Option<Context> {
+    None
+}
+
+/// Returns the kubernetes context from the given environment variables.
+///
+/// The variables are usually populated via the downward API, and contain the
+/// name of the pod, its namespace and the name of the node it runs on.  This
+/// returns `None` if the pod name variable is not set.
+pub fn kubernetes_context(pod_name: &str, namespace: &str, node_name: &str) -> Option<Context> {
+    let pod_name = std::env::var(pod_name).ok().filter(|v| !v.is_empty())?;
+    let mut map = Map::default();
+    map.insert("pod_name".into(), pod_name.into());
+    for (key, var) in &[("namespace", namespace), ("node_name", node_name)] {
+        if let Some(value) = std::env::var(var).ok().filter(|v| !v.is_empty()) {
+            map.insert((*key).into(), value.into());
+        }
+    }
+    Some(Context::Other(map))
+}
+
+/// The environment variables of a CI provider.
+struct CiProvider {
+    name: &'static str,
+    /// The variable that is set when running on this provider.
+    detect: &'static str,
+    job: &'static str,
+    commit: &'static str,
+    build_id: &'static str,
+}
+
+const CI_PROVIDERS: &[CiProvider] = &[
+    CiProvider {
+        name: "github-actions",
+        detect: "GITHUB_ACTIONS",
+        job: "GITHUB_JOB",
+        commit: "GITHUB_SHA",
+        build_id: "GITHUB_RUN_ID",
+    },
+    CiProvider {
+        name: "gitlab",
+        detect: "GITLAB_CI",
+        job: "CI_JOB_NAME",
+        commit: "CI_COMMIT_SHA",
+        build_id: "CI_JOB_ID",
+    },
+    CiProvider {
+        name: "buildkite",
+        detect: "BUILDKITE",
+        job: "BUILDKITE_LABEL",
+        commit: "BUILDKITE_COMMIT",
+        build_id: "BUILDKITE_BUILD_ID",
+    },
+    CiProvider {
+        name: "jenkins",
+        detect: "JENKINS_URL",
+        job: "JOB_NAME",
+        commit: "GIT_COMMIT",
+        build_id: "BUILD_NUMBER",
+    },
+];
+
+/// Returns the CI context.
+///
+/// This detects GitHub Actions, GitLab CI, Buildkite and Jenkins from their
+/// environment variables, and contains the `provider`, `job`, `commit` and
+/// `build_id`.  This returns `None` when not running on any of these.
+pub fn ci_context() -> Option<Context> {
+    ci_context_from(|name| std::env::var(name).ok())
+}
+
+/// Returns the CI context from the environment variables looked up by `var`.
+fn ci_context_from<F: Fn(&str) -> Option<String>>(var: F) -> Option<Context> {
+    let var = |name: &str| var(name).filter(|v| !v.is_empty());
+    let provider = CI_PROVIDERS
+        .iter()
+        .find(|provider| var(provider.detect).is_some())?;
+
+    let mut map = Map::default();
+    map.insert("provider".into(), provider.name.into());
+    for (key, name) in &[
+        ("job", provider.job),
+        ("commit", provider.commit),
+        ("build_id", provider.build_id),
+    ] {
+        if let Some(value) = var(name) {
+            map.insert((*key).into(), value.into());
+        }
+    }
+    Some(Context::Other(map))
+}
+
+/// The address of the EC2 and Azure instance metadata services.
+#[cfg(feature = "cloud")]
+const METADATA_IP: &str = "http://169.254.169.254";
+
+#[cfg(feature = "cloud")]
+async fn query_ec2(client: &reqwest::Client) -> Option<Map<String, Value>> {
+    let token = client
+        .put(&format!("{}/latest/api/token", METADATA_IP))
+        .header("X-aws-ec2-metadata-token-ttl-seconds", "60")
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+    let doc: Value = client
+        .get(&format!(
+            "{}/latest/dynamic/instance-identity/document",
+            METADATA_IP
+        ))
+        .header("X-aws-ec2-metadata-token", token)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+
+    let mut map = Map::default();
+    map.insert("cloud.provider".into(), "aws".into());
+    map.insert("cloud.platform".into(), "aws_ec2".into());
+    for (key, field) in &[
+        ("cloud.account.id", "accountId"),
+        ("cloud.region", "region"),
+        ("cloud.availability_zone", "availabilityZone"),
+        ("host.id", "instanceId"),
+        ("host.type", "instanceType"),
+    ] {
+        if let Some(value) = doc.get(field) {
+            map.insert((*key).into(), value.clone());
+        }
+    }
+    Some(map)
+}
+
+#[cfg(feature = "cloud")]
+async fn query_gce(client: &reqwest::Client) -> Option<Map<String, Value>> {
+    let doc: Value = client
+        .get("http://metadata.google.internal/computeMetadata/v1/instance/?recursive=true")
+        .header("Metadata-Flavor", "Google")
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    // the zone and machine type are given as `projects/<id>/zones/<zone>`
+    let last_segment = |field| {
+        doc.get(field)
+            .and_then(Value::as_str)
+            .and_then(|value| value.rsplit('/').next())
+            .map(ToString::to_string)
+    };
+
+    let mut map = Map::default();
+    map.insert("cloud.provider".into(), "gcp".into());
+    map.insert("cloud.platform".into(), "gcp_compute_engine".into());
+    if let Some(zone) = last_segment("zone") {
+        if let Some(idx) = zone.rfind('-') {
+            map.insert("cloud.region".into(), zone[..idx].into());
+        }
+        map.insert("cloud.availability_zone".into(), zone.into());
+    }
+    if let Some(id) = doc.get("id") {
+        // the numeric id exceeds the range of JSON numbers in some consumers
+        let id = id
+            .as_str()
+            .map_or_else(|| id.to_string(), ToString::to_string);
+        map.insert("host.id".into(), id.into());
+    }
+    if let Some(machine_type) = last_segment("machineType") {
+        map.insert("host.type".into(), machine_type.into());
+    }
+    Some(map)
+}
+
+#[cfg(feature = "cloud")]
+async fn query_azure(client: &reqwest::Client) -> Option<Map<String, Value>> {
+    let doc: Value = client
+        .get(&format!(
+            "{}/metadata/instance/compute?api-version=2021-02-01",
+            METADATA_IP
+        ))
+        .header("Metadata", "true")
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+
+    let mut map = Map::default();
+    map.insert("cloud.provider".into(), "azure".into());
+    map.insert("cloud.platform".into(), "azure_vm".into());
+    for (key, field) in &[
+        ("cloud.account.id", "subscriptionId"),
+        ("cloud.region", "location"),
+        ("cloud.availability_zone", "zone"),
+        ("host.id", "vmId"),
+        ("host.type", "vmSize"),
+    ] {
+        match doc.get(field) {
+            Some(Value::String(value)) if value.is_empty() => {}
+            Some(value) => {
+                map.insert((*key).into(), value.clone());
+            }
+            None => {}
+        }
+    }
+    Some(map)
+}
+
+/// Returns the cloud resource context from the instance metadata service.
+///
+/// This queries the metadata services of EC2, Google Compute Engine and Azure
+/// in turn, each with the given timeout, and returns the instance id, region,
+/// availability zone and machine type of the first one that responds.  It
+/// returns `None` when not running on any of these clouds.
+///
+/// The detection is not part of the [`ContextIntegration`], since it performs
+/// network requests.  Instead, the result should be set on the scope once:
+///
+/// ```no_run
+/// # async fn run() {
+/// use std::time::Duration;
+///
+/// if let Some(context) =
+///     sentry_contexts::utils::cloud_resource_context(Duration::from_secs(1)).await
+/// {
+///     sentry::configure_scope(|scope| scope.set_context("cloud_resource", context));
+/// }
+/// # }
+/// ```
+///
+/// [`ContextIntegration`]: ../struct.ContextIntegration.html
+#[cfg(feature = "cloud")]
+pub async fn cloud_resource_context(timeout: std::time::Duration) -> Option<Context> {
+    let client = reqwest::Client::builder().timeout(timeout).build().ok()?;
+    let map = match query_ec2(&client).await {
+        Some(map) => map,
+        None => match query_gce(&client).await {
+            Some(map) => map,
+            None => query_azure(&client).await?,
+        },
+    };
+    Some(Context::Other(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(parse_locale("de_AT.UTF-8@euro").as_deref(), Some("de-AT"));
+        assert_eq!(parse_locale("en_US.UTF-8").as_deref(), Some("en-US"));
+        assert_eq!(parse_locale("fr").as_deref(), Some("fr"));
+        assert_eq!(parse_locale("C.UTF-8"), None);
+        assert_eq!(parse_locale("POSIX"), None);
+        assert_eq!(parse_locale(""), None);
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(
+            parse_timezone("Europe/Vienna").as_deref(),
+            Some("Europe/Vienna")
+        );
+        assert_eq!(
+            parse_timezone(":America/New_York").as_deref(),
+            Some("America/New_York")
+        );
+        // `/etc/timezone` ends with a newline
+        assert_eq!(parse_timezone("Etc/UTC\n").as_deref(), Some("Etc/UTC"));
+        // `/etc/localtime` links into the zoneinfo database
+        assert_eq!(
+            parse_timezone("/usr/share/zoneinfo/Asia/Tokyo").as_deref(),
+            Some("Asia/Tokyo")
+        );
+        assert_eq!(
+            parse_timezone(":/usr/share/zoneinfo/UTC").as_deref(),
+            Some("UTC")
+        );
+        assert_eq!(parse_timezone(":"), None);
+        assert_eq!(parse_timezone(""), None);
+    }
+
+    #[test]
+    fn test_kubernetes_context() {
+        let vars = (
+            "SENTRY_TEST_K8S_POD",
+            "SENTRY_TEST_K8S_NAMESPACE",
+            "SENTRY_TEST_K8S_NODE",
+        );
+        assert_eq!(kubernetes_context(vars.0, vars.1, vars.2), None);
+
+        std::env::set_var(vars.0, "web-5d8f7b9c4-x2x7k");
+        std::env::set_var(vars.1, "production");
+        let map = match kubernetes_context(vars.0, vars.1, vars.2) {
+            Some(Context::Other(map)) => map,
+            context => panic!("unexpected context: {:?}", context),
+        };
+        assert_eq!(map["pod_name"], "web-5d8f7b9c4-x2x7k");
+        assert_eq!(map["namespace"], "production");
+        assert!(!map.contains_key("node_name"));
+    }
+
+    #[test]
+    fn test_ci_context() {
+        let context = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            match ci_context_from(|name| vars.get(name).cloned()) {
+                Some(Context::Other(map)) => Some(map),
+                None => None,
+                context => panic!("unexpected context: {:?}", context),
+            }
+        };
+
+        assert_eq!(context(&[]), None);
+        assert_eq!(context(&[("GITHUB_ACTIONS", "")]), None);
+
+        let map = context(&[
+            ("GITLAB_CI", "true"),
+            ("CI_JOB_NAME", "test"),
+            ("CI_COMMIT_SHA", "1a2b3c"),
+            ("CI_JOB_ID", "42"),
+        ])
+        .unwrap();
+        assert_eq!(map["provider"], "gitlab");
+        assert_eq!(map["job"], "test");
+        assert_eq!(map["commit"], "1a2b3c");
+        assert_eq!(map["build_id"], "42");
+
+        let map = context(&[("JENKINS_URL", "https://ci.example.com/"), ("JOB_NAME", "")]).unwrap();
+        assert_eq!(map["provider"], "jenkins");
+        assert!(!map.contains_key("job"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_container_cgroup_v1() {
+        use container_support::*;
+
+        let cgroup = "\
+12:memory:/docker/3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e
+11:cpu,cpuacct:/docker/3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e
+1:name=systemd:/docker/3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e
+";
+        assert_eq!(cgroup_version(cgroup), Some(1));
+        assert_eq!(
+            container_id(cgroup).as_deref(),
+            Some("3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_container_cgroup_v2() {
+        use container_support::*;
+
+        let cgroup =
+            "0::/system.slice/docker-3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e.scope\n";
+        assert_eq!(cgroup_version(cgroup), Some(2));
+        assert_eq!(
+            container_id(cgroup).as_deref(),
+            Some("3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e")
+        );
+
+        let host = "0::/user.slice/user-1000.slice/session-2.scope\n";
+        assert_eq!(cgroup_version(host), Some(2));
+        assert_eq!(container_id(host), None);
+
+        assert_eq!(cgroup_version(""), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_container_mountinfo() {
+        use container_support::*;
+
+        // with a cgroup namespace, the cgroup path is just the root
+        let cgroup = "0::/\n";
+        let mountinfo = "\
+712 697 0:62 / / rw,relatime master:318 - overlay overlay rw
+745 712 259:2 /var/lib/docker/containers/3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e/resolv.conf /etc/resolv.conf rw,relatime - ext4 /dev/nvme0n1p2 rw
+746 712 259:2 /var/lib/docker/containers/3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e/hostname /etc/hostname rw,relatime - ext4 /dev/nvme0n1p2 rw
+";
+        assert_eq!(cgroup_version(cgroup), Some(2));
+        assert_eq!(container_id(cgroup), None);
+        assert_eq!(
+            mount_container_id(mountinfo).as_deref(),
+            Some("3f2e1d9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e")
+        );
+        assert_eq!(
+            mount_container_id("22 1 8:1 / / rw,relatime - ext4 /dev/sda1 rw\n"),
+            None
+        );
+    }
+}
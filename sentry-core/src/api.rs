@@ -44,7 +44,8 @@ pub fn capture_event(event: Event<'static>) -> Uuid {
 /// Captures an arbitrary message.
 ///
 /// This creates an event from the given message and sends it via
-/// [`capture_event`](fn.capture_event.html).
+/// [`capture_event`](fn.capture_event.html), and returns the ID of the event,
+/// or the nil UUID if it was discarded.
 ///
 /// # Examples
 ///
@@ -187,6 +188,9 @@ where
 {
     #[cfg(feature = "client")]
     {
+        if !crate::hub::any_client_bound() {
+            return callback();
+        }
         Hub::with(|hub| {
             if hub.is_active_and_usage_safe() {
                 hub.with_scope(scope_config, callback)
@@ -202,6 +206,101 @@ where
     }
 }
 
+/// Runs a function with an isolated scope, which is discarded afterwards.
+///
+/// The function runs with a new [`Hub`] that starts with a copy of the current
+/// scope.  All changes to the scope within the function, such as tags, breadcrumbs
+/// and pushed scopes, only apply to the events captured within it, and do not leak
+/// into the code that runs after it.  This is useful for CLIs with multiple
+/// subcommands, or REPL-style tools, that run one command after another in the same
+/// process.
+///
+/// Unlike [`with_scope`], the scope is isolated even when the function leaks a
+/// [`ScopeGuard`](crate::ScopeGuard), or configures the scope of a nested hub.
+///
+/// # Examples
+///
+/// ```
+/// # let events = sentry::test::with_captured_events(|| {
+/// sentry::with_isolated_scope(|| {
+///     sentry::configure_scope(|scope| scope.set_tag("command", "deploy"));
+///     sentry::add_breadcrumb(sentry::Breadcrumb::default());
+/// });
+/// sentry::capture_message("after the command", sentry::Level::Info);
+/// # });
+/// # let captured_event = events.into_iter().next().unwrap();
+///
+/// assert!(captured_event.tags.is_empty());
+/// assert!(captured_event.breadcrumbs.is_empty());
+/// ```
+pub fn with_isolated_scope<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    #[cfg(feature = "client")]
+    {
+        if !crate::hub::any_client_bound() {
+            return f();
+        }
+        let hub = std::sync::Arc::new(Hub::new_from_top(Hub::current()));
+        Hub::run(hub, f)
+    }
+    #[cfg(not(feature = "client"))]
+    {
+        f()
+    }
+}
+
+/// Sets a tag on the current scope until the returned guard is dropped.
+///
+/// Dropping the guard removes the tag again, or restores its previous value if it
+/// was set before.  This tags a section of code without cleaning up the tag on
+/// every early return.
+///
+/// # Examples
+///
+/// ```
+/// # let events = sentry::test::with_captured_events(|| {
+/// fn process(job_id: u64) {
+///     let _tag = sentry::tag_scope("job_id", job_id);
+///     sentry::capture_message("processing job", sentry::Level::Info);
+/// }
+///
+/// process(42);
+/// sentry::capture_message("idle", sentry::Level::Info);
+/// # });
+/// # assert_eq!(events[0].tags["job_id"], "42");
+/// # assert!(events[1].tags.get("job_id").is_none());
+/// ```
+pub fn tag_scope<V: ToString>(key: &str, value: V) -> TagGuard {
+    let previous = configure_scope(|scope| {
+        let previous = scope.get_tag(key).map(ToOwned::to_owned);
+        scope.set_tag(key, value);
+        previous
+    });
+    TagGuard {
+        key: key.to_owned(),
+        previous,
+    }
+}
+
+/// Removes a tag that was set with [`tag_scope`] when dropped.
+#[derive(Debug)]
+pub struct TagGuard {
+    key: String,
+    previous: Option<String>,
+}
+
+impl Drop for TagGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        configure_scope(|scope| match previous {
+            Some(value) => scope.set_tag(&self.key, value),
+            None => scope.remove_tag(&self.key),
+        });
+    }
+}
+
 /// Looks up an integration on the current Hub.
 ///
 /// Calls the given function with the requested integration instance when it
@@ -240,7 +339,8 @@ where
 /// Returns the last event ID captured.
 ///
 /// This uses the current thread local [`Hub`], and will return `None` if no
-/// event has been captured yet on this [`Hub`].
+/// event has been captured yet on this [`Hub`].  Events that were discarded,
+/// for example by `before_send` or sampling, do not count as captured.
 ///
 /// # Examples
 ///
@@ -264,6 +364,30 @@ pub fn last_event_id() -> Option<Uuid> {
     }}
 }
 
+/// Returns whether events are sent to Sentry.
+///
+/// This is the case if a client is bound to the current [`Hub`], and that client
+/// has a DSN and a transport.  To also check that the transport is able to send
+/// events, use [`Client::is_healthy`].
+///
+/// # Examples
+///
+/// ```
+/// assert!(!sentry::is_enabled());
+///
+/// # sentry::test::with_captured_events(|| {
+/// assert!(sentry::is_enabled());
+/// # });
+/// ```
+///
+/// [`Hub`]: struct.Hub.html
+/// [`Client::is_healthy`]: struct.Client.html#method.is_healthy
+pub fn is_enabled() -> bool {
+    with_client_impl! {{
+        Hub::with(|hub| hub.client().map_or(false, |client| client.is_enabled()))
+    }}
+}
+
 /// Start a new session for Release Health.
 ///
 /// This is still **experimental** for the moment and is not recommended to be
@@ -1,19 +1,33 @@
 use std::any::TypeId;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::panic::RefUnwindSafe;
-use std::sync::Arc;
-use std::sync::RwLock;
-use std::time::Duration;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
 use rand::random;
 use sentry_types::protocol::v7::SessionUpdate;
 
 use crate::constants::SDK_INFO;
-use crate::protocol::{ClientSdkInfo, Event};
+use crate::limits::{limit_event, move_large_values};
+use crate::metrics::{Metric, MetricAggregator};
+use crate::protocol::{
+    Attachment, Breadcrumb, ClientSdkInfo, EnvelopeItem, Event, Level, Transaction,
+};
 use crate::session::SessionFlusher;
-use crate::types::{Dsn, Uuid};
-use crate::{ClientOptions, Envelope, Hub, Integration, Scope, Transport};
+use crate::types::{DateTime, Dsn, Utc, Uuid};
+use crate::{
+    ClientOptions, DeliveryHandle, DeliveryReceipt, DeliveryStatus, Envelope, Hub, Integration,
+    QueueStats, Scope, TransactionContext, Transport, TransportError,
+};
+
+/// Timestamps older than this when captured were set explicitly, rather than being
+/// read from the system clock when the event or breadcrumb was created.
+const DEFAULT_TIMESTAMP_MAX_AGE: Duration = Duration::from_secs(10);
 
 impl<T: Into<ClientOptions>> From<T> for Client {
     fn from(o: T) -> Client {
@@ -44,8 +58,12 @@ pub struct Client {
     options: ClientOptions,
     transport: TransportArc,
     session_flusher: SessionFlusher,
+    metric_aggregator: MetricAggregator,
     integrations: Vec<(TypeId, Arc<dyn Integration>)>,
     sdk_info: ClientSdkInfo,
+    breadcrumb_counters: Mutex<HashMap<&'static str, usize>>,
+    dsn_transports: Mutex<HashMap<Dsn, Arc<dyn Transport>>>,
+    last_timestamp: Mutex<Option<(DateTime<Utc>, Instant)>>,
 }
 
 impl fmt::Debug for Client {
@@ -61,12 +79,17 @@ impl Clone for Client {
     fn clone(&self) -> Client {
         let transport = Arc::new(RwLock::new(self.transport.read().unwrap().clone()));
         let session_flusher = SessionFlusher::new(transport.clone());
+        let metric_aggregator = MetricAggregator::new(transport.clone());
         Client {
             options: self.options.clone(),
             transport,
             session_flusher,
+            metric_aggregator,
             integrations: self.integrations.clone(),
             sdk_info: self.sdk_info.clone(),
+            breadcrumb_counters: Default::default(),
+            dsn_transports: Default::default(),
+            last_timestamp: Default::default(),
         }
     }
 }
@@ -106,6 +129,9 @@ impl Client {
         Hub::with(|_| {});
 
         let create_transport = || {
+            if options.dry_run {
+                return Some(Arc::new(DryRunTransport) as Arc<dyn Transport>);
+            }
             options.dsn.as_ref()?;
             let factory = options.transport.as_ref()?;
             Some(factory.create_transport(&options))
@@ -129,12 +155,17 @@ impl Client {
         }
 
         let session_flusher = SessionFlusher::new(transport.clone());
+        let metric_aggregator = MetricAggregator::new(transport.clone());
         Client {
             options,
             transport,
             session_flusher,
+            metric_aggregator,
             integrations,
             sdk_info,
+            breadcrumb_counters: Default::default(),
+            dsn_transports: Default::default(),
+            last_timestamp: Default::default(),
         }
     }
 
@@ -166,6 +197,8 @@ impl Client {
             event.event_id = Uuid::new_v4();
         }
 
+        event.timestamp = self.timestamp(event.timestamp);
+
         if event.sdk.is_none() {
             // NOTE: we need to clone here because `Event` must be `'static`
             event.sdk = Some(Cow::Owned(self.sdk_info.clone()));
@@ -189,6 +222,14 @@ impl Client {
             }
         }
 
+        if let Some(ref func) = self.options.fingerprint_fn {
+            if is_default_fingerprint(&event.fingerprint) {
+                if let Some(fingerprint) = func(&event) {
+                    event.fingerprint = fingerprint.into();
+                }
+            }
+        }
+
         if event.release.is_none() {
             event.release = self.options.release.clone();
         }
@@ -219,6 +260,59 @@ impl Client {
         &self.options
     }
 
+    /// Returns the timestamp of an event or breadcrumb that was recorded at `recorded`.
+    ///
+    /// With a configured `clock`, the clock is read instead.  Otherwise, timestamps
+    /// earlier than the previous one continue from it with the monotonic clock.
+    ///
+    /// Timestamps from well before now were set explicitly and are returned unchanged.
+    pub(crate) fn timestamp(&self, recorded: DateTime<Utc>) -> DateTime<Utc> {
+        if let Ok(age) = SystemTime::now().duration_since(recorded.into()) {
+            if age > DEFAULT_TIMESTAMP_MAX_AGE {
+                return recorded;
+            }
+        }
+        if let Some(ref clock) = self.options.clock {
+            return clock();
+        }
+        let now = Instant::now();
+        let mut last = self.last_timestamp.lock().unwrap();
+        let timestamp = match *last {
+            Some((last_timestamp, last_instant)) if recorded < last_timestamp => {
+                let elapsed = now.duration_since(last_instant);
+                DateTime::from(SystemTime::from(last_timestamp) + elapsed)
+            }
+            _ => recorded,
+        };
+        *last = Some((timestamp, now));
+        timestamp
+    }
+
+    /// Returns `true` if the breadcrumb is kept by the `breadcrumb_sample_rates`.
+    pub(crate) fn sample_breadcrumb(&self, breadcrumb: &Breadcrumb) -> bool {
+        let category = match breadcrumb.category {
+            Some(ref category) => category.as_str(),
+            None => return true,
+        };
+        let (category, rate) = match self
+            .options
+            .breadcrumb_sample_rates
+            .iter()
+            .find(|(c, _)| *c == category)
+        {
+            Some(&(category, rate)) if rate > 1 => (category, rate),
+            _ => return true,
+        };
+        let mut counters = self
+            .breadcrumb_counters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let counter = counters.entry(category).or_insert(0);
+        let keep = *counter % rate == 0;
+        *counter += 1;
+        keep
+    }
+
     /// Returns the DSN that constructed this client.
     pub fn dsn(&self) -> Option<&Dsn> {
         self.options.dsn.as_ref()
@@ -248,37 +342,345 @@ impl Client {
     /// assert!(client.is_enabled());
     /// ```
     pub fn is_enabled(&self) -> bool {
-        self.options.dsn.is_some() && self.transport.read().unwrap().is_some()
+        (self.options.dsn.is_some() || self.options.dry_run)
+            && self.transport.read().unwrap().is_some()
+    }
+
+    /// Checks whether the client is able to send events.
+    ///
+    /// The client is healthy if it is enabled, and its transport reports that it
+    /// is healthy, which means that the transport worker is still running and is
+    /// not dropping events because of rate limits.  This can be reported on the
+    /// health endpoint of an application.
+    pub fn is_healthy(&self) -> bool {
+        (self.options.dsn.is_some() || self.options.dry_run)
+            && self
+                .transport
+                .read()
+                .unwrap()
+                .as_ref()
+                .map_or(false, |transport| transport.is_healthy())
     }
 
     /// Captures an event and sends it to sentry.
     pub fn capture_event(&self, event: Event<'static>, scope: Option<&Scope>) -> Uuid {
+        self.capture_event_internal(event, scope, None)
+    }
+
+    /// Captures an event and returns a handle to wait for it to be sent.
+    ///
+    /// This is useful on critical error paths, for example right before the
+    /// process exits, to confirm the event actually left the machine.  See
+    /// [`DeliveryHandle`] for details.
+    ///
+    /// [`DeliveryHandle`]: struct.DeliveryHandle.html
+    pub fn capture_event_with_receipt(
+        &self,
+        event: Event<'static>,
+        scope: Option<&Scope>,
+    ) -> DeliveryHandle {
+        let (receipt, handle) = DeliveryHandle::new();
+        let event_id = self.capture_event_internal(event, scope, Some(receipt));
+        handle.with_event_id(event_id)
+    }
+
+    /// Captures multiple events at once, for example the validation errors collected
+    /// by a batch job, and returns their IDs.
+    ///
+    /// Sentry accepts a single event per envelope, so every event is still sent in its
+    /// own envelope, but the transport is only resolved once for all events.  The IDs of
+    /// events that were discarded are nil UUIDs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sentry::protocol::{Event, Level};
+    ///
+    /// # let events = sentry::test::with_captured_events(|| {
+    /// let errors = vec!["row 3: missing id", "row 7: invalid date"];
+    /// let events = errors.into_iter().map(|error| Event {
+    ///     message: Some(error.into()),
+    ///     level: Level::Warning,
+    ///     ..Default::default()
+    /// });
+    ///
+    /// if let Some(client) = sentry::Hub::current().client() {
+    ///     let ids = client.capture_events(events, None);
+    ///     assert_eq!(ids.len(), 2);
+    /// }
+    /// # });
+    /// # assert_eq!(events.len(), 2);
+    /// ```
+    pub fn capture_events<I>(&self, events: I, scope: Option<&Scope>) -> Vec<Uuid>
+    where
+        I: IntoIterator<Item = Event<'static>>,
+    {
+        let transport = match *self.transport.read().unwrap() {
+            Some(ref transport) => match scope.and_then(|scope| scope.dsn.as_ref()) {
+                Some(dsn) => self.transport_for_dsn(dsn, transport),
+                None => transport.clone(),
+            },
+            None => return events.into_iter().map(|_| Uuid::nil()).collect(),
+        };
+        events
+            .into_iter()
+            .map(|event| match self.envelope_from_event(event, scope) {
+                Some((event_id, mut envelope)) => {
+                    if let Some(session_item) = scope.and_then(session_item) {
+                        envelope.add_item(session_item);
+                    }
+                    self.dispatch(&transport, envelope, None);
+                    event_id
+                }
+                None => Uuid::nil(),
+            })
+            .collect()
+    }
+
+    fn capture_event_internal(
+        &self,
+        event: Event<'static>,
+        scope: Option<&Scope>,
+        receipt: Option<DeliveryReceipt>,
+    ) -> Uuid {
         if let Some(ref transport) = *self.transport.read().unwrap() {
-            if let Some(event) = self.prepare_event(event, scope) {
-                let event_id = event.event_id;
-                let mut envelope: Envelope = event.into();
-                let session_item = scope.and_then(|scope| {
-                    scope
-                        .session
-                        .lock()
-                        .unwrap()
-                        .as_mut()
-                        .and_then(|session| session.create_envelope_item())
-                });
-                if let Some(session_item) = session_item {
+            let transport = match scope.and_then(|scope| scope.dsn.as_ref()) {
+                Some(dsn) => self.transport_for_dsn(dsn, transport),
+                None => transport.clone(),
+            };
+            if let Some((event_id, mut envelope)) = self.envelope_from_event(event, scope) {
+                if let Some(session_item) = scope.and_then(session_item) {
                     envelope.add_item(session_item);
                 }
-                transport.send_envelope(envelope);
+                self.dispatch(&transport, envelope, receipt);
                 return event_id;
             }
         }
         Default::default()
     }
 
+    /// Prepares an event, and packs it into an envelope together with its attachments.
+    ///
+    /// Returns `None` if the event was discarded.
+    fn envelope_from_event(
+        &self,
+        event: Event<'static>,
+        scope: Option<&Scope>,
+    ) -> Option<(Uuid, Envelope)> {
+        let mut event = self.prepare_event(event, scope)?;
+        let event_id = event.event_id;
+        let mut attachments = match self.options.max_inline_value_size {
+            Some(max_size) => move_large_values(&mut event, max_size),
+            None => Vec::new(),
+        };
+        limit_event(&mut event, &self.options);
+        attachments.extend(
+            self.integrations
+                .iter()
+                .flat_map(|(_, integration)| integration.attachments(&event, &self.options)),
+        );
+        attachments.extend(self.screenshot(&event));
+        attachments.extend(self.log_file_tail(&event));
+        let mut envelope: Envelope = event.into();
+        for attachment in attachments {
+            envelope.add_item(attachment);
+        }
+        Some((event_id, envelope))
+    }
+
+    /// Captures a screenshot for `error` and `fatal` events, if a callback is configured.
+    fn screenshot(&self, event: &Event<'static>) -> Option<Attachment> {
+        let func = self.options.screenshot_fn.as_ref()?;
+        match event.level {
+            Level::Error | Level::Fatal => {}
+            _ => return None,
+        }
+        sentry_debug!("invoking screenshot callback");
+        Some(Attachment {
+            buffer: func()?,
+            filename: "screenshot.png".into(),
+            content_type: Some("image/png".into()),
+            ..Default::default()
+        })
+    }
+
+    /// Reads the end of the configured log file for `fatal` events.
+    fn log_file_tail(&self, event: &Event<'static>) -> Option<Attachment> {
+        let path = self.options.log_file.as_ref()?;
+        if event.level != Level::Fatal {
+            return None;
+        }
+        match read_tail(path, self.options.log_file_tail_bytes) {
+            Ok(buffer) => Some(Attachment {
+                buffer,
+                filename: path
+                    .file_name()
+                    .map_or_else(|| "log.txt".into(), |name| name.to_string_lossy().into()),
+                content_type: Some("text/plain".into()),
+                ..Default::default()
+            }),
+            Err(err) => {
+                sentry_debug!("failed to read log file {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    /// Returns the transport that sends envelopes to the given DSN.
+    ///
+    /// Transports for DSNs other than the one of the client are created from
+    /// the configured transport factory on first use, and reused afterwards.
+    fn transport_for_dsn(&self, dsn: &Dsn, default: &Arc<dyn Transport>) -> Arc<dyn Transport> {
+        if self.options.dry_run || self.options.dsn.as_ref() == Some(dsn) {
+            return default.clone();
+        }
+        let factory = match self.options.transport {
+            Some(ref factory) => factory,
+            None => return default.clone(),
+        };
+        let mut transports = self
+            .dsn_transports
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        transports
+            .entry(dsn.clone())
+            .or_insert_with(|| {
+                sentry_debug!("creating transport for DSN {}", dsn);
+                let options = ClientOptions {
+                    dsn: Some(dsn.clone()),
+                    ..self.options.clone()
+                };
+                factory.create_transport(&options)
+            })
+            .clone()
+    }
+
+    /// Hands an envelope to the transport.
+    ///
+    /// If `on_transport_error` is configured, the envelope is always sent with a
+    /// receipt, so that failures can be reported to the callback.
+    fn dispatch(
+        &self,
+        transport: &Arc<dyn Transport>,
+        envelope: Envelope,
+        receipt: Option<DeliveryReceipt>,
+    ) {
+        let receipt = match self.options.on_transport_error {
+            Some(ref callback) => {
+                let callback = callback.clone();
+                let event_id = envelope.uuid().copied();
+                let receipt = receipt.unwrap_or_else(|| DeliveryHandle::new().0);
+                Some(receipt.on_report(move |status| {
+                    if let DeliveryStatus::Failed(ref message) = *status {
+                        callback(&TransportError {
+                            event_id,
+                            message: message.clone(),
+                        });
+                    }
+                }))
+            }
+            None => receipt,
+        };
+        match receipt {
+            Some(receipt) => transport.send_envelope_with_receipt(envelope, receipt),
+            None => transport.send_envelope(envelope),
+        }
+    }
+
+    /// Returns all transports created for DSN overrides.
+    fn dsn_transports(&self) -> Vec<Arc<dyn Transport>> {
+        let transports = self
+            .dsn_transports
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        transports.values().cloned().collect()
+    }
+
+    /// Sends an envelope to sentry as-is.
+    ///
+    /// Unlike `capture_event`, events contained in the envelope are not run through
+    /// the scope, integrations or `before_send`, which allows sending envelopes that
+    /// were prepared earlier, for example by a previous run of the application.
+    pub fn send_envelope(&self, envelope: Envelope) {
+        if let Some(ref transport) = *self.transport.read().unwrap() {
+            self.dispatch(transport, envelope, None);
+        }
+    }
+
     pub(crate) fn enqueue_session(&self, session_update: SessionUpdate<'static>) {
         self.session_flusher.enqueue(session_update)
     }
 
+    /// Adds a metric to the aggregator of this client.
+    ///
+    /// Metrics are aggregated into buckets of ten seconds, which are sent
+    /// periodically, or when the client is flushed.
+    pub fn add_metric(&self, metric: Metric) {
+        self.metric_aggregator.add(metric)
+    }
+
+    /// Returns the number of envelopes waiting to be sent.
+    ///
+    /// This can be used to decide whether a flush is needed, for example to only
+    /// extend the deadline of a shutdown sequence if something is still pending.
+    pub fn pending_events(&self) -> usize {
+        self.queue_stats().pending
+    }
+
+    /// Returns the number of envelopes the transports dropped without sending
+    /// them, for instance because their queue was full or because of rate limits.
+    pub fn dropped_events(&self) -> usize {
+        self.queue_stats().dropped
+    }
+
+    /// Returns the number of envelopes that are currently being sent.
+    pub fn in_flight(&self) -> usize {
+        self.queue_stats().in_flight
+    }
+
+    /// Sums up the queue statistics of all transports of this client.
+    fn queue_stats(&self) -> QueueStats {
+        let mut transports = self.dsn_transports();
+        transports.extend(self.transport.read().unwrap().clone());
+        transports
+            .iter()
+            .fold(QueueStats::default(), |stats, transport| {
+                let other = transport.queue_stats();
+                QueueStats {
+                    pending: stats.pending + other.pending,
+                    in_flight: stats.in_flight + other.in_flight,
+                    dropped: stats.dropped + other.dropped,
+                }
+            })
+    }
+
+    /// Drains all pending events without shutting down the transport.
+    ///
+    /// This returns `true` if the queue was successfully drained in the
+    /// given time or `false` if not (for instance because of a timeout).
+    /// If no timeout is provided the client will wait for as long a
+    /// `shutdown_timeout` in the client options.
+    ///
+    /// This is useful in environments where the process might be frozen
+    /// or terminated without dropping the client, like serverless functions.
+    pub fn flush(&self, timeout: Option<Duration>) -> bool {
+        self.session_flusher.flush_queue();
+        self.metric_aggregator.flush_buckets();
+        let timeout = timeout.unwrap_or(self.options.shutdown_timeout);
+        let flushed = self
+            .dsn_transports()
+            .iter()
+            .fold(true, |flushed, transport| {
+                transport.flush(timeout) && flushed
+            });
+        if let Some(ref transport) = *self.transport.read().unwrap() {
+            sentry_debug!("client flush; request transport to flush");
+            transport.flush(timeout) && flushed
+        } else {
+            flushed
+        }
+    }
+
     /// Drains all pending events and shuts down the transport behind the
     /// client.  After shutting down the transport is removed.
     ///
@@ -287,13 +689,22 @@ impl Client {
     /// If no timeout is provided the client will wait for as long a
     /// `shutdown_timeout` in the client options.
     pub fn close(&self, timeout: Option<Duration>) -> bool {
+        let timeout = timeout.unwrap_or(self.options.shutdown_timeout);
         let transport_opt = self.transport.write().unwrap().take();
+        let closed = self
+            .dsn_transports
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain()
+            .fold(true, |closed, (_, transport)| {
+                transport.shutdown(timeout) && closed
+            });
         if let Some(transport) = transport_opt {
             sentry_debug!("client close; request transport to shut down");
-            transport.shutdown(timeout.unwrap_or(self.options.shutdown_timeout))
+            transport.shutdown(timeout) && closed
         } else {
             sentry_debug!("client close; no transport to shut down");
-            true
+            closed
         }
     }
 
@@ -305,8 +716,110 @@ impl Client {
             random::<f32>() <= rate
         }
     }
+
+    /// Decides whether a new transaction is sampled.
+    ///
+    /// An explicit decision of the context wins, followed by the `traces_sampler`, the
+    /// decision of the parent trace, and finally the `traces_sample_rate`.
+    pub(crate) fn sample_transaction(&self, ctx: &TransactionContext) -> bool {
+        if let Some(sampled) = ctx.sampled() {
+            return sampled;
+        }
+        let rate = match self.options.traces_sampler {
+            Some(ref sampler) => sampler(ctx),
+            None => match ctx.parent_sampled() {
+                Some(sampled) => return sampled,
+                None => self.options.traces_sample_rate,
+            },
+        };
+        if rate >= 1.0 {
+            true
+        } else {
+            rate > 0.0 && random::<f32>() <= rate
+        }
+    }
+
+    /// Runs a newly started transaction through the integrations.
+    pub(crate) fn start_transaction(&self, transaction: &mut Transaction<'static>) {
+        for (_, integration) in self.integrations.iter() {
+            integration.start_transaction(transaction);
+        }
+    }
+
+    /// Fills in the release, environment and SDK info of a transaction, runs it
+    /// through the integrations, and sends it.
+    pub(crate) fn send_transaction(&self, mut transaction: Transaction<'static>) {
+        if transaction.release.is_none() {
+            transaction.release = self.options.release.clone();
+        }
+        if transaction.environment.is_none() {
+            transaction.environment = self.options.environment.clone();
+        }
+        transaction.sdk = Some(Cow::Owned(self.sdk_info.clone()));
+        for (_, integration) in self.integrations.iter() {
+            let id = transaction.event_id;
+            transaction = match integration.process_transaction(transaction, &self.options) {
+                Some(transaction) => transaction,
+                None => {
+                    sentry_debug!("integration dropped transaction {:?}", id);
+                    return;
+                }
+            }
+        }
+        self.send_envelope(transaction.into());
+    }
+}
+
+/// Reads at most `max_bytes` from the end of a file, starting at a complete line.
+fn read_tail(path: &Path, max_bytes: usize) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    // also read the byte before the tail, to tell whether the tail starts a line
+    let start = len.saturating_sub(max_bytes as u64 + 1);
+    file.seek(SeekFrom::Start(start))?;
+    let mut buffer = Vec::with_capacity((len - start) as usize);
+    file.read_to_end(&mut buffer)?;
+    if buffer.len() > max_bytes {
+        let cut = buffer
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(buffer.len(), |i| i + 1);
+        buffer.drain(..cut);
+    }
+    Ok(buffer)
+}
+
+/// The transport used in `dry_run` mode, which writes envelopes to the debug
+/// output instead of sending them.
+struct DryRunTransport;
+
+impl Transport for DryRunTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        let mut body = Vec::new();
+        if envelope.to_writer(&mut body).is_err() {
+            return;
+        }
+        sentry_debug!(
+            "dry run, not sending envelope:\n{}",
+            String::from_utf8_lossy(&body)
+        );
+    }
 }
 
 // Make this unwind safe. It's not out of the box because of the
 // `BeforeCallback`s inside `ClientOptions`, and the contained Integrations
 impl RefUnwindSafe for Client {}
+
+/// Returns the pending update of the session of the `scope`, if there is one.
+fn session_item(scope: &Scope) -> Option<EnvelopeItem> {
+    scope
+        .session
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|session| session.create_envelope_item())
+}
+
+fn is_default_fingerprint(fingerprint: &[Cow<'_, str>]) -> bool {
+    fingerprint.len() == 1 && fingerprint[0] == crate::fingerprint::DEFAULT
+}
@@ -1,16 +1,35 @@
 use std::borrow::Cow;
 use std::fmt;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
 use crate::constants::USER_AGENT;
 use crate::protocol::{Breadcrumb, Event};
-use crate::types::Dsn;
-use crate::{Integration, IntoDsn, TransportFactory};
+use crate::types::{DateTime, Dsn, ParseDsnError, Utc};
+use crate::{
+    BacktraceTrimmer, Integration, IntoDsn, TransactionContext, TransportError, TransportFactory,
+};
 
 /// Type alias for before event/breadcrumb handlers.
 pub type BeforeCallback<T> = Arc<dyn Fn(T) -> Option<T> + Send + Sync>;
 
+/// Type alias for the fingerprint callback.
+pub type FingerprintCallback =
+    Arc<dyn Fn(&Event<'static>) -> Option<Vec<Cow<'static, str>>> + Send + Sync>;
+
+/// Type alias for the screenshot callback.
+pub type ScreenshotCallback = Arc<dyn Fn() -> Option<Vec<u8>> + Send + Sync>;
+
+/// Type alias for the clock callback.
+pub type ClockCallback = Arc<dyn Fn() -> DateTime<Utc> + Send + Sync>;
+
+/// Type alias for the transport error callback.
+pub type TransportErrorCallback = Arc<dyn Fn(&TransportError) + Send + Sync>;
+
+/// Type alias for the traces sampler.
+pub type TracesSampler = Arc<dyn Fn(&TransactionContext) -> f32 + Send + Sync>;
+
 /// Configuration settings for the client.
 ///
 /// These options are explained in more detail in the general
@@ -35,6 +54,13 @@ pub struct ClientOptions {
     /// sentry is doing.  When the `log` feature is enabled, Sentry will instead
     /// log to the `sentry` logger independently of this flag with the `Debug` level.
     pub debug: bool,
+    /// Enables dry-run mode.
+    ///
+    /// In dry-run mode events run through the full pipeline, including the
+    /// scope, integrations and `before_send`, but the resulting envelopes are
+    /// written to the debug output, which is enabled by `debug`, instead of being
+    /// sent.  This works without a DSN, and replaces any configured transport.
+    pub dry_run: bool,
     /// The release to be sent with events.
     pub release: Option<Cow<'static, str>>,
     /// The environment to be sent with events.
@@ -44,10 +70,25 @@ pub struct ClientOptions {
     pub environment: Option<Cow<'static, str>>,
     /// The sample rate for event submission. (0.0 - 1.0, defaults to 1.0)
     pub sample_rate: f32,
+    /// The sample rate for tracing transactions. (0.0 - 1.0, defaults to 0.0)
+    pub traces_sample_rate: f32,
     /// Maximum number of breadcrumbs. (defaults to 100)
     pub max_breadcrumbs: usize,
+    /// Keeps only one in `n` breadcrumbs of the given categories.
+    ///
+    /// For example `("db.query", 10)` keeps every tenth `db.query` breadcrumb,
+    /// so high-frequency instrumentation does not evict rarer breadcrumbs.
+    pub breadcrumb_sample_rates: Vec<(&'static str, usize)>,
+    /// The maximum number of breadcrumbs kept for the given categories.
+    ///
+    /// Once a category reaches its limit, its own oldest breadcrumb is
+    /// dropped instead of the oldest breadcrumb overall.
+    pub max_breadcrumbs_per_category: Vec<(&'static str, usize)>,
     /// Attaches stacktraces to messages.
     pub attach_stacktrace: bool,
+    /// Captures stacktraces without symbols and resolves them on the transport
+    /// worker right before sending, keeping symbolication off the hot path.
+    pub defer_symbolication: bool,
     /// If turned on some default PII informat is attached.
     pub send_default_pii: bool,
     /// The server name to be reported.
@@ -72,6 +113,46 @@ pub struct ClientOptions {
     pub before_send: Option<BeforeCallback<Event<'static>>>,
     /// Callback that is executed for each Breadcrumb being added.
     pub before_breadcrumb: Option<BeforeCallback<Breadcrumb>>,
+    /// Callback that computes the fingerprint of events that still have the default
+    /// fingerprint, after the scope and integrations have been applied.
+    ///
+    /// Returning `None` keeps the default grouping.  See the
+    /// [`fingerprint`](fingerprint/index.html) module for helpers.
+    pub fingerprint_fn: Option<FingerprintCallback>,
+    /// Callback that captures a screenshot of the application as a PNG image.
+    ///
+    /// It is called for every `error` and `fatal` event, and the image is sent
+    /// along with the event as `screenshot.png`.  Returning `None` sends the event
+    /// without a screenshot.  The callback runs on the thread capturing the event,
+    /// which may be the UI thread itself, so it must not block on the UI thread.
+    pub screenshot_fn: Option<ScreenshotCallback>,
+    /// Callback that returns the current time, which is used as the timestamp of
+    /// events and breadcrumbs.
+    ///
+    /// This is useful for tests, and for platforms without a reliable wall clock.
+    /// Without a clock, the system time is used, but a timestamp is never earlier
+    /// than a previous one: if the system time jumps backwards, for instance due to
+    /// NTP, timestamps continue from the previous one using a monotonic clock, so
+    /// that breadcrumbs stay in order.  Timestamps that were set explicitly to a
+    /// time more than a few seconds in the past are never changed.
+    /// (defaults to `None`)
+    pub clock: Option<ClockCallback>,
+    /// Callback that is executed when the transport gives up on sending an envelope.
+    ///
+    /// This is called after retries are exhausted, on the thread of the transport,
+    /// and can be used to alert about lost events or to persist them elsewhere.  It
+    /// is only called by transports that report the outcome of deliveries, which
+    /// the transports of the `sentry` crate do.  (defaults to `None`)
+    pub on_transport_error: Option<TransportErrorCallback>,
+    /// Callback that returns the sample rate of a new transaction, overriding both
+    /// `traces_sample_rate` and the sampling decision of the parent trace.
+    ///
+    /// The sampler gets the [`TransactionContext`] of the transaction, whose
+    /// [`parent_sampled`](TransactionContext::parent_sampled) is the decision of the
+    /// upstream service, so that a sampler can keep traces complete by returning `1.0`
+    /// if the parent was sampled.  Without a sampler, a transaction that continues a
+    /// trace inherits the decision of its parent.  (defaults to `None`)
+    pub traces_sampler: Option<TracesSampler>,
     // Transport options
     /// The transport to use.
     ///
@@ -90,6 +171,12 @@ pub struct ClientOptions {
     pub https_proxy: Option<Cow<'static, str>>,
     /// The timeout on client drop for draining events on shutdown.
     pub shutdown_timeout: Duration,
+    /// The number of requests the default `reqwest` transport sends
+    /// concurrently.  (defaults to 1)
+    ///
+    /// Rate limits reported by Sentry apply to all senders.  Note that
+    /// envelopes may arrive out of order with more than one sender.
+    pub transport_senders: usize,
     // Other options not documented in Unified API
     /// Enable Release Health Session tracking.
     ///
@@ -102,8 +189,71 @@ pub struct ClientOptions {
     pub extra_border_frames: Vec<&'static str>,
     /// Automatically trim backtraces of junk before sending. (defaults to true)
     pub trim_backtraces: bool,
+    /// The rules used to trim backtraces if `trim_backtraces` is enabled.
+    ///
+    /// See [`BacktraceTrimmer`](struct.BacktraceTrimmer.html) for how to add
+    /// custom border frames, or to disable trimming.
+    pub backtrace_trimmer: BacktraceTrimmer,
+    /// The number of source lines to attach before and after the line of each
+    /// in-app frame. (defaults to 0, which disables source context)
+    ///
+    /// The source files are read from disk at the time the event is captured,
+    /// so this is mostly useful for debug builds and internal tools that run
+    /// on the machine they were built on.
+    pub source_context_lines: usize,
+    /// The number of levels of nested arrays and objects that are kept in the
+    /// `extra` and `contexts` of events, and in the `data` of breadcrumbs.
+    ///
+    /// Deeper arrays and objects are replaced by `"[Array]"` and `"[Object]"`.
+    /// (defaults to 10)
+    pub max_value_depth: usize,
+    /// The number of characters after which strings in the `extra` and `contexts`
+    /// of events, and in the `data` of breadcrumbs, are truncated.
+    ///
+    /// Truncated strings end with `…`.  (defaults to 8192)
+    pub max_value_length: usize,
+    /// The size in bytes above which extra values and messages are sent as
+    /// attachments instead of as part of the event.
+    ///
+    /// This preserves large values that would otherwise be truncated, or exceed the
+    /// size limits of Sentry for events.  The event keeps a reference to the
+    /// attachment, and the start of the message.  (defaults to `None`, which
+    /// disables this)
+    pub max_inline_value_size: Option<usize>,
     /// The user agent that should be reported.
     pub user_agent: Cow<'static, str>,
+    /// A directory in which the SDK keeps state across restarts of the application.
+    ///
+    /// When set, `sentry::init` writes a marker for the current process into
+    /// this directory, which is removed again when the client init guard is
+    /// dropped.  If a marker of a process that is no longer running still
+    /// exists on the next start, that process crashed before it could shut
+    /// down, and an event is sent to report this.  The liveness of other
+    /// processes is only checked on unix, so elsewhere, processes should not
+    /// share this directory.  As the marker is only removed by the guard, this
+    /// option should not be combined with calling `mem::forget` on it.
+    ///
+    /// The HTTP transports also persist envelopes into this directory that
+    /// could not be sent because of a network error, or that were still queued
+    /// when the `shutdown_timeout` expired.  They are sent again by the next
+    /// call to `sentry::init`.  (defaults to `None`, which disables this)
+    ///
+    /// Envelopes that do not fit into the queue of the HTTP transports during
+    /// an outage are spilled into this directory as well, instead of being
+    /// dropped.  They are moved back into the queue in order once sending
+    /// succeeds again.
+    pub cache_dir: Option<PathBuf>,
+    /// A log file whose end is attached to `fatal` events, such as panics and
+    /// crashes.
+    ///
+    /// This ships the log lines that did not make it into breadcrumbs along with
+    /// the crash.  (defaults to `None`)
+    pub log_file: Option<PathBuf>,
+    /// The number of bytes at the end of the `log_file` that are attached.
+    ///
+    /// The attachment starts at the first complete line within this limit.
+    /// (defaults to 64 KiB)
+    pub log_file_tail_bytes: usize,
 }
 
 impl ClientOptions {
@@ -139,6 +289,21 @@ impl fmt::Debug for ClientOptions {
         struct BeforeBreadcrumb;
         let before_breadcrumb = self.before_breadcrumb.as_ref().map(|_| BeforeBreadcrumb);
         #[derive(Debug)]
+        struct FingerprintFn;
+        let fingerprint_fn = self.fingerprint_fn.as_ref().map(|_| FingerprintFn);
+        #[derive(Debug)]
+        struct ScreenshotFn;
+        let screenshot_fn = self.screenshot_fn.as_ref().map(|_| ScreenshotFn);
+        #[derive(Debug)]
+        struct Clock;
+        let clock = self.clock.as_ref().map(|_| Clock);
+        #[derive(Debug)]
+        struct OnTransportError;
+        let on_transport_error = self.on_transport_error.as_ref().map(|_| OnTransportError);
+        #[derive(Debug)]
+        struct TracesSampler;
+        let traces_sampler = self.traces_sampler.as_ref().map(|_| TracesSampler);
+        #[derive(Debug)]
         struct TransportFactory;
 
         let integrations: Vec<_> = self.integrations.iter().map(|i| i.name()).collect();
@@ -146,11 +311,19 @@ impl fmt::Debug for ClientOptions {
         f.debug_struct("ClientOptions")
             .field("dsn", &self.dsn)
             .field("debug", &self.debug)
+            .field("dry_run", &self.dry_run)
             .field("release", &self.release)
             .field("environment", &self.environment)
             .field("sample_rate", &self.sample_rate)
+            .field("traces_sample_rate", &self.traces_sample_rate)
             .field("max_breadcrumbs", &self.max_breadcrumbs)
+            .field("breadcrumb_sample_rates", &self.breadcrumb_sample_rates)
+            .field(
+                "max_breadcrumbs_per_category",
+                &self.max_breadcrumbs_per_category,
+            )
             .field("attach_stacktrace", &self.attach_stacktrace)
+            .field("defer_symbolication", &self.defer_symbolication)
             .field("send_default_pii", &self.send_default_pii)
             .field("server_name", &self.server_name)
             .field("in_app_include", &self.in_app_include)
@@ -159,14 +332,28 @@ impl fmt::Debug for ClientOptions {
             .field("default_integrations", &self.default_integrations)
             .field("before_send", &before_send)
             .field("before_breadcrumb", &before_breadcrumb)
+            .field("fingerprint_fn", &fingerprint_fn)
+            .field("screenshot_fn", &screenshot_fn)
+            .field("clock", &clock)
+            .field("on_transport_error", &on_transport_error)
+            .field("traces_sampler", &traces_sampler)
             .field("transport", &TransportFactory)
             .field("http_proxy", &self.http_proxy)
             .field("https_proxy", &self.https_proxy)
             .field("shutdown_timeout", &self.shutdown_timeout)
+            .field("transport_senders", &self.transport_senders)
             .field("auto_session_tracking", &self.auto_session_tracking)
             .field("extra_border_frames", &self.extra_border_frames)
             .field("trim_backtraces", &self.trim_backtraces)
+            .field("backtrace_trimmer", &self.backtrace_trimmer)
+            .field("source_context_lines", &self.source_context_lines)
+            .field("max_value_depth", &self.max_value_depth)
+            .field("max_value_length", &self.max_value_length)
+            .field("max_inline_value_size", &self.max_inline_value_size)
             .field("user_agent", &self.user_agent)
+            .field("cache_dir", &self.cache_dir)
+            .field("log_file", &self.log_file)
+            .field("log_file_tail_bytes", &self.log_file_tail_bytes)
             .finish()
     }
 }
@@ -176,11 +363,16 @@ impl Default for ClientOptions {
         ClientOptions {
             dsn: None,
             debug: false,
+            dry_run: false,
             release: None,
             environment: None,
             sample_rate: 1.0,
+            traces_sample_rate: 0.0,
             max_breadcrumbs: 100,
+            breadcrumb_sample_rates: vec![],
+            max_breadcrumbs_per_category: vec![],
             attach_stacktrace: false,
+            defer_symbolication: false,
             send_default_pii: false,
             server_name: None,
             in_app_include: vec![],
@@ -189,14 +381,28 @@ impl Default for ClientOptions {
             default_integrations: true,
             before_send: None,
             before_breadcrumb: None,
+            fingerprint_fn: None,
+            screenshot_fn: None,
+            clock: None,
+            on_transport_error: None,
+            traces_sampler: None,
             transport: None,
             http_proxy: None,
             https_proxy: None,
             shutdown_timeout: Duration::from_secs(2),
+            transport_senders: 1,
             auto_session_tracking: false,
             extra_border_frames: vec![],
             trim_backtraces: true,
+            backtrace_trimmer: Default::default(),
+            source_context_lines: 0,
+            max_value_depth: 10,
+            max_value_length: 8192,
+            max_inline_value_size: None,
             user_agent: Cow::Borrowed(&USER_AGENT),
+            cache_dir: None,
+            log_file: None,
+            log_file_tail_bytes: 64 * 1024,
         }
     }
 }
@@ -216,3 +422,35 @@ impl<T: IntoDsn> From<T> for ClientOptions {
         }
     }
 }
+
+/// Fallible conversion into [`ClientOptions`].
+///
+/// This mirrors the `From` conversions of [`ClientOptions`], but returns the
+/// DSN parse error instead of panicking on an invalid DSN.
+pub trait TryIntoClientOptions {
+    /// Converts the value into `ClientOptions`, failing on an invalid DSN.
+    fn try_into_client_options(self) -> Result<ClientOptions, ParseDsnError>;
+}
+
+impl TryIntoClientOptions for ClientOptions {
+    fn try_into_client_options(self) -> Result<ClientOptions, ParseDsnError> {
+        Ok(self)
+    }
+}
+
+impl<T: IntoDsn> TryIntoClientOptions for (T, ClientOptions) {
+    fn try_into_client_options(self) -> Result<ClientOptions, ParseDsnError> {
+        let (into_dsn, mut opts) = self;
+        opts.dsn = into_dsn.into_dsn()?;
+        Ok(opts)
+    }
+}
+
+impl<T: IntoDsn> TryIntoClientOptions for T {
+    fn try_into_client_options(self) -> Result<ClientOptions, ParseDsnError> {
+        Ok(ClientOptions {
+            dsn: self.into_dsn()?,
+            ..ClientOptions::default()
+        })
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::protocol::{Breadcrumb, Level, Map};
+
+/// A writer that records the lines written through it as breadcrumbs.
+///
+/// All writes are forwarded to the wrapped writer unchanged.  Every complete
+/// line is additionally recorded as a `console` breadcrumb, truncated to
+/// [`max_length`](Self::max_length) characters.  Incomplete lines are recorded
+/// once the writer is dropped.
+///
+/// This is useful to capture the output of libraries that print errors
+/// instead of returning them.  The output of C libraries, which write to the
+/// file descriptors directly, needs to be redirected through a pipe into this
+/// writer.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// let mut stderr = sentry::ConsoleWriter::stderr();
+/// writeln!(stderr, "failed to open the configuration file").ok();
+/// ```
+pub struct ConsoleWriter<W> {
+    inner: W,
+    stream: &'static str,
+    level: Level,
+    max_length: usize,
+    line: Vec<u8>,
+}
+
+impl<W> fmt::Debug for ConsoleWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsoleWriter")
+            .field("stream", &self.stream)
+            .field("level", &self.level)
+            .field("max_length", &self.max_length)
+            .finish()
+    }
+}
+
+impl ConsoleWriter<io::Stdout> {
+    /// Creates a writer for `stdout`, which records breadcrumbs with level `info`.
+    pub fn stdout() -> Self {
+        ConsoleWriter::new(io::stdout(), "stdout")
+    }
+}
+
+impl ConsoleWriter<io::Stderr> {
+    /// Creates a writer for `stderr`, which records breadcrumbs with level `warning`.
+    pub fn stderr() -> Self {
+        ConsoleWriter::new(io::stderr(), "stderr").level(Level::Warning)
+    }
+}
+
+impl<W> ConsoleWriter<W> {
+    /// Creates a writer that forwards to `inner`.
+    ///
+    /// The name of the `stream` is added to the data of the breadcrumbs.
+    pub fn new(inner: W, stream: &'static str) -> Self {
+        ConsoleWriter {
+            inner,
+            stream,
+            level: Level::Info,
+            max_length: 256,
+            line: Vec::new(),
+        }
+    }
+
+    /// Sets the level of the recorded breadcrumbs.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the number of characters after which lines are truncated.
+    ///
+    /// The default is 256 characters.
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Returns a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Appends written bytes to the current line, and records complete lines.
+    fn record(&mut self, mut buf: &[u8]) {
+        while let Some(index) = buf.iter().position(|&b| b == b'\n') {
+            self.append(&buf[..index]);
+            self.add_breadcrumb();
+            buf = &buf[index + 1..];
+        }
+        self.append(buf);
+    }
+
+    fn append(&mut self, buf: &[u8]) {
+        // four bytes per character, the line is truncated when it is recorded
+        let capacity = self.max_length.saturating_mul(4) + 1;
+        let len = buf.len().min(capacity.saturating_sub(self.line.len()));
+        self.line.extend_from_slice(&buf[..len]);
+    }
+
+    fn add_breadcrumb(&mut self) {
+        let line = String::from_utf8_lossy(&self.line);
+        let line = line.trim_end_matches('\r');
+        if !line.trim().is_empty() {
+            let mut message: String = line.chars().take(self.max_length).collect();
+            if message.len() < line.len() {
+                message.push('…');
+            }
+            let mut data = Map::new();
+            data.insert("stream".into(), self.stream.into());
+            crate::add_breadcrumb(Breadcrumb {
+                category: Some("console".into()),
+                message: Some(message),
+                level: self.level,
+                data,
+                ..Default::default()
+            });
+        }
+        self.line.clear();
+    }
+}
+
+impl<W: Write> Write for ConsoleWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.record(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W> Drop for ConsoleWriter<W> {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            self.add_breadcrumb();
+        }
+    }
+}
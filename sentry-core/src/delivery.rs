@@ -0,0 +1,249 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::types::Uuid;
+
+/// The outcome of sending an event, as reported by a [`DeliveryHandle`].
+///
+/// [`DeliveryHandle`]: struct.DeliveryHandle.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeliveryStatus {
+    /// The event was accepted by the server.
+    Sent,
+    /// The event was discarded before it was sent, for example by sampling,
+    /// `before_send`, a full queue or because the client is disabled.
+    Discarded,
+    /// The event was not sent because of rate limits.
+    RateLimited,
+    /// Sending the event failed with the given error.
+    Failed(String),
+    /// The event could not be sent right away, and was persisted to the cache
+    /// directory to be sent on a later attempt.
+    Persisted,
+    /// The event was handed to a transport that does not report deliveries.
+    Unknown,
+}
+
+#[derive(Default)]
+struct Shared {
+    status: Mutex<Option<DeliveryStatus>>,
+    signal: Condvar,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Shared {
+    fn resolve(&self, status: DeliveryStatus) {
+        let mut guard = self.status.lock().unwrap();
+        if guard.is_none() {
+            *guard = Some(status);
+            self.signal.notify_all();
+            if let Some(waker) = self.waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Reports the outcome of sending an event to its [`DeliveryHandle`].
+///
+/// This is passed to [`Transport::send_envelope_with_receipt`].  A receipt that is
+/// dropped without reporting a status reports [`DeliveryStatus::Discarded`].
+///
+/// [`DeliveryHandle`]: struct.DeliveryHandle.html
+/// [`Transport::send_envelope_with_receipt`]: trait.Transport.html#method.send_envelope_with_receipt
+/// [`DeliveryStatus::Discarded`]: enum.DeliveryStatus.html#variant.Discarded
+pub struct DeliveryReceipt {
+    shared: Arc<Shared>,
+    on_report: Option<Box<dyn FnOnce(&DeliveryStatus) + Send>>,
+}
+
+impl DeliveryReceipt {
+    /// Reports the outcome of sending the event.
+    pub fn report(mut self, status: DeliveryStatus) {
+        if let Some(on_report) = self.on_report.take() {
+            on_report(&status);
+        }
+        self.shared.resolve(status);
+    }
+
+    /// Sets a callback that is invoked with the status passed to `report`.
+    #[allow(unused)]
+    pub(crate) fn on_report<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&DeliveryStatus) + Send + 'static,
+    {
+        self.on_report = Some(Box::new(f));
+        self
+    }
+}
+
+impl Drop for DeliveryReceipt {
+    fn drop(&mut self) {
+        self.shared.resolve(DeliveryStatus::Discarded);
+    }
+}
+
+impl std::fmt::Debug for DeliveryReceipt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeliveryReceipt").finish()
+    }
+}
+
+/// A handle to wait for an event to be sent.
+///
+/// This is returned by [`Client::capture_event_with_receipt`] and
+/// [`Hub::capture_event_with_receipt`], and can either be awaited, or waited on
+/// with [`wait`](#method.wait), for example to make sure a critical event was sent
+/// right before the process exits.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use sentry::protocol::{Event, Level};
+/// use sentry::{DeliveryStatus, Hub};
+///
+/// # let _ = sentry::test::with_captured_events(|| {
+/// let handle = Hub::current().capture_event_with_receipt(Event {
+///     message: Some("shutting down".into()),
+///     level: Level::Fatal,
+///     ..Default::default()
+/// });
+/// assert!(!handle.event_id().is_nil());
+/// assert_eq!(
+///     handle.wait(Some(Duration::from_secs(2))),
+///     Some(DeliveryStatus::Sent)
+/// );
+/// # });
+/// ```
+///
+/// [`Client::capture_event_with_receipt`]: struct.Client.html#method.capture_event_with_receipt
+/// [`Hub::capture_event_with_receipt`]: struct.Hub.html#method.capture_event_with_receipt
+pub struct DeliveryHandle {
+    event_id: Uuid,
+    shared: Arc<Shared>,
+}
+
+impl Default for DeliveryHandle {
+    fn default() -> Self {
+        Self::resolved(Uuid::nil(), DeliveryStatus::Discarded)
+    }
+}
+
+impl std::fmt::Debug for DeliveryHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeliveryHandle")
+            .field("event_id", &self.event_id)
+            .field("status", &self.status())
+            .finish()
+    }
+}
+
+impl DeliveryHandle {
+    /// Creates a new handle and its receipt.
+    #[allow(unused)]
+    pub(crate) fn new() -> (DeliveryReceipt, DeliveryHandle) {
+        let shared = Arc::new(Shared::default());
+        let receipt = DeliveryReceipt {
+            shared: shared.clone(),
+            on_report: None,
+        };
+        let handle = DeliveryHandle {
+            event_id: Uuid::nil(),
+            shared,
+        };
+        (receipt, handle)
+    }
+
+    /// Sets the ID of the event.
+    #[allow(unused)]
+    pub(crate) fn with_event_id(mut self, event_id: Uuid) -> Self {
+        self.event_id = event_id;
+        self
+    }
+
+    /// Creates a handle that has already been resolved.
+    pub(crate) fn resolved(event_id: Uuid, status: DeliveryStatus) -> DeliveryHandle {
+        let shared = Arc::new(Shared::default());
+        shared.resolve(status);
+        DeliveryHandle { event_id, shared }
+    }
+
+    /// The ID of the event, or the nil UUID if it was discarded.
+    pub fn event_id(&self) -> Uuid {
+        self.event_id
+    }
+
+    /// Returns the outcome of sending the event, if it is known yet.
+    pub fn status(&self) -> Option<DeliveryStatus> {
+        self.shared.status.lock().unwrap().clone()
+    }
+
+    /// Blocks until the outcome of sending the event is known.
+    ///
+    /// Returns `None` if the `timeout` elapsed before that.  Without a timeout, this
+    /// waits until the transport reports the outcome.
+    pub fn wait(&self, timeout: Option<Duration>) -> Option<DeliveryStatus> {
+        let guard = self.shared.status.lock().unwrap();
+        let guard = match timeout {
+            Some(timeout) => {
+                self.shared
+                    .signal
+                    .wait_timeout_while(guard, timeout, |status| status.is_none())
+                    .unwrap()
+                    .0
+            }
+            None => self
+                .shared
+                .signal
+                .wait_while(guard, |status| status.is_none())
+                .unwrap(),
+        };
+        guard.clone()
+    }
+}
+
+impl Future for DeliveryHandle {
+    type Output = DeliveryStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // the waker is registered while holding the status lock, so that a status
+        // reported concurrently either is seen here, or wakes the new waker.
+        let status = self.shared.status.lock().unwrap();
+        match *status {
+            Some(ref status) => Poll::Ready(status.clone()),
+            None => {
+                *self.shared.waker.lock().unwrap() = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt() {
+        let (receipt, handle) = DeliveryHandle::new();
+        assert_eq!(handle.status(), None);
+        assert_eq!(handle.wait(Some(Duration::from_millis(1))), None);
+
+        let thread = std::thread::spawn(move || receipt.report(DeliveryStatus::Sent));
+        assert_eq!(handle.wait(None), Some(DeliveryStatus::Sent));
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_dropped_receipt() {
+        let (receipt, handle) = DeliveryHandle::new();
+        drop(receipt);
+        assert_eq!(handle.status(), Some(DeliveryStatus::Discarded));
+    }
+}
@@ -0,0 +1,56 @@
+use std::cell::Cell;
+use std::fmt;
+
+#[cfg(all(feature = "client", not(feature = "debug-logs")))]
+use crate::Hub;
+
+/// The `log` target of the internal diagnostics of the SDK.
+///
+/// With the `debug-logs` feature, the SDK logs its diagnostics through the `log`
+/// facade under this target, instead of printing them when the `debug` option
+/// is set.  Logging integrations never turn records of this target into
+/// breadcrumbs or events.
+pub const INTERNAL_LOG_TARGET: &str = "sentry::internals";
+
+thread_local! {
+    static IN_DIAGNOSTIC: Cell<bool> = Cell::new(false);
+}
+
+/// Resets the recursion flag, even if logging panics.
+struct ResetGuard;
+
+impl Drop for ResetGuard {
+    fn drop(&mut self) {
+        IN_DIAGNOSTIC.with(|flag| flag.set(false));
+    }
+}
+
+/// Emits an internal diagnostic, which is used by the `sentry_debug!` macro.
+///
+/// Diagnostics that are emitted while another one is being emitted on the same
+/// thread are dropped, so that a logger that reports to Sentry cannot recurse
+/// into the SDK indefinitely.
+#[doc(hidden)]
+pub fn __emit_diagnostic(args: fmt::Arguments<'_>) {
+    if IN_DIAGNOSTIC.with(|flag| flag.replace(true)) {
+        return;
+    }
+    let _guard = ResetGuard;
+
+    #[cfg(feature = "debug-logs")]
+    {
+        ::log_::debug!(target: INTERNAL_LOG_TARGET, "{}", args);
+    }
+    #[cfg(all(feature = "client", not(feature = "debug-logs")))]
+    {
+        Hub::with(|hub| {
+            if hub.client().map_or(false, |c| c.options().debug) {
+                eprintln!("[sentry] {}", args);
+            }
+        });
+    }
+    #[cfg(not(any(feature = "client", feature = "debug-logs")))]
+    {
+        let _ = args;
+    }
+}
@@ -0,0 +1,242 @@
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::error::Error;
+use std::sync::RwLock;
+
+use crate::protocol::{Context, Event, Level, Map};
+
+/// Allows error types to control how they are reported to Sentry.
+///
+/// Errors implementing this trait can contribute a level, a fingerprint, tags and
+/// contexts to the events created from them, for example to control their own grouping
+/// without resorting to a `before_send` callback.
+///
+/// The trait is applied by [`event_from_enriched_error`] and [`capture_enriched_error`],
+/// as well as integrations using them, once the error type has been registered using
+/// [`register_enrichment`].  This is applied to the whole chain of errors, starting with
+/// the root cause, so outer errors take precedence over their sources.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::protocol::Level;
+/// use sentry::SentryEnrich;
+/// use std::borrow::Cow;
+///
+/// #[derive(Debug)]
+/// struct QuotaExceeded {
+///     tenant: String,
+/// }
+///
+/// impl std::fmt::Display for QuotaExceeded {
+///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///         write!(f, "quota exceeded for {}", self.tenant)
+///     }
+/// }
+///
+/// impl std::error::Error for QuotaExceeded {}
+///
+/// impl SentryEnrich for QuotaExceeded {
+///     fn level(&self) -> Option<Level> {
+///         Some(Level::Warning)
+///     }
+///
+///     fn fingerprint(&self) -> Option<Vec<Cow<'static, str>>> {
+///         Some(vec!["quota-exceeded".into()])
+///     }
+///
+///     fn tags(&self) -> Vec<(String, String)> {
+///         vec![("tenant".into(), self.tenant.clone())]
+///     }
+/// }
+///
+/// sentry::register_enrichment::<QuotaExceeded>();
+///
+/// let event = sentry::event_from_enriched_error(&QuotaExceeded {
+///     tenant: "acme".into(),
+/// });
+/// assert_eq!(event.level, Level::Warning);
+/// assert_eq!(event.fingerprint[0], "quota-exceeded");
+/// assert_eq!(event.tags["tenant"], "acme");
+/// ```
+///
+/// [`event_from_enriched_error`]: fn.event_from_enriched_error.html
+/// [`capture_enriched_error`]: fn.capture_enriched_error.html
+/// [`register_enrichment`]: fn.register_enrichment.html
+pub trait SentryEnrich: Error {
+    /// The level of the event, `error` by default.
+    fn level(&self) -> Option<Level> {
+        None
+    }
+
+    /// The fingerprint used to group the event.
+    fn fingerprint(&self) -> Option<Vec<Cow<'static, str>>> {
+        None
+    }
+
+    /// Tags added to the event.
+    fn tags(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Contexts added to the event.
+    fn contexts(&self) -> Map<String, Context> {
+        Map::new()
+    }
+
+    /// Applies the level, fingerprint, tags and contexts of this error to the `event`.
+    fn enrich_event(&self, event: &mut Event<'static>) {
+        if let Some(level) = self.level() {
+            event.level = level;
+        }
+        if let Some(fingerprint) = self.fingerprint() {
+            event.fingerprint = fingerprint.into();
+        }
+        event.tags.extend(self.tags());
+        event.contexts.extend(self.contexts());
+    }
+}
+
+type Enricher = for<'a> fn(&'a (dyn Error + 'static)) -> Option<&'a dyn SentryEnrich>;
+
+/// The registered error types, with the functions that downcast to them.
+#[derive(Default)]
+struct Enrichers(Vec<(TypeId, Enricher)>);
+
+impl Enrichers {
+    fn register<E: SentryEnrich + 'static>(&mut self) {
+        fn downcast<'a, E: SentryEnrich + 'static>(
+            error: &'a (dyn Error + 'static),
+        ) -> Option<&'a dyn SentryEnrich> {
+            error
+                .downcast_ref::<E>()
+                .map(|error| error as &dyn SentryEnrich)
+        }
+
+        let type_id = TypeId::of::<E>();
+        if !self.0.iter().any(|(id, _)| *id == type_id) {
+            self.0.push((type_id, downcast::<E>));
+        }
+    }
+
+    fn enrich_event(&self, error: &(dyn Error + 'static), event: &mut Event<'static>) {
+        if self.0.is_empty() {
+            return;
+        }
+
+        let mut chain = vec![error];
+        let mut current = error;
+        while let Some(source) = current.source() {
+            chain.push(source);
+            current = source;
+        }
+
+        for error in chain.into_iter().rev() {
+            if let Some(enrich) = self.0.iter().find_map(|(_, enricher)| enricher(error)) {
+                enrich.enrich_event(event);
+            }
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref ENRICHERS: RwLock<Enrichers> = RwLock::new(Enrichers::default());
+}
+
+/// Registers an error type implementing [`SentryEnrich`].
+///
+/// Events created from errors of this type, or from errors caused by it, are enriched
+/// from then on.  Registering a type more than once has no effect.
+///
+/// [`SentryEnrich`]: trait.SentryEnrich.html
+pub fn register_enrichment<E: SentryEnrich + 'static>() {
+    ENRICHERS.write().unwrap().register::<E>();
+}
+
+/// Enriches the `event` from all errors in the chain of `error` with a registered type.
+pub(crate) fn enrich_event(error: &(dyn Error + 'static), event: &mut Event<'static>) {
+    // enrichments must not register other types, as the registry is locked meanwhile
+    ENRICHERS.read().unwrap().enrich_event(error, event);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::*;
+    use crate::event_from_error;
+
+    #[derive(Debug)]
+    struct DatabaseError;
+
+    impl fmt::Display for DatabaseError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("database unavailable")
+        }
+    }
+
+    impl Error for DatabaseError {}
+
+    impl SentryEnrich for DatabaseError {
+        fn level(&self) -> Option<Level> {
+            Some(Level::Fatal)
+        }
+
+        fn fingerprint(&self) -> Option<Vec<Cow<'static, str>>> {
+            Some(vec!["database".into()])
+        }
+
+        fn tags(&self) -> Vec<(String, String)> {
+            vec![("component".into(), "database".into())]
+        }
+    }
+
+    #[derive(Debug)]
+    struct RequestError(DatabaseError);
+
+    impl fmt::Display for RequestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("request failed")
+        }
+    }
+
+    impl Error for RequestError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    impl SentryEnrich for RequestError {
+        fn level(&self) -> Option<Level> {
+            Some(Level::Warning)
+        }
+
+        fn tags(&self) -> Vec<(String, String)> {
+            vec![("route".into(), "/users".into())]
+        }
+    }
+
+    #[test]
+    fn test_enrich_chain() {
+        let error = RequestError(DatabaseError);
+        let mut enrichers = Enrichers::default();
+
+        let mut event = event_from_error(&error);
+        enrichers.enrich_event(&error, &mut event);
+        assert_eq!(event.level, Level::Error);
+        assert!(event.tags.is_empty());
+
+        enrichers.register::<DatabaseError>();
+        enrichers.register::<RequestError>();
+        enrichers.register::<RequestError>();
+        assert_eq!(enrichers.0.len(), 2);
+
+        let mut event = event_from_error(&error);
+        enrichers.enrich_event(&error, &mut event);
+        // the outer error takes precedence over its source
+        assert_eq!(event.level, Level::Warning);
+        assert_eq!(event.fingerprint.as_ref(), &["database"]);
+        assert_eq!(event.tags["component"], "database");
+        assert_eq!(event.tags["route"], "/users");
+    }
+}
@@ -1,5 +1,6 @@
 use std::error::Error;
 
+use crate::enrich::enrich_event;
 use crate::protocol::{Event, Exception, Level};
 use crate::types::Uuid;
 use crate::Hub;
@@ -23,11 +24,31 @@ impl Hub {
             })
         }}
     }
+
+    /// Capture any `std::error::Error`, enriched by its registered error types.
+    ///
+    /// See the global [`capture_enriched_error`](fn.capture_enriched_error.html)
+    /// for more documentation.
+    #[allow(unused)]
+    pub fn capture_enriched_error(&self, error: &(dyn Error + 'static)) -> Uuid {
+        with_client_impl! {{
+            self.inner.with(|stack| {
+                let top = stack.top();
+                if top.client.is_some() {
+                    let event = event_from_enriched_error(error);
+                    self.capture_event(event)
+                } else {
+                    Uuid::nil()
+                }
+            })
+        }}
+    }
 }
 
 /// Captures a `std::error::Error`.
 ///
-/// Creates an event from the given error and sends it to the current hub.
+/// Creates an event from the given error and sends it to the current hub, and
+/// returns the ID of the event, or the nil UUID if it was discarded.
 /// A chain of errors will be resolved as well, and sorted oldest to newest, as
 /// described in the [sentry event payloads].
 ///
@@ -51,6 +72,20 @@ pub fn capture_error<E: Error + ?Sized>(error: &E) -> Uuid {
     Hub::with_active(|hub| hub.capture_error(error))
 }
 
+/// Captures a `std::error::Error`, enriched by its registered error types.
+///
+/// This works like [`capture_error`], but errors in the chain implementing
+/// [`SentryEnrich`] that have been registered with [`register_enrichment`] can
+/// customize the captured event.
+///
+/// [`capture_error`]: fn.capture_error.html
+/// [`SentryEnrich`]: trait.SentryEnrich.html
+/// [`register_enrichment`]: fn.register_enrichment.html
+#[allow(unused_variables)]
+pub fn capture_enriched_error(error: &(dyn Error + 'static)) -> Uuid {
+    Hub::with_active(|hub| hub.capture_enriched_error(error))
+}
+
 /// Create a sentry `Event` from a `std::error::Error`.
 ///
 /// A chain of errors will be resolved as well, and sorted oldest to newest, as
@@ -96,6 +131,21 @@ pub fn event_from_error<E: Error + ?Sized>(err: &E) -> Event<'static> {
     }
 }
 
+/// Create a sentry `Event` from a `std::error::Error`, enriched by its registered
+/// error types.
+///
+/// This works like [`event_from_error`], and then applies [`SentryEnrich`] for all
+/// errors in the chain whose type has been registered with [`register_enrichment`].
+///
+/// [`event_from_error`]: fn.event_from_error.html
+/// [`SentryEnrich`]: trait.SentryEnrich.html
+/// [`register_enrichment`]: fn.register_enrichment.html
+pub fn event_from_enriched_error(err: &(dyn Error + 'static)) -> Event<'static> {
+    let mut event = event_from_error(err);
+    enrich_event(err, &mut event);
+    event
+}
+
 fn exception_from_error<E: Error + ?Sized>(err: &E) -> Exception {
     let dbg = format!("{:?}", err);
     Exception {
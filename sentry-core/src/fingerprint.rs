@@ -0,0 +1,154 @@
+//! Helpers for building fingerprints.
+//!
+//! Sentry groups events by their stacktrace by default, so the same error raised from
+//! different call sites ends up in different issues.  The fingerprint of an event
+//! overrides this grouping.  The helpers in this module build fingerprints from the
+//! types of errors, and can be used with the
+//! [`fingerprint_fn`](../struct.ClientOptions.html#structfield.fingerprint_fn) option,
+//! [`Scope::set_fingerprint`](../struct.Scope.html#method.set_fingerprint) or
+//! [`SentryEnrich::fingerprint`](../trait.SentryEnrich.html#method.fingerprint).
+//!
+//! # Examples
+//!
+//! Group all errors with the same exception types and messages, regardless of where
+//! they were captured:
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! let options = sentry::ClientOptions {
+//!     fingerprint_fn: Some(Arc::new(|event| sentry::fingerprint::from_exceptions(event))),
+//!     ..Default::default()
+//! };
+//! ```
+//!
+//! Split the default grouping further by the exception types:
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! let options = sentry::ClientOptions {
+//!     fingerprint_fn: Some(Arc::new(|event| {
+//!         let mut fingerprint = sentry::fingerprint::from_exception_types(event)?;
+//!         fingerprint.insert(0, sentry::fingerprint::DEFAULT.into());
+//!         Some(fingerprint)
+//!     })),
+//!     ..Default::default()
+//! };
+//! ```
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::io;
+
+use crate::parse_type_from_debug;
+use crate::protocol::Event;
+
+/// The fingerprint entry that stands for the default grouping of Sentry.
+pub const DEFAULT: &str = "{{ default }}";
+
+/// Builds a fingerprint from the types of the exceptions of an `event`.
+///
+/// Returns `None` if the event has no exceptions.
+pub fn from_exception_types(event: &Event<'_>) -> Option<Vec<Cow<'static, str>>> {
+    if event.exception.is_empty() {
+        return None;
+    }
+    Some(
+        event
+            .exception
+            .iter()
+            .map(|exception| Cow::Owned(exception.ty.clone()))
+            .collect(),
+    )
+}
+
+/// Builds a fingerprint from the types and values of the exceptions of an `event`.
+///
+/// Returns `None` if the event has no exceptions.
+pub fn from_exceptions(event: &Event<'_>) -> Option<Vec<Cow<'static, str>>> {
+    if event.exception.is_empty() {
+        return None;
+    }
+    Some(
+        event
+            .exception
+            .iter()
+            .map(|exception| match exception.value {
+                Some(ref value) => Cow::Owned(format!("{}: {}", exception.ty, value)),
+                None => Cow::Owned(exception.ty.clone()),
+            })
+            .collect(),
+    )
+}
+
+/// Builds a fingerprint from the types of an `error` and its sources.
+///
+/// The types are sorted oldest to newest, like the exceptions of the event created by
+/// [`event_from_error`](../fn.event_from_error.html).  `std::io::Error`s are represented
+/// by their kind, such as `io::Error(NotFound)`, since their type name is not meaningful.
+///
+/// # Examples
+///
+/// ```
+/// let error = std::io::Error::new(std::io::ErrorKind::NotFound, "config.toml");
+/// assert_eq!(sentry::fingerprint::from_error(&error), ["io::Error(NotFound)"]);
+/// ```
+pub fn from_error(error: &(dyn Error + 'static)) -> Vec<Cow<'static, str>> {
+    let mut fingerprint = vec![];
+    let mut current = Some(error);
+    while let Some(error) = current {
+        fingerprint.push(type_of_error(error));
+        current = error.source();
+    }
+    fingerprint.reverse();
+    fingerprint
+}
+
+fn type_of_error(error: &(dyn Error + 'static)) -> Cow<'static, str> {
+    match error.downcast_ref::<io::Error>() {
+        Some(error) => Cow::Owned(format!("io::Error({:?})", error.kind())),
+        None => Cow::Owned(parse_type_from_debug(&format!("{:?}", error)).to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::*;
+    use crate::event_from_error;
+
+    #[derive(Debug)]
+    struct ConfigError(io::Error);
+
+    impl fmt::Display for ConfigError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("failed to load config")
+        }
+    }
+
+    impl Error for ConfigError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        let error = ConfigError(io::Error::new(io::ErrorKind::NotFound, "no config"));
+
+        assert_eq!(from_error(&error), ["io::Error(NotFound)", "ConfigError"]);
+
+        let event = event_from_error(&error);
+        assert_eq!(
+            from_exception_types(&event).unwrap(),
+            ["Custom", "ConfigError"]
+        );
+        assert_eq!(
+            from_exceptions(&event).unwrap(),
+            ["Custom: no config", "ConfigError: failed to load config"]
+        );
+        assert_eq!(from_exceptions(&Event::default()), None);
+    }
+}
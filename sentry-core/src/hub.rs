@@ -10,7 +10,7 @@ use std::time::Duration;
 
 use crate::protocol::{Breadcrumb, Event, Level, SessionStatus};
 use crate::types::Uuid;
-use crate::{event_from_error, Integration, IntoBreadcrumbs, Scope, ScopeGuard};
+use crate::{event_from_error, DeliveryHandle, Integration, IntoBreadcrumbs, Scope, ScopeGuard};
 #[cfg(feature = "client")]
 use crate::{scope::Stack, session::Session, Client, Envelope};
 
@@ -22,6 +22,17 @@ lazy_static::lazy_static! {
     );
 }
 
+/// Set once a client was bound to any hub.  Until then, the API can return
+/// early without touching the thread local hub at all.
+#[cfg(feature = "client")]
+static ANY_CLIENT_BOUND: AtomicBool = AtomicBool::new(false);
+
+/// Returns `true` if a client was ever bound to any hub.
+#[cfg(feature = "client")]
+pub(crate) fn any_client_bound() -> bool {
+    ANY_CLIENT_BOUND.load(Ordering::Relaxed)
+}
+
 #[cfg(feature = "client")]
 thread_local! {
     static THREAD_HUB: UnsafeCell<Arc<Hub>> = UnsafeCell::new(
@@ -61,6 +72,38 @@ impl HubImpl {
     }
 }
 
+/// Makes room for `breadcrumb` within the `max_breadcrumbs_per_category` limit
+/// by dropping the oldest breadcrumb of its category.
+///
+/// Returns `false` if the breadcrumb should not be added at all.
+#[cfg(feature = "client")]
+fn limit_breadcrumb_category(
+    breadcrumbs: &mut im::Vector<Breadcrumb>,
+    breadcrumb: &Breadcrumb,
+    options: &crate::ClientOptions,
+) -> bool {
+    let category = match breadcrumb.category {
+        Some(ref category) => category,
+        None => return true,
+    };
+    let limit = match options
+        .max_breadcrumbs_per_category
+        .iter()
+        .find(|(c, _)| *c == category.as_str())
+    {
+        Some(&(_, 0)) => return false,
+        Some(&(_, limit)) => limit,
+        None => return true,
+    };
+    let same_category = |b: &Breadcrumb| b.category.as_ref() == Some(category);
+    if breadcrumbs.iter().filter(|b| same_category(b)).count() >= limit {
+        if let Some(index) = breadcrumbs.iter().position(same_category) {
+            breadcrumbs.remove(index);
+        }
+    }
+    true
+}
+
 /// The central object that can manages scopes and clients.
 ///
 /// This can be used to capture events and manage the scope.  This object is
@@ -98,6 +141,9 @@ impl Hub {
     /// Creates a new hub from the given client and scope.
     #[cfg(feature = "client")]
     pub fn new(client: Option<Arc<Client>>, scope: Arc<Scope>) -> Hub {
+        if client.is_some() {
+            ANY_CLIENT_BOUND.store(true, Ordering::Relaxed);
+        }
         Hub {
             inner: HubImpl {
                 stack: Arc::new(RwLock::new(Stack::from_client_and_scope(client, scope))),
@@ -176,6 +222,9 @@ impl Hub {
         R: Default,
     {
         with_client_impl! {{
+            if !any_client_bound() {
+                return Default::default();
+            }
             Hub::with(|hub| {
                 if hub.is_active_and_usage_safe() {
                     f(hub)
@@ -279,7 +328,9 @@ impl Hub {
                 let top = stack.top();
                 if let Some(ref client) = top.client {
                     let event_id = client.capture_event(event, Some(&top.scope));
-                    *self.last_event_id.write().unwrap() = Some(event_id);
+                    if !event_id.is_nil() {
+                        *self.last_event_id.write().unwrap() = Some(event_id);
+                    }
                     event_id
                 } else {
                     Default::default()
@@ -288,6 +339,56 @@ impl Hub {
         }}
     }
 
+    /// Sends multiple events to the current client with the current scope.
+    ///
+    /// In case no client is bound this does nothing and returns no IDs.  See
+    /// [`Client::capture_events`] for more documentation.
+    ///
+    /// [`Client::capture_events`]: struct.Client.html#method.capture_events
+    pub fn capture_events<I>(&self, events: I) -> Vec<Uuid>
+    where
+        I: IntoIterator<Item = Event<'static>>,
+    {
+        with_client_impl! {{
+            self.inner.with(|stack| {
+                let top = stack.top();
+                if let Some(ref client) = top.client {
+                    let event_ids = client.capture_events(events, Some(&top.scope));
+                    if let Some(event_id) = event_ids.iter().rev().find(|id| !id.is_nil()) {
+                        *self.last_event_id.write().unwrap() = Some(*event_id);
+                    }
+                    event_ids
+                } else {
+                    Default::default()
+                }
+            })
+        }}
+    }
+
+    /// Sends the event to the current client with the current scope, and returns
+    /// a handle to wait for it to be sent.
+    ///
+    /// In case no client is bound, the returned handle reports the event as
+    /// discarded.  See [`Client::capture_event_with_receipt`] for more documentation.
+    ///
+    /// [`Client::capture_event_with_receipt`]: struct.Client.html#method.capture_event_with_receipt
+    pub fn capture_event_with_receipt(&self, event: Event<'static>) -> DeliveryHandle {
+        with_client_impl! {{
+            self.inner.with(|stack| {
+                let top = stack.top();
+                if let Some(ref client) = top.client {
+                    let handle = client.capture_event_with_receipt(event, Some(&top.scope));
+                    if !handle.event_id().is_nil() {
+                        *self.last_event_id.write().unwrap() = Some(handle.event_id());
+                    }
+                    handle
+                } else {
+                    Default::default()
+                }
+            })
+        }}
+    }
+
     /// Captures an arbitrary message.
     ///
     /// See the global [`capture_message`](fn.capture_message.html)
@@ -319,6 +420,9 @@ impl Hub {
     /// Binds a new client to the hub.
     #[cfg(feature = "client")]
     pub fn bind_client(&self, client: Option<Arc<Client>>) {
+        if client.is_some() {
+            ANY_CLIENT_BOUND.store(true, Ordering::Relaxed);
+        }
         self.inner.with_mut(|stack| {
             stack.top_mut().client = client;
         })
@@ -427,13 +531,19 @@ impl Hub {
                 if let Some(ref client) = top.client {
                     let scope = Arc::make_mut(&mut top.scope);
                     let options = client.options();
-                    for breadcrumb in breadcrumb.into_breadcrumbs() {
+                    for mut breadcrumb in breadcrumb.into_breadcrumbs() {
+                        if !client.sample_breadcrumb(&breadcrumb) {
+                            continue;
+                        }
+                        breadcrumb.timestamp = client.timestamp(breadcrumb.timestamp);
                         let breadcrumb_opt = match options.before_breadcrumb {
                             Some(ref callback) => callback(breadcrumb),
                             None => Some(breadcrumb)
                         };
                         if let Some(breadcrumb) = breadcrumb_opt {
-                            scope.breadcrumbs.push_back(breadcrumb);
+                            if limit_breadcrumb_category(&mut scope.breadcrumbs, &breadcrumb, options) {
+                                scope.breadcrumbs.push_back(breadcrumb);
+                            }
                         }
                         while scope.breadcrumbs.len() > options.max_breadcrumbs {
                             scope.breadcrumbs.pop_front();
@@ -1,6 +1,6 @@
 use std::any::{type_name, Any};
 
-use crate::protocol::Event;
+use crate::protocol::{Attachment, Event, Transaction};
 use crate::ClientOptions;
 
 /// Integration abstraction.
@@ -78,6 +78,37 @@ pub trait Integration: Sync + Send + Any + AsAny {
         let _ = options;
         Some(event)
     }
+
+    /// Called whenever a sampled transaction is started.
+    ///
+    /// This allows integrations to record the state at the start of a transaction,
+    /// which they can compare against in
+    /// [`process_transaction`](Integration::process_transaction).
+    fn start_transaction(&self, transaction: &mut Transaction<'static>) {
+        let _ = transaction;
+    }
+
+    /// The Integrations Transaction Processor Hook.
+    ///
+    /// Like [`process_event`](Integration::process_event), but for finished
+    /// transactions, which do not pass through the event processors.
+    fn process_transaction(
+        &self,
+        transaction: Transaction<'static>,
+        options: &ClientOptions,
+    ) -> Option<Transaction<'static>> {
+        let _ = options;
+        Some(transaction)
+    }
+
+    /// The Integrations Attachment Hook.
+    ///
+    /// Called for every event that passed all event processors and `before_send`.
+    /// The returned attachments are sent along with the event.
+    fn attachments(&self, event: &Event<'static>, options: &ClientOptions) -> Vec<Attachment> {
+        let _ = (event, options);
+        Vec::new()
+    }
 }
 
 // This is needed as a workaround to be able to safely downcast integrations
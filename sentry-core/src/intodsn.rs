@@ -1,12 +1,33 @@
 use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::ffi::{OsStr, OsString};
 
-use crate::types::{Dsn, ParseDsnError};
+use crate::types::{Dsn, ParseDsnError, Url};
 
 /// Helper trait to convert a string into an `Option<Dsn>`.
 ///
 /// This converts a value into a DSN by parsing.  The empty string or
 /// null values result in no DSN being parsed.
+///
+/// Strings, OS strings, URLs and DSNs can be converted, as well as options of
+/// these, where `None` results in no DSN.  Invalid DSNs result in a
+/// [`ParseDsnError`] that describes what is wrong with the DSN.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::types::ParseDsnError;
+/// use sentry::IntoDsn;
+///
+/// let dsn = "https://public@example.com/1".into_dsn().unwrap();
+/// assert_eq!(dsn.unwrap().project_id().value(), 1);
+/// assert!(None::<&str>.into_dsn().unwrap().is_none());
+///
+/// match "https://example.com/1".into_dsn() {
+///     Err(ParseDsnError::NoUsername) => {}
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+/// ```
 pub trait IntoDsn {
     /// Converts the value into a `Result<Option<Dsn>, E>`.
     fn into_dsn(self) -> Result<Option<Dsn>, ParseDsnError>;
@@ -73,3 +94,21 @@ impl IntoDsn for Dsn {
         Ok(Some(self))
     }
 }
+
+impl<'a> IntoDsn for &'a String {
+    fn into_dsn(self) -> Result<Option<Dsn>, ParseDsnError> {
+        self.as_str().into_dsn()
+    }
+}
+
+impl<'a> IntoDsn for &'a Url {
+    fn into_dsn(self) -> Result<Option<Dsn>, ParseDsnError> {
+        Dsn::try_from(self).map(Some)
+    }
+}
+
+impl IntoDsn for Url {
+    fn into_dsn(self) -> Result<Option<Dsn>, ParseDsnError> {
+        (&self).into_dsn()
+    }
+}
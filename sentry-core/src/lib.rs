@@ -24,6 +24,12 @@
 //! the [`Client`] is not available and the [`Hub`] does not retain all API
 //! functionality.
 //!
+//! Library authors should depend on this crate in minimal mode, without
+//! enabling the `client` feature. The application then depends on the
+//! `sentry` crate, which enables the `client` feature and provides the
+//! transports and default integrations, so that the instrumentation in
+//! libraries reports to the client bound by the application.
+//!
 //! # Features
 //!
 //! * `feature = "client"`: Activates the [`Client`] type and certain
@@ -31,14 +37,22 @@
 //! * `feature = "test"`: Activates the [`test`] module, which can be used to
 //!   write integration tests. It comes with a test transport which can capture
 //!   all sent events for inspection.
-//! * `feature = "debug-logs"`: Uses the `log` crate for debug output, instead
-//!   of printing to `stderr`. This feature is **deprecated** and will be
-//!   replaced by a dedicated log callback in the future.
+//! * `feature = "debug-logs"`: Logs the internal diagnostics of the SDK through
+//!   the `log` crate under the [`INTERNAL_LOG_TARGET`], instead of printing
+//!   them to `stderr` when the `debug` option is set.  `tracing` subscribers
+//!   can receive them through `tracing-log`.
+//! * `feature = "http"`: Adds conversions from `http::Request` into the
+//!   `Request` interface, which are shared by the HTTP framework integrations.
+//! * `feature = "log"`, `feature = "slog"` and `feature = "tracing"`: Add
+//!   conversions from the levels of these crates into [`Level`], which are shared
+//!   by the logging integrations.
 //!
 //! [`sentry`]: https://crates.io/crates/sentry
 //! [Unified API]: https://develop.sentry.dev/sdk/unified-api/
 //! [`Client`]: struct.Client.html
 //! [`Hub`]: struct.Hub.html
+//! [`Level`]: enum.Level.html
+//! [`INTERNAL_LOG_TARGET`]: constant.INTERNAL_LOG_TARGET.html
 //! [`Scope`]: struct.Scope.html
 //! [`Integration`]: trait.Integration.html
 //! [`Transport`]: trait.Transport.html
@@ -56,31 +70,57 @@ mod macros;
 mod api;
 mod breadcrumbs;
 mod clientoptions;
+mod console;
 mod constants;
+mod delivery;
+mod diagnostics;
+mod enrich;
 mod error;
+pub mod fingerprint;
 mod futures;
 mod hub;
 mod integration;
 mod intodsn;
+pub mod metrics;
+mod performance;
+pub mod process;
 mod scope;
+pub mod spans;
+mod throttle;
 mod transport;
+mod trimmer;
 
 // public api or exports from this crate
 pub use crate::api::*;
 pub use crate::breadcrumbs::IntoBreadcrumbs;
-pub use crate::clientoptions::ClientOptions;
-pub use crate::error::{capture_error, event_from_error, parse_type_from_debug};
+pub use crate::clientoptions::{ClientOptions, TryIntoClientOptions};
+pub use crate::console::ConsoleWriter;
+pub use crate::delivery::{DeliveryHandle, DeliveryReceipt, DeliveryStatus};
+pub use crate::diagnostics::{__emit_diagnostic, INTERNAL_LOG_TARGET};
+pub use crate::enrich::{register_enrichment, SentryEnrich};
+pub use crate::error::{
+    capture_enriched_error, capture_error, event_from_enriched_error, event_from_error,
+    parse_type_from_debug,
+};
 pub use crate::futures::{SentryFuture, SentryFutureExt};
 pub use crate::hub::Hub;
 pub use crate::integration::Integration;
 pub use crate::intodsn::IntoDsn;
+pub use crate::performance::{
+    start_child_or_transaction, start_transaction, Span, SpanGuard, Transaction,
+    TransactionContext, TransactionOrSpan,
+};
 pub use crate::scope::{Scope, ScopeGuard};
-pub use crate::transport::{Transport, TransportFactory};
+pub use crate::throttle::capture_error_throttled;
+pub use crate::transport::{QueueStats, Transport, TransportError, TransportFactory};
+pub use crate::trimmer::BacktraceTrimmer;
 
 // client feature
 #[cfg(feature = "client")]
 mod client;
 #[cfg(feature = "client")]
+mod limits;
+#[cfg(feature = "client")]
 mod session;
 #[cfg(feature = "client")]
 pub use crate::client::Client;
@@ -0,0 +1,139 @@
+//! Limits on the size of the arbitrary data attached to events.
+
+use crate::protocol::{Attachment, Context, Event, Map, Value};
+use crate::ClientOptions;
+
+/// Moves extra values and messages larger than `max_size` bytes into attachments.
+///
+/// Extra values are serialized as JSON into `extra.<key>.json`, and replaced by a
+/// reference to the attachment.  Messages are sent as `message.txt`, and cut to
+/// `max_size` bytes in the event, followed by a reference to the attachment.
+pub(crate) fn move_large_values(event: &mut Event<'static>, max_size: usize) -> Vec<Attachment> {
+    let mut attachments = Vec::new();
+
+    for (key, value) in event.extra.iter_mut() {
+        if let Value::Null | Value::Bool(_) | Value::Number(_) = *value {
+            continue;
+        }
+        let buffer = match serde_json::to_vec(value) {
+            Ok(buffer) if buffer.len() > max_size => buffer,
+            _ => continue,
+        };
+        let filename = format!("extra.{}.json", key);
+        *value = format!("[attachment: {}]", filename).into();
+        attachments.push(Attachment {
+            buffer,
+            filename,
+            content_type: Some("application/json".into()),
+            ..Default::default()
+        });
+    }
+
+    if let Some(ref mut message) = event.message {
+        if message.len() > max_size {
+            let mut end = max_size;
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            let buffer = message.clone().into_bytes();
+            message.truncate(end);
+            message.push_str("… [attachment: message.txt]");
+            attachments.push(Attachment {
+                buffer,
+                filename: "message.txt".into(),
+                content_type: Some("text/plain".into()),
+                ..Default::default()
+            });
+        }
+    }
+
+    attachments
+}
+
+/// Replaces the parts of the `extra`, `contexts` and breadcrumb `data` of an event
+/// that exceed the `max_value_depth` and `max_value_length` options.
+pub(crate) fn limit_event(event: &mut Event<'static>, options: &ClientOptions) {
+    let limits = Limits {
+        max_depth: options.max_value_depth,
+        max_length: options.max_value_length,
+    };
+    limits.apply_to_map(&mut event.extra);
+    for context in event.contexts.values_mut() {
+        if let Context::Other(ref mut map) = *context {
+            limits.apply_to_map(map);
+        }
+    }
+    for breadcrumb in event.breadcrumbs.iter_mut() {
+        limits.apply_to_map(&mut breadcrumb.data);
+    }
+}
+
+struct Limits {
+    max_depth: usize,
+    max_length: usize,
+}
+
+impl Limits {
+    fn apply_to_map(&self, map: &mut Map<String, Value>) {
+        for value in map.values_mut() {
+            self.apply(value, self.max_depth);
+        }
+    }
+
+    /// Limits a value, in which `depth` more levels of arrays and objects are kept.
+    ///
+    /// Arrays and objects below that are replaced by `"[Array]"` and `"[Object]"`,
+    /// and strings are cut after `max_length` characters and marked with `…`.
+    fn apply(&self, value: &mut Value, depth: usize) {
+        match *value {
+            Value::String(ref mut string) => {
+                if let Some((index, _)) = string.char_indices().nth(self.max_length) {
+                    string.truncate(index);
+                    string.push('…');
+                }
+            }
+            Value::Array(_) if depth == 0 => *value = "[Array]".into(),
+            Value::Object(_) if depth == 0 => *value = "[Object]".into(),
+            Value::Array(ref mut items) => {
+                for item in items {
+                    self.apply(item, depth - 1);
+                }
+            }
+            Value::Object(ref mut map) => {
+                for item in map.values_mut() {
+                    self.apply(item, depth - 1);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_limit_value() {
+        let limits = Limits {
+            max_depth: 2,
+            max_length: 5,
+        };
+        let mut value = json!({
+            "short": "hello",
+            "long": "hello world",
+            "nested": {"list": [1, [2], {"three": 3}], "text": "ünïcödé"},
+        });
+        limits.apply(&mut value, limits.max_depth);
+        assert_eq!(
+            value,
+            json!({
+                "short": "hello",
+                "long": "hello…",
+                "nested": {"list": "[Array]", "text": "ünïcö…"},
+            })
+        );
+    }
+}
@@ -34,6 +34,36 @@ macro_rules! release_name {
     }};
 }
 
+/// Builds a map of tags from key-value pairs.
+///
+/// Keys and values can be of any type implementing `ToString`, and are
+/// converted into the `Map<String, String>` that events and scopes use for
+/// their tags.  The map can be passed to `Scope::set_tags`.
+///
+/// # Examples
+///
+/// ```
+/// let tags = sentry::tags! { "region" => "eu", "shard" => 7 };
+/// assert_eq!(tags["region"], "eu");
+/// assert_eq!(tags["shard"], "7");
+///
+/// sentry::configure_scope(|scope| scope.set_tags(tags));
+/// ```
+#[macro_export]
+macro_rules! tags {
+    ($($key:expr => $value:expr),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut tags = $crate::protocol::Map::<::std::string::String, ::std::string::String>::new();
+        $(
+            tags.insert(
+                ::std::string::ToString::to_string(&$key),
+                ::std::string::ToString::to_string(&$value),
+            );
+        )*
+        tags
+    }};
+}
+
 // TODO: temporarily exported for use in `sentry` crate
 #[macro_export]
 #[doc(hidden)]
@@ -55,17 +85,7 @@ macro_rules! with_client_impl {
 #[doc(hidden)]
 macro_rules! sentry_debug {
     ($($arg:tt)*) => {
-        #[cfg(feature = "debug-logs")] {
-            ::log_::debug!(target: "sentry", $($arg)*);
-        }
-        #[cfg(not(feature = "debug-logs"))] {
-            $crate::Hub::with(|hub| {
-                if hub.client().map_or(false, |c| c.options().debug) {
-                    eprint!("[sentry] ");
-                    eprintln!($($arg)*);
-                }
-            });
-        }
+        $crate::__emit_diagnostic(format_args!($($arg)*))
     }
 }
 
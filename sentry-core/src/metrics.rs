@@ -0,0 +1,458 @@
+//! Metrics
+//!
+//! <https://develop.sentry.dev/sdk/metrics/>
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// The value of a [`Metric`], which also determines its type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetricValue {
+    /// A counter, whose values are summed up.
+    Counter(f64),
+    /// A distribution, of which all values are kept.
+    Distribution(f64),
+    /// A gauge, of which the last, minimum, maximum, sum and count are kept.
+    Gauge(f64),
+    /// A set, of which only the unique values are kept.
+    Set(u32),
+}
+
+impl MetricValue {
+    #[cfg(feature = "client")]
+    fn ty(&self) -> &'static str {
+        match self {
+            MetricValue::Counter(_) => "c",
+            MetricValue::Distribution(_) => "d",
+            MetricValue::Gauge(_) => "g",
+            MetricValue::Set(_) => "s",
+        }
+    }
+}
+
+/// A single metric value, which is aggregated by the client before it is sent.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::metrics::Metric;
+///
+/// Metric::count("requests")
+///     .with_tag("route", "/users")
+///     .send();
+///
+/// Metric::distribution("response_time", 12.5)
+///     .with_unit("millisecond")
+///     .send();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metric {
+    name: Cow<'static, str>,
+    value: MetricValue,
+    unit: Option<Cow<'static, str>>,
+    tags: BTreeMap<String, String>,
+}
+
+impl Metric {
+    /// Creates a new metric.
+    pub fn new<N: Into<Cow<'static, str>>>(name: N, value: MetricValue) -> Self {
+        Metric {
+            name: name.into(),
+            value,
+            unit: None,
+            tags: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a counter that is incremented by one.
+    pub fn count<N: Into<Cow<'static, str>>>(name: N) -> Self {
+        Self::incr(name, 1.0)
+    }
+
+    /// Creates a counter that is incremented by `value`.
+    pub fn incr<N: Into<Cow<'static, str>>>(name: N, value: f64) -> Self {
+        Self::new(name, MetricValue::Counter(value))
+    }
+
+    /// Creates a distribution value.
+    pub fn distribution<N: Into<Cow<'static, str>>>(name: N, value: f64) -> Self {
+        Self::new(name, MetricValue::Distribution(value))
+    }
+
+    /// Creates a gauge value.
+    pub fn gauge<N: Into<Cow<'static, str>>>(name: N, value: f64) -> Self {
+        Self::new(name, MetricValue::Gauge(value))
+    }
+
+    /// Creates a set value.
+    pub fn set<N: Into<Cow<'static, str>>>(name: N, value: u32) -> Self {
+        Self::new(name, MetricValue::Set(value))
+    }
+
+    /// Sets the unit of the metric, such as `millisecond` or `byte`.
+    pub fn with_unit<U: Into<Cow<'static, str>>>(mut self, unit: U) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+
+    /// Adds a tag to the metric.
+    pub fn with_tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns the name of the metric.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of the metric.
+    pub fn value(&self) -> &MetricValue {
+        &self.value
+    }
+
+    /// Sends the metric to the aggregator of the client of the current hub.
+    pub fn send(self) {
+        with_client_impl! {{
+            crate::Hub::with_active(|hub| {
+                if let Some(client) = hub.client() {
+                    client.add_metric(self);
+                }
+            })
+        }}
+    }
+}
+
+#[cfg(feature = "client")]
+pub(crate) use self::aggregator::MetricAggregator;
+
+#[cfg(feature = "client")]
+mod aggregator {
+    use std::borrow::Cow;
+    use std::collections::{BTreeSet, HashMap};
+    use std::fmt::Write;
+    use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+    use std::thread::JoinHandle;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::{Metric, MetricValue};
+    use crate::client::TransportArc;
+    use crate::protocol::EnvelopeItem;
+    use crate::Envelope;
+
+    /// The width of the time buckets metrics are aggregated in, in seconds.
+    const BUCKET_INTERVAL: u64 = 10;
+    #[cfg(not(target_arch = "wasm32"))]
+    const FLUSH_INTERVAL: Duration = Duration::from_secs(BUCKET_INTERVAL);
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    struct BucketKey {
+        timestamp: u64,
+        ty: &'static str,
+        name: String,
+        unit: Option<String>,
+        tags: Vec<(String, String)>,
+    }
+
+    #[derive(Debug)]
+    enum BucketValue {
+        Counter(f64),
+        Distribution(Vec<f64>),
+        Gauge {
+            last: f64,
+            min: f64,
+            max: f64,
+            sum: f64,
+            count: u64,
+        },
+        Set(BTreeSet<u32>),
+    }
+
+    impl BucketValue {
+        fn new(value: MetricValue) -> Self {
+            match value {
+                MetricValue::Counter(v) => BucketValue::Counter(v),
+                MetricValue::Distribution(v) => BucketValue::Distribution(vec![v]),
+                MetricValue::Gauge(v) => BucketValue::Gauge {
+                    last: v,
+                    min: v,
+                    max: v,
+                    sum: v,
+                    count: 1,
+                },
+                MetricValue::Set(v) => BucketValue::Set(Some(v).into_iter().collect()),
+            }
+        }
+
+        fn merge(&mut self, value: MetricValue) {
+            match (self, value) {
+                (BucketValue::Counter(sum), MetricValue::Counter(v)) => *sum += v,
+                (BucketValue::Distribution(values), MetricValue::Distribution(v)) => values.push(v),
+                (
+                    BucketValue::Gauge {
+                        last,
+                        min,
+                        max,
+                        sum,
+                        count,
+                    },
+                    MetricValue::Gauge(v),
+                ) => {
+                    *last = v;
+                    *min = min.min(v);
+                    *max = max.max(v);
+                    *sum += v;
+                    *count += 1;
+                }
+                (BucketValue::Set(values), MetricValue::Set(v)) => {
+                    values.insert(v);
+                }
+                // the type is part of the bucket key, so values always match
+                _ => {}
+            }
+        }
+
+        fn write_values(&self, out: &mut String) {
+            match self {
+                BucketValue::Counter(sum) => write!(out, "{}", sum).ok(),
+                BucketValue::Distribution(values) => write_joined(out, values),
+                BucketValue::Gauge {
+                    last,
+                    min,
+                    max,
+                    sum,
+                    count,
+                } => write!(out, "{}:{}:{}:{}:{}", last, min, max, sum, count).ok(),
+                BucketValue::Set(values) => write_joined(out, values),
+            };
+        }
+    }
+
+    fn write_joined<T: std::fmt::Display>(
+        out: &mut String,
+        values: impl IntoIterator<Item = T>,
+    ) -> Option<()> {
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                out.push(':');
+            }
+            write!(out, "{}", value).ok()?;
+        }
+        Some(())
+    }
+
+    type Buckets = Arc<Mutex<HashMap<BucketKey, BucketValue>>>;
+
+    /// Aggregates metrics into time buckets, and periodically sends them.
+    pub(crate) struct MetricAggregator {
+        transport: TransportArc,
+        buckets: Buckets,
+        shutdown: Arc<(Mutex<bool>, Condvar)>,
+        worker: Option<JoinHandle<()>>,
+    }
+
+    impl MetricAggregator {
+        /// Creates a new aggregator that will submit envelopes to the given `transport`.
+        pub fn new(transport: TransportArc) -> Self {
+            let buckets = Arc::new(Mutex::new(HashMap::new()));
+            #[allow(clippy::mutex_atomic)]
+            let shutdown = Arc::new((Mutex::new(false), Condvar::new()));
+
+            #[cfg(not(target_arch = "wasm32"))]
+            let worker = Some(Self::spawn_worker(&transport, &buckets, &shutdown));
+            #[cfg(target_arch = "wasm32")]
+            let worker = None;
+
+            Self {
+                transport,
+                buckets,
+                shutdown,
+                worker,
+            }
+        }
+
+        /// Spawns the background thread that periodically flushes the buckets.
+        #[cfg(not(target_arch = "wasm32"))]
+        fn spawn_worker(
+            transport: &TransportArc,
+            buckets: &Buckets,
+            shutdown: &Arc<(Mutex<bool>, Condvar)>,
+        ) -> JoinHandle<()> {
+            let worker_transport = transport.clone();
+            let worker_buckets = buckets.clone();
+            let worker_shutdown = shutdown.clone();
+            std::thread::Builder::new()
+                .name("sentry-metrics".into())
+                .spawn(move || {
+                    let (lock, cvar) = worker_shutdown.as_ref();
+                    let mut shutdown = lock.lock().unwrap();
+                    loop {
+                        if *shutdown {
+                            return;
+                        }
+                        shutdown = cvar.wait_timeout(shutdown, FLUSH_INTERVAL).unwrap().0;
+                        if !*shutdown {
+                            Self::flush(worker_buckets.lock().unwrap(), &worker_transport);
+                        }
+                    }
+                })
+                .unwrap()
+        }
+
+        /// Adds a metric to the bucket of the current time window.
+        pub fn add(&self, metric: Metric) {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs());
+            let key = BucketKey {
+                timestamp: now - now % BUCKET_INTERVAL,
+                ty: metric.value.ty(),
+                name: metric.name.into_owned(),
+                unit: metric.unit.map(Cow::into_owned),
+                tags: metric.tags.into_iter().collect(),
+            };
+
+            let mut buckets = self.buckets.lock().unwrap();
+            match buckets.get_mut(&key) {
+                Some(bucket) => bucket.merge(metric.value),
+                None => {
+                    buckets.insert(key, BucketValue::new(metric.value));
+                }
+            }
+            if cfg!(target_arch = "wasm32") {
+                Self::flush(buckets, &self.transport);
+            }
+        }
+
+        /// Flushes all buckets to the transport immediately.
+        pub fn flush_buckets(&self) {
+            Self::flush(self.buckets.lock().unwrap(), &self.transport);
+        }
+
+        fn flush(
+            mut buckets: MutexGuard<HashMap<BucketKey, BucketValue>>,
+            transport: &TransportArc,
+        ) {
+            let buckets = std::mem::take(&mut *buckets);
+            if buckets.is_empty() {
+                return;
+            }
+
+            let mut envelope = Envelope::new();
+            envelope.add_item(EnvelopeItem::Statsd(to_statsd(buckets).into_bytes()));
+            if let Some(ref transport) = *transport.read().unwrap() {
+                transport.send_envelope(envelope);
+            }
+        }
+    }
+
+    impl Drop for MetricAggregator {
+        fn drop(&mut self) {
+            let (lock, cvar) = self.shutdown.as_ref();
+            *lock.lock().unwrap() = true;
+            cvar.notify_one();
+
+            if let Some(worker) = self.worker.take() {
+                worker.join().ok();
+            }
+            self.flush_buckets();
+        }
+    }
+
+    /// Serializes buckets into the statsd format, one bucket per line.
+    fn to_statsd(buckets: HashMap<BucketKey, BucketValue>) -> String {
+        let mut buckets: Vec<_> = buckets.into_iter().collect();
+        buckets.sort_by(|(a, _), (b, _)| {
+            (a.timestamp, &a.name, a.ty).cmp(&(b.timestamp, &b.name, b.ty))
+        });
+
+        let mut out = String::new();
+        for (key, value) in buckets {
+            out.push_str(&sanitize_name(&key.name));
+            if let Some(unit) = &key.unit {
+                out.push('@');
+                out.push_str(&sanitize_name(unit));
+            }
+            out.push(':');
+            value.write_values(&mut out);
+            out.push('|');
+            out.push_str(key.ty);
+            for (i, (tag, tag_value)) in key.tags.iter().enumerate() {
+                out.push_str(if i == 0 { "|#" } else { "," });
+                out.push_str(&sanitize_name(tag));
+                out.push(':');
+                out.push_str(&sanitize_tag_value(tag_value));
+            }
+            write!(out, "|T{}", key.timestamp).ok();
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Replaces characters that are not allowed in names, units and tag keys.
+    fn sanitize_name(name: &str) -> String {
+        name.chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' | '.' | '/' => c,
+                _ => '_',
+            })
+            .collect()
+    }
+
+    /// Removes characters that would break the statsd format from tag values.
+    fn sanitize_tag_value(value: &str) -> String {
+        value
+            .chars()
+            .filter(|c| !matches!(c, '\n' | '|' | ',' | '\r'))
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn key(name: &str, ty: &'static str, tags: &[(&str, &str)]) -> BucketKey {
+            BucketKey {
+                timestamp: 10,
+                ty,
+                name: name.into(),
+                unit: None,
+                tags: tags
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            }
+        }
+
+        #[test]
+        fn test_to_statsd() {
+            let mut counter = BucketValue::new(MetricValue::Counter(1.0));
+            counter.merge(MetricValue::Counter(2.0));
+            let mut gauge = BucketValue::new(MetricValue::Gauge(4.0));
+            gauge.merge(MetricValue::Gauge(2.0));
+            let mut set = BucketValue::new(MetricValue::Set(3));
+            set.merge(MetricValue::Set(3));
+            set.merge(MetricValue::Set(1));
+
+            let mut distribution_key = key("response time", "d", &[]);
+            distribution_key.unit = Some("millisecond".into());
+
+            let mut buckets = HashMap::new();
+            buckets.insert(
+                key("requests", "c", &[("route", "/a"), ("status", "2|00")]),
+                counter,
+            );
+            buckets.insert(key("memory", "g", &[]), gauge);
+            buckets.insert(key("users", "s", &[]), set);
+            buckets.insert(distribution_key, BucketValue::Distribution(vec![1.5, 2.0]));
+
+            assert_eq!(
+                to_statsd(buckets),
+                "memory:2:2:4:6:2|g|T10\n\
+                 requests:3|c|#route:/a,status:200|T10\n\
+                 response_time@millisecond:1.5:2|d|T10\n\
+                 users:1:3|s|T10\n"
+            );
+        }
+    }
+}
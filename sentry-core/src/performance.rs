@@ -0,0 +1,521 @@
+use std::sync::{Arc, Mutex};
+
+use crate::configure_scope;
+use crate::protocol::{self, Context, SpanId, TraceContext, Value};
+use crate::types::{DateTime, SentryTrace, Utc, Uuid};
+
+#[cfg(feature = "client")]
+use crate::Client;
+
+/// The information needed to start a new [`Transaction`].
+///
+/// A transaction either starts a new trace, or continues a trace that was
+/// started by another service, for example from an incoming `sentry-trace`
+/// header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionContext {
+    name: String,
+    op: String,
+    trace_id: Uuid,
+    parent_span_id: Option<SpanId>,
+    parent_sampled: Option<bool>,
+    sampled: Option<bool>,
+}
+
+impl TransactionContext {
+    /// Creates the context of a transaction that starts a new trace.
+    pub fn new(name: &str, op: &str) -> Self {
+        TransactionContext {
+            name: name.into(),
+            op: op.into(),
+            trace_id: Uuid::new_v4(),
+            parent_span_id: None,
+            parent_sampled: None,
+            sampled: None,
+        }
+    }
+
+    /// Creates the context of a transaction that continues the given upstream trace.
+    ///
+    /// The sampling decision of the upstream service is inherited, if it made one,
+    /// unless the `traces_sampler` decides otherwise.
+    pub fn continue_from_sentry_trace(name: &str, op: &str, trace: &SentryTrace) -> Self {
+        TransactionContext {
+            name: name.into(),
+            op: op.into(),
+            trace_id: trace.trace_id,
+            parent_span_id: Some(trace.span_id),
+            parent_sampled: trace.sampled,
+            sampled: None,
+        }
+    }
+
+    /// Sets the sampling decision, overriding the `traces_sampler`, the
+    /// `traces_sample_rate` and the decision of the parent.
+    pub fn set_sampled(&mut self, sampled: Option<bool>) {
+        self.sampled = sampled;
+    }
+
+    /// Returns the sampling decision of the upstream service, if the transaction
+    /// continues a trace and the upstream service made a decision.
+    pub fn parent_sampled(&self) -> Option<bool> {
+        self.parent_sampled
+    }
+
+    /// Returns the sampling decision set with [`set_sampled`](Self::set_sampled).
+    #[cfg(feature = "client")]
+    pub(crate) fn sampled(&self) -> Option<bool> {
+        self.sampled
+    }
+
+    /// Returns the name of the transaction.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the operation of the transaction.
+    pub fn operation(&self) -> &str {
+        &self.op
+    }
+}
+
+/// Starts a new [`Transaction`] using the client of the current hub.
+///
+/// The transaction is only sent to Sentry when it is sampled, and it is not
+/// bound to the current scope.  Use [`Scope::set_span`](crate::Scope::set_span)
+/// to make it the current span, so that events and outgoing requests are
+/// associated with it.
+///
+/// # Examples
+///
+/// ```
+/// let ctx = sentry::TransactionContext::new("process job", "queue.task");
+/// let transaction = sentry::start_transaction(ctx);
+///
+/// let span = transaction.start_child("db.query", "SELECT * FROM jobs");
+/// span.finish();
+///
+/// transaction.finish();
+/// ```
+pub fn start_transaction(ctx: TransactionContext) -> Transaction {
+    #[cfg(feature = "client")]
+    {
+        let client = crate::Hub::with_active(|hub| hub.client());
+        Transaction::new(client, ctx)
+    }
+    #[cfg(not(feature = "client"))]
+    {
+        Transaction::new_noop(ctx)
+    }
+}
+
+/// Starts a child of the current span, or a new transaction if there is no current span.
+///
+/// The `name` is used as the description of a child span, or as the name of a new
+/// transaction.  A new transaction continues the trace of the `trace` context of the
+/// current scope, if one was set by a server integration.  The span is not bound to
+/// the current scope, see [`TransactionOrSpan::enter`] for that.
+pub fn start_child_or_transaction(op: &str, name: &str) -> TransactionOrSpan {
+    let (parent, trace) = configure_scope(|scope| {
+        let trace = match scope.get_context("trace") {
+            Some(Context::Trace(context)) => Some(SentryTrace::from_trace_context(context)),
+            _ => None,
+        };
+        (scope.get_span(), trace)
+    });
+    match (parent, trace) {
+        (Some(parent), _) => parent.start_child(op, name).into(),
+        (None, Some(trace)) => start_transaction(TransactionContext::continue_from_sentry_trace(
+            name, op, &trace,
+        ))
+        .into(),
+        (None, None) => start_transaction(TransactionContext::new(name, op)).into(),
+    }
+}
+
+#[derive(Debug)]
+struct TransactionInner {
+    #[cfg(feature = "client")]
+    client: Option<Arc<Client>>,
+    sampled: bool,
+    #[cfg(feature = "client")]
+    name: String,
+    context: TraceContext,
+    transaction: Option<protocol::Transaction<'static>>,
+}
+
+/// A running tracing transaction.
+///
+/// The transaction is sent to Sentry once it is [finished](Transaction::finish),
+/// together with all child spans that were finished before.
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    inner: Arc<Mutex<TransactionInner>>,
+}
+
+impl Transaction {
+    #[cfg(feature = "client")]
+    fn new(client: Option<Arc<Client>>, ctx: TransactionContext) -> Self {
+        let sampled = match &client {
+            Some(client) if client.is_enabled() => client.sample_transaction(&ctx),
+            _ => false,
+        };
+        let transaction = match client {
+            Some(ref client) if sampled => {
+                let mut transaction = protocol::Transaction {
+                    name: Some(ctx.name.clone()),
+                    ..Default::default()
+                };
+                client.start_transaction(&mut transaction);
+                Some(transaction)
+            }
+            _ => None,
+        };
+
+        Transaction {
+            inner: Arc::new(Mutex::new(TransactionInner {
+                client,
+                sampled,
+                name: ctx.name.clone(),
+                context: context_from(&ctx),
+                transaction,
+            })),
+        }
+    }
+
+    #[cfg(not(feature = "client"))]
+    fn new_noop(ctx: TransactionContext) -> Self {
+        Transaction {
+            inner: Arc::new(Mutex::new(TransactionInner {
+                sampled: false,
+                context: context_from(&ctx),
+                transaction: None,
+            })),
+        }
+    }
+
+    /// Returns whether the transaction is sampled, and will be sent to Sentry.
+    pub fn is_sampled(&self) -> bool {
+        self.inner.lock().unwrap().sampled
+    }
+
+    /// Returns the trace context of the transaction, for use on events.
+    pub fn get_trace_context(&self) -> TraceContext {
+        self.inner.lock().unwrap().context.clone()
+    }
+
+    /// Returns the `sentry-trace` header value that continues this transaction.
+    pub fn sentry_trace(&self) -> SentryTrace {
+        let inner = self.inner.lock().unwrap();
+        SentryTrace {
+            trace_id: inner.context.trace_id,
+            span_id: inner.context.span_id,
+            sampled: Some(inner.sampled),
+        }
+    }
+
+    /// Sets the status of the transaction, such as `ok` or `internal_error`.
+    ///
+    /// See [`SpanStatus`](crate::protocol::SpanStatus) for the known statuses.
+    pub fn set_status(&self, status: &str) {
+        self.inner.lock().unwrap().context.status = Some(status.into());
+    }
+
+    /// Renames the transaction, for example once the route of a request is known.
+    #[allow(unused)]
+    pub fn set_name(&self, name: &str) {
+        with_client_impl! {{
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(transaction) = inner.transaction.as_mut() {
+                transaction.name = Some(name.into());
+            }
+            inner.name = name.into();
+        }}
+    }
+
+    /// Sets an arbitrary piece of data on the transaction.
+    pub fn set_data(&self, key: &str, value: Value) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.sampled {
+            inner.context.data.insert(key.into(), value);
+        }
+    }
+
+    /// Starts a new child span of the transaction.
+    pub fn start_child(&self, op: &str, description: &str) -> Span {
+        let inner = self.inner.lock().unwrap();
+        Span::new(
+            self.inner.clone(),
+            inner.sampled,
+            &inner.context,
+            op,
+            description,
+        )
+    }
+
+    /// Finishes the transaction, and sends it to Sentry if it is sampled.
+    pub fn finish(self) {
+        with_client_impl! {{
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(mut transaction) = inner.transaction.take() {
+                if let Some(client) = inner.client.take() {
+                    transaction.finish();
+                    transaction
+                        .contexts
+                        .insert("trace".into(), inner.context.clone().into());
+                    client.send_transaction(transaction);
+                }
+            }
+        }}
+    }
+}
+
+/// A running span, which is part of a [`Transaction`].
+#[derive(Clone, Debug)]
+pub struct Span {
+    transaction: Arc<Mutex<TransactionInner>>,
+    sampled: bool,
+    span: Arc<Mutex<protocol::Span>>,
+}
+
+impl Span {
+    fn new(
+        transaction: Arc<Mutex<TransactionInner>>,
+        sampled: bool,
+        parent: &TraceContext,
+        op: &str,
+        description: &str,
+    ) -> Self {
+        let span = protocol::Span {
+            trace_id: parent.trace_id,
+            parent_span_id: Some(parent.span_id.to_string()),
+            op: Some(op.into()),
+            description: Some(description.into()),
+            ..Default::default()
+        };
+        Span {
+            transaction,
+            sampled,
+            span: Arc::new(Mutex::new(span)),
+        }
+    }
+
+    /// Returns whether the span is sampled, and will be sent to Sentry.
+    pub fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// Returns the trace context of the span, for use on events.
+    pub fn get_trace_context(&self) -> TraceContext {
+        let span = self.span.lock().unwrap();
+        TraceContext {
+            span_id: span.span_id,
+            trace_id: span.trace_id,
+            parent_span_id: span.parent_span_id.clone(),
+            op: span.op.clone(),
+            description: span.description.clone(),
+            status: span.status.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the `sentry-trace` header value that continues this span.
+    pub fn sentry_trace(&self) -> SentryTrace {
+        let span = self.span.lock().unwrap();
+        SentryTrace {
+            trace_id: span.trace_id,
+            span_id: span.span_id,
+            sampled: Some(self.sampled),
+        }
+    }
+
+    /// Sets the status of the span, such as `ok` or `internal_error`.
+    ///
+    /// See [`SpanStatus`](crate::protocol::SpanStatus) for the known statuses.
+    pub fn set_status(&self, status: &str) {
+        self.span.lock().unwrap().status = Some(status.into());
+    }
+
+    /// Sets an arbitrary piece of data on the span.
+    pub fn set_data(&self, key: &str, value: Value) {
+        if self.sampled {
+            self.span.lock().unwrap().data.insert(key.into(), value);
+        }
+    }
+
+    /// Sets the time at which the span started.
+    ///
+    /// This is useful for operations that are only reported once they have completed.
+    pub fn set_start_timestamp(&self, timestamp: DateTime<Utc>) {
+        self.span.lock().unwrap().start_timestamp = timestamp;
+    }
+
+    /// Starts a new child span of this span.
+    pub fn start_child(&self, op: &str, description: &str) -> Span {
+        let context = self.get_trace_context();
+        Span::new(
+            self.transaction.clone(),
+            self.sampled,
+            &context,
+            op,
+            description,
+        )
+    }
+
+    /// Finishes the span, and adds it to its transaction.
+    ///
+    /// Spans that are finished after their transaction are discarded.
+    pub fn finish(self) {
+        if !self.sampled {
+            return;
+        }
+        let mut span = self.span.lock().unwrap().clone();
+        span.finish();
+        if let Some(transaction) = self.transaction.lock().unwrap().transaction.as_mut() {
+            transaction.spans.push(span);
+        }
+    }
+}
+
+/// Either a [`Transaction`] or a [`Span`].
+///
+/// This is the type of the current span of a [`Scope`](crate::Scope).
+#[derive(Clone, Debug)]
+pub enum TransactionOrSpan {
+    /// A transaction.
+    Transaction(Transaction),
+    /// A span.
+    Span(Span),
+}
+
+impl From<Transaction> for TransactionOrSpan {
+    fn from(transaction: Transaction) -> Self {
+        TransactionOrSpan::Transaction(transaction)
+    }
+}
+
+impl From<Span> for TransactionOrSpan {
+    fn from(span: Span) -> Self {
+        TransactionOrSpan::Span(span)
+    }
+}
+
+impl TransactionOrSpan {
+    /// Returns whether the span is sampled, and will be sent to Sentry.
+    pub fn is_sampled(&self) -> bool {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => transaction.is_sampled(),
+            TransactionOrSpan::Span(span) => span.is_sampled(),
+        }
+    }
+
+    /// Returns the trace context of the span, for use on events.
+    pub fn get_trace_context(&self) -> TraceContext {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => transaction.get_trace_context(),
+            TransactionOrSpan::Span(span) => span.get_trace_context(),
+        }
+    }
+
+    /// Returns the name of the transaction this is, or is part of.
+    #[cfg(feature = "client")]
+    pub(crate) fn transaction_name(&self) -> String {
+        let inner = match self {
+            TransactionOrSpan::Transaction(transaction) => &transaction.inner,
+            TransactionOrSpan::Span(span) => &span.transaction,
+        };
+        inner.lock().unwrap().name.clone()
+    }
+
+    /// Returns the `sentry-trace` header value that continues this span.
+    pub fn sentry_trace(&self) -> SentryTrace {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => transaction.sentry_trace(),
+            TransactionOrSpan::Span(span) => span.sentry_trace(),
+        }
+    }
+
+    /// Sets the status of the span, such as `ok` or `internal_error`.
+    ///
+    /// See [`SpanStatus`](crate::protocol::SpanStatus) for the known statuses.
+    pub fn set_status(&self, status: &str) {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => transaction.set_status(status),
+            TransactionOrSpan::Span(span) => span.set_status(status),
+        }
+    }
+
+    /// Sets an arbitrary piece of data on the span.
+    pub fn set_data(&self, key: &str, value: Value) {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => transaction.set_data(key, value),
+            TransactionOrSpan::Span(span) => span.set_data(key, value),
+        }
+    }
+
+    /// Starts a new child span.
+    pub fn start_child(&self, op: &str, description: &str) -> Span {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => transaction.start_child(op, description),
+            TransactionOrSpan::Span(span) => span.start_child(op, description),
+        }
+    }
+
+    /// Finishes the transaction or span.
+    pub fn finish(self) {
+        match self {
+            TransactionOrSpan::Transaction(transaction) => transaction.finish(),
+            TransactionOrSpan::Span(span) => span.finish(),
+        }
+    }
+
+    /// Makes this the current span of the current scope until the returned guard is dropped.
+    ///
+    /// Dropping the guard finishes the span, and restores the previous span of the scope.
+    pub fn enter(self) -> SpanGuard {
+        let previous = configure_scope(|scope| {
+            let previous = scope.get_span();
+            scope.set_span(Some(self.clone()));
+            previous
+        });
+        SpanGuard {
+            span: Some(self),
+            previous,
+        }
+    }
+}
+
+/// Finishes a span that was [entered](TransactionOrSpan::enter) when dropped.
+#[derive(Debug)]
+pub struct SpanGuard {
+    span: Option<TransactionOrSpan>,
+    previous: Option<TransactionOrSpan>,
+}
+
+impl SpanGuard {
+    /// Returns the entered span.
+    pub fn span(&self) -> &TransactionOrSpan {
+        // the span is only taken on drop
+        self.span.as_ref().unwrap()
+    }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        configure_scope(|scope| scope.set_span(previous));
+        if let Some(span) = self.span.take() {
+            span.finish();
+        }
+    }
+}
+
+fn context_from(ctx: &TransactionContext) -> TraceContext {
+    TraceContext {
+        span_id: SpanId::default(),
+        trace_id: ctx.trace_id,
+        parent_span_id: ctx.parent_span_id.map(|span_id| span_id.to_string()),
+        op: Some(ctx.op.clone()),
+        ..Default::default()
+    }
+}
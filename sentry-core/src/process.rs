@@ -0,0 +1,277 @@
+//! Subprocess instrumentation.
+//!
+//! The [`Command`] wrapper records a `process` breadcrumb with the program,
+//! arguments and exit status of every child process it runs, and propagates the
+//! trace of the current scope to the child process in the `SENTRY_TRACE` and
+//! `SENTRY_BAGGAGE` environment variables.  An instrumented child process can
+//! continue the trace with [`transaction_context_from_env`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use sentry::process::Command;
+//!
+//! let status = Command::new("git").arg("status").status();
+//! ```
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process::{self, Child, ExitStatus, Output, Stdio};
+use std::time::Instant;
+
+use crate::protocol::{Breadcrumb, Context, Level, Map, Value};
+use crate::types::SentryTrace;
+use crate::TransactionContext;
+
+/// The environment variable that propagates a trace to child processes.
+pub const SENTRY_TRACE_ENV: &str = "SENTRY_TRACE";
+
+/// The environment variable that propagates baggage to child processes.
+pub const BAGGAGE_ENV: &str = "SENTRY_BAGGAGE";
+
+/// A wrapper around [`std::process::Command`] that instruments child processes.
+///
+/// Only the most common builder methods are wrapped; the inner command can be
+/// configured further through [`as_inner_mut`](Command::as_inner_mut).
+pub struct Command {
+    inner: process::Command,
+    program: OsString,
+    args: Vec<OsString>,
+    propagate_trace: bool,
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl Command {
+    /// Creates a new command for the given program.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Command {
+            inner: process::Command::new(program.as_ref()),
+            program: program.as_ref().to_owned(),
+            args: Vec::new(),
+            propagate_trace: true,
+        }
+    }
+
+    /// Adds an argument to pass to the program.
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.inner.arg(arg.as_ref());
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds multiple arguments to pass to the program.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        for arg in args {
+            self.arg(arg);
+        }
+        self
+    }
+
+    /// Sets an environment variable of the child process.
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.env(key, val);
+        self
+    }
+
+    /// Removes an environment variable of the child process.
+    pub fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Self {
+        self.inner.env_remove(key);
+        self
+    }
+
+    /// Sets the working directory of the child process.
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.inner.current_dir(dir);
+        self
+    }
+
+    /// Configures the standard input of the child process.
+    pub fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stdin(cfg);
+        self
+    }
+
+    /// Configures the standard output of the child process.
+    pub fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stdout(cfg);
+        self
+    }
+
+    /// Configures the standard error of the child process.
+    pub fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stderr(cfg);
+        self
+    }
+
+    /// Enables or disables propagating the trace to the child process.
+    ///
+    /// The default is to propagate the trace.
+    pub fn propagate_trace(&mut self, val: bool) -> &mut Self {
+        self.propagate_trace = val;
+        self
+    }
+
+    /// Returns a reference to the wrapped command.
+    pub fn as_inner(&self) -> &process::Command {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped command.
+    pub fn as_inner_mut(&mut self) -> &mut process::Command {
+        &mut self.inner
+    }
+
+    /// Spawns the child process.
+    ///
+    /// The breadcrumb is recorded when the process was spawned, and thus does not
+    /// contain the exit status.
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        self.inject_trace();
+        let result = self.inner.spawn();
+        self.add_breadcrumb(result.as_ref().err(), None, None);
+        result
+    }
+
+    /// Runs the child process, waits for it to exit and returns its exit status.
+    pub fn status(&mut self) -> io::Result<ExitStatus> {
+        self.inject_trace();
+        let start = Instant::now();
+        let result = self.inner.status();
+        self.add_breadcrumb(result.as_ref().err(), result.as_ref().ok(), Some(start));
+        result
+    }
+
+    /// Runs the child process, waits for it to exit and collects its output.
+    pub fn output(&mut self) -> io::Result<Output> {
+        self.inject_trace();
+        let start = Instant::now();
+        let result = self.inner.output();
+        let status = result.as_ref().ok().map(|output| &output.status);
+        self.add_breadcrumb(result.as_ref().err(), status, Some(start));
+        result
+    }
+
+    fn inject_trace(&mut self) {
+        if !self.propagate_trace {
+            return;
+        }
+        let (trace, baggage) = current_trace();
+        if let Some(trace) = trace {
+            self.inner.env(SENTRY_TRACE_ENV, trace.to_string());
+            if let Some(baggage) = baggage {
+                self.inner.env(BAGGAGE_ENV, baggage);
+            }
+        }
+    }
+
+    fn add_breadcrumb(
+        &self,
+        error: Option<&io::Error>,
+        status: Option<&ExitStatus>,
+        start: Option<Instant>,
+    ) {
+        let program = self.program.to_string_lossy();
+        let args: Vec<_> = self.args.iter().map(|arg| arg.to_string_lossy()).collect();
+
+        let mut data = Map::new();
+        data.insert("program".into(), program.as_ref().into());
+        let arg_values = args.iter().map(|arg| arg.as_ref().into()).collect();
+        data.insert("args".into(), Value::Array(arg_values));
+        if let Some(start) = start {
+            let duration = start.elapsed().as_millis() as u64;
+            data.insert("duration_ms".into(), duration.into());
+        }
+
+        let level = match (error, status) {
+            (Some(error), _) => {
+                data.insert("error".into(), error.to_string().into());
+                Level::Error
+            }
+            (None, Some(status)) => {
+                if let Some(code) = status.code() {
+                    data.insert("exit_code".into(), code.into());
+                }
+                #[cfg(unix)]
+                {
+                    use std::os::unix::process::ExitStatusExt;
+                    if let Some(signal) = status.signal() {
+                        data.insert("signal".into(), signal.into());
+                    }
+                }
+                if status.success() {
+                    Level::Info
+                } else {
+                    Level::Warning
+                }
+            }
+            (None, None) => Level::Info,
+        };
+
+        let mut message = program.into_owned();
+        for arg in &args {
+            message.push(' ');
+            message.push_str(arg);
+        }
+        crate::add_breadcrumb(Breadcrumb {
+            category: Some("process".into()),
+            message: Some(message),
+            level,
+            data,
+            ..Default::default()
+        });
+    }
+}
+
+/// Returns the trace and baggage of the current scope, if it has a span or a
+/// trace context.
+fn current_trace() -> (Option<SentryTrace>, Option<String>) {
+    crate::configure_scope(|scope| {
+        let trace = match (scope.get_span(), scope.get_context("trace")) {
+            (Some(span), _) => Some(span.sentry_trace()),
+            (None, Some(Context::Trace(trace_context))) => {
+                Some(SentryTrace::from_trace_context(trace_context))
+            }
+            _ => None,
+        };
+        let baggage = scope.get_baggage().map(ToOwned::to_owned);
+        (trace, baggage)
+    })
+}
+
+/// Creates the context of a transaction that continues the trace of the parent
+/// process.
+///
+/// If the parent process did not propagate a valid trace in the `SENTRY_TRACE`
+/// environment variable, the transaction starts a new trace.
+///
+/// # Examples
+///
+/// ```
+/// let ctx = sentry::process::transaction_context_from_env("import", "cli.command");
+/// let transaction = sentry::start_transaction(ctx);
+/// transaction.finish();
+/// ```
+pub fn transaction_context_from_env(name: &str, op: &str) -> TransactionContext {
+    let trace = std::env::var(SENTRY_TRACE_ENV)
+        .ok()
+        .and_then(|trace| trace.parse::<SentryTrace>().ok());
+    match trace {
+        Some(trace) => TransactionContext::continue_from_sentry_trace(name, op, &trace),
+        None => TransactionContext::new(name, op),
+    }
+}
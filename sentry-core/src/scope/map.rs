@@ -0,0 +1,142 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// The number of entries up to which a [`SmallMap`] is a plain vector.
+const SMALL_CAPACITY: usize = 8;
+
+/// A copy-on-write map for the tags, extras and contexts of a scope.
+///
+/// Scopes are cloned all the time but usually only carry a handful of entries.
+/// Up to `SMALL_CAPACITY` entries are kept in a shared vector that is searched
+/// linearly, and only larger maps switch to a persistent hash map.
+#[derive(Clone)]
+pub(crate) enum SmallMap<K, V> {
+    Small(Arc<Vec<(K, V)>>),
+    Large(im::HashMap<K, V>),
+}
+
+impl<K, V> Default for SmallMap<K, V> {
+    fn default() -> Self {
+        SmallMap::Small(Arc::new(Vec::new()))
+    }
+}
+
+impl<K: fmt::Debug + Hash + Eq + Clone, V: fmt::Debug + Clone> fmt::Debug for SmallMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> SmallMap<K, V> {
+    /// Inserts a value, replacing the previous value of the key.
+    pub fn insert(&mut self, key: K, value: V) {
+        let entries = match self {
+            SmallMap::Small(entries) => Arc::make_mut(entries),
+            SmallMap::Large(map) => {
+                map.insert(key, value);
+                return;
+            }
+        };
+
+        if let Some(entry) = entries.iter_mut().find(|(k, _)| *k == key) {
+            entry.1 = value;
+        } else if entries.len() < SMALL_CAPACITY {
+            entries.push((key, value));
+        } else {
+            let mut map: im::HashMap<K, V> = entries.drain(..).collect();
+            map.insert(key, value);
+            *self = SmallMap::Large(map);
+        }
+    }
+
+    /// Returns the value of a key, if it is set.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self {
+            SmallMap::Small(entries) => entries
+                .iter()
+                .find(|(k, _)| k.borrow() == key)
+                .map(|(_, v)| v),
+            SmallMap::Large(map) => map.get(key),
+        }
+    }
+
+    /// Removes a key from the map.
+    pub fn remove<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self {
+            SmallMap::Small(entries) => {
+                // avoid unsharing the entries if there is nothing to remove
+                if let Some(index) = entries.iter().position(|(k, _)| k.borrow() == key) {
+                    Arc::make_mut(entries).remove(index);
+                }
+            }
+            SmallMap::Large(map) => {
+                map.remove(key);
+            }
+        }
+    }
+
+    /// Iterates over all entries of the map.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let (small, large) = match self {
+            SmallMap::Small(entries) => (Some(entries.iter().map(|(k, v)| (k, v))), None),
+            SmallMap::Large(map) => (None, Some(map.iter())),
+        };
+        small
+            .into_iter()
+            .flatten()
+            .chain(large.into_iter().flatten())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Extend<(K, V)> for SmallMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_map() {
+        let mut map = SmallMap::default();
+        map.insert("a".to_string(), 1);
+        map.insert("b".to_string(), 2);
+        map.insert("a".to_string(), 3);
+        assert_eq!(map.get("a"), Some(&3));
+        assert_eq!(map.iter().count(), 2);
+
+        let cloned = map.clone();
+        map.remove("a");
+        assert_eq!(map.get("a"), None);
+        assert_eq!(cloned.get("a"), Some(&3));
+
+        map.extend(vec![("b".to_string(), 4), ("c".to_string(), 5)]);
+        assert_eq!(map.get("b"), Some(&4));
+        assert_eq!(map.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_small_map_grows() {
+        let mut map = SmallMap::default();
+        for i in 0..=SMALL_CAPACITY {
+            map.insert(i, i);
+        }
+        assert!(matches!(map, SmallMap::Large(_)));
+        assert_eq!(map.iter().count(), SMALL_CAPACITY + 1);
+        assert_eq!(map.get(&SMALL_CAPACITY), Some(&SMALL_CAPACITY));
+    }
+}
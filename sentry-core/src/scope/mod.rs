@@ -1,4 +1,6 @@
 #[cfg(feature = "client")]
+mod map;
+#[cfg(feature = "client")]
 mod real;
 
 #[cfg(not(feature = "client"))]
@@ -1,6 +1,8 @@
 use std::fmt;
 
 use crate::protocol::{Context, Event, Level, User, Value};
+use crate::types::Dsn;
+use crate::TransactionOrSpan;
 
 /// A minimal API scope guard.
 ///
@@ -55,6 +57,12 @@ impl Scope {
         minimal_unreachable!();
     }
 
+    /// Sets a DSN override for the events captured in this scope.
+    pub fn set_dsn(&mut self, dsn: Option<Dsn>) {
+        let _dsn = dsn;
+        minimal_unreachable!();
+    }
+
     /// Sets a tag to a specific value.
     pub fn set_tag<V: ToString>(&mut self, key: &str, value: V) {
         let _key = key;
@@ -62,6 +70,23 @@ impl Scope {
         minimal_unreachable!();
     }
 
+    /// Sets multiple tags at once.
+    pub fn set_tags<I, K, V>(&mut self, tags: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: ToString,
+    {
+        let _tags = tags;
+        minimal_unreachable!();
+    }
+
+    /// Returns the value of a tag, if it is set.
+    pub fn get_tag(&self, key: &str) -> Option<&str> {
+        let _key = key;
+        minimal_unreachable!();
+    }
+
     /// Removes a tag.
     pub fn remove_tag(&mut self, key: &str) {
         let _key = key;
@@ -75,6 +100,12 @@ impl Scope {
         minimal_unreachable!();
     }
 
+    /// Returns the context for a key, if it is set.
+    pub fn get_context(&self, key: &str) -> Option<&Context> {
+        let _key = key;
+        minimal_unreachable!();
+    }
+
     /// Removes a context for a key.
     pub fn remove_context(&mut self, key: &str) {
         let _key = key;
@@ -88,12 +119,40 @@ impl Scope {
         minimal_unreachable!();
     }
 
+    /// Returns the extra for a key, if it is set.
+    pub fn get_extra(&self, key: &str) -> Option<&Value> {
+        let _key = key;
+        minimal_unreachable!();
+    }
+
     /// Removes a extra.
     pub fn remove_extra(&mut self, key: &str) {
         let _key = key;
         minimal_unreachable!();
     }
 
+    /// Sets the current span of the scope.
+    pub fn set_span(&mut self, span: Option<TransactionOrSpan>) {
+        let _span = span;
+        minimal_unreachable!();
+    }
+
+    /// Returns the current span of the scope, if any.
+    pub fn get_span(&self) -> Option<TransactionOrSpan> {
+        minimal_unreachable!();
+    }
+
+    /// Sets the baggage that is propagated along with the trace of the scope.
+    pub fn set_baggage(&mut self, baggage: Option<&str>) {
+        let _baggage = baggage;
+        minimal_unreachable!();
+    }
+
+    /// Returns the baggage of the scope, if any.
+    pub fn get_baggage(&self) -> Option<&str> {
+        minimal_unreachable!();
+    }
+
     /// Add an event processor to the scope.
     pub fn add_event_processor(
         &mut self,
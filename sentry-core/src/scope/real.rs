@@ -2,9 +2,11 @@ use std::borrow::Cow;
 use std::fmt;
 use std::sync::{Arc, Mutex, PoisonError, RwLock};
 
+use super::map::SmallMap;
 use crate::protocol::{Breadcrumb, Context, Event, Level, User, Value};
 use crate::session::Session;
-use crate::Client;
+use crate::types::Dsn;
+use crate::{Client, TransactionOrSpan};
 
 #[derive(Debug)]
 pub struct Stack {
@@ -38,11 +40,14 @@ pub struct Scope {
     pub(crate) transaction: Option<Arc<str>>,
     pub(crate) breadcrumbs: im::Vector<Breadcrumb>,
     pub(crate) user: Option<Arc<User>>,
-    pub(crate) extra: im::HashMap<String, Value>,
-    pub(crate) tags: im::HashMap<String, String>,
-    pub(crate) contexts: im::HashMap<String, Context>,
+    pub(crate) extra: SmallMap<String, Value>,
+    pub(crate) tags: SmallMap<String, String>,
+    pub(crate) contexts: SmallMap<String, Context>,
     pub(crate) event_processors: im::Vector<Arc<EventProcessor>>,
     pub(crate) session: Arc<Mutex<Option<Session>>>,
+    pub(crate) span: Option<TransactionOrSpan>,
+    pub(crate) dsn: Option<Arc<Dsn>>,
+    pub(crate) baggage: Option<Arc<str>>,
 }
 
 impl fmt::Debug for Scope {
@@ -58,6 +63,9 @@ impl fmt::Debug for Scope {
             .field("contexts", &self.contexts)
             .field("event_processors", &self.event_processors.len())
             .field("session", &self.session)
+            .field("span", &self.span)
+            .field("dsn", &self.dsn)
+            .field("baggage", &self.baggage)
             .finish()
     }
 }
@@ -75,6 +83,9 @@ impl Default for Scope {
             contexts: Default::default(),
             event_processors: Default::default(),
             session: Default::default(),
+            span: None,
+            dsn: None,
+            baggage: None,
         }
     }
 }
@@ -181,12 +192,53 @@ impl Scope {
         self.user = user.map(Arc::new);
     }
 
+    /// Sets a DSN override for the events captured in this scope.
+    ///
+    /// Events are sent to this DSN instead of the DSN of the client, which
+    /// allows routing the events of different tenants to different projects
+    /// with a single client.  The client creates and reuses one transport per
+    /// DSN.  Sessions, metrics and transactions are always sent to the DSN of
+    /// the client.
+    pub fn set_dsn(&mut self, dsn: Option<Dsn>) {
+        self.dsn = dsn.map(Arc::new);
+    }
+
     /// Sets a tag to a specific value.
     #[allow(clippy::needless_pass_by_value)]
     pub fn set_tag<V: ToString>(&mut self, key: &str, value: V) {
         self.tags.insert(key.to_string(), value.to_string());
     }
 
+    /// Sets multiple tags at once.
+    ///
+    /// This accepts any iterator of key-value pairs, like the map built by the
+    /// [`tags!`](macro.tags.html) macro.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// sentry::configure_scope(|scope| {
+    ///     scope.set_tags(sentry::tags! { "region" => "eu", "shard" => 7 });
+    ///     scope.set_tags(vec![("worker", 3), ("attempt", 1)]);
+    /// });
+    /// ```
+    pub fn set_tags<I, K, V>(&mut self, tags: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: ToString,
+    {
+        self.tags.extend(
+            tags.into_iter()
+                .map(|(key, value)| (key.into(), value.to_string())),
+        );
+    }
+
+    /// Returns the value of a tag, if it is set.
+    pub fn get_tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+
     /// Removes a tag.
     ///
     /// If the tag is not set, does nothing.
@@ -199,6 +251,11 @@ impl Scope {
         self.contexts.insert(key.to_string(), value.into());
     }
 
+    /// Returns the context for a key, if it is set.
+    pub fn get_context(&self, key: &str) -> Option<&Context> {
+        self.contexts.get(key)
+    }
+
     /// Removes a context for a key.
     pub fn remove_context(&mut self, key: &str) {
         self.contexts.remove(key);
@@ -209,11 +266,43 @@ impl Scope {
         self.extra.insert(key.to_string(), value);
     }
 
+    /// Returns the extra for a key, if it is set.
+    pub fn get_extra(&self, key: &str) -> Option<&Value> {
+        self.extra.get(key)
+    }
+
     /// Removes a extra.
     pub fn remove_extra(&mut self, key: &str) {
         self.extra.remove(key);
     }
 
+    /// Sets the current span of the scope.
+    ///
+    /// Events captured within the scope carry the trace context of the span, as
+    /// well as the name of its transaction unless a transaction name is set on the
+    /// scope, and new spans are started as its children.
+    pub fn set_span(&mut self, span: Option<TransactionOrSpan>) {
+        self.span = span;
+    }
+
+    /// Returns the current span of the scope, if any.
+    pub fn get_span(&self) -> Option<TransactionOrSpan> {
+        self.span.clone()
+    }
+
+    /// Sets the baggage that is propagated along with the trace of the scope.
+    ///
+    /// Server integrations keep the `baggage` header of the incoming request here, so
+    /// that client integrations can forward it to the services they call.
+    pub fn set_baggage(&mut self, baggage: Option<&str>) {
+        self.baggage = baggage.map(Arc::from);
+    }
+
+    /// Returns the baggage of the scope, if any.
+    pub fn get_baggage(&self) -> Option<&str> {
+        self.baggage.as_deref()
+    }
+
     /// Add an event processor to the scope.
     pub fn add_event_processor(
         &mut self,
@@ -239,13 +328,26 @@ impl Scope {
         event
             .breadcrumbs
             .extend(self.breadcrumbs.clone().into_iter());
-        event.extra.extend(self.extra.clone().into_iter());
-        event.tags.extend(self.tags.clone().into_iter());
-        event.contexts.extend(self.contexts.clone().into_iter());
+        event
+            .extra
+            .extend(self.extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+        event
+            .tags
+            .extend(self.tags.iter().map(|(k, v)| (k.clone(), v.clone())));
+        event
+            .contexts
+            .extend(self.contexts.iter().map(|(k, v)| (k.clone(), v.clone())));
+        if let Some(span) = &self.span {
+            event
+                .contexts
+                .insert("trace".into(), span.get_trace_context().into());
+        }
 
         if event.transaction.is_none() {
             if let Some(txn) = self.transaction.as_deref() {
                 event.transaction = Some(txn.to_owned());
+            } else if let Some(span) = &self.span {
+                event.transaction = Some(span.transaction_name());
             }
         }
 
@@ -4,6 +4,7 @@
 
 use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 use std::thread::JoinHandle;
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 use crate::client::TransportArc;
@@ -18,7 +19,6 @@ use crate::{Client, Envelope};
 pub struct Session {
     client: Arc<Client>,
     session_update: SessionUpdate<'static>,
-    started: Instant,
     dirty: bool,
 }
 
@@ -63,7 +63,6 @@ impl Session {
                     user_agent: None,
                 },
             },
-            started: Instant::now(),
             dirty: true,
         })
     }
@@ -101,7 +100,11 @@ impl Session {
                 SessionStatus::Ok => SessionStatus::Exited,
                 s => s,
             };
-            self.session_update.duration = Some(self.started.elapsed().as_secs_f64());
+            // `Instant` is not available on all targets, notably wasm32, so
+            // the duration is derived from the wall clock start timestamp.
+            let duration = Utc::now() - self.session_update.started;
+            // a negative duration after a clock adjustment is reported as zero
+            self.session_update.duration = Some(duration.to_std().map_or(0.0, |d| d.as_secs_f64()));
             self.session_update.status = status;
             self.dirty = true;
         }
@@ -120,6 +123,7 @@ impl Session {
 
 // as defined here: https://develop.sentry.dev/sdk/envelopes/#size-limits
 const MAX_SESSION_ITEMS: usize = 100;
+#[cfg(not(target_arch = "wasm32"))]
 const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
 
 type SessionQueue = Arc<Mutex<Vec<SessionUpdate<'static>>>>;
@@ -130,6 +134,9 @@ type SessionQueue = Arc<Mutex<Vec<SessionUpdate<'static>>>>;
 /// It has its own background thread that will flush its queue once every
 /// `FLUSH_INTERVAL`.
 ///
+/// On `wasm32` targets, which can not spawn threads, there is no background
+/// thread and session updates are flushed as soon as they are enqueued.
+///
 /// For now it just batches all the session updates together into one envelope,
 /// but in the future it will also pre-aggregate session numbers.
 pub(crate) struct SessionFlusher {
@@ -146,10 +153,30 @@ impl SessionFlusher {
         #[allow(clippy::mutex_atomic)]
         let shutdown = Arc::new((Mutex::new(false), Condvar::new()));
 
+        #[cfg(not(target_arch = "wasm32"))]
+        let worker = Some(Self::spawn_worker(&transport, &queue, &shutdown));
+        #[cfg(target_arch = "wasm32")]
+        let worker = None;
+
+        Self {
+            transport,
+            queue,
+            shutdown,
+            worker,
+        }
+    }
+
+    /// Spawns the background thread that periodically flushes the queue.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn_worker(
+        transport: &TransportArc,
+        queue: &SessionQueue,
+        shutdown: &Arc<(Mutex<bool>, Condvar)>,
+    ) -> JoinHandle<()> {
         let worker_transport = transport.clone();
         let worker_queue = queue.clone();
         let worker_shutdown = shutdown.clone();
-        let worker = std::thread::Builder::new()
+        std::thread::Builder::new()
             .name("sentry-session-flusher".into())
             .spawn(move || {
                 let (lock, cvar) = worker_shutdown.as_ref();
@@ -172,14 +199,7 @@ impl SessionFlusher {
                     last_flush = Instant::now();
                 }
             })
-            .unwrap();
-
-        Self {
-            transport,
-            queue,
-            shutdown,
-            worker: Some(worker),
-        }
+            .unwrap()
     }
 
     /// Enqueues a session update for delayed sending.
@@ -188,11 +208,16 @@ impl SessionFlusher {
     pub fn enqueue(&self, session_update: SessionUpdate<'static>) {
         let mut queue = self.queue.lock().unwrap();
         queue.push(session_update);
-        if queue.len() >= MAX_SESSION_ITEMS {
+        if queue.len() >= MAX_SESSION_ITEMS || cfg!(target_arch = "wasm32") {
             SessionFlusher::flush(queue, &self.transport);
         }
     }
 
+    /// Flushes all enqueued session updates to the transport immediately.
+    pub fn flush_queue(&self) {
+        SessionFlusher::flush(self.queue.lock().unwrap(), &self.transport);
+    }
+
     /// Flushes the queue to the transport.
     ///
     /// This is a static method as it will be called from both the background
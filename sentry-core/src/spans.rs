@@ -0,0 +1,90 @@
+//! Helpers for creating spans of common operations.
+//!
+//! The messaging helpers create the spans of producers and consumers of message
+//! brokers, such as NATS, RabbitMQ or SQS.  The trace is carried in the headers of the
+//! messages, which are represented as a map of header names to bytes, so that they
+//! can be converted into the headers of any broker client.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! // the producer
+//! let span = sentry::spans::queue_publish("nats", "orders");
+//! let headers: HashMap<String, Vec<u8>> = sentry::spans::trace_headers(&span);
+//! // publish the message with the headers
+//! span.finish();
+//!
+//! // the consumer
+//! let transaction = sentry::spans::queue_consume("nats", "orders", &headers);
+//! // process the message
+//! transaction.finish();
+//! ```
+
+use std::collections::HashMap;
+
+use crate::types::{SentryTrace, BAGGAGE_HEADER, SENTRY_TRACE_HEADER};
+use crate::{Transaction, TransactionContext, TransactionOrSpan};
+
+/// Starts a `queue.publish` span for a message published to `destination`.
+///
+/// The `system` is the name of the messaging system, such as `rabbitmq` or `aws_sqs`.
+/// The span is a child of the current span, or a new transaction if there is none.
+pub fn queue_publish(system: &str, destination: &str) -> TransactionOrSpan {
+    let span = crate::start_child_or_transaction("queue.publish", destination);
+    span.set_data("messaging.system", system.into());
+    span.set_data("messaging.destination.name", destination.into());
+    span
+}
+
+/// Starts a `queue.process` transaction for a message consumed from `destination`.
+///
+/// The transaction is named after the destination, and continues the trace from the
+/// `sentry-trace` header of the message, or starts a new trace if the header is missing.
+pub fn queue_consume<I, K, V>(system: &str, destination: &str, headers: I) -> Transaction
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<[u8]>,
+{
+    let ctx = match trace_from_headers(headers) {
+        Some(trace) => {
+            TransactionContext::continue_from_sentry_trace(destination, "queue.process", &trace)
+        }
+        None => TransactionContext::new(destination, "queue.process"),
+    };
+    let transaction = crate::start_transaction(ctx);
+    transaction.set_data("messaging.system", system.into());
+    transaction.set_data("messaging.destination.name", destination.into());
+    transaction
+}
+
+/// Returns the `sentry-trace` and `baggage` headers that continue the trace of `span`.
+///
+/// Baggage is taken from the current scope, where it is kept by the server integrations.
+pub fn trace_headers(span: &TransactionOrSpan) -> HashMap<String, Vec<u8>> {
+    let mut headers = HashMap::new();
+    headers.insert(
+        SENTRY_TRACE_HEADER.to_owned(),
+        span.sentry_trace().to_string().into_bytes(),
+    );
+    let baggage = crate::configure_scope(|scope| scope.get_baggage().map(ToOwned::to_owned));
+    if let Some(baggage) = baggage {
+        headers.insert(BAGGAGE_HEADER.to_owned(), baggage.into_bytes());
+    }
+    headers
+}
+
+/// Returns the trace carried in the `sentry-trace` header, if it is valid.
+fn trace_from_headers<I, K, V>(headers: I) -> Option<SentryTrace>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<[u8]>,
+{
+    headers
+        .into_iter()
+        .find(|(name, _)| name.as_ref().eq_ignore_ascii_case(SENTRY_TRACE_HEADER))
+        .and_then(|(_, value)| std::str::from_utf8(value.as_ref()).ok()?.parse().ok())
+}
@@ -18,12 +18,31 @@
 //! assert_eq!(events.len(), 1);
 //! assert_eq!(events[0].message.as_ref().unwrap(), "Hello World!");
 //! ```
+//!
+//! The [`assert_event!`] and [`assert_breadcrumb!`] macros make assertions about
+//! multiple fields of captured events and breadcrumbs more readable:
+//!
+//! ```
+//! use sentry::test::with_captured_events;
+//! use sentry::{assert_event, capture_message, Level};
+//!
+//! let events = with_captured_events(|| {
+//!     capture_message("Hello World!", Level::Warning);
+//! });
+//! assert_event!(events[0], level: Warning, message contains "World");
+//! ```
+//!
+//! [`assert_event!`]: ../macro.assert_event.html
+//! [`assert_breadcrumb!`]: ../macro.assert_breadcrumb.html
 
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
+use crate::clientoptions::ClockCallback;
 use crate::protocol::Event;
-use crate::types::Dsn;
-use crate::{ClientOptions, Envelope, Hub, Transport};
+use crate::types::{DateTime, Dsn, TimeZone, Utc, Uuid};
+use crate::{ClientOptions, DeliveryReceipt, DeliveryStatus, Envelope, Hub, Transport};
 
 lazy_static::lazy_static! {
     static ref TEST_DSN: Dsn = "https://public@sentry.invalid/1".parse().unwrap();
@@ -78,6 +97,11 @@ impl Transport for TestTransport {
     fn send_envelope(&self, envelope: Envelope) {
         self.collected.lock().unwrap().push(envelope);
     }
+
+    fn send_envelope_with_receipt(&self, envelope: Envelope, receipt: DeliveryReceipt) {
+        self.send_envelope(envelope);
+        receipt.report(DeliveryStatus::Sent);
+    }
 }
 
 /// Runs some code with the default test hub and returns the captured events.
@@ -134,3 +158,274 @@ pub fn with_captured_envelopes_options<F: FnOnce(), O: Into<ClientOptions>>(
     );
     transport.fetch_and_clear_envelopes()
 }
+
+type IdGenerator = Arc<dyn Fn() -> Uuid + Send + Sync>;
+
+/// Makes the timestamps and IDs of captured events deterministic.
+///
+/// Event IDs and timestamps are normally random or taken from the system clock,
+/// which makes snapshots of captured events unstable.  `Deterministic` overrides
+/// the ID and timestamp of every captured event, and the timestamp of every
+/// recorded breadcrumb, using an injectable clock and ID generator.
+///
+/// By default, the clock starts at `2020-01-01T00:00:00Z` and advances by one
+/// second every time it is read, so breadcrumbs and events are ordered by the
+/// time they were recorded, and event IDs are counted up from `1`.
+///
+/// # Examples
+///
+/// ```
+/// use sentry::test::{with_captured_events_options, Deterministic};
+/// use sentry::types::Uuid;
+///
+/// let options = Deterministic::new().apply(sentry::ClientOptions::default());
+/// let events = with_captured_events_options(
+///     || {
+///         sentry::add_breadcrumb(sentry::Breadcrumb::default());
+///         sentry::capture_message("first", sentry::Level::Info);
+///     },
+///     options,
+/// );
+///
+/// assert_eq!(events[0].event_id, Uuid::from_u128(1));
+/// assert_eq!(events[0].breadcrumbs[0].timestamp.to_rfc3339(), "2020-01-01T00:00:00+00:00");
+/// assert_eq!(events[0].timestamp.to_rfc3339(), "2020-01-01T00:00:01+00:00");
+/// ```
+#[derive(Clone)]
+pub struct Deterministic {
+    clock: ClockCallback,
+    id_generator: IdGenerator,
+}
+
+impl Default for Deterministic {
+    fn default() -> Self {
+        let seconds = Arc::new(AtomicI64::new(
+            Utc.ymd(2020, 1, 1).and_hms(0, 0, 0).timestamp(),
+        ));
+        let ids = Arc::new(AtomicU64::new(1));
+        Deterministic {
+            clock: Arc::new(move || Utc.timestamp(seconds.fetch_add(1, Ordering::SeqCst), 0)),
+            id_generator: Arc::new(move || {
+                Uuid::from_u128(ids.fetch_add(1, Ordering::SeqCst).into())
+            }),
+        }
+    }
+}
+
+impl std::fmt::Debug for Deterministic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Deterministic").finish()
+    }
+}
+
+impl Deterministic {
+    /// Creates the default deterministic clock and ID generator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the clock used for the timestamps of events and breadcrumbs.
+    pub fn with_clock<F>(mut self, clock: F) -> Self
+    where
+        F: Fn() -> DateTime<Utc> + Send + Sync + 'static,
+    {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the generator used for event IDs.
+    pub fn with_id_generator<F>(mut self, id_generator: F) -> Self
+    where
+        F: Fn() -> Uuid + Send + Sync + 'static,
+    {
+        self.id_generator = Arc::new(id_generator);
+        self
+    }
+
+    /// Installs the clock and ID generator on the given options.
+    ///
+    /// This sets the `clock` of the options, and wraps their `before_send` callback,
+    /// which is called with the deterministic ID already set.
+    pub fn apply<O: Into<ClientOptions>>(&self, options: O) -> ClientOptions {
+        let mut options = options.into();
+        options.clock = Some(self.clock.clone());
+
+        let id_generator = self.id_generator.clone();
+        let before_send = options.before_send.take();
+        options.before_send = Some(Arc::new(move |mut event: Event<'static>| {
+            event.event_id = id_generator();
+            match before_send {
+                Some(ref before_send) => before_send(event),
+                None => Some(event),
+            }
+        }));
+
+        options
+    }
+}
+
+/// Asserts that a captured [`Event`] matches a list of field assertions.
+///
+/// The assertions are separated by commas, and each one is of one of the forms:
+///
+/// * `level: Error`: the level of the event is the given [`Level`](crate::Level) variant.
+/// * `field: "value"`: the string field, such as `message`, `transaction`, `logger`,
+///   `release` or `environment`, is set to the value.
+/// * `field contains "value"`: the string field is set and contains the value.
+/// * `tag "key": "value"`: the tag `key` is set to the value.
+/// * `exception: "Type"`: one of the exceptions of the event has the given type.
+/// * `exception contains "value"`: the value of one of the exceptions contains the value.
+///
+/// **Feature:** `test` (*disabled by default*)
+///
+/// # Examples
+///
+/// ```
+/// use sentry::assert_event;
+/// use sentry::test::with_captured_events;
+///
+/// let events = with_captured_events(|| {
+///     sentry::configure_scope(|scope| scope.set_tag("worker", "7"));
+///     sentry::capture_message("boom went the worker", sentry::Level::Error);
+/// });
+///
+/// assert_event!(events[0], level: Error, message contains "boom", tag "worker": "7");
+/// ```
+#[macro_export]
+macro_rules! assert_event {
+    ($event:expr, $($assertions:tt)*) => {{
+        let event: &$crate::protocol::Event<'_> = &$event;
+        $crate::__assert_fields!(event; $($assertions)*);
+    }};
+}
+
+/// Asserts that a recorded [`Breadcrumb`](crate::Breadcrumb) matches a list of field assertions.
+///
+/// This supports the `level`, `field: "value"` and `field contains "value"`
+/// assertions of [`assert_event!`], with the `ty`, `category` and `message` fields of
+/// breadcrumbs.
+///
+/// **Feature:** `test` (*disabled by default*)
+///
+/// # Examples
+///
+/// ```
+/// use sentry::assert_breadcrumb;
+/// use sentry::test::with_captured_events;
+/// use sentry::{add_breadcrumb, capture_message, Breadcrumb, Level};
+///
+/// let events = with_captured_events(|| {
+///     add_breadcrumb(Breadcrumb {
+///         category: Some("db.query".into()),
+///         message: Some("SELECT * FROM users".into()),
+///         ..Default::default()
+///     });
+///     capture_message("Hello World!", Level::Warning);
+/// });
+///
+/// assert_breadcrumb!(
+///     events[0].breadcrumbs[0],
+///     level: Info,
+///     category: "db.query",
+///     message contains "users",
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_breadcrumb {
+    ($breadcrumb:expr, $($assertions:tt)*) => {{
+        let breadcrumb: &$crate::protocol::Breadcrumb = &$breadcrumb;
+        $crate::__assert_fields!(breadcrumb; $($assertions)*);
+    }};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __assert_fields {
+    ($subject:ident;) => {};
+    ($subject:ident; level: $level:ident $(, $($rest:tt)*)?) => {
+        assert_eq!($subject.level, $crate::Level::$level, "unexpected `level`");
+        $crate::__assert_fields!($subject; $($($rest)*)?);
+    };
+    ($subject:ident; tag $key:literal: $value:expr $(, $($rest:tt)*)?) => {
+        assert_eq!(
+            $subject.tags.get($key).map(String::as_str),
+            Some(AsRef::<str>::as_ref(&$value)),
+            "unexpected tag {:?}",
+            $key,
+        );
+        $crate::__assert_fields!($subject; $($($rest)*)?);
+    };
+    ($subject:ident; exception: $ty:expr $(, $($rest:tt)*)?) => {
+        let ty: &str = AsRef::<str>::as_ref(&$ty);
+        assert!(
+            $subject.exception.iter().any(|exception| exception.ty == ty),
+            "expected an exception of type {:?}, got {:?}",
+            ty,
+            $subject.exception.iter().map(|exception| &exception.ty).collect::<Vec<_>>(),
+        );
+        $crate::__assert_fields!($subject; $($($rest)*)?);
+    };
+    ($subject:ident; exception contains $needle:expr $(, $($rest:tt)*)?) => {
+        let needle: &str = AsRef::<str>::as_ref(&$needle);
+        assert!(
+            $subject.exception.iter().any(|exception| {
+                exception.value.as_ref().map_or(false, |value| value.contains(needle))
+            }),
+            "expected an exception value containing {:?}, got {:?}",
+            needle,
+            $subject.exception.iter().map(|exception| &exception.value).collect::<Vec<_>>(),
+        );
+        $crate::__assert_fields!($subject; $($($rest)*)?);
+    };
+    ($subject:ident; $field:ident contains $needle:expr $(, $($rest:tt)*)?) => {
+        let needle: &str = AsRef::<str>::as_ref(&$needle);
+        let value = $crate::test::FieldValue::field_str(&$subject.$field);
+        assert!(
+            value.map_or(false, |value| value.contains(needle)),
+            "expected `{}` to contain {:?}, got {:?}",
+            stringify!($field),
+            needle,
+            value,
+        );
+        $crate::__assert_fields!($subject; $($($rest)*)?);
+    };
+    ($subject:ident; $field:ident: $value:expr $(, $($rest:tt)*)?) => {
+        assert_eq!(
+            $crate::test::FieldValue::field_str(&$subject.$field),
+            Some(AsRef::<str>::as_ref(&$value)),
+            "unexpected `{}`",
+            stringify!($field),
+        );
+        $crate::__assert_fields!($subject; $($($rest)*)?);
+    };
+}
+
+/// The string fields of events and breadcrumbs that the assertion macros can check.
+#[doc(hidden)]
+pub trait FieldValue {
+    fn field_str(&self) -> Option<&str>;
+}
+
+impl FieldValue for String {
+    fn field_str(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl FieldValue for Option<String> {
+    fn field_str(&self) -> Option<&str> {
+        self.as_deref()
+    }
+}
+
+impl FieldValue for Cow<'_, str> {
+    fn field_str(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl FieldValue for Option<Cow<'_, str>> {
+    fn field_str(&self) -> Option<&str> {
+        self.as_deref()
+    }
+}
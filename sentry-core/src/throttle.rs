@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::Uuid;
+use crate::{event_from_enriched_error, Hub};
+
+/// The length of the window in which captures are counted.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// The extra that holds the number of suppressed captures.
+const SUPPRESSED_EXTRA: &str = "suppressed_events";
+
+lazy_static::lazy_static! {
+    static ref WINDOWS: Mutex<HashMap<String, Window>> = Mutex::new(HashMap::new());
+}
+
+/// The captures of a single key within the current window.
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    captured: u32,
+    suppressed: u64,
+}
+
+impl Window {
+    fn new(now: Instant) -> Self {
+        Window {
+            start: now,
+            captured: 0,
+            suppressed: 0,
+        }
+    }
+
+    fn admit(&mut self, max_per_window: u32, now: Instant) -> Option<u64> {
+        if now.duration_since(self.start) >= WINDOW {
+            self.start = now;
+            self.captured = 0;
+        }
+        if self.captured < max_per_window {
+            self.captured += 1;
+            Some(std::mem::replace(&mut self.suppressed, 0))
+        } else {
+            self.suppressed += 1;
+            None
+        }
+    }
+}
+
+/// Decides whether a capture for `key` goes through.
+///
+/// Returns the number of captures that were suppressed since the last one went
+/// through, or `None` if this capture is suppressed as well.
+fn admit(
+    windows: &mut HashMap<String, Window>,
+    key: &str,
+    max_per_window: u32,
+    now: Instant,
+) -> Option<u64> {
+    if let Some(window) = windows.get_mut(key) {
+        return window.admit(max_per_window, now);
+    }
+    let mut window = Window::new(now);
+    let admitted = window.admit(max_per_window, now);
+    windows.insert(key.to_owned(), window);
+    admitted
+}
+
+/// Captures an error, but at most `max_per_minute` times per minute for the same `key`.
+///
+/// This is meant for errors on hot paths, which may fire thousands of times per
+/// second and would otherwise exhaust the quota of the project.  Captures beyond the
+/// limit are dropped before an event is created, and return the nil UUID.  The number
+/// of dropped captures is attached as the `suppressed_events` extra to the next event
+/// of the same key that goes through.
+///
+/// The limit is counted in fixed windows of one minute, per key and per process.  Keys
+/// are kept for the lifetime of the process, so they should be static, such as the
+/// name of the operation that failed, rather than including request specific data.
+///
+/// # Examples
+///
+/// ```
+/// # let events = sentry::test::with_captured_events(|| {
+/// for input in &["1", "x", "y", "z"] {
+///     if let Err(err) = input.parse::<u32>() {
+///         sentry::capture_error_throttled(&err, "parse-input", 2);
+///     }
+/// }
+/// # });
+/// # assert_eq!(events.len(), 2);
+/// ```
+pub fn capture_error_throttled(
+    error: &(dyn Error + 'static),
+    key: &str,
+    max_per_minute: u32,
+) -> Uuid {
+    let suppressed = {
+        let mut windows = WINDOWS.lock().unwrap();
+        admit(&mut windows, key, max_per_minute, Instant::now())
+    };
+    match suppressed {
+        Some(suppressed) => {
+            let mut event = event_from_enriched_error(error);
+            if suppressed > 0 {
+                event
+                    .extra
+                    .insert(SUPPRESSED_EXTRA.into(), suppressed.into());
+            }
+            Hub::with_active(|hub| hub.capture_event(event))
+        }
+        None => Uuid::nil(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admit() {
+        let mut windows = HashMap::new();
+        let start = Instant::now();
+
+        assert_eq!(admit(&mut windows, "a", 2, start), Some(0));
+        assert_eq!(admit(&mut windows, "a", 2, start), Some(0));
+        assert_eq!(admit(&mut windows, "a", 2, start), None);
+        assert_eq!(admit(&mut windows, "b", 2, start), Some(0));
+        assert_eq!(
+            admit(&mut windows, "a", 2, start + Duration::from_secs(30)),
+            None
+        );
+
+        let later = start + WINDOW;
+        assert_eq!(admit(&mut windows, "a", 2, later), Some(2));
+        assert_eq!(admit(&mut windows, "a", 2, later), Some(0));
+        assert_eq!(admit(&mut windows, "a", 2, later), None);
+        assert_eq!(admit(&mut windows, "a", 0, later), None);
+    }
+}
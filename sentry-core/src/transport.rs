@@ -1,7 +1,49 @@
+use std::fmt;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::{ClientOptions, Envelope};
+use crate::types::Uuid;
+use crate::{ClientOptions, DeliveryReceipt, DeliveryStatus, Envelope};
+
+/// An envelope that a transport failed to send.
+///
+/// This is passed to the [`on_transport_error`] callback once a transport has
+/// given up on sending an envelope.
+///
+/// [`on_transport_error`]: struct.ClientOptions.html#structfield.on_transport_error
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TransportError {
+    /// The ID of the event in the envelope, if it contained one.
+    pub event_id: Option<Uuid>,
+    /// The error reported by the transport.
+    pub message: String,
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.event_id {
+            Some(event_id) => write!(f, "failed to send event {}: {}", event_id, self.message),
+            None => write!(f, "failed to send envelope: {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Statistics about the queue of a [`Transport`].
+///
+/// [`Transport`]: trait.Transport.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// The number of envelopes waiting in the queue.
+    pub pending: usize,
+    /// The number of envelopes that are currently being sent.
+    pub in_flight: usize,
+    /// The number of envelopes dropped without sending them, for instance
+    /// because the queue was full or because of rate limits.
+    pub dropped: usize,
+}
 
 /// The trait for transports.
 ///
@@ -14,6 +56,45 @@ pub trait Transport: Send + Sync + 'static {
     /// [`Envelope`]: struct.Envelope.html
     fn send_envelope(&self, envelope: Envelope);
 
+    /// Sends an [`Envelope`], and reports the outcome to the `receipt`.
+    ///
+    /// The default implementation sends the envelope using `send_envelope`, and
+    /// reports [`DeliveryStatus::Unknown`].  Transports that know when an envelope
+    /// was accepted by the server should override this.
+    ///
+    /// [`Envelope`]: struct.Envelope.html
+    /// [`DeliveryStatus::Unknown`]: enum.DeliveryStatus.html#variant.Unknown
+    fn send_envelope_with_receipt(&self, envelope: Envelope, receipt: DeliveryReceipt) {
+        self.send_envelope(envelope);
+        receipt.report(DeliveryStatus::Unknown);
+    }
+
+    /// Waits until all queued envelopes are sent, without shutting down.
+    ///
+    /// The default implementation does nothing.  If the queue was successfully
+    /// drained the return value should be `true` or `false` if envelopes were
+    /// left in it.
+    fn flush(&self, timeout: Duration) -> bool {
+        let _timeout = timeout;
+        true
+    }
+
+    /// Returns whether the transport is able to send envelopes.
+    ///
+    /// Transports should return `false` if their worker stopped, or if they
+    /// currently drop envelopes because of rate limits.  The default
+    /// implementation returns `true`.
+    fn is_healthy(&self) -> bool {
+        true
+    }
+
+    /// Returns statistics about the queue of the transport.
+    ///
+    /// The default implementation reports an empty queue.
+    fn queue_stats(&self) -> QueueStats {
+        QueueStats::default()
+    }
+
     /// Drains the queue if there is one.
     ///
     /// The default implementation does nothing.  If the queue was successfully
@@ -57,6 +138,22 @@ impl<T: Transport> Transport for Arc<T> {
         (**self).send_envelope(envelope)
     }
 
+    fn send_envelope_with_receipt(&self, envelope: Envelope, receipt: DeliveryReceipt) {
+        (**self).send_envelope_with_receipt(envelope, receipt)
+    }
+
+    fn flush(&self, timeout: Duration) -> bool {
+        (**self).flush(timeout)
+    }
+
+    fn is_healthy(&self) -> bool {
+        (**self).is_healthy()
+    }
+
+    fn queue_stats(&self) -> QueueStats {
+        (**self).queue_stats()
+    }
+
     fn shutdown(&self, timeout: Duration) -> bool {
         (**self).shutdown(timeout)
     }
@@ -0,0 +1,84 @@
+use std::borrow::Cow;
+
+/// Rules for trimming backtraces before they are sent.
+///
+/// Backtraces captured for panics and errors start deep inside the machinery
+/// that captured them.  Trimming cuts each stacktrace at the innermost border
+/// frame, removing the border frame and everything that was called from it.
+/// Border frames are matched by function name prefix.
+///
+/// # Examples
+///
+/// ```
+/// let options = sentry::ClientOptions {
+///     backtrace_trimmer: sentry::BacktraceTrimmer::new()
+///         .add_border_frame("my_crate::errors::report"),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct BacktraceTrimmer {
+    enabled: bool,
+    border_frames: Vec<Cow<'static, str>>,
+}
+
+impl BacktraceTrimmer {
+    /// The border frames a new trimmer starts out with.
+    pub const DEFAULT_BORDER_FRAMES: &'static [&'static str] = &[
+        "std::panicking::begin_panic",
+        "core::panicking::panic",
+        "std::backtrace::Backtrace::",
+        // well-known library frames
+        "anyhow::",
+        "<sentry_log::Logger as log::Log>::log",
+    ];
+
+    /// Creates a trimmer with the default border frames.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a trimmer that leaves all backtraces untouched.
+    ///
+    /// This is mostly useful to debug the trimming itself.
+    pub fn disabled() -> Self {
+        BacktraceTrimmer {
+            enabled: false,
+            border_frames: Vec::new(),
+        }
+    }
+
+    /// Adds a function name prefix at which backtraces are cut.
+    pub fn add_border_frame<S: Into<Cow<'static, str>>>(mut self, prefix: S) -> Self {
+        self.border_frames.push(prefix.into());
+        self
+    }
+
+    /// Removes all border frames, including the default ones.
+    pub fn clear_border_frames(mut self) -> Self {
+        self.border_frames.clear();
+        self
+    }
+
+    /// Returns `true` if backtraces are trimmed.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns the function name prefixes at which backtraces are cut.
+    pub fn border_frames(&self) -> impl Iterator<Item = &str> {
+        self.border_frames.iter().map(|frame| frame.as_ref())
+    }
+}
+
+impl Default for BacktraceTrimmer {
+    fn default() -> Self {
+        BacktraceTrimmer {
+            enabled: true,
+            border_frames: Self::DEFAULT_BORDER_FRAMES
+                .iter()
+                .map(|&frame| frame.into())
+                .collect(),
+        }
+    }
+}
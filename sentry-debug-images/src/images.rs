@@ -1,6 +1,6 @@
 use std::env;
 
-use sentry_core::protocol::debugid::DebugId;
+use sentry_core::protocol::debugid::{CodeId, DebugId};
 use sentry_core::protocol::{DebugImage, SymbolicDebugImage};
 use sentry_core::types::Uuid;
 
@@ -40,9 +40,11 @@ pub fn debug_images() -> Vec<DebugImage> {
     }
 
     TargetSharedLibrary::each(|shlib| {
-        let maybe_debug_id = shlib.id().and_then(|id| match id {
+        let maybe_debug_id = shlib.debug_id().and_then(|id| match id {
             SharedLibraryId::Uuid(bytes) => Some(DebugId::from_uuid(Uuid::from_bytes(bytes))),
             SharedLibraryId::GnuBuildId(ref id) => debug_id_from_build_id(id),
+            SharedLibraryId::PdbSignature(guid, age) => DebugId::from_guid_age(&guid, age).ok(),
+            _ => None,
         });
 
         let debug_id = match maybe_debug_id {
@@ -50,6 +52,15 @@ pub fn debug_images() -> Vec<DebugImage> {
             None => return,
         };
 
+        // the debug id identifies ELF and Mach-O files, while PE files are identified
+        // by their timestamp and size
+        let code_id = shlib.id().and_then(|id| match id {
+            SharedLibraryId::PeSignature(timestamp, size) => {
+                Some(CodeId::new(format!("{:08X}{:x}", timestamp, size)))
+            }
+            _ => None,
+        });
+
         let mut name = shlib.name().to_string_lossy().to_string();
         if name.is_empty() {
             name = env::current_exe()
@@ -65,6 +76,10 @@ pub fn debug_images() -> Vec<DebugImage> {
                 image_size: shlib.len() as u64,
                 image_vmaddr: shlib.stated_load_addr().0.into(),
                 id: debug_id,
+                code_id,
+                debug_file: shlib
+                    .debug_name()
+                    .map(|name| name.to_string_lossy().to_string()),
             }
             .into(),
         );
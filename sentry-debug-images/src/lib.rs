@@ -3,8 +3,9 @@
 //! The [`DebugImagesIntegration`] adds metadata about the loaded shared
 //! libraries to Sentry [`Event`]s.
 //!
-//! This Integration only works on Unix-like OSs right now. Support for Windows
-//! will be added in the future.
+//! The images are identified by their build id on Linux, their `LC_UUID` on macOS, and
+//! the GUID and age of their PDB file on Windows, so that server-side symbolication works
+//! on all of these platforms.
 //!
 //! # Configuration
 //!
@@ -24,16 +25,9 @@
 #![warn(missing_docs)]
 #![deny(unsafe_code)]
 
-#[cfg(unix)]
-mod unix;
+mod images;
 
-#[cfg(unix)]
-use unix::debug_images;
-
-#[cfg(not(unix))]
-fn debug_images() -> Vec<sentry_core::protocol::DebugImage> {
-    vec![]
-}
+use images::debug_images;
 
 mod integration;
 
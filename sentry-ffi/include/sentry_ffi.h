@@ -0,0 +1,64 @@
+/*
+ * A C API for reporting to Sentry through the client of a Rust application.
+ *
+ * All strings are null-terminated, and invalid UTF-8 is replaced.  See the
+ * documentation of the `sentry-ffi` crate for details.
+ */
+
+#ifndef SENTRY_FFI_H
+#define SENTRY_FFI_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SENTRY_FFI_LEVEL_DEBUG 0
+#define SENTRY_FFI_LEVEL_INFO 1
+#define SENTRY_FFI_LEVEL_WARNING 2
+#define SENTRY_FFI_LEVEL_ERROR 3
+#define SENTRY_FFI_LEVEL_FATAL 4
+
+/*
+ * Initializes Sentry, and returns 1 if the client is enabled.
+ *
+ * `release` and `environment` may be NULL.  Returns 0 if the `dsn` is invalid.
+ * Only call this if the C side owns the process.
+ */
+int sentry_ffi_init(const char *dsn, const char *release, const char *environment);
+
+/*
+ * Shuts down the client that was initialized with `sentry_ffi_init`.
+ */
+void sentry_ffi_shutdown(void);
+
+/*
+ * Captures a message with the given level, and returns 1 if it was captured.
+ */
+int sentry_ffi_capture_message(const char *message, int level);
+
+/*
+ * Records a breadcrumb with the given category, message and level.
+ *
+ * `category` may be NULL.
+ */
+void sentry_ffi_add_breadcrumb(const char *category, const char *message, int level);
+
+/*
+ * Sets a tag on the current scope, or removes it if `value` is NULL.
+ */
+void sentry_ffi_set_tag(const char *key, const char *value);
+
+/*
+ * Waits up to `timeout_ms` milliseconds for pending events to be sent.
+ *
+ * Returns 1 if all events were sent, and 0 on timeout or without a client.
+ */
+int sentry_ffi_flush(uint64_t timeout_ms);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SENTRY_FFI_H */
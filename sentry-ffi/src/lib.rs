@@ -0,0 +1,264 @@
+//! A C API for reporting to Sentry through the client of a Rust application.
+//!
+//! C and C++ components that are linked into a Rust application can use these functions
+//! to capture messages, record breadcrumbs and set tags.  They report through the hub of
+//! the calling thread, so they share the client and the scope with the Rust code, just
+//! like calls to the `sentry` crate from the same thread.
+//!
+//! The declarations of the functions are in `include/sentry_ffi.h`.  All strings are
+//! null-terminated, and invalid UTF-8 is replaced.  Panics never unwind into the caller,
+//! the functions report failure instead.
+//!
+//! # Example
+//!
+//! ```c
+//! #include "sentry_ffi.h"
+//!
+//! void load_plugin(const char *name) {
+//!     sentry_ffi_set_tag("plugin", name);
+//!     sentry_ffi_add_breadcrumb("plugin", "loading plugin", SENTRY_FFI_LEVEL_INFO);
+//!     if (!plugin_open(name)) {
+//!         sentry_ffi_capture_message("failed to load plugin", SENTRY_FFI_LEVEL_ERROR);
+//!     }
+//!     sentry_ffi_set_tag("plugin", NULL);
+//! }
+//! ```
+//!
+//! If the C side owns the process instead, it initializes Sentry with [`sentry_ffi_init`],
+//! and shuts it down with [`sentry_ffi_shutdown`], which sends all pending events.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, UnwindSafe};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use sentry::{Breadcrumb, ClientInitGuard, ClientOptions, Hub, Level};
+
+/// The `debug` level.
+pub const SENTRY_FFI_LEVEL_DEBUG: c_int = 0;
+/// The `info` level.
+pub const SENTRY_FFI_LEVEL_INFO: c_int = 1;
+/// The `warning` level.
+pub const SENTRY_FFI_LEVEL_WARNING: c_int = 2;
+/// The `error` level.
+pub const SENTRY_FFI_LEVEL_ERROR: c_int = 3;
+/// The `fatal` level.
+pub const SENTRY_FFI_LEVEL_FATAL: c_int = 4;
+
+lazy_static::lazy_static! {
+    static ref GUARD: Mutex<Option<ClientInitGuard>> = Mutex::new(None);
+}
+
+/// Converts a level constant, unknown levels are treated as errors.
+fn level_from_c(level: c_int) -> Level {
+    match level {
+        SENTRY_FFI_LEVEL_DEBUG => Level::Debug,
+        SENTRY_FFI_LEVEL_INFO => Level::Info,
+        SENTRY_FFI_LEVEL_WARNING => Level::Warning,
+        SENTRY_FFI_LEVEL_FATAL => Level::Fatal,
+        _ => Level::Error,
+    }
+}
+
+/// Copies a C string, which may be null.
+unsafe fn string_from_c(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+/// Runs `f`, and returns `default` if it panics.
+fn guarded<F, R>(default: R, f: F) -> R
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    panic::catch_unwind(f).unwrap_or(default)
+}
+
+/// Initializes Sentry, and returns `1` if the client is enabled.
+///
+/// The `release` and `environment` may be null, in which case the defaults of the `sentry`
+/// crate are used.  Returns `0` if the `dsn` is invalid.  This replaces the client of a
+/// Rust application that already initialized Sentry, so it should only be called if the
+/// C side owns the process.
+///
+/// # Safety
+///
+/// All arguments must be null or point to null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn sentry_ffi_init(
+    dsn: *const c_char,
+    release: *const c_char,
+    environment: *const c_char,
+) -> c_int {
+    let dsn = string_from_c(dsn);
+    let release = string_from_c(release);
+    let environment = string_from_c(environment);
+    guarded(0, move || {
+        let mut options = ClientOptions::default();
+        if let Some(dsn) = dsn {
+            match dsn.parse() {
+                Ok(dsn) => options.dsn = Some(dsn),
+                Err(_) => return 0,
+            }
+        }
+        if let Some(release) = release {
+            options.release = Some(release.into());
+        }
+        if let Some(environment) = environment {
+            options.environment = Some(environment.into());
+        }
+        let guard = sentry::init(options);
+        let enabled = guard.is_enabled();
+        *GUARD.lock().unwrap() = Some(guard);
+        enabled as c_int
+    })
+}
+
+/// Shuts down the client that was initialized with [`sentry_ffi_init`].
+///
+/// This waits for pending events to be sent, up to the shutdown timeout of the client.
+#[no_mangle]
+pub extern "C" fn sentry_ffi_shutdown() {
+    guarded((), || {
+        let guard = GUARD.lock().unwrap().take();
+        drop(guard);
+    })
+}
+
+/// Captures a message with the given level, and returns `1` if it was captured.
+///
+/// # Safety
+///
+/// The `message` must be null or point to a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sentry_ffi_capture_message(message: *const c_char, level: c_int) -> c_int {
+    let message = match string_from_c(message) {
+        Some(message) => message,
+        None => return 0,
+    };
+    guarded(0, move || {
+        let event_id = sentry::capture_message(&message, level_from_c(level));
+        (!event_id.is_nil()) as c_int
+    })
+}
+
+/// Records a breadcrumb with the given category, message and level.
+///
+/// The `category` may be null.
+///
+/// # Safety
+///
+/// The `category` and `message` must be null or point to null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn sentry_ffi_add_breadcrumb(
+    category: *const c_char,
+    message: *const c_char,
+    level: c_int,
+) {
+    let category = string_from_c(category);
+    let message = string_from_c(message);
+    guarded((), move || {
+        sentry::add_breadcrumb(Breadcrumb {
+            category,
+            message,
+            level: level_from_c(level),
+            ..Default::default()
+        })
+    })
+}
+
+/// Sets a tag on the current scope, or removes it if `value` is null.
+///
+/// # Safety
+///
+/// The `key` and `value` must be null or point to null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn sentry_ffi_set_tag(key: *const c_char, value: *const c_char) {
+    let key = match string_from_c(key) {
+        Some(key) => key,
+        None => return,
+    };
+    let value = string_from_c(value);
+    guarded((), move || {
+        sentry::configure_scope(|scope| match value {
+            Some(value) => scope.set_tag(&key, value),
+            None => scope.remove_tag(&key),
+        })
+    })
+}
+
+/// Waits up to `timeout_ms` milliseconds for pending events to be sent.
+///
+/// Returns `1` if all events were sent, and `0` on timeout or without a client.
+#[no_mangle]
+pub extern "C" fn sentry_ffi_flush(timeout_ms: u64) -> c_int {
+    guarded(0, || match Hub::current().client() {
+        Some(client) => client.flush(Some(Duration::from_millis(timeout_ms))) as c_int,
+        None => 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::ptr;
+
+    use super::*;
+
+    #[test]
+    fn test_capture() {
+        let events = sentry::test::with_captured_events(|| unsafe {
+            let key = CString::new("component").unwrap();
+            let value = CString::new("decoder").unwrap();
+            let category = CString::new("decoder").unwrap();
+            let message = CString::new("frame dropped").unwrap();
+            let invalid = b"bad \xff frame\0";
+
+            sentry_ffi_set_tag(key.as_ptr(), value.as_ptr());
+            sentry_ffi_add_breadcrumb(category.as_ptr(), message.as_ptr(), SENTRY_FFI_LEVEL_INFO);
+            assert_eq!(
+                sentry_ffi_capture_message(invalid.as_ptr() as *const c_char, 42),
+                1
+            );
+
+            sentry_ffi_set_tag(key.as_ptr(), ptr::null());
+            assert_eq!(
+                sentry_ffi_capture_message(message.as_ptr(), SENTRY_FFI_LEVEL_WARNING),
+                1
+            );
+            assert_eq!(
+                sentry_ffi_capture_message(ptr::null(), SENTRY_FFI_LEVEL_ERROR),
+                0
+            );
+        });
+
+        assert_eq!(events.len(), 2);
+        let event = &events[0];
+        assert_eq!(event.message.as_deref(), Some("bad \u{fffd} frame"));
+        assert_eq!(event.level, Level::Error);
+        assert_eq!(event.tags["component"], "decoder");
+        assert_eq!(event.breadcrumbs[0].category.as_deref(), Some("decoder"));
+        assert_eq!(event.breadcrumbs[0].level, Level::Info);
+
+        assert_eq!(events[1].level, Level::Warning);
+        assert!(!events[1].tags.contains_key("component"));
+    }
+
+    #[test]
+    fn test_init_invalid_dsn() {
+        let dsn = CString::new("not a dsn").unwrap();
+        assert_eq!(
+            unsafe { sentry_ffi_init(dsn.as_ptr(), ptr::null(), ptr::null()) },
+            0
+        );
+        assert_eq!(sentry_ffi_flush(0), 0);
+    }
+}
@@ -0,0 +1,194 @@
+//! Reads entries from the journal with `journalctl`.
+
+use std::fmt::Write;
+use std::io;
+use std::process::{Command, Stdio};
+
+use sentry_core::protocol::{Attachment, Breadcrumb, Level, Map, Value};
+use sentry_core::types::{DateTime, TimeZone, Utc};
+
+/// An entry of the journal.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Entry {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub priority: Option<u8>,
+    pub identifier: Option<String>,
+    pub pid: Option<String>,
+    pub message: String,
+}
+
+/// The journal entries to read.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Filter {
+    /// The entries of a systemd unit.
+    Unit(String),
+    /// The entries of the current invocation of the unit running this process.
+    Invocation(String),
+    /// The entries of this process.
+    Pid(u32),
+}
+
+impl Filter {
+    /// Returns the filter for the current process.
+    ///
+    /// systemd sets `INVOCATION_ID` for the processes it starts, which also matches
+    /// the entries of the other processes of the unit.
+    pub fn current() -> Self {
+        match std::env::var("INVOCATION_ID") {
+            Ok(id) if !id.is_empty() => Filter::Invocation(id),
+            _ => Filter::Pid(std::process::id()),
+        }
+    }
+}
+
+/// Reads the last `max_entries` entries matching the filter.
+pub(crate) fn read_entries(filter: &Filter, max_entries: usize) -> io::Result<Vec<Entry>> {
+    let mut command = Command::new("journalctl");
+    command
+        .args(&["--no-pager", "--quiet", "--output=json"])
+        .arg(format!("--lines={}", max_entries))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null());
+    match filter {
+        Filter::Unit(unit) => command.arg(format!("--unit={}", unit)),
+        Filter::Invocation(id) => command.arg(format!("_SYSTEMD_INVOCATION_ID={}", id)),
+        Filter::Pid(pid) => command.arg(format!("_PID={}", pid)),
+    };
+
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("journalctl failed with {}", output.status),
+        ));
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    Ok(output.lines().filter_map(parse_entry).collect())
+}
+
+/// Parses a line of `journalctl --output=json`.
+pub(crate) fn parse_entry(line: &str) -> Option<Entry> {
+    let fields: Map<String, Value> = serde_json::from_str(line).ok()?;
+    let string = |key: &str| match fields.get(key) {
+        Some(Value::String(value)) => Some(value.clone()),
+        _ => None,
+    };
+
+    // binary messages are serialized as arrays of bytes
+    let message = match fields.get("MESSAGE")? {
+        Value::String(message) => message.clone(),
+        Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .filter_map(|b| Some(b.as_u64()? as u8))
+                .collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        _ => return None,
+    };
+    let timestamp = string("__REALTIME_TIMESTAMP")
+        .and_then(|micros| micros.parse::<i64>().ok())
+        .and_then(|micros| {
+            Utc.timestamp_opt(
+                micros.div_euclid(1_000_000),
+                (micros.rem_euclid(1_000_000) * 1000) as u32,
+            )
+            .single()
+        });
+
+    Some(Entry {
+        timestamp,
+        priority: string("PRIORITY").and_then(|priority| priority.parse().ok()),
+        identifier: string("SYSLOG_IDENTIFIER").or_else(|| string("_COMM")),
+        pid: string("_PID"),
+        message,
+    })
+}
+
+/// Converts a syslog priority into a level.
+fn level_from_priority(priority: u8) -> Level {
+    match priority {
+        0..=2 => Level::Fatal,
+        3 => Level::Error,
+        4 => Level::Warning,
+        5 | 6 => Level::Info,
+        _ => Level::Debug,
+    }
+}
+
+/// Creates a `journal` breadcrumb from an entry.
+pub(crate) fn breadcrumb_from_entry(entry: &Entry) -> Breadcrumb {
+    let mut data = Map::new();
+    if let Some(ref identifier) = entry.identifier {
+        data.insert("identifier".into(), identifier.as_str().into());
+    }
+    if let Some(ref pid) = entry.pid {
+        data.insert("pid".into(), pid.as_str().into());
+    }
+    Breadcrumb {
+        timestamp: entry.timestamp.unwrap_or_else(Utc::now),
+        category: Some("journal".into()),
+        level: entry.priority.map_or(Level::Info, level_from_priority),
+        message: Some(entry.message.clone()),
+        data,
+        ..Default::default()
+    }
+}
+
+/// Creates a `journal.log` attachment with one line per entry.
+pub(crate) fn attachment_from_entries(entries: &[Entry]) -> Attachment {
+    let mut buffer = String::new();
+    for entry in entries {
+        if let Some(timestamp) = entry.timestamp {
+            write!(buffer, "{} ", timestamp.to_rfc3339()).ok();
+        }
+        buffer.push_str(entry.identifier.as_deref().unwrap_or("-"));
+        if let Some(ref pid) = entry.pid {
+            write!(buffer, "[{}]", pid).ok();
+        }
+        writeln!(buffer, ": {}", entry.message).ok();
+    }
+    Attachment {
+        buffer: buffer.into_bytes(),
+        filename: "journal.log".into(),
+        content_type: Some("text/plain".into()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        let line = r#"{"__REALTIME_TIMESTAMP":"1600000000123456","PRIORITY":"3","SYSLOG_IDENTIFIER":"myd","_PID":"42","MESSAGE":"connection refused"}"#;
+        let entry = parse_entry(line).unwrap();
+        assert_eq!(
+            entry.timestamp,
+            Utc.timestamp_opt(1_600_000_000, 123_456_000).single()
+        );
+        assert_eq!(entry.message, "connection refused");
+
+        let breadcrumb = breadcrumb_from_entry(&entry);
+        assert_eq!(breadcrumb.category.as_deref(), Some("journal"));
+        assert_eq!(breadcrumb.level, Level::Error);
+        assert_eq!(breadcrumb.data["identifier"], "myd");
+
+        let attachment = attachment_from_entries(&[entry]);
+        assert_eq!(
+            String::from_utf8(attachment.buffer).unwrap(),
+            "2020-09-13T12:26:40.123456+00:00 myd[42]: connection refused\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_binary_message() {
+        let line = r#"{"MESSAGE":[104,105,255],"_COMM":"myd"}"#;
+        let entry = parse_entry(line).unwrap();
+        assert_eq!(entry.message, "hi\u{fffd}");
+        assert_eq!(entry.identifier.as_deref(), Some("myd"));
+        assert_eq!(entry.priority, None);
+        assert!(parse_entry("not json").is_none());
+    }
+}
@@ -0,0 +1,157 @@
+//! Adds support for attaching systemd journal entries to events.
+//!
+//! Daemons running under systemd often log to the journal rather than through an
+//! in-process logger, so the context of an error is missing from the breadcrumbs.
+//! The [`JournalIntegration`] reads the most recent journal entries with
+//! `journalctl` whenever an error is captured, and adds them to the event as
+//! `journal` breadcrumbs, or as a `journal.log` attachment.
+//!
+//! By default, the entries of the current invocation of the unit running the
+//! process are read, which systemd identifies by the `INVOCATION_ID` environment
+//! variable.  Outside of systemd, the entries logged by the process itself are read.
+//!
+//! # Example
+//!
+//! ```no_run
+//! let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(
+//!     sentry_journal::JournalIntegration::new()
+//!         .max_entries(100)
+//!         .attachment(true),
+//! ));
+//! ```
+//!
+//! Reading the journal requires the permission to read the entries of the unit,
+//! for example through membership in the `systemd-journal` group.  When the journal
+//! cannot be read, events are sent without its entries.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use sentry_core::protocol::{Attachment, Event, Level};
+use sentry_core::{ClientOptions, Integration};
+
+mod journal;
+
+use crate::journal::{Entry, Filter};
+
+/// The Sentry systemd journal Integration.
+#[derive(Clone, Debug)]
+pub struct JournalIntegration {
+    filter: Filter,
+    max_entries: usize,
+    breadcrumbs: bool,
+    attachment: bool,
+    only_errors: bool,
+}
+
+impl JournalIntegration {
+    /// Creates a new systemd journal Integration.
+    pub fn new() -> Self {
+        Self {
+            filter: Filter::current(),
+            max_entries: 50,
+            breadcrumbs: true,
+            attachment: false,
+            only_errors: true,
+        }
+    }
+
+    /// Reads the entries of the given systemd unit, instead of those of the
+    /// current process.
+    pub fn unit<S: Into<String>>(mut self, unit: S) -> Self {
+        self.filter = Filter::Unit(unit.into());
+        self
+    }
+
+    /// Sets the number of journal entries attached to events.
+    ///
+    /// The default is 50 entries.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Enables or disables adding the entries as breadcrumbs.
+    ///
+    /// The breadcrumbs of the event and the entries are merged by their timestamp,
+    /// keeping at most `max_breadcrumbs` of the client.  This is enabled by default.
+    pub fn breadcrumbs(mut self, enabled: bool) -> Self {
+        self.breadcrumbs = enabled;
+        self
+    }
+
+    /// Enables or disables attaching the entries as a `journal.log` file.
+    ///
+    /// This is disabled by default.
+    pub fn attachment(mut self, enabled: bool) -> Self {
+        self.attachment = enabled;
+        self
+    }
+
+    /// Sets whether the entries are only attached to `error` and `fatal` events.
+    ///
+    /// This is enabled by default.
+    pub fn only_errors(mut self, enabled: bool) -> Self {
+        self.only_errors = enabled;
+        self
+    }
+
+    fn read_entries(&self, event: &Event<'static>) -> Option<Vec<Entry>> {
+        if self.only_errors {
+            match event.level {
+                Level::Error | Level::Fatal => {}
+                _ => return None,
+            }
+        }
+        match journal::read_entries(&self.filter, self.max_entries) {
+            Ok(entries) => Some(entries),
+            Err(err) => {
+                sentry_core::sentry_debug!("failed to read the journal: {}", err);
+                None
+            }
+        }
+    }
+}
+
+impl Default for JournalIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integration for JournalIntegration {
+    fn name(&self) -> &'static str {
+        "journal"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        if !self.breadcrumbs {
+            return Some(event);
+        }
+        if let Some(entries) = self.read_entries(&event) {
+            let breadcrumbs = &mut event.breadcrumbs.values;
+            breadcrumbs.extend(entries.iter().map(journal::breadcrumb_from_entry));
+            breadcrumbs.sort_by_key(|breadcrumb| breadcrumb.timestamp);
+            let excess = breadcrumbs.len().saturating_sub(options.max_breadcrumbs);
+            breadcrumbs.drain(..excess);
+        }
+        Some(event)
+    }
+
+    fn attachments(&self, event: &Event<'static>, _options: &ClientOptions) -> Vec<Attachment> {
+        if !self.attachment {
+            return Vec::new();
+        }
+        match self.read_entries(event) {
+            Some(entries) if !entries.is_empty() => {
+                vec![journal::attachment_from_entries(&entries)]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Adds support for instrumenting [`rdkafka`](https://docs.rs/rdkafka) producers and
+//! consumers.
+//!
+//! This crate provides helpers to:
+//!
+//! * Create `queue.publish` spans for produced messages with [`start_publish`], and
+//!   propagate their trace to the consumers in the `sentry-trace` and `baggage` message
+//!   headers with [`inject_trace`].
+//! * Create `queue.process` transactions for consumed messages with [`start_process`],
+//!   which continue the trace of the producer.
+//! * Capture errors that occur while processing a message with
+//!   [`capture_processing_error`], tagged with the topic, partition and offset of the
+//!   message.
+//!
+//! [`process`] combines the consumer helpers for synchronous processing functions.
+//!
+//! # Producer Example
+//!
+//! ```ignore
+//! use rdkafka::message::OwnedHeaders;
+//! use rdkafka::producer::FutureRecord;
+//!
+//! let span = sentry_kafka::start_publish("events");
+//! let record = FutureRecord::to("events")
+//!     .key("key")
+//!     .payload("payload")
+//!     .headers(sentry_kafka::inject_trace(&span, OwnedHeaders::new()));
+//! let result = producer.send(record, Duration::from_secs(0)).await;
+//! span.set_status(if result.is_ok() { "ok" } else { "internal_error" });
+//! span.finish();
+//! ```
+//!
+//! # Consumer Example
+//!
+//! ```ignore
+//! use rdkafka::consumer::{BaseConsumer, Consumer};
+//!
+//! for message in consumer.iter() {
+//!     let message = message?;
+//!     // errors are captured and tagged with the topic, partition and offset
+//!     let _ = sentry_kafka::process(&message, |message| handle(message));
+//! }
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::error::Error;
+
+use rdkafka::message::{Header, Headers, Message, OwnedHeaders};
+use sentry_core::spans;
+use sentry_core::types::{Uuid, BAGGAGE_HEADER};
+use sentry_core::{Scope, Transaction, TransactionOrSpan};
+
+/// Starts a `queue.publish` span for a message that is produced to `topic`.
+///
+/// The span is a child of the current span, or a new transaction if there is none.
+pub fn start_publish(topic: &str) -> TransactionOrSpan {
+    spans::queue_publish("kafka", topic)
+}
+
+/// Adds the `sentry-trace` and `baggage` headers that continue the trace of `span`.
+///
+/// Baggage is taken from the current scope, where it is kept by the server
+/// integrations and by [`process`].
+pub fn inject_trace(span: &TransactionOrSpan, headers: OwnedHeaders) -> OwnedHeaders {
+    spans::trace_headers(span)
+        .into_iter()
+        .fold(headers, |headers, (name, value)| {
+            headers.insert(Header {
+                key: &name,
+                value: Some(&value),
+            })
+        })
+}
+
+/// Starts a `queue.process` transaction for a consumed message.
+///
+/// The transaction is named after the topic of the message, and continues the trace
+/// from its `sentry-trace` header, or starts a new trace if the header is missing.
+pub fn start_process<M: Message>(message: &M) -> Transaction {
+    let headers = message.headers().map_or_else(Vec::new, |headers| {
+        (0..headers.count())
+            .map(|idx| headers.get(idx))
+            .filter_map(|header| Some((header.key, header.value?)))
+            .collect()
+    });
+    let transaction = spans::queue_consume("kafka", message.topic(), headers);
+    transaction.set_data("messaging.kafka.partition", message.partition().into());
+    transaction.set_data("messaging.kafka.offset", message.offset().into());
+    transaction
+}
+
+/// Captures an error that occurred while processing a message.
+///
+/// The event is tagged with the `kafka.topic`, `kafka.partition` and `kafka.offset` of
+/// the message.
+pub fn capture_processing_error<M: Message>(message: &M, error: &(dyn Error + 'static)) -> Uuid {
+    sentry_core::with_scope(
+        |scope| configure_message_scope(scope, message),
+        || sentry_core::capture_error(error),
+    )
+}
+
+/// Processes a consumed message inside of a `queue.process` transaction.
+///
+/// The transaction is the current span of a new scope while `f` runs, and the scope is
+/// tagged with the topic, partition and offset of the message.  If `f` fails, the error
+/// is captured and the transaction gets the `internal_error` status.
+pub fn process<M, F, T, E>(message: &M, f: F) -> Result<T, E>
+where
+    M: Message,
+    F: FnOnce(&M) -> Result<T, E>,
+    E: Error + 'static,
+{
+    let transaction = start_process(message);
+    let baggage = header_str(message, BAGGAGE_HEADER).map(ToOwned::to_owned);
+    let result = sentry_core::with_scope(
+        |scope| {
+            configure_message_scope(scope, message);
+            scope.set_span(Some(transaction.clone().into()));
+            if let Some(baggage) = baggage {
+                scope.set_baggage(Some(&baggage));
+            }
+        },
+        || {
+            let result = f(message);
+            if let Err(ref error) = result {
+                sentry_core::capture_error(error);
+            }
+            result
+        },
+    );
+    let status = if result.is_ok() {
+        "ok"
+    } else {
+        "internal_error"
+    };
+    transaction.set_status(status);
+    transaction.finish();
+    result
+}
+
+/// Tags the scope with the topic, partition and offset of a message.
+fn configure_message_scope<M: Message>(scope: &mut Scope, message: &M) {
+    scope.set_tags(sentry_core::tags! {
+        "kafka.topic" => message.topic(),
+        "kafka.partition" => message.partition(),
+        "kafka.offset" => message.offset(),
+    });
+}
+
+/// Returns the value of a message header, if it is valid UTF-8.
+fn header_str<'a, M: Message>(message: &'a M, key: &str) -> Option<&'a str> {
+    let headers = message.headers()?;
+    (0..headers.count())
+        .map(|idx| headers.get(idx))
+        .find(|header| header.key == key)
+        .and_then(|header| std::str::from_utf8(header.value?).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use rdkafka::message::{OwnedMessage, Timestamp};
+    use sentry::protocol::{Context, EnvelopeItem};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct ProcessingError;
+
+    impl fmt::Display for ProcessingError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("invalid payload")
+        }
+    }
+
+    impl Error for ProcessingError {}
+
+    #[test]
+    fn test_trace_propagation() {
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let span = start_publish("events");
+                let headers = inject_trace(&span, OwnedHeaders::new());
+                span.finish();
+
+                let message = OwnedMessage::new(
+                    Some(b"payload".to_vec()),
+                    None,
+                    "events".into(),
+                    Timestamp::NotAvailable,
+                    3,
+                    42,
+                    Some(headers),
+                );
+                let result: Result<(), _> = process(&message, |_| Err(ProcessingError));
+                assert!(result.is_err());
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        let event = envelopes
+            .iter()
+            .find_map(|envelope| envelope.event())
+            .unwrap();
+        assert_eq!(event.tags["kafka.topic"], "events");
+        assert_eq!(event.tags["kafka.partition"], "3");
+        assert_eq!(event.tags["kafka.offset"], "42");
+
+        let transactions: Vec<_> = envelopes
+            .iter()
+            .flat_map(|envelope| envelope.items())
+            .filter_map(|item| match item {
+                EnvelopeItem::Transaction(transaction) => Some(transaction),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(transactions.len(), 2);
+        let trace_id =
+            |transaction: &sentry::protocol::Transaction| match transaction.contexts.get("trace") {
+                Some(Context::Trace(trace)) => trace.trace_id,
+                context => panic!("expected trace context, got {:?}", context),
+            };
+        assert_eq!(trace_id(transactions[0]), trace_id(transactions[1]));
+        assert_eq!(transactions[1].name.as_deref(), Some("events"));
+    }
+}
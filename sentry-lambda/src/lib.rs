@@ -0,0 +1,256 @@
+//! Adds support for AWS Lambda functions built with
+//! [`lambda_runtime`](https://docs.rs/lambda_runtime).
+//!
+//! The [`SentryLambdaLayer`] wraps the handler service of a function, and for every
+//! invocation:
+//!
+//! * binds a new [`Hub`], so the scope of one invocation does not leak into the next,
+//! * tags events with the request id and the ARN of the invoked function, and adds an
+//!   `aws_lambda` context with details about the function,
+//! * captures errors returned by the handler, and
+//! * flushes all pending events before returning the result to the runtime.
+//!
+//! Flushing is important, as the Lambda sandbox is frozen as soon as the result of an
+//! invocation has been returned, which would otherwise prevent the transport from sending
+//! the events that were captured during the invocation.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use lambda_runtime::{service_fn, Error, LambdaEvent};
+//! use sentry_lambda::SentryLambdaLayer;
+//! use tower::ServiceBuilder;
+//!
+//! async fn handler(event: LambdaEvent<String>) -> Result<String, Error> {
+//!     Ok(event.payload)
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Error> {
+//!     let _guard = sentry::init(());
+//!
+//!     let handler = ServiceBuilder::new()
+//!         .layer(SentryLambdaLayer::new())
+//!         .service(service_fn(handler));
+//!     lambda_runtime::run(handler).await
+//! }
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lambda_runtime::LambdaEvent;
+use sentry_core::protocol::{self, Map};
+use sentry_core::{Hub, SentryFutureExt};
+use sentry_tower::ServiceError;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Tower layer that reports Lambda invocations to Sentry.
+#[derive(Clone, Debug)]
+pub struct SentryLambdaLayer {
+    capture_errors: bool,
+    flush_timeout: Duration,
+}
+
+impl SentryLambdaLayer {
+    /// Creates a new layer that captures handler errors.
+    pub fn new() -> Self {
+        Self {
+            capture_errors: true,
+            flush_timeout: Duration::from_secs(2),
+        }
+    }
+
+    /// Enables or disables capturing errors returned by the handler.
+    ///
+    /// The default is to capture all errors.
+    pub fn capture_errors(mut self, val: bool) -> Self {
+        self.capture_errors = val;
+        self
+    }
+
+    /// Sets the maximum time to wait for pending events after each invocation.
+    ///
+    /// The wait is cut short if the deadline of the invocation is closer.  The default
+    /// is two seconds.
+    pub fn flush_timeout(mut self, timeout: Duration) -> Self {
+        self.flush_timeout = timeout;
+        self
+    }
+}
+
+impl Default for SentryLambdaLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for SentryLambdaLayer {
+    type Service = SentryLambdaService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SentryLambdaService {
+            service,
+            capture_errors: self.capture_errors,
+            flush_timeout: self.flush_timeout,
+        }
+    }
+}
+
+/// Tower service that reports Lambda invocations to Sentry.
+#[derive(Clone, Debug)]
+pub struct SentryLambdaService<S> {
+    service: S,
+    capture_errors: bool,
+    flush_timeout: Duration,
+}
+
+impl<S, T> Service<LambdaEvent<T>> for SentryLambdaService<S>
+where
+    S: Service<LambdaEvent<T>>,
+    S::Future: Send + 'static,
+    S::Response: Send,
+    S::Error: ServiceError + Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, event: LambdaEvent<T>) -> Self::Future {
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        configure_scope_from_context(&hub, &event.context);
+
+        let flush_timeout = remaining_time(event.context.deadline)
+            .map_or(self.flush_timeout, |remaining| {
+                remaining.min(self.flush_timeout)
+            });
+        let capture_errors = self.capture_errors;
+
+        let service = &mut self.service;
+        let future = Hub::run(hub.clone(), || service.call(event)).bind_hub(hub.clone());
+
+        Box::pin(async move {
+            let result = future.await;
+            if let Err(err) = &result {
+                if capture_errors {
+                    if let Some(error) = err.as_error() {
+                        hub.capture_error(error);
+                    }
+                }
+            }
+            // flushing blocks until the events are sent, so it must not block the runtime
+            if let Some(client) = hub.client() {
+                let flush = tokio::task::spawn_blocking(move || client.flush(Some(flush_timeout)));
+                flush.await.ok();
+            }
+            result
+        })
+    }
+}
+
+/// Adds the details of the invocation to the scope of the hub
+fn configure_scope_from_context(hub: &Hub, context: &lambda_runtime::Context) {
+    let config = &context.env_config;
+
+    let mut lambda_context = Map::new();
+    lambda_context.insert("function_name".into(), config.function_name.clone().into());
+    lambda_context.insert("function_version".into(), config.version.clone().into());
+    lambda_context.insert("memory_limit_mb".into(), config.memory.into());
+    lambda_context.insert("log_group".into(), config.log_group.clone().into());
+    lambda_context.insert("log_stream".into(), config.log_stream.clone().into());
+    if let Some(remaining) = remaining_time(context.deadline) {
+        lambda_context.insert(
+            "remaining_time_ms".into(),
+            (remaining.as_millis() as u64).into(),
+        );
+    }
+
+    hub.configure_scope(|scope| {
+        scope.set_transaction(Some(&config.function_name));
+        scope.set_tags(sentry_core::tags! {
+            "aws.request_id" => context.request_id,
+            "aws.function_arn" => context.invoked_function_arn,
+        });
+        scope.set_context("aws_lambda", protocol::Context::Other(lambda_context));
+    });
+}
+
+/// Returns the time left until the deadline, given in milliseconds since the epoch
+fn remaining_time(deadline: u64) -> Option<Duration> {
+    let deadline = UNIX_EPOCH + Duration::from_millis(deadline);
+    deadline.duration_since(SystemTime::now()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use lambda_runtime::Context;
+    use sentry::protocol::Context as SentryContext;
+    use sentry::Level;
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    fn lambda_event(request_id: &str) -> LambdaEvent<&'static str> {
+        let mut context = Context::default();
+        context.request_id = request_id.into();
+        context.invoked_function_arn = "arn:aws:lambda:us-east-1:123456789012:function:test".into();
+        context.env_config.function_name = "test".into();
+        LambdaEvent::new("payload", context)
+    }
+
+    #[test]
+    fn test_invocations() {
+        let events = sentry::test::with_captured_events(|| {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let service = SentryLambdaLayer::new().layer(service_fn(
+                |event: LambdaEvent<&'static str>| async move {
+                    if event.context.request_id == "failing" {
+                        return Err(io::Error::new(io::ErrorKind::Other, "Test Error"));
+                    }
+                    sentry::configure_scope(|scope| scope.set_tag("invocation", "first"));
+                    sentry::capture_message("Message", Level::Warning);
+                    Ok(event.payload)
+                },
+            ));
+
+            assert_eq!(
+                runtime
+                    .block_on(service.clone().oneshot(lambda_event("first")))
+                    .unwrap(),
+                "payload"
+            );
+            assert!(runtime
+                .block_on(service.oneshot(lambda_event("failing")))
+                .is_err());
+        });
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].transaction, Some("test".into()));
+        assert_eq!(events[0].tags.get("aws.request_id").unwrap(), "first");
+        assert!(matches!(
+            events[0].contexts.get("aws_lambda"),
+            Some(SentryContext::Other(_))
+        ));
+        assert_eq!(events[1].tags.get("aws.request_id").unwrap(), "failing");
+        assert!(events[1].tags.get("invocation").is_none());
+        assert_eq!(
+            events[1].exception.values[0].value,
+            Some("Test Error".into())
+        );
+    }
+}
@@ -3,12 +3,7 @@ use sentry_core::{Breadcrumb, Level};
 
 /// Converts a [`log::Level`] to a Sentry [`Level`]
 pub fn convert_log_level(level: log::Level) -> Level {
-    match level {
-        log::Level::Error => Level::Error,
-        log::Level::Warn => Level::Warning,
-        log::Level::Info => Level::Info,
-        log::Level::Debug | log::Level::Trace => Level::Debug,
-    }
+    level.into()
 }
 
 /// Creates a [`Breadcrumb`] from a given [`log::Record`].
@@ -125,6 +125,14 @@ impl<L: log::Log> log::Log for SentryLogger<L> {
     }
 
     fn log(&self, record: &log::Record<'_>) {
+        // the diagnostics of the SDK itself are never reported to Sentry
+        if record
+            .target()
+            .starts_with(sentry_core::INTERNAL_LOG_TARGET)
+        {
+            return self.dest.log(record);
+        }
+
         let item: RecordMapping = match &self.mapper {
             Some(mapper) => mapper(record),
             None => match (self.filter)(record.metadata()) {
@@ -0,0 +1,214 @@
+//! Attribute macros for instrumenting code with Sentry.
+//!
+//! These macros are re-exported by the `sentry` crate when its `macros` feature is
+//! enabled, and expand to code that refers to the `sentry` crate.  They should be used
+//! through that re-export.
+//!
+//! # Example
+//!
+//! ```ignore
+//! #[sentry::traced]
+//! fn process_job(#[trace] job_id: u64, payload: &[u8]) {
+//!     // ...
+//! }
+//!
+//! #[sentry::capture_err(level = "warning", tags(component = "config"))]
+//! fn load_config(path: &str) -> Result<String, std::io::Error> {
+//!     std::fs::read_to_string(path)
+//! }
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, AttributeArgs, FnArg, ItemFn, Lit, LitStr, Meta, NestedMeta, Pat};
+
+/// Wraps the body of a function in a Sentry span.
+///
+/// The span is a child of the current span, or a new transaction if there is no current
+/// span, and it is named after the function.  The span is the current span of the scope
+/// while the function runs, so that spans started within are nested, and events carry
+/// its trace context.
+///
+/// Arguments marked with `#[trace]` are recorded as data of the span, using their
+/// `Debug` representation.
+///
+/// The operation of the span defaults to `function`, and can be set using
+/// `#[sentry::traced(op = "db.query")]`.
+#[proc_macro_attribute]
+pub fn traced(args: TokenStream, item: TokenStream) -> TokenStream {
+    let op = match parse_op(args) {
+        Ok(op) => op,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let mut function = parse_macro_input!(item as ItemFn);
+
+    let mut traced_args = Vec::new();
+    for input in &mut function.sig.inputs {
+        let pat_type = match input {
+            FnArg::Typed(pat_type) => pat_type,
+            FnArg::Receiver(_) => continue,
+        };
+        let len = pat_type.attrs.len();
+        pat_type.attrs.retain(|attr| !attr.path.is_ident("trace"));
+        if pat_type.attrs.len() == len {
+            continue;
+        }
+        match &*pat_type.pat {
+            Pat::Ident(pat) => traced_args.push(pat.ident.clone()),
+            pat => {
+                return syn::Error::new_spanned(pat, "only named arguments can be traced")
+                    .to_compile_error()
+                    .into()
+            }
+        }
+    }
+
+    let name = function.sig.ident.to_string();
+    let keys = traced_args.iter().map(|ident| ident.to_string());
+    let block = &function.block;
+    function.block = syn::parse_quote!({
+        let __sentry_span_guard = {
+            let span = ::sentry::start_child_or_transaction(
+                #op,
+                concat!(module_path!(), "::", #name),
+            );
+            if span.is_sampled() {
+                #(span.set_data(
+                    #keys,
+                    ::sentry::protocol::Value::from(format!("{:?}", &#traced_args)),
+                );)*
+            }
+            span.enter()
+        };
+        #block
+    });
+
+    quote!(#function).into()
+}
+
+/// Captures the `Err` variant returned by a function, and then returns it as usual.
+///
+/// The error type has to implement `std::error::Error`, and is captured using
+/// `sentry::capture_error`.  The level of the event can be set with `level = "warning"`,
+/// and tags can be added with `tags(component = "db")`.
+///
+/// This works for both regular and `async` functions.  Errors propagated with `?` and
+/// early `return`s are captured as well.
+#[proc_macro_attribute]
+pub fn capture_err(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let mut function = parse_macro_input!(item as ItemFn);
+
+    let mut level = None;
+    let mut tags = Vec::new();
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("level") => {
+                match parse_level(&meta.lit) {
+                    Ok(parsed) => level = Some(parsed),
+                    Err(err) => return err.to_compile_error().into(),
+                }
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("tags") => {
+                for nested in list.nested {
+                    match nested {
+                        NestedMeta::Meta(Meta::NameValue(tag))
+                            if tag.path.get_ident().is_some() =>
+                        {
+                            let key = tag.path.get_ident().unwrap().to_string();
+                            tags.push((key, tag.lit));
+                        }
+                        other => {
+                            return syn::Error::new_spanned(other, "expected `key = value`")
+                                .to_compile_error()
+                                .into()
+                        }
+                    }
+                }
+            }
+            other => {
+                return syn::Error::new_spanned(other, "expected `level` or `tags`")
+                    .to_compile_error()
+                    .into()
+            }
+        }
+    }
+
+    let output = match &function.sig.output {
+        syn::ReturnType::Type(_, ty) => ty.clone(),
+        syn::ReturnType::Default => {
+            return syn::Error::new_spanned(&function.sig, "the function must return a `Result`")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let block = &function.block;
+    let result = if function.sig.asyncness.is_some() {
+        quote!(async move #block.await)
+    } else {
+        quote!((move || -> #output #block)())
+    };
+    let capture = if level.is_none() && tags.is_empty() {
+        quote!(::sentry::capture_error(err);)
+    } else {
+        let set_level = level.map(|level| quote!(scope.set_level(Some(::sentry::Level::#level));));
+        let set_tags = tags
+            .iter()
+            .map(|(key, value)| quote!(scope.set_tag(#key, #value);));
+        quote!(::sentry::with_scope(
+            |scope| {
+                #set_level
+                #(#set_tags)*
+            },
+            || ::sentry::capture_error(err),
+        );)
+    };
+
+    function.block = syn::parse_quote!({
+        let __sentry_result: #output = #result;
+        if let Err(err) = &__sentry_result {
+            #capture
+        }
+        __sentry_result
+    });
+
+    quote!(#function).into()
+}
+
+/// Parses a level name into the name of the `Level` variant.
+fn parse_level(lit: &Lit) -> syn::Result<syn::Ident> {
+    let name = match lit {
+        Lit::Str(name) => name.value(),
+        _ => return Err(syn::Error::new_spanned(lit, "expected a string")),
+    };
+    let variant = match name.as_str() {
+        "debug" => "Debug",
+        "info" => "Info",
+        "warning" => "Warning",
+        "error" => "Error",
+        "fatal" => "Fatal",
+        _ => return Err(syn::Error::new_spanned(lit, "unknown level")),
+    };
+    Ok(syn::Ident::new(variant, proc_macro2::Span::call_site()))
+}
+
+/// Parses the optional `op = "..."` argument.
+fn parse_op(args: TokenStream) -> syn::Result<LitStr> {
+    let default = LitStr::new("function", proc_macro2::Span::call_site());
+    if args.is_empty() {
+        return Ok(default);
+    }
+    let meta: syn::MetaNameValue = syn::parse(args)?;
+    if !meta.path.is_ident("op") {
+        return Err(syn::Error::new_spanned(meta.path, "expected `op`"));
+    }
+    match meta.lit {
+        syn::Lit::Str(op) => Ok(op),
+        lit => Err(syn::Error::new_spanned(lit, "expected a string")),
+    }
+}
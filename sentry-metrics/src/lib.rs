@@ -0,0 +1,29 @@
+//! Adds support for forwarding [`metrics`](https://docs.rs/metrics) to Sentry.
+//!
+//! The [`SentryRecorder`] implements the [`metrics::Recorder`] trait, and forwards all
+//! counters, gauges and histograms recorded through the `metrics` facade to the metrics
+//! aggregator of the current Sentry client.  Applications that are already instrumented
+//! with `metrics` can report their metrics to Sentry without a second instrumentation
+//! layer.
+//!
+//! Counters are sent as Sentry counters, gauges as gauges and histograms as
+//! distributions.  The labels of a metric become its tags, and units given via the
+//! `describe_*` macros are attached to the metrics.
+//!
+//! # Examples
+//!
+//! ```
+//! sentry_metrics::install().unwrap();
+//!
+//! metrics::describe_histogram!("response_time", metrics::Unit::Milliseconds, "");
+//! metrics::counter!("requests", 1, "route" => "/users");
+//! metrics::histogram!("response_time", 12.5);
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+mod recorder;
+
+pub use recorder::*;
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use metrics::{
+    Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Recorder,
+    SetRecorderError, SharedString, Unit,
+};
+use sentry_core::metrics::{Metric, MetricValue};
+
+type Units = Arc<RwLock<HashMap<String, Unit>>>;
+
+/// A [`metrics::Recorder`] that forwards metrics to Sentry.
+///
+/// Every recorded value is sent to the metrics aggregator of the client bound to the
+/// current hub, so metrics recorded without a client are discarded.
+#[derive(Debug, Default)]
+pub struct SentryRecorder {
+    units: Units,
+}
+
+impl SentryRecorder {
+    /// Creates a new Sentry metrics recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn describe(&self, key: KeyName, unit: Option<Unit>) {
+        if let Some(unit) = unit {
+            self.units
+                .write()
+                .unwrap()
+                .insert(key.as_str().to_owned(), unit);
+        }
+    }
+
+    fn handle(&self, key: &Key) -> Arc<Handle> {
+        Arc::new(Handle {
+            key: key.clone(),
+            units: self.units.clone(),
+            state: AtomicU64::new(0),
+        })
+    }
+}
+
+/// Installs a [`SentryRecorder`] as the global recorder of the `metrics` facade.
+///
+/// This fails if another recorder has already been installed.
+pub fn install() -> Result<(), SetRecorderError> {
+    metrics::set_boxed_recorder(Box::new(SentryRecorder::new()))
+}
+
+impl Recorder for SentryRecorder {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, _description: SharedString) {
+        self.describe(key, unit)
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, _description: SharedString) {
+        self.describe(key, unit)
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, _description: SharedString) {
+        self.describe(key, unit)
+    }
+
+    fn register_counter(&self, key: &Key) -> Counter {
+        Counter::from_arc(self.handle(key))
+    }
+
+    fn register_gauge(&self, key: &Key) -> Gauge {
+        Gauge::from_arc(self.handle(key))
+    }
+
+    fn register_histogram(&self, key: &Key) -> Histogram {
+        Histogram::from_arc(self.handle(key))
+    }
+}
+
+/// The handle behind a registered counter, gauge or histogram.
+///
+/// `state` holds the last absolute value of a counter, or the bits of the current value
+/// of a gauge, since `metrics` only reports changes to gauges.
+struct Handle {
+    key: Key,
+    units: Units,
+    state: AtomicU64,
+}
+
+impl Handle {
+    fn send(&self, value: MetricValue) {
+        let mut metric = Metric::new(self.key.name().to_owned(), value);
+        if let Some(unit) = self
+            .units
+            .read()
+            .unwrap()
+            .get(self.key.name())
+            .and_then(convert_unit)
+        {
+            metric = metric.with_unit(unit);
+        }
+        for label in self.key.labels() {
+            metric = metric.with_tag(label.key(), label.value());
+        }
+        metric.send();
+    }
+
+    fn update_gauge<F: Fn(f64) -> f64>(&self, f: F) -> f64 {
+        let mut current = self.state.load(Ordering::Relaxed);
+        loop {
+            let value = f(f64::from_bits(current));
+            match self.state.compare_exchange_weak(
+                current,
+                value.to_bits(),
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return value,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+impl CounterFn for Handle {
+    fn increment(&self, value: u64) {
+        self.send(MetricValue::Counter(value as f64));
+    }
+
+    fn absolute(&self, value: u64) {
+        let previous = self.state.swap(value, Ordering::AcqRel);
+        // a counter that went backwards has been reset, so it counts up from zero again
+        let delta = value.checked_sub(previous).unwrap_or(value);
+        if delta > 0 {
+            self.send(MetricValue::Counter(delta as f64));
+        }
+    }
+}
+
+impl GaugeFn for Handle {
+    fn increment(&self, value: f64) {
+        let value = self.update_gauge(|current| current + value);
+        self.send(MetricValue::Gauge(value));
+    }
+
+    fn decrement(&self, value: f64) {
+        let value = self.update_gauge(|current| current - value);
+        self.send(MetricValue::Gauge(value));
+    }
+
+    fn set(&self, value: f64) {
+        self.update_gauge(|_| value);
+        self.send(MetricValue::Gauge(value));
+    }
+}
+
+impl HistogramFn for Handle {
+    fn record(&self, value: f64) {
+        self.send(MetricValue::Distribution(value));
+    }
+}
+
+/// Converts a `metrics` unit into the name of the corresponding Sentry unit.
+fn convert_unit(unit: &Unit) -> Option<&'static str> {
+    Some(match unit {
+        Unit::Count => return None,
+        Unit::Percent => "percent",
+        Unit::Seconds => "second",
+        Unit::Milliseconds => "millisecond",
+        Unit::Microseconds => "microsecond",
+        Unit::Nanoseconds => "nanosecond",
+        Unit::Bytes => "byte",
+        Unit::Kibibytes => "kibibyte",
+        Unit::Mebibytes => "mebibyte",
+        Unit::Gigibytes => "gibibyte",
+        Unit::Tebibytes => "tebibyte",
+        Unit::TerabitsPerSecond => "terabits_per_second",
+        Unit::GigabitsPerSecond => "gigabits_per_second",
+        Unit::MegabitsPerSecond => "megabits_per_second",
+        Unit::KilobitsPerSecond => "kilobits_per_second",
+        Unit::BitsPerSecond => "bits_per_second",
+        Unit::CountPerSecond => "count_per_second",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sentry::protocol::EnvelopeItem;
+    use sentry::test::with_captured_envelopes_options;
+
+    fn captured_statsd<F: FnOnce()>(f: F) -> String {
+        let envelopes = with_captured_envelopes_options(
+            || {
+                f();
+                sentry::Hub::current().client().unwrap().flush(None);
+            },
+            sentry::ClientOptions::default(),
+        );
+        let mut statsd = String::new();
+        for envelope in envelopes {
+            for item in envelope.items() {
+                if let EnvelopeItem::Statsd(payload) = item {
+                    statsd.push_str(std::str::from_utf8(payload).unwrap());
+                }
+            }
+        }
+        // strip the bucket timestamps
+        statsd
+            .lines()
+            .map(|line| line.rsplitn(2, "|T").nth(1).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_counter() {
+        let recorder = SentryRecorder::new();
+        let statsd = captured_statsd(|| {
+            let key = Key::from_parts("requests", vec![metrics::Label::new("route", "/a")]);
+            let counter = recorder.register_counter(&key);
+            counter.increment(2);
+            counter.absolute(10);
+            counter.absolute(12);
+        });
+        assert_eq!(statsd, "requests:14|c|#route:/a");
+    }
+
+    #[test]
+    fn test_gauge_and_histogram() {
+        let recorder = SentryRecorder::new();
+        let statsd = captured_statsd(|| {
+            recorder.describe_histogram(
+                "response_time".into(),
+                Some(Unit::Milliseconds),
+                "".into(),
+            );
+            let gauge = recorder.register_gauge(&Key::from_name("connections"));
+            gauge.set(4.0);
+            gauge.increment(2.0);
+            gauge.decrement(5.0);
+            let histogram = recorder.register_histogram(&Key::from_name("response_time"));
+            histogram.record(1.5);
+            histogram.record(3.0);
+        });
+        assert_eq!(
+            statsd,
+            "connections:1:1:6:11:3|g\nresponse_time@millisecond:1.5:3|d"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use sentry_core::protocol::{Attachment, AttachmentType, Envelope, Event, Level};
+use sentry_core::types::{DateTime, Utc, Uuid};
+use sentry_core::Client;
+
+/// Uploads the minidumps in the `database` directory, and removes them.
+///
+/// Every minidump is sent as an attachment of a fatal event, which contains the scope
+/// that was last synced before the crash.  Nothing is uploaded or removed if the client
+/// is disabled.
+///
+/// This is called by [`init`](crate::init), and only needs to be called manually when
+/// minidumps are written by other means.
+pub fn upload_pending(client: &Client, database: &Path) -> io::Result<()> {
+    if !client.is_enabled() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(database)? {
+        let path = entry?.path();
+        if path.extension() != Some("dmp".as_ref()) {
+            continue;
+        }
+
+        let buffer = fs::read(&path)?;
+        let scope_path = path.with_extension("json");
+        let mut event: Event<'static> = fs::read(&scope_path)
+            .ok()
+            .and_then(|scope| serde_json::from_slice(&scope).ok())
+            .unwrap_or_default();
+
+        event.event_id = Uuid::new_v4();
+        event.level = Level::Fatal;
+        event.platform = "native".into();
+        if let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            event.timestamp = DateTime::<Utc>::from(modified);
+        }
+        let options = client.options();
+        if event.release.is_none() {
+            event.release = options.release.clone();
+        }
+        if event.environment.is_none() {
+            event.environment = options.environment.clone();
+        }
+
+        let mut envelope: Envelope = event.into();
+        envelope.add_item(Attachment {
+            buffer,
+            filename: "minidump.dmp".into(),
+            ty: Some(AttachmentType::Minidump),
+            ..Default::default()
+        });
+        client.send_envelope(envelope);
+
+        fs::remove_file(&path)?;
+        fs::remove_file(&scope_path).ok();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use sentry::protocol::EnvelopeItem;
+    use sentry::test::TestTransport;
+
+    use super::*;
+
+    #[test]
+    fn test_upload_pending() {
+        let database = std::env::temp_dir().join(format!("sentry-minidump-{}", Uuid::new_v4()));
+        fs::create_dir_all(&database).unwrap();
+
+        let scope = Event {
+            release: Some("app@1.0".into()),
+            tags: vec![("phase".to_owned(), "startup".to_owned())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        fs::write(database.join("crash.dmp"), b"MDMP").unwrap();
+        fs::write(
+            database.join("crash.json"),
+            serde_json::to_vec(&scope).unwrap(),
+        )
+        .unwrap();
+        fs::write(database.join("unrelated.txt"), b"").unwrap();
+
+        let transport = TestTransport::new();
+        let client = Client::from_config(sentry::ClientOptions {
+            dsn: Some("https://public@example.com/1".parse().unwrap()),
+            transport: Some(Arc::new(transport.clone())),
+            ..Default::default()
+        });
+        upload_pending(&client, &database).unwrap();
+
+        let envelopes = transport.fetch_and_clear_envelopes();
+        assert_eq!(envelopes.len(), 1);
+        let mut items = envelopes[0].items();
+        match items.next() {
+            Some(EnvelopeItem::Event(event)) => {
+                assert_eq!(event.level, Level::Fatal);
+                assert_eq!(event.release, Some("app@1.0".into()));
+                assert_eq!(event.tags.get("phase").unwrap(), "startup");
+            }
+            item => panic!("expected event, got {:?}", item),
+        }
+        match items.next() {
+            Some(EnvelopeItem::Attachment(attachment)) => {
+                assert_eq!(attachment.buffer, b"MDMP");
+                assert_eq!(attachment.ty, Some(AttachmentType::Minidump));
+            }
+            item => panic!("expected attachment, got {:?}", item),
+        }
+
+        assert!(!database.join("crash.dmp").exists());
+        assert!(!database.join("crash.json").exists());
+        assert!(database.join("unrelated.txt").exists());
+        fs::remove_dir_all(&database).ok();
+    }
+}
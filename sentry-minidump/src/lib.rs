@@ -0,0 +1,207 @@
+//! Adds support for capturing hard crashes of the application as minidumps.
+//!
+//! Crashes such as segmentation faults or aborts can not be reported from within the
+//! crashing process.  Instead, [`init`] starts a small crash reporter process, which
+//! writes a minidump of the application when it crashes.  Minidumps are written to a
+//! database directory, and uploaded to Sentry as an attachment of a fatal event the next
+//! time the application calls [`init`].
+//!
+//! The crash reporter process is a copy of the application itself, started with the same
+//! arguments.  [`init`] detects when it runs in the crash reporter process and never
+//! returns in that case, so it should be called as early as possible in `main`, right
+//! after the Sentry client was initialized:
+//!
+//! ```no_run
+//! let client = sentry::init("https://key@sentry.io/42");
+//! let minidump = sentry_minidump::init(&client, "./crashes").expect("crash reporter");
+//!
+//! sentry::configure_scope(|scope| scope.set_tag("phase", "startup"));
+//! // the scope is not shared with the crash reporter automatically
+//! minidump.sync_scope();
+//! ```
+//!
+//! The fatal event contains the scope, as it was sent with the last call to
+//! [`MinidumpGuard::sync_scope`].  The scope is synced once when the crash reporter is
+//! started.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::process::{self, Child, Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crash_handler::{make_crash_event, CrashContext, CrashEventResult, CrashHandler};
+use sentry_core::protocol::Event;
+use sentry_core::types::Uuid;
+use sentry_core::{sentry_debug, Client, Hub};
+
+mod database;
+mod server;
+
+pub use crate::database::upload_pending;
+
+/// The environment variable that tells the crash reporter process its socket name.
+const SERVER_ENV: &str = "SENTRY_MINIDUMP_SERVER";
+
+/// The kind of the messages that contain a serialized scope.
+const SCOPE_MESSAGE: u32 = 1;
+
+/// An error that occurred while starting the crash reporter.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The crash reporter process could not be started.
+    #[error("failed to start the crash reporter process")]
+    Spawn(#[source] io::Error),
+    /// The crash database could not be accessed.
+    #[error("failed to access the crash database")]
+    Database(#[from] io::Error),
+    /// The connection to the crash reporter process failed.
+    #[error("failed to connect to the crash reporter process")]
+    Ipc(#[from] minidumper::Error),
+    /// The crash handler could not be attached.
+    #[error("failed to attach the crash handler")]
+    Handler(#[from] crash_handler::Error),
+}
+
+/// Starts the crash reporter, and uploads minidumps of previous crashes.
+///
+/// Minidumps are stored in the `database` directory, which is created if it does not
+/// exist.  When called in the crash reporter process, this function runs the crash
+/// reporter and exits the process once the application has exited.
+///
+/// The crash reporter is stopped when the returned guard is dropped.
+pub fn init<P: Into<PathBuf>>(client: &Client, database: P) -> Result<MinidumpGuard, Error> {
+    let database = database.into();
+
+    if let Ok(socket_name) = env::var(SERVER_ENV) {
+        let code = match server::run(&socket_name, &database) {
+            Ok(()) => 0,
+            Err(err) => {
+                sentry_debug!("crash reporter failed: {}", err);
+                1
+            }
+        };
+        process::exit(code);
+    }
+
+    std::fs::create_dir_all(&database)?;
+    upload_pending(client, &database)?;
+
+    let socket_name = format!("sentry-minidump-{}", Uuid::new_v4().to_simple());
+    let mut server = Command::new(env::current_exe().map_err(Error::Spawn)?)
+        .args(env::args_os().skip(1))
+        .env(SERVER_ENV, &socket_name)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(Error::Spawn)?;
+
+    let guard = connect(&socket_name).and_then(|ipc| {
+        let ipc = Arc::new(ipc);
+        let handler_ipc = ipc.clone();
+        #[allow(unsafe_code)]
+        let handler = CrashHandler::attach(unsafe {
+            make_crash_event(move |crash_context: &CrashContext| {
+                CrashEventResult::Handled(handler_ipc.request_dump(crash_context).is_ok())
+            })
+        })?;
+        // on linux, the crash reporter needs to be allowed to ptrace us
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        handler.set_ptracer(Some(server.id()));
+        Ok((ipc, handler))
+    });
+
+    match guard {
+        Ok((ipc, handler)) => {
+            let guard = MinidumpGuard {
+                ipc: Some(ipc),
+                handler: Some(handler),
+                server,
+            };
+            guard.sync_scope();
+            Ok(guard)
+        }
+        Err(err) => {
+            server.kill().ok();
+            server.wait().ok();
+            Err(err)
+        }
+    }
+}
+
+/// Connects to the crash reporter process, waiting for it to start up.
+fn connect(socket_name: &str) -> Result<minidumper::Client, Error> {
+    let mut attempts = 0;
+    loop {
+        match minidumper::Client::with_name(socket_name) {
+            Ok(ipc) => return Ok(ipc),
+            Err(err) if attempts >= 50 => return Err(err.into()),
+            Err(_) => {
+                attempts += 1;
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+/// Keeps the crash reporter running.
+///
+/// Dropping the guard detaches the crash handler and stops the crash reporter process.
+pub struct MinidumpGuard {
+    ipc: Option<Arc<minidumper::Client>>,
+    handler: Option<CrashHandler>,
+    server: Child,
+}
+
+impl MinidumpGuard {
+    /// Sends the scope of the current hub to the crash reporter.
+    ///
+    /// The last scope that was sent is attached to the event of a crash.
+    pub fn sync_scope(&self) {
+        let ipc = match &self.ipc {
+            Some(ipc) => ipc,
+            None => return,
+        };
+        if let Some(snapshot) = scope_snapshot(&Hub::current()) {
+            if let Err(err) = ipc.send_message(SCOPE_MESSAGE, snapshot) {
+                sentry_debug!("failed to send scope to crash reporter: {}", err);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for MinidumpGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MinidumpGuard")
+            .field("server", &self.server.id())
+            .finish()
+    }
+}
+
+impl Drop for MinidumpGuard {
+    fn drop(&mut self) {
+        // the crash handler holds on to the connection, and the crash reporter
+        // exits once the connection is closed
+        self.handler.take();
+        self.ipc.take();
+        self.server.wait().ok();
+    }
+}
+
+/// Serializes the scope of the hub, applied to an empty event.
+fn scope_snapshot(hub: &Hub) -> Option<Vec<u8>> {
+    let client = hub.client()?;
+    let options = client.options();
+    let mut event = hub.configure_scope(|scope| scope.apply_to_event(Event::default()))?;
+    event.release = options.release.clone();
+    event.environment = options.environment.clone();
+    event.server_name = options.server_name.clone();
+    serde_json::to_vec(&event).ok()
+}
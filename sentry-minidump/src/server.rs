@@ -0,0 +1,59 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+
+use minidumper::{LoopAction, MinidumpBinary, Server, ServerHandler};
+use sentry_core::types::Uuid;
+
+use crate::SCOPE_MESSAGE;
+
+/// Runs the crash reporter until the application disconnects.
+pub(crate) fn run(socket_name: &str, database: &Path) -> Result<(), minidumper::Error> {
+    let mut server = Server::with_name(socket_name)?;
+    let handler = Handler {
+        database: database.to_owned(),
+        scope: Mutex::new(None),
+    };
+    let shutdown = AtomicBool::new(false);
+    server.run(Box::new(handler), &shutdown, None)
+}
+
+/// Writes minidumps and the last synced scope into the database.
+struct Handler {
+    database: PathBuf,
+    scope: Mutex<Option<Vec<u8>>>,
+}
+
+impl ServerHandler for Handler {
+    fn create_minidump_file(&self) -> Result<(File, PathBuf), io::Error> {
+        let path = self.database.join(format!("{}.dmp", Uuid::new_v4()));
+        let file = File::create(&path)?;
+        Ok((file, path))
+    }
+
+    fn on_minidump_created(&self, result: Result<MinidumpBinary, minidumper::Error>) -> LoopAction {
+        if let Ok(binary) = result {
+            if let Some(scope) = &*self.scope.lock().unwrap() {
+                fs::write(binary.path.with_extension("json"), scope).ok();
+            }
+        }
+        // the application is about to go away after crashing
+        LoopAction::Exit
+    }
+
+    fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+        if kind == SCOPE_MESSAGE {
+            *self.scope.lock().unwrap() = Some(buffer);
+        }
+    }
+
+    fn on_client_disconnected(&self, num_clients: usize) -> LoopAction {
+        if num_clients == 0 {
+            LoopAction::Exit
+        } else {
+            LoopAction::Continue
+        }
+    }
+}
@@ -13,42 +13,354 @@
 //! ```
 //! let integration = sentry_panic::PanicIntegration::default().add_extractor(|info| None);
 //! ```
+//!
+//! Only `&str` and `String` panic payloads, as created by the `panic!` macro, produce a
+//! meaningful message by default.  Extractors for other payload types, such as the ones
+//! passed to `std::panic::panic_any`, can be registered by their type:
+//!
+//! ```
+//! use sentry_panic::PanicPayload;
+//!
+//! struct HttpPanic {
+//!     status: u16,
+//! }
+//!
+//! let integration = sentry_panic::PanicIntegration::default()
+//!     .add_payload_extractor(|payload: &HttpPanic| {
+//!         PanicPayload::new("request handler panicked").with_extra("status", payload.status)
+//!     });
+//! ```
+//!
+//! # Grouping
+//!
+//! Panics with the same message, such as `index out of bounds`, are common across
+//! unrelated parts of a program.  So that distinct panic sites do not collapse into one
+//! issue, the location of the panic is added to the default fingerprint of panic events.
+//! A fingerprint function replaces this, and returning `None` from it keeps the default
+//! grouping of Sentry:
+//!
+//! ```
+//! let integration = sentry_panic::PanicIntegration::default().fingerprint_fn(|info, _event| {
+//!     let location = info.location()?;
+//!     Some(vec![location.file().to_owned().into()])
+//! });
+//! ```
+//!
+//! # Catching Panics
+//!
+//! [`capture_unwind`] runs a closure under [`std::panic::catch_unwind`], reports a panic as
+//! a handled error, and returns its payload, for plugin hosts and FFI boundaries that must
+//! not crash:
+//!
+//! ```
+//! let result = sentry_panic::capture_unwind(|| {
+//!     // call into the plugin
+//! });
+//! ```
+//!
+//! Integrations of frameworks that catch the panics of request handlers use
+//! [`report_unwind`] instead, which reports the panic as unhandled unless the panic handler
+//! already did.
+//!
+//! # WebAssembly
+//!
+//! With the `console-error-panic-hook` feature, panics are forwarded to the hook of the
+//! [`console_error_panic_hook`](https://docs.rs/console_error_panic_hook) crate instead of
+//! the previously registered panic hook, so that panics in the browser are both reported to
+//! Sentry and logged to the developer console.
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
 #![warn(missing_docs)]
 #![deny(unsafe_code)]
 
-use std::panic::{self, PanicInfo};
+use std::any::Any;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::panic::{self, PanicInfo, UnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Once;
+use std::thread;
+use std::time::Duration;
 
-use sentry_backtrace::current_stacktrace;
-use sentry_core::protocol::{Event, Exception, Level, Mechanism};
-use sentry_core::{ClientOptions, Integration};
+use sentry_backtrace::capture_stacktrace;
+use sentry_core::protocol::{
+    Breadcrumb, Event, Exception, Level, Map, Mechanism, SessionStatus, Value,
+};
+use sentry_core::{ClientOptions, Hub, Integration};
 
 /// A panic handler that sends to Sentry.
 ///
 /// This panic handler reports panics to Sentry. It also attempts to prevent
 /// double faults in some cases where it's known to be unsafe to invoke the
 /// Sentry panic handler.
+///
+/// Before the event is created, a final breadcrumb with the message and location
+/// of the panic is recorded.  A panic that is not caught by [`capture_unwind`] or
+/// [`report_unwind`] terminates the process when it happens on the main thread, so in
+/// that case the current session is ended as crashed.  As the panic may be the last
+/// chance to get data out, the client is then flushed with the
+/// [`flush_timeout`](PanicIntegration::flush_timeout) of the integration.  When building
+/// with `panic = "abort"`, enable [`flush_all_threads`](PanicIntegration::flush_all_threads)
+/// to flush for panics on other threads as well.
 pub fn panic_handler(info: &PanicInfo<'_>) {
     sentry_core::with_integration(|integration: &PanicIntegration, hub| {
-        hub.capture_event(integration.event_from_panic_info(info))
+        let catching = CATCHING.with(Cell::get);
+        hub.add_breadcrumb(breadcrumb_from_panic_info(info));
+        let mut event = integration.event_from_panic_info(info);
+        if catching == Some(Catcher::CaptureUnwind) {
+            mark_caught(&mut event);
+        }
+        hub.capture_event(event);
+        CAPTURED.with(|captured| captured.set(true));
+        if catching.is_some() {
+            return;
+        }
+        let main_thread = thread::current().name() == Some("main");
+        if main_thread {
+            hub.end_session_with_status(SessionStatus::Crashed);
+        }
+        if main_thread || integration.flush_all_threads {
+            if let Some(client) = hub.client() {
+                client.flush(Some(
+                    integration.flush_timeout.unwrap_or(DEFAULT_FLUSH_TIMEOUT),
+                ));
+            }
+        }
     });
 }
 
+/// The helper that catches the panics of the current thread.
+#[derive(Clone, Copy, PartialEq)]
+enum Catcher {
+    CaptureUnwind,
+    ReportUnwind,
+}
+
+thread_local! {
+    /// The innermost [`capture_unwind`] or [`report_unwind`] call the current thread is in.
+    static CATCHING: Cell<Option<Catcher>> = Cell::new(None);
+    /// Whether the panic handler captured a panic within [`capture_unwind`] or
+    /// [`report_unwind`].
+    static CAPTURED: Cell<bool> = Cell::new(false);
+}
+
+/// Runs a closure, and captures a panic in it as a handled error.
+///
+/// The panic is caught with [`std::panic::catch_unwind`], and its payload is returned
+/// to the caller, so that plugin hosts and FFI boundaries can recover from it instead of
+/// crashing.  The event of the panic is reported with a `catch_unwind` mechanism and the
+/// `error` level.  If the [`PanicIntegration`] is installed, the event is created by its
+/// panic handler, which includes the stacktrace of the panic.  Otherwise, the event is
+/// created from the payload after the panic was caught.
+///
+/// The previous panic hook still runs as well, which prints the panic by default.
+///
+/// # Examples
+///
+/// ```
+/// # let events = sentry::test::with_captured_events(|| {
+/// let result = sentry_panic::capture_unwind(|| {
+///     panic!("plugin failed");
+/// });
+/// assert!(result.is_err());
+/// # });
+/// # assert_eq!(events.len(), 1);
+/// ```
+pub fn capture_unwind<F, R>(f: F) -> thread::Result<R>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    let (result, captured) = catch_unwind_captured(Catcher::CaptureUnwind, f);
+
+    if let Err(ref payload) = result {
+        if !captured {
+            let mut event =
+                Hub::with_active(|hub| event_from_caught_payload(hub, payload.as_ref()));
+            mark_caught(&mut event);
+            sentry_core::capture_event(event);
+        }
+    }
+    result
+}
+
+/// Runs a closure, and captures a panic in it as unhandled on the given hub.
+///
+/// This is meant for integrations of web frameworks and other runtimes, which catch the
+/// panics of request handlers or tasks so that they do not take down the server.  A panic
+/// that was already captured by the panic handler of the [`PanicIntegration`] is not
+/// captured a second time.  Otherwise, the event is created from the payload after the
+/// panic was caught, like the panic handler would create it.  The payload is returned to
+/// the caller, which usually resumes unwinding with [`std::panic::resume_unwind`].
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use sentry::Hub;
+///
+/// # let events = sentry::test::with_captured_events(|| {
+/// let hub = Arc::new(Hub::new_from_top(Hub::current()));
+/// let result = Hub::run(hub.clone(), || {
+///     sentry_panic::report_unwind(&hub, || panic!("request handler failed"))
+/// });
+/// assert!(result.is_err());
+/// # });
+/// # assert_eq!(events.len(), 1);
+/// ```
+pub fn report_unwind<F, R>(hub: &Hub, f: F) -> thread::Result<R>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    let (result, captured) = catch_unwind_captured(Catcher::ReportUnwind, f);
+    if let Err(ref payload) = result {
+        if !captured {
+            hub.capture_event(event_from_caught_payload(hub, payload.as_ref()));
+        }
+    }
+    result
+}
+
+/// Catches a panic, and returns whether the panic handler captured it.
+fn catch_unwind_captured<F, R>(catcher: Catcher, f: F) -> (thread::Result<R>, bool)
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    let catching_before = CATCHING.with(|catching| catching.replace(Some(catcher)));
+    let captured_before = CAPTURED.with(|captured| captured.replace(false));
+    let result = panic::catch_unwind(f);
+    let captured = CAPTURED.with(|captured| captured.replace(captured_before));
+    CATCHING.with(|catching| catching.set(catching_before));
+    (result, captured)
+}
+
+/// Creates the event of a panic that was caught before the panic handler captured it.
+fn event_from_caught_payload(hub: &Hub, payload: &(dyn Any + Send)) -> Event<'static> {
+    hub.with_integration(|integration: &PanicIntegration| {
+        Some(integration.event_from_payload(payload))
+    })
+    .unwrap_or_else(|| PanicIntegration::new().event_from_payload(payload))
+}
+
+/// Marks the event of a panic as caught by [`capture_unwind`].
+fn mark_caught(event: &mut Event<'static>) {
+    event.level = Level::Error;
+    for exception in event.exception.iter_mut() {
+        exception.mechanism = Some(Mechanism {
+            ty: "catch_unwind".into(),
+            handled: Some(true),
+            ..Default::default()
+        });
+    }
+}
+
+/// The time the panic handler waits for pending events to be sent by default.
+pub const DEFAULT_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Creates the breadcrumb that is recorded for a panic.
+fn breadcrumb_from_panic_info(info: &PanicInfo<'_>) -> Breadcrumb {
+    let mut breadcrumb = Breadcrumb {
+        ty: "error".into(),
+        category: Some("panic".into()),
+        level: Level::Fatal,
+        message: Some(message_from_panic_info(info).into()),
+        ..Default::default()
+    };
+    if let Some(location) = info.location() {
+        breadcrumb.data.insert(
+            "location".into(),
+            format!(
+                "{}:{}:{}",
+                location.file(),
+                location.line(),
+                location.column()
+            )
+            .into(),
+        );
+    }
+    breadcrumb
+}
+
 type PanicExtractor = dyn Fn(&PanicInfo<'_>) -> Option<Event<'static>> + Send + Sync;
+type PayloadExtractor = dyn Fn(&(dyn Any + Send)) -> Option<PanicPayload> + Send + Sync;
+type PanicFingerprintFn =
+    dyn Fn(&PanicInfo<'_>, &Event<'static>) -> Option<Vec<Cow<'static, str>>> + Send + Sync;
+
+/// The information extracted from a panic payload.
+///
+/// This is returned by the extractors registered with
+/// [`PanicIntegration::add_payload_extractor`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PanicPayload {
+    /// A message and extras, which are added to the default panic event.
+    Message {
+        /// The message of the panic.
+        message: String,
+        /// Additional data attached to the event.
+        extra: Map<String, Value>,
+    },
+    /// A complete event, which is captured as is.
+    Event(Box<Event<'static>>),
+}
+
+impl PanicPayload {
+    /// Creates a payload with the given message and no extras.
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        PanicPayload::Message {
+            message: message.into(),
+            extra: Map::new(),
+        }
+    }
+
+    /// Adds an extra to the event.
+    ///
+    /// This has no effect on payloads that are complete events.
+    pub fn with_extra<K: Into<String>, V: Into<Value>>(mut self, key: K, value: V) -> Self {
+        if let PanicPayload::Message { ref mut extra, .. } = self {
+            extra.insert(key.into(), value.into());
+        }
+        self
+    }
+}
+
+impl From<String> for PanicPayload {
+    fn from(message: String) -> Self {
+        PanicPayload::new(message)
+    }
+}
+
+impl From<&str> for PanicPayload {
+    fn from(message: &str) -> Self {
+        PanicPayload::new(message)
+    }
+}
+
+impl From<Event<'static>> for PanicPayload {
+    fn from(event: Event<'static>) -> Self {
+        PanicPayload::Event(Box::new(event))
+    }
+}
 
 /// The Sentry Panic handler Integration.
 #[derive(Default)]
 pub struct PanicIntegration {
     extractors: Vec<Box<PanicExtractor>>,
+    payload_extractors: Vec<Box<PayloadExtractor>>,
+    fingerprint_fn: Option<Box<PanicFingerprintFn>>,
+    flush_timeout: Option<Duration>,
+    flush_all_threads: bool,
+    defer_symbolication: AtomicBool,
 }
 
 impl std::fmt::Debug for PanicIntegration {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PanicIntegration")
             .field("extractors", &self.extractors.len())
+            .field("payload_extractors", &self.payload_extractors.len())
+            .field("fingerprint_fn", &self.fingerprint_fn.is_some())
+            .field("flush_timeout", &self.flush_timeout)
+            .field("flush_all_threads", &self.flush_all_threads)
             .finish()
     }
 }
@@ -60,8 +372,14 @@ impl Integration for PanicIntegration {
         "panic"
     }
 
-    fn setup(&self, _cfg: &mut ClientOptions) {
+    fn setup(&self, cfg: &mut ClientOptions) {
+        self.defer_symbolication
+            .store(cfg.defer_symbolication, Ordering::Relaxed);
         INIT.call_once(|| {
+            #[cfg(feature = "console-error-panic-hook")]
+            let next: Box<dyn Fn(&PanicInfo<'_>) + Send + Sync> =
+                Box::new(console_error_panic_hook::hook);
+            #[cfg(not(feature = "console-error-panic-hook"))]
             let next = panic::take_hook();
             panic::set_hook(Box::new(move |info| {
                 panic_handler(info);
@@ -71,11 +389,31 @@ impl Integration for PanicIntegration {
     }
 }
 
+/// Builds the default fingerprint of a panic, which adds its location to the default
+/// grouping.
+///
+/// Returns `None` if the location of the panic is unknown.
+pub fn fingerprint_from_location(info: &PanicInfo<'_>) -> Option<Vec<Cow<'static, str>>> {
+    let location = info.location()?;
+    Some(vec![
+        sentry_core::fingerprint::DEFAULT.into(),
+        format!("{}:{}", location.file(), location.line()).into(),
+    ])
+}
+
+fn is_default_fingerprint(fingerprint: &[Cow<'_, str>]) -> bool {
+    fingerprint.len() == 1 && fingerprint[0] == sentry_core::fingerprint::DEFAULT
+}
+
 /// Extract the message of a panic.
 pub fn message_from_panic_info<'a>(info: &'a PanicInfo<'_>) -> &'a str {
-    match info.payload().downcast_ref::<&'static str>() {
+    message_from_payload(info.payload())
+}
+
+fn message_from_payload(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&'static str>() {
         Some(s) => *s,
-        None => match info.payload().downcast_ref::<String>() {
+        None => match payload.downcast_ref::<String>() {
             Some(s) => &s[..],
             None => "Box<Any>",
         },
@@ -97,21 +435,97 @@ impl PanicIntegration {
         self
     }
 
+    /// Registers a new extractor for panic payloads of type `T`.
+    ///
+    /// The extractor is called for panics with a payload of type `T`, and returns either a
+    /// message with additional extras, or a complete event.  Extractors for payload types
+    /// are consulted after the ones registered with [`add_extractor`](Self::add_extractor),
+    /// in the order they were registered.
+    pub fn add_payload_extractor<T, F, R>(mut self, f: F) -> Self
+    where
+        T: Any,
+        F: Fn(&T) -> R + Send + Sync + 'static,
+        R: Into<PanicPayload>,
+    {
+        self.payload_extractors
+            .push(Box::new(move |payload: &(dyn Any + Send)| {
+                payload.downcast_ref::<T>().map(|payload| f(payload).into())
+            }));
+        self
+    }
+
+    /// Registers a function that computes the fingerprint of panic events.
+    ///
+    /// This replaces the default fingerprint, which is built by
+    /// [`fingerprint_from_location`].  The function is only called for events that still
+    /// have the default fingerprint, and returning `None` keeps it.
+    pub fn fingerprint_fn<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&PanicInfo<'_>, &Event<'static>) -> Option<Vec<Cow<'static, str>>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.fingerprint_fn = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the time the panic handler waits for pending events to be sent.
+    ///
+    /// This is independent of the `shutdown_timeout` of the client, and defaults to
+    /// [`DEFAULT_FLUSH_TIMEOUT`], because the panic may terminate the process.
+    pub fn flush_timeout(mut self, timeout: Duration) -> Self {
+        self.flush_timeout = Some(timeout);
+        self
+    }
+
+    /// Flushes the client for uncaught panics on all threads, not only the main thread.
+    ///
+    /// A panic on another thread only ends that thread by default, so the panic handler
+    /// does not block it.  Enable this when building with `panic = "abort"`, where any
+    /// panic terminates the process.
+    pub fn flush_all_threads(mut self, flush_all_threads: bool) -> Self {
+        self.flush_all_threads = flush_all_threads;
+        self
+    }
+
     /// Creates an event from the given panic info.
     ///
     /// The stacktrace is calculated from the current frame.
     pub fn event_from_panic_info(&self, info: &PanicInfo<'_>) -> Event<'static> {
-        for extractor in &self.extractors {
-            if let Some(event) = extractor(info) {
-                return event;
+        let mut event = self
+            .extractors
+            .iter()
+            .find_map(|extractor| extractor(info))
+            .unwrap_or_else(|| self.event_from_payload(info.payload()));
+
+        if is_default_fingerprint(&event.fingerprint) {
+            let fingerprint = match self.fingerprint_fn {
+                Some(ref fingerprint_fn) => fingerprint_fn(info, &event),
+                None => fingerprint_from_location(info),
+            };
+            if let Some(fingerprint) = fingerprint {
+                event.fingerprint = fingerprint.into();
             }
         }
+        event
+    }
+
+    fn event_from_payload(&self, payload: &(dyn Any + Send)) -> Event<'static> {
+        let extracted = self
+            .payload_extractors
+            .iter()
+            .find_map(|extractor| extractor(payload));
+        let (msg, extra) = match extracted {
+            Some(PanicPayload::Event(event)) => return *event,
+            Some(PanicPayload::Message { message, extra }) => (message, extra),
+            None => (message_from_payload(payload).to_string(), Map::new()),
+        };
 
         // TODO: We would ideally want to downcast to `std::error:Error` here
         // and use `event_from_error`, but that way we won‘t get meaningful
         // backtraces yet.
 
-        let msg = message_from_panic_info(info);
         Event {
             exception: vec![Exception {
                 ty: "panic".into(),
@@ -120,13 +534,285 @@ impl PanicIntegration {
                     handled: Some(false),
                     ..Default::default()
                 }),
-                value: Some(msg.to_string()),
-                stacktrace: current_stacktrace(),
+                value: Some(msg),
+                stacktrace: capture_stacktrace(self.defer_symbolication.load(Ordering::Relaxed)),
                 ..Default::default()
             }]
             .into(),
             level: Level::Fatal,
+            extra,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct HttpPanic {
+        status: u16,
+    }
+
+    fn event_from(integration: &PanicIntegration, payload: Box<dyn Any + Send>) -> Event<'static> {
+        integration.event_from_payload(payload.as_ref())
+    }
+
+    #[test]
+    fn test_default_payloads() {
+        let integration = PanicIntegration::new();
+
+        let event = event_from(&integration, Box::new("static message"));
+        assert_eq!(event.exception[0].value.as_deref(), Some("static message"));
+
+        let event = event_from(&integration, Box::new(String::from("owned message")));
+        assert_eq!(event.exception[0].value.as_deref(), Some("owned message"));
+
+        let event = event_from(&integration, Box::new(HttpPanic { status: 500 }));
+        assert_eq!(event.exception[0].value.as_deref(), Some("Box<Any>"));
+    }
+
+    #[test]
+    fn test_payload_extractors() {
+        let integration = PanicIntegration::new()
+            .add_payload_extractor(|payload: &HttpPanic| {
+                PanicPayload::new("request handler panicked").with_extra("status", payload.status)
+            })
+            .add_payload_extractor(|code: &i32| Event {
+                message: Some(format!("exit code {}", code)),
+                ..Default::default()
+            });
+
+        let event = event_from(&integration, Box::new(HttpPanic { status: 503 }));
+        assert_eq!(
+            event.exception[0].value.as_deref(),
+            Some("request handler panicked")
+        );
+        assert_eq!(event.exception[0].ty, "panic");
+        assert_eq!(event.level, Level::Fatal);
+        assert_eq!(event.extra["status"], 503);
+
+        let event = event_from(&integration, Box::new(2i32));
+        assert_eq!(event.message.as_deref(), Some("exit code 2"));
+        assert!(event.exception.is_empty());
+
+        let event = event_from(&integration, Box::new("plain message"));
+        assert_eq!(event.exception[0].value.as_deref(), Some("plain message"));
+    }
+
+    #[test]
+    fn test_location_fingerprint() {
+        fn fail() {
+            panic!("index out of bounds");
+        }
+
+        let events = sentry::test::with_captured_events_options(
+            || {
+                let _ = panic::catch_unwind(fail);
+                let _ = panic::catch_unwind(|| panic!("index out of bounds"));
+            },
+            ClientOptions::new().add_integration(PanicIntegration::new()),
+        );
+
+        assert_eq!(events.len(), 2);
+        let fingerprints: Vec<_> = events
+            .iter()
+            .map(|event| event.fingerprint.to_vec())
+            .collect();
+        for fingerprint in &fingerprints {
+            assert_eq!(fingerprint.len(), 2);
+            assert_eq!(fingerprint[0], sentry_core::fingerprint::DEFAULT);
+            assert!(fingerprint[1].starts_with(file!()));
+        }
+        assert_ne!(fingerprints[0], fingerprints[1]);
+
+        let events = sentry::test::with_captured_events_options(
+            || {
+                let _ = panic::catch_unwind(fail);
+            },
+            ClientOptions::new()
+                .add_integration(PanicIntegration::new().fingerprint_fn(|_, _| None)),
+        );
+        assert_eq!(events[0].fingerprint.len(), 1);
+    }
+
+    #[test]
+    fn test_panic_breadcrumb() {
+        let events = sentry::test::with_captured_events_options(
+            || {
+                let _ = panic::catch_unwind(|| panic!("out of memory"));
+            },
+            ClientOptions::new()
+                .add_integration(PanicIntegration::new().flush_timeout(Duration::from_millis(100))),
+        );
+
+        assert_eq!(events.len(), 1);
+        let breadcrumb = events[0].breadcrumbs.last().unwrap();
+        assert_eq!(breadcrumb.category.as_deref(), Some("panic"));
+        assert_eq!(breadcrumb.level, Level::Fatal);
+        assert_eq!(breadcrumb.message.as_deref(), Some("out of memory"));
+        assert!(breadcrumb.data["location"]
+            .as_str()
+            .unwrap()
+            .starts_with(file!()));
+    }
+
+    #[test]
+    fn test_capture_unwind() {
+        let mut results = Vec::new();
+        let events = sentry::test::with_captured_events_options(
+            || {
+                results.push(capture_unwind(|| 42).ok());
+                let payload = capture_unwind(|| panic!("plugin failed")).unwrap_err();
+                results.push(payload.downcast_ref::<&str>().map(|_| 0));
+            },
+            ClientOptions::new().add_integration(PanicIntegration::new()),
+        );
+        assert_eq!(results, vec![Some(42), Some(0)]);
+
+        let events_without_hook = sentry::test::with_captured_events(|| {
+            assert!(capture_unwind(|| panic!("plugin failed")).is_err());
+        });
+
+        for events in &[events, events_without_hook] {
+            assert_eq!(events.len(), 1);
+            let event = &events[0];
+            assert_eq!(event.level, Level::Error);
+            let exception = &event.exception[0];
+            assert_eq!(exception.value.as_deref(), Some("plugin failed"));
+            let mechanism = exception.mechanism.as_ref().unwrap();
+            assert_eq!(mechanism.ty, "catch_unwind");
+            assert_eq!(mechanism.handled, Some(true));
+        }
+    }
+
+    #[test]
+    fn test_report_unwind() {
+        let handler = || {
+            sentry::capture_message("handling request", Level::Info);
+            panic!("request handler failed");
+        };
+
+        let events = sentry::test::with_captured_events_options(
+            || {
+                let hub = Hub::current();
+                assert!(report_unwind(&hub, handler).is_err());
+            },
+            ClientOptions::new().add_integration(PanicIntegration::new()),
+        );
+        let events_without_hook = sentry::test::with_captured_events(|| {
+            let hub = Hub::current();
+            assert!(report_unwind(&hub, handler).is_err());
+        });
+
+        for events in &[events, events_without_hook] {
+            assert_eq!(events.len(), 2);
+            let event = &events[1];
+            assert_eq!(event.level, Level::Fatal);
+            let exception = &event.exception[0];
+            assert_eq!(exception.value.as_deref(), Some("request handler failed"));
+            let mechanism = exception.mechanism.as_ref().unwrap();
+            assert_eq!(mechanism.ty, "panic");
+            assert_eq!(mechanism.handled, Some(false));
+        }
+    }
+
+    #[test]
+    fn test_caught_panic_keeps_session() {
+        use sentry::protocol::EnvelopeItem;
+
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let hub = Hub::current();
+                let plugin = move || {
+                    sentry::start_session();
+                    assert!(capture_unwind(|| panic!("plugin failed")).is_err());
+                    sentry::capture_message("still running", Level::Error);
+                    sentry::end_session();
+                };
+                thread::Builder::new()
+                    .name("main".into())
+                    .spawn(move || Hub::run(hub, plugin))
+                    .unwrap()
+                    .join()
+                    .unwrap();
+            },
+            ClientOptions {
+                release: Some("some-release".into()),
+                ..Default::default()
+            }
+            .add_integration(PanicIntegration::new()),
+        );
+
+        let session = envelopes
+            .iter()
+            .flat_map(|envelope| envelope.items())
+            .filter_map(|item| match item {
+                EnvelopeItem::SessionUpdate(session) => Some(session),
+                _ => None,
+            })
+            .last()
+            .unwrap();
+        assert_eq!(session.status, SessionStatus::Exited);
+        assert_eq!(session.errors, 2);
+    }
+
+    #[derive(Default)]
+    struct FlushCounter {
+        flushes: std::sync::atomic::AtomicUsize,
+    }
+
+    impl sentry::Transport for FlushCounter {
+        fn send_envelope(&self, _envelope: sentry::Envelope) {}
+
+        fn flush(&self, _timeout: Duration) -> bool {
+            self.flushes.fetch_add(1, Ordering::SeqCst);
+            true
+        }
+    }
+
+    fn count_flushes<F>(integration: PanicIntegration, thread_name: &str, f: F) -> usize
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let transport = std::sync::Arc::new(FlushCounter::default());
+        let options = ClientOptions {
+            dsn: "https://public@example.com/1".parse().ok(),
+            transport: Some(std::sync::Arc::new(transport.clone())),
             ..Default::default()
         }
+        .add_integration(integration.flush_timeout(Duration::from_millis(10)));
+        let hub = std::sync::Arc::new(Hub::new(
+            Some(std::sync::Arc::new(options.into())),
+            Default::default(),
+        ));
+        thread::Builder::new()
+            .name(thread_name.into())
+            .spawn(move || Hub::run(hub, f))
+            .unwrap()
+            .join()
+            .unwrap();
+        transport.flushes.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_flush_uncaught_panics() {
+        let caught = || {
+            let hub = Hub::current();
+            assert!(report_unwind(&hub, || panic!("request handler failed")).is_err());
+            assert!(capture_unwind(|| panic!("plugin failed")).is_err());
+        };
+        let uncaught = || {
+            let _ = panic::catch_unwind(|| panic!("out of memory"));
+        };
+
+        assert_eq!(count_flushes(PanicIntegration::new(), "main", caught), 0);
+        assert_eq!(count_flushes(PanicIntegration::new(), "main", uncaught), 1);
+        assert_eq!(
+            count_flushes(PanicIntegration::new(), "worker", uncaught),
+            0
+        );
+        let integration = PanicIntegration::new().flush_all_threads(true);
+        assert_eq!(count_flushes(integration, "worker", uncaught), 1);
     }
 }
@@ -0,0 +1,278 @@
+//! Adds support for recording [`redis`](https://docs.rs/redis) commands as breadcrumbs.
+//!
+//! The [`SentryConnection`] wraps any connection implementing [`redis::ConnectionLike`] and
+//! records a `db.redis` breadcrumb for every command or pipeline sent through it.  The
+//! breadcrumb contains the name of the command, the number of arguments it was called with
+//! and how long it took.  Keys and values are never recorded, as they commonly contain
+//! personal data.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use redis::Commands;
+//! use sentry_redis::SentryConnection;
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/").unwrap();
+//! let mut con = SentryConnection::new(client.get_connection().unwrap());
+//!
+//! let _: () = con.set("my_key", 42).unwrap();
+//! ```
+//!
+//! With the `aio` feature, the [`SentryConnection`] also implements
+//! `redis::aio::ConnectionLike` when wrapping an async connection.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::time::{Duration, Instant};
+
+use redis::{ConnectionLike, RedisResult, Value};
+use sentry_core::{Breadcrumb, Level};
+
+/// A redis connection that records breadcrumbs for all commands.
+#[derive(Debug)]
+pub struct SentryConnection<C> {
+    inner: C,
+}
+
+impl<C> SentryConnection<C> {
+    /// Wraps a connection.
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    /// Returns a reference to the wrapped connection.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped connection.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Unwraps the connection.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C: ConnectionLike> ConnectionLike for SentryConnection<C> {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> RedisResult<Value> {
+        let start = Instant::now();
+        let result = self.inner.req_packed_command(cmd);
+        let error = result.as_ref().err().map(ToString::to_string);
+        sentry_core::add_breadcrumb(|| breadcrumb_from_packed(cmd, false, start.elapsed(), error));
+        result
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        let start = Instant::now();
+        let result = self.inner.req_packed_commands(cmd, offset, count);
+        let error = result.as_ref().err().map(ToString::to_string);
+        sentry_core::add_breadcrumb(|| breadcrumb_from_packed(cmd, true, start.elapsed(), error));
+        result
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.get_db()
+    }
+
+    fn supports_pipelining(&self) -> bool {
+        self.inner.supports_pipelining()
+    }
+
+    fn check_connection(&mut self) -> bool {
+        self.inner.check_connection()
+    }
+
+    fn is_open(&self) -> bool {
+        self.inner.is_open()
+    }
+}
+
+#[cfg(feature = "aio")]
+impl<C> redis::aio::ConnectionLike for SentryConnection<C>
+where
+    C: redis::aio::ConnectionLike + Send,
+{
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a redis::Cmd) -> redis::RedisFuture<'a, Value> {
+        let hub = sentry_core::Hub::current();
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = self.inner.req_packed_command(cmd).await;
+            let error = result.as_ref().err().map(ToString::to_string);
+            hub.add_breadcrumb(|| {
+                breadcrumb_from_packed(&cmd.get_packed_command(), false, start.elapsed(), error)
+            });
+            result
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a redis::Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisFuture<'a, Vec<Value>> {
+        let hub = sentry_core::Hub::current();
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = self.inner.req_packed_commands(cmd, offset, count).await;
+            let error = result.as_ref().err().map(ToString::to_string);
+            hub.add_breadcrumb(|| {
+                breadcrumb_from_packed(&cmd.get_packed_pipeline(), true, start.elapsed(), error)
+            });
+            result
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.get_db()
+    }
+}
+
+/// Creates a `db.redis` [`Breadcrumb`] from packed commands
+fn breadcrumb_from_packed(
+    packed: &[u8],
+    pipeline: bool,
+    elapsed: Duration,
+    error: Option<String>,
+) -> Breadcrumb {
+    let commands = parse_packed_commands(packed);
+
+    let mut breadcrumb = if pipeline {
+        let mut breadcrumb = Breadcrumb::query("PIPELINE", elapsed);
+        breadcrumb.data.insert(
+            "commands".into(),
+            commands.iter().map(|(name, _)| name.as_str()).collect(),
+        );
+        breadcrumb
+    } else {
+        let (name, args) = commands.into_iter().next().unwrap_or_default();
+        let mut breadcrumb = Breadcrumb::query(name, elapsed);
+        breadcrumb.data.insert("args".into(), args.into());
+        breadcrumb
+    };
+    breadcrumb.category = Some("db.redis".into());
+    if let Some(error) = error {
+        breadcrumb.data.insert("error".into(), error.into());
+        breadcrumb.level = Level::Error;
+    }
+    breadcrumb
+}
+
+/// Parses the command names and their number of arguments from packed commands.
+///
+/// Commands are packed as arrays of bulk strings, where the first element is the command.
+fn parse_packed_commands(mut packed: &[u8]) -> Vec<(String, u64)> {
+    fn read_line<'a>(packed: &mut &'a [u8]) -> Option<&'a [u8]> {
+        let pos = packed.windows(2).position(|w| w == b"\r\n")?;
+        let line = &packed[..pos];
+        *packed = &packed[pos + 2..];
+        Some(line)
+    }
+
+    fn read_len(line: &[u8], prefix: u8) -> Option<usize> {
+        match line.split_first() {
+            Some((&first, rest)) if first == prefix => std::str::from_utf8(rest).ok()?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    let mut commands = Vec::new();
+    while let Some(count) = read_line(&mut packed).and_then(|line| read_len(line, b'*')) {
+        let mut name = None;
+        for _ in 0..count {
+            let len = match read_line(&mut packed).and_then(|line| read_len(line, b'$')) {
+                Some(len) if len + 2 <= packed.len() => len,
+                _ => return commands,
+            };
+            if name.is_none() {
+                name = Some(String::from_utf8_lossy(&packed[..len]).to_uppercase());
+            }
+            packed = &packed[len + 2..];
+        }
+        if let Some(name) = name {
+            commands.push((name, count as u64 - 1));
+        }
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::RedisError;
+    use sentry_core::protocol::Value as DataValue;
+
+    use super::*;
+
+    struct FakeConnection;
+
+    impl ConnectionLike for FakeConnection {
+        fn req_packed_command(&mut self, _cmd: &[u8]) -> RedisResult<Value> {
+            Ok(Value::Okay)
+        }
+
+        fn req_packed_commands(
+            &mut self,
+            _cmd: &[u8],
+            _offset: usize,
+            count: usize,
+        ) -> RedisResult<Vec<Value>> {
+            Ok(vec![Value::Okay; count])
+        }
+
+        fn get_db(&self) -> i64 {
+            0
+        }
+
+        fn check_connection(&mut self) -> bool {
+            true
+        }
+
+        fn is_open(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_command_breadcrumbs() {
+        let events = sentry::test::with_captured_events(|| {
+            let mut con = SentryConnection::new(FakeConnection);
+
+            let result: Result<(), RedisError> = redis::cmd("set")
+                .arg("secret_key")
+                .arg("secret_value")
+                .query(&mut con);
+            result.unwrap();
+
+            let result: Result<(), RedisError> =
+                redis::pipe().get("a").get("b").incr("c", 1).query(&mut con);
+            result.unwrap();
+
+            sentry::capture_message("Message", Level::Warning);
+        });
+
+        assert_eq!(events.len(), 1);
+        let breadcrumbs = &events[0].breadcrumbs;
+        assert_eq!(breadcrumbs.len(), 2);
+        assert_eq!(breadcrumbs[0].category, Some("db.redis".into()));
+        assert_eq!(breadcrumbs[0].message, Some("SET".into()));
+        assert_eq!(breadcrumbs[0].data["args"], DataValue::from(2));
+        assert_eq!(breadcrumbs[1].message, Some("PIPELINE".into()));
+        assert_eq!(
+            breadcrumbs[1].data["commands"],
+            DataValue::from(vec!["GET", "GET", "INCRBY"])
+        );
+
+        let serialized = serde_json::to_string(&events[0]).unwrap();
+        assert!(!serialized.contains("secret"));
+    }
+}
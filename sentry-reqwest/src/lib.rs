@@ -0,0 +1,331 @@
+//! Adds Sentry support for outgoing requests made with
+//! [`reqwest`](https://docs.rs/reqwest), using
+//! [`reqwest-middleware`](https://docs.rs/reqwest-middleware).
+//!
+//! The [`SentryMiddleware`] records an `http` breadcrumb for every request, containing the
+//! method, URL, status code and duration of the request.  The query string is only included
+//! in the URL if `send_default_pii` is enabled, as it may contain personal data.
+//!
+//! If the current scope has a span, for example the transaction of a server integration, an
+//! `http.client` child span is recorded for every request.  The trace of that span, or the
+//! trace context of the scope if there is no span, is propagated to the called service using
+//! the `sentry-trace` and `baggage` headers.
+//!
+//! # Example
+//!
+//! ```
+//! use reqwest_middleware::ClientBuilder;
+//! use sentry_reqwest::SentryMiddleware;
+//!
+//! let client = ClientBuilder::new(reqwest::Client::new())
+//!     .with(SentryMiddleware::new())
+//!     .build();
+//! ```
+//!
+//! Trace propagation can be limited to specific hosts, so that trace information is not
+//! sent to third parties:
+//!
+//! ```
+//! use sentry_reqwest::SentryMiddleware;
+//!
+//! let middleware = SentryMiddleware::new().propagate_traces_to(&["api.example.com"]);
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::time::Instant;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderValue;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next, Result};
+use sentry_core::protocol::{Breadcrumb, Context, Level, SpanStatus};
+use sentry_core::types::{SentryTrace, BAGGAGE_HEADER, SENTRY_TRACE_HEADER};
+use sentry_core::Hub;
+use task_local_extensions::Extensions;
+
+/// Middleware that records breadcrumbs for outgoing requests and propagates traces.
+#[derive(Clone, Debug)]
+pub struct SentryMiddleware {
+    breadcrumbs: bool,
+    trace_propagation_targets: Option<Vec<String>>,
+}
+
+impl SentryMiddleware {
+    /// Creates a new middleware that records breadcrumbs and propagates traces to all hosts.
+    pub fn new() -> Self {
+        Self {
+            breadcrumbs: true,
+            trace_propagation_targets: None,
+        }
+    }
+
+    /// Enables or disables recording of breadcrumbs.
+    pub fn breadcrumbs(mut self, val: bool) -> Self {
+        self.breadcrumbs = val;
+        self
+    }
+
+    /// Only propagates traces to the given hosts.
+    ///
+    /// By default, the trace is propagated with every request.
+    pub fn propagate_traces_to(mut self, hosts: &[&str]) -> Self {
+        self.trace_propagation_targets = Some(hosts.iter().map(|&host| host.into()).collect());
+        self
+    }
+
+    fn should_propagate(&self, request: &Request) -> bool {
+        match (&self.trace_propagation_targets, request.url().host_str()) {
+            (None, _) => true,
+            (Some(targets), Some(host)) => targets.iter().any(|target| target == host),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+impl Default for SentryMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Middleware for SentryMiddleware {
+    async fn handle(
+        &self,
+        mut request: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let hub = Hub::current();
+        let with_pii = hub
+            .client()
+            .map_or(false, |client| client.options().send_default_pii);
+
+        let method = request.method().to_string();
+        let mut url = request.url().clone();
+        if !with_pii {
+            url.set_query(None);
+        }
+        let url = url.to_string();
+
+        let (span, trace, baggage) = hub.configure_scope(|scope| {
+            let span = scope
+                .get_span()
+                .map(|parent| parent.start_child("http.client", &format!("{} {}", method, url)));
+            let trace = match (&span, scope.get_context("trace")) {
+                (Some(span), _) => Some(span.sentry_trace()),
+                (None, Some(Context::Trace(trace_context))) => {
+                    Some(SentryTrace::from_trace_context(trace_context))
+                }
+                _ => None,
+            };
+            let baggage = scope.get_baggage().map(ToOwned::to_owned);
+            (span, trace, baggage)
+        });
+        if let Some(ref span) = span {
+            span.set_data("method", method.clone().into());
+            span.set_data("url", url.clone().into());
+        }
+
+        if self.should_propagate(&request) {
+            let headers = request.headers_mut();
+            if let Some(trace) = trace {
+                if let Ok(value) = HeaderValue::from_str(&trace.to_string()) {
+                    headers.insert(SENTRY_TRACE_HEADER, value);
+                }
+                if let Some(Ok(value)) = baggage.map(|baggage| HeaderValue::from_str(&baggage)) {
+                    headers.insert(BAGGAGE_HEADER, value);
+                }
+            }
+        }
+
+        let start = Instant::now();
+        let result = next.run(request, extensions).await;
+
+        let status = result.as_ref().ok().map(Response::status);
+        if let Some(span) = span {
+            match status {
+                Some(status) => {
+                    span.set_data("status_code", status.as_u16().into());
+                    span.set_status(SpanStatus::from_http_status(status.as_u16()).as_str());
+                }
+                None => span.set_status(SpanStatus::InternalError.as_str()),
+            }
+            span.finish();
+        }
+
+        if self.breadcrumbs {
+            let duration = start.elapsed();
+            let mut breadcrumb = Breadcrumb::http(method, url, status.map(|s| s.as_u16()));
+            breadcrumb
+                .data
+                .insert("duration_ms".into(), (duration.as_millis() as u64).into());
+            if let Some(reason) = status.and_then(|status| status.canonical_reason()) {
+                breadcrumb.data.insert("reason".into(), reason.into());
+            }
+            if let Err(err) = &result {
+                breadcrumb
+                    .data
+                    .insert("error".into(), err.to_string().into());
+                breadcrumb.level = Level::Error;
+            }
+            hub.add_breadcrumb(breadcrumb);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::header::HeaderMap;
+    use reqwest_middleware::{ClientBuilder, Error};
+    use sentry_core::protocol::{SpanId, TraceContext, Value};
+    use sentry_core::types::Uuid;
+
+    use super::*;
+
+    /// Records the headers of the request instead of sending it.
+    struct RecordHeaders(Arc<Mutex<Option<HeaderMap>>>);
+
+    #[async_trait]
+    impl Middleware for RecordHeaders {
+        async fn handle(
+            &self,
+            request: Request,
+            _extensions: &mut Extensions,
+            _next: Next<'_>,
+        ) -> Result<Response> {
+            *self.0.lock().unwrap() = Some(request.headers().clone());
+            Err(Error::Middleware(anyhow::anyhow!("not sent")))
+        }
+    }
+
+    fn send(middleware: SentryMiddleware, url: &str) -> HeaderMap {
+        let headers = Arc::new(Mutex::new(None));
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(middleware)
+            .with(RecordHeaders(headers.clone()))
+            .build();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        assert!(runtime.block_on(client.get(url).send()).is_err());
+
+        let headers = headers.lock().unwrap().take();
+        headers.unwrap()
+    }
+
+    #[test]
+    fn test_breadcrumbs_and_propagation() {
+        let trace_context = TraceContext {
+            span_id: SpanId::default(),
+            trace_id: Uuid::new_v4(),
+            ..Default::default()
+        };
+
+        let mut headers = Vec::new();
+        let events = sentry::test::with_captured_events(|| {
+            sentry::configure_scope(|scope| {
+                scope.set_context("trace", trace_context.clone());
+                scope.set_baggage(Some("sentry-release=1.0"));
+            });
+
+            headers.push(send(
+                SentryMiddleware::new(),
+                "http://api.example.com/users?token=secret",
+            ));
+            headers.push(send(
+                SentryMiddleware::new().propagate_traces_to(&["api.example.com"]),
+                "http://example.org/",
+            ));
+
+            sentry::capture_message("Message", Level::Warning);
+        });
+
+        let trace = SentryTrace::from_trace_context(&trace_context).to_string();
+        assert_eq!(headers[0].get(SENTRY_TRACE_HEADER).unwrap(), &trace);
+        assert_eq!(
+            headers[0].get(BAGGAGE_HEADER).unwrap(),
+            "sentry-release=1.0"
+        );
+        assert!(headers[1].get(SENTRY_TRACE_HEADER).is_none());
+
+        assert_eq!(events.len(), 1);
+        let breadcrumbs = &events[0].breadcrumbs;
+        assert_eq!(breadcrumbs.len(), 2);
+        assert_eq!(breadcrumbs[0].ty, "http");
+        assert_eq!(breadcrumbs[0].level, Level::Error);
+        assert_eq!(
+            breadcrumbs[0].data["url"],
+            Value::from("http://api.example.com/users")
+        );
+        assert_eq!(breadcrumbs[0].data["method"], Value::from("GET"));
+    }
+
+    #[test]
+    fn test_url_with_pii() {
+        let events = sentry::test::with_captured_events_options(
+            || {
+                send(SentryMiddleware::new(), "http://example.com/?token=secret");
+                sentry::capture_message("Message", Level::Warning);
+            },
+            sentry::ClientOptions {
+                send_default_pii: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            events[0].breadcrumbs[0].data["url"],
+            Value::from("http://example.com/?token=secret")
+        );
+    }
+
+    #[test]
+    fn test_client_span() {
+        let mut headers = None;
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let transaction =
+                    sentry::start_transaction(sentry::TransactionContext::new("test", "test"));
+                sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+
+                headers = Some(send(
+                    SentryMiddleware::new(),
+                    "http://example.com/users?token=secret",
+                ));
+
+                sentry::configure_scope(|scope| scope.set_span(None));
+                transaction.finish();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(envelopes.len(), 1);
+        let transaction = match envelopes[0].items().next().unwrap() {
+            sentry::protocol::EnvelopeItem::Transaction(transaction) => transaction,
+            item => panic!("unexpected envelope item {:?}", item),
+        };
+        assert_eq!(transaction.spans.len(), 1);
+        let span = &transaction.spans[0];
+        assert_eq!(span.op.as_deref(), Some("http.client"));
+        assert_eq!(
+            span.description.as_deref(),
+            Some("GET http://example.com/users")
+        );
+        assert_eq!(span.status.as_deref(), Some("internal_error"));
+
+        let trace = format!("{}-{}-1", span.trace_id.to_simple_ref(), span.span_id);
+        assert_eq!(headers.unwrap().get(SENTRY_TRACE_HEADER).unwrap(), &trace);
+    }
+}
@@ -0,0 +1,49 @@
+//! Adds support for scrubbing sensitive data from events before they are sent.
+//!
+//! The [`ScrubbingProcessor`] is an [`Integration`](sentry_core::Integration) that runs
+//! over the extras, breadcrumbs, request data and contexts of every event, and replaces
+//! sensitive values with `[Filtered]`.
+//!
+//! Values are scrubbed in two ways:
+//!
+//! * Fields with a name containing one of the denied words, such as `password`, `token`,
+//!   `secret` or `authorization`, are replaced entirely.  Names are compared
+//!   case-insensitively, so `DB_PASSWORD` or `X-Auth-Token` are scrubbed as well.
+//! * Within all other strings, matches of the rules are replaced.  By default, this
+//!   scrubs credit card numbers, email addresses and bearer tokens.
+//!
+//! # Examples
+//!
+//! ```
+//! use sentry_scrubbing::ScrubbingProcessor;
+//!
+//! let scrubbing = ScrubbingProcessor::new()
+//!     .deny_field("session_id")
+//!     .add_rule(regex::Regex::new(r"\bsk_live_[0-9a-zA-Z]+").unwrap());
+//!
+//! let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(scrubbing));
+//! ```
+//!
+//! # Transaction Names
+//!
+//! The [`TransactionNameNormalizer`] replaces ids, UUIDs and hashes in the names of
+//! transactions with placeholders, so that `GET /users/42` and `GET /users/43` are grouped
+//! as `GET /users/{id}`:
+//!
+//! ```
+//! use sentry_scrubbing::TransactionNameNormalizer;
+//!
+//! let _sentry = sentry::init(
+//!     sentry::ClientOptions::new().add_integration(TransactionNameNormalizer::new()),
+//! );
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+mod processor;
+mod transaction;
+
+pub use processor::*;
+pub use transaction::*;
@@ -0,0 +1,325 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+use sentry_core::protocol::{Breadcrumb, Context, Event, Map, Request, Value};
+use sentry_core::{ClientOptions, Integration};
+
+/// The value sensitive data is replaced with.
+pub const FILTERED: &str = "[Filtered]";
+
+/// The field names that are denied by default.
+pub const DEFAULT_DENYLIST: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "authorization",
+    "api_key",
+    "apikey",
+];
+
+lazy_static::lazy_static! {
+    static ref CREDIT_CARD: Regex = Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap();
+    static ref EMAIL: Regex =
+        Regex::new(r"\b[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}\b").unwrap();
+    static ref BEARER_TOKEN: Regex = Regex::new(r"(?i)\bbearer\s+[a-z0-9._~+/-]+=*").unwrap();
+}
+
+/// Scrubs sensitive data from events.
+///
+/// See the [crate level documentation](index.html) for details.
+#[derive(Debug, Clone)]
+pub struct ScrubbingProcessor {
+    denylist: Vec<String>,
+    rules: Vec<Regex>,
+}
+
+impl Default for ScrubbingProcessor {
+    fn default() -> Self {
+        Self {
+            denylist: DEFAULT_DENYLIST.iter().map(|s| s.to_string()).collect(),
+            rules: vec![CREDIT_CARD.clone(), EMAIL.clone(), BEARER_TOKEN.clone()],
+        }
+    }
+}
+
+impl ScrubbingProcessor {
+    /// Creates a new processor with the default denylist and rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new processor without any denied fields or rules.
+    pub fn empty() -> Self {
+        Self {
+            denylist: vec![],
+            rules: vec![],
+        }
+    }
+
+    /// Denies all fields with a name containing `field`.
+    pub fn deny_field<S: Into<String>>(mut self, field: S) -> Self {
+        self.denylist.push(field.into().to_lowercase());
+        self
+    }
+
+    /// Replaces all matches of `rule` within string values.
+    pub fn add_rule(mut self, rule: Regex) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Scrubs the extras, breadcrumbs, request and contexts of the `event`.
+    pub fn scrub_event(&self, mut event: Event<'static>) -> Event<'static> {
+        self.scrub_map(&mut event.extra);
+        for breadcrumb in event.breadcrumbs.iter_mut() {
+            self.scrub_breadcrumb(breadcrumb);
+        }
+        if let Some(request) = event.request.as_mut() {
+            self.scrub_request(request);
+        }
+        for (key, context) in event.contexts.iter_mut() {
+            if let Context::Other(map) = context {
+                if self.is_denied(key) {
+                    map.values_mut().for_each(filter);
+                } else {
+                    self.scrub_map(map);
+                }
+            }
+        }
+        event
+    }
+
+    /// Scrubs the message and data of a `breadcrumb`.
+    pub fn scrub_breadcrumb(&self, breadcrumb: &mut Breadcrumb) {
+        if let Some(message) = breadcrumb.message.as_mut() {
+            self.scrub_string(message);
+        }
+        self.scrub_map(&mut breadcrumb.data);
+    }
+
+    fn scrub_request(&self, request: &mut Request) {
+        if let Some(url) = request.url.as_mut() {
+            if let Some(query) = url.query() {
+                let query = self.scrub_pairs(query, '&');
+                url.set_query(Some(&query));
+            }
+        }
+        if let Some(query) = request.query_string.as_mut() {
+            *query = self.scrub_pairs(query, '&');
+        }
+        if let Some(cookies) = request.cookies.as_mut() {
+            *cookies = self.scrub_pairs(cookies, ';');
+        }
+        if let Some(data) = request.data.as_mut() {
+            match serde_json::from_str::<Value>(data) {
+                Ok(mut value) => {
+                    self.scrub_value(&mut value);
+                    *data = value.to_string();
+                }
+                Err(_) => self.scrub_string(data),
+            }
+        }
+        for (key, value) in request.headers.iter_mut() {
+            if self.is_denied(key) || key.eq_ignore_ascii_case("cookie") {
+                *value = FILTERED.into();
+            } else {
+                self.scrub_string(value);
+            }
+        }
+        for (key, value) in request.env.iter_mut() {
+            if self.is_denied(key) {
+                *value = FILTERED.into();
+            }
+        }
+    }
+
+    fn scrub_map(&self, map: &mut Map<String, Value>) {
+        for (key, value) in map.iter_mut() {
+            if self.is_denied(key) {
+                filter(value);
+            } else {
+                self.scrub_value(value);
+            }
+        }
+    }
+
+    fn scrub_value(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => self.scrub_string(s),
+            Value::Array(values) => values.iter_mut().for_each(|v| self.scrub_value(v)),
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self.is_denied(key) {
+                        filter(value);
+                    } else {
+                        self.scrub_value(value);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn scrub_string(&self, s: &mut String) {
+        for rule in &self.rules {
+            if let Cow::Owned(scrubbed) = rule.replace_all(s, FILTERED) {
+                *s = scrubbed;
+            }
+        }
+    }
+
+    /// Scrubs a list of `key=value` pairs, such as a query string or cookies.
+    fn scrub_pairs(&self, pairs: &str, separator: char) -> String {
+        let pairs: Vec<_> = pairs
+            .split(separator)
+            .map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                let key = parts.next().unwrap_or_default();
+                match parts.next() {
+                    Some(_) if self.is_denied(key.trim()) => format!("{}={}", key, FILTERED),
+                    Some(value) => {
+                        let mut value = value.to_owned();
+                        self.scrub_string(&mut value);
+                        format!("{}={}", key, value)
+                    }
+                    None => pair.to_owned(),
+                }
+            })
+            .collect();
+        pairs.join(&separator.to_string())
+    }
+
+    fn is_denied(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        self.denylist
+            .iter()
+            .any(|denied| key.contains(denied.as_str()))
+    }
+}
+
+fn filter(value: &mut Value) {
+    if !value.is_null() {
+        *value = FILTERED.into();
+    }
+}
+
+impl Integration for ScrubbingProcessor {
+    fn name(&self) -> &'static str {
+        "scrubbing"
+    }
+
+    fn process_event(
+        &self,
+        event: Event<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        Some(self.scrub_event(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_extra() {
+        let mut event = Event::default();
+        event.extra.insert("DB_PASSWORD".into(), "hunter2".into());
+        event.extra.insert("count".into(), 42.into());
+        event.extra.insert(
+            "nested".into(),
+            serde_json::json!({ "auth": { "api_key": "abc" }, "user": "jane@example.com" }),
+        );
+
+        let event = ScrubbingProcessor::new().scrub_event(event);
+        assert_eq!(event.extra["DB_PASSWORD"], FILTERED);
+        assert_eq!(event.extra["count"], 42);
+        assert_eq!(
+            event.extra["nested"],
+            serde_json::json!({ "auth": { "api_key": FILTERED }, "user": FILTERED })
+        );
+    }
+
+    #[test]
+    fn test_scrub_strings() {
+        let processor = ScrubbingProcessor::new();
+        let mut s = "paid with 4111 1111 1111 1111 using Bearer abc.def-123".to_owned();
+        processor.scrub_string(&mut s);
+        assert_eq!(s, "paid with [Filtered] using [Filtered]");
+
+        let mut s = "order 12345 shipped".to_owned();
+        processor.scrub_string(&mut s);
+        assert_eq!(s, "order 12345 shipped");
+    }
+
+    #[test]
+    fn test_scrub_breadcrumb() {
+        let mut breadcrumb = Breadcrumb {
+            message: Some("login by jane@example.com".into()),
+            ..Default::default()
+        };
+        breadcrumb
+            .data
+            .insert("secret_answer".into(), "blue".into());
+
+        ScrubbingProcessor::new().scrub_breadcrumb(&mut breadcrumb);
+        assert_eq!(breadcrumb.message.unwrap(), "login by [Filtered]");
+        assert_eq!(breadcrumb.data["secret_answer"], FILTERED);
+    }
+
+    #[test]
+    fn test_scrub_request() {
+        let mut request = Request {
+            url: "https://example.com/login?user=jane&token=abc".parse().ok(),
+            query_string: Some("user=jane&token=abc".into()),
+            cookies: Some("theme=dark; session_token=abc".into()),
+            data: Some(r#"{"user":"jane","password":"hunter2"}"#.into()),
+            ..Default::default()
+        };
+        request
+            .headers
+            .insert("Authorization".into(), "Bearer abc".into());
+        request.headers.insert("Accept".into(), "*/*".into());
+        let event = Event {
+            request: Some(request),
+            ..Default::default()
+        };
+
+        let request = ScrubbingProcessor::new()
+            .scrub_event(event)
+            .request
+            .unwrap();
+        assert_eq!(
+            request.url.unwrap().as_str(),
+            "https://example.com/login?user=jane&token=[Filtered]"
+        );
+        assert_eq!(request.query_string.unwrap(), "user=jane&token=[Filtered]");
+        assert_eq!(
+            request.cookies.unwrap(),
+            "theme=dark; session_token=[Filtered]"
+        );
+        assert_eq!(
+            request.data.unwrap(),
+            r#"{"password":"[Filtered]","user":"jane"}"#
+        );
+        assert_eq!(request.headers["Authorization"], FILTERED);
+        assert_eq!(request.headers["Accept"], "*/*");
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let mut event = Event::default();
+        event.extra.insert("session_id".into(), "abc".into());
+        event
+            .extra
+            .insert("key".into(), "sk_live_123 and jane@example.com".into());
+
+        let event = ScrubbingProcessor::empty()
+            .deny_field("Session")
+            .add_rule(Regex::new(r"\bsk_live_[0-9a-zA-Z]+").unwrap())
+            .scrub_event(event);
+        assert_eq!(event.extra["session_id"], FILTERED);
+        assert_eq!(event.extra["key"], "[Filtered] and jane@example.com");
+    }
+}
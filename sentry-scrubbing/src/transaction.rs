@@ -0,0 +1,203 @@
+use regex::Regex;
+use sentry_core::protocol::{Event, Transaction};
+use sentry_core::{ClientOptions, Integration};
+
+/// The HTTP methods that are recognized at the start of transaction names.
+const METHODS: &[&str] = &[
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+lazy_static::lazy_static! {
+    static ref UUID: Regex = Regex::new(
+        r"^(?i)[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$"
+    ).unwrap();
+    static ref HASH: Regex = Regex::new(r"^(?i)[0-9a-f]{16,}$").unwrap();
+    static ref ID: Regex = Regex::new(r"^\d+$").unwrap();
+}
+
+/// Normalizes the names of transactions before they are sent.
+///
+/// Transactions are often named after the path of a request, such as `GET /users/42`.  Ids
+/// in the path produce one transaction per user in the performance data, so that they
+/// cannot be grouped.  This integration replaces every path segment that matches one of
+/// its rules with a placeholder, which turns the name into `GET /users/{id}`.
+///
+/// By default, UUIDs are replaced with `{uuid}`, numbers with `{id}` and hexadecimal
+/// strings of at least 16 characters with `{hash}`.  The `transaction` of events is
+/// normalized as well, so that errors are associated with the same transaction.
+///
+/// # Examples
+///
+/// ```
+/// use sentry_scrubbing::TransactionNameNormalizer;
+///
+/// let normalizer = TransactionNameNormalizer::new()
+///     .add_rule(regex::Regex::new(r"^[a-z]+-[a-z]+-\d+$").unwrap(), "{slug}")
+///     .lowercase_methods(true);
+///
+/// assert_eq!(
+///     normalizer.normalize("GET /posts/hello-world-7/comments/12"),
+///     "get /posts/{slug}/comments/{id}"
+/// );
+///
+/// let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(normalizer));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TransactionNameNormalizer {
+    rules: Vec<(Regex, String)>,
+    lowercase_methods: bool,
+}
+
+impl Default for TransactionNameNormalizer {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                (UUID.clone(), "{uuid}".into()),
+                (ID.clone(), "{id}".into()),
+                (HASH.clone(), "{hash}".into()),
+            ],
+            lowercase_methods: false,
+        }
+    }
+}
+
+impl TransactionNameNormalizer {
+    /// Creates a new normalizer with the default rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new normalizer without any rules.
+    pub fn empty() -> Self {
+        Self {
+            rules: vec![],
+            lowercase_methods: false,
+        }
+    }
+
+    /// Replaces path segments matching `rule` with `replacement`.
+    ///
+    /// Rules are matched against single path segments, and are tried in the order they were
+    /// added.  Segments that are not matched by any rule are kept.
+    pub fn add_rule<S: Into<String>>(mut self, rule: Regex, replacement: S) -> Self {
+        self.rules.push((rule, replacement.into()));
+        self
+    }
+
+    /// Lowercases the HTTP method at the start of names like `GET /users`.
+    pub fn lowercase_methods(mut self, val: bool) -> Self {
+        self.lowercase_methods = val;
+        self
+    }
+
+    /// Returns the normalized form of the transaction `name`.
+    pub fn normalize(&self, name: &str) -> String {
+        let (method, path) = match name.find(' ') {
+            Some(index) if is_method(&name[..index]) => (Some(&name[..index]), &name[index + 1..]),
+            _ => (None, name),
+        };
+
+        let segments: Vec<_> = path
+            .split('/')
+            .map(|segment| {
+                self.rules
+                    .iter()
+                    .find(|(rule, _)| !segment.is_empty() && rule.is_match(segment))
+                    .map_or(segment, |(_, replacement)| replacement.as_str())
+            })
+            .collect();
+        let path = segments.join("/");
+
+        match method {
+            Some(method) if self.lowercase_methods => {
+                format!("{} {}", method.to_ascii_lowercase(), path)
+            }
+            Some(method) => format!("{} {}", method, path),
+            None => path,
+        }
+    }
+}
+
+fn is_method(s: &str) -> bool {
+    METHODS.iter().any(|method| method.eq_ignore_ascii_case(s))
+}
+
+impl Integration for TransactionNameNormalizer {
+    fn name(&self) -> &'static str {
+        "transaction-names"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        if let Some(name) = event.transaction.as_mut() {
+            *name = self.normalize(name);
+        }
+        Some(event)
+    }
+
+    fn process_transaction(
+        &self,
+        mut transaction: Transaction<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Transaction<'static>> {
+        if let Some(name) = transaction.name.as_mut() {
+            *name = self.normalize(name);
+        }
+        Some(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_rules() {
+        let normalizer = TransactionNameNormalizer::new();
+        assert_eq!(
+            normalizer.normalize("GET /users/42/orders/e4b3c9a6-0f3c-4a57-9c2e-2a1f0c7d9b11"),
+            "GET /users/{id}/orders/{uuid}"
+        );
+        assert_eq!(
+            normalizer.normalize("/files/9f86d081884c7d659a2feaa0c55ad015/raw"),
+            "/files/{hash}/raw"
+        );
+        assert_eq!(
+            normalizer.normalize("POST /api/v2/accounts/"),
+            "POST /api/v2/accounts/"
+        );
+        assert_eq!(normalizer.normalize("process-orders"), "process-orders");
+        assert_eq!(normalizer.normalize("/cafe/deadbeef"), "/cafe/deadbeef");
+    }
+
+    #[test]
+    fn test_methods() {
+        let normalizer = TransactionNameNormalizer::empty().lowercase_methods(true);
+        assert_eq!(normalizer.normalize("DELETE /users/42"), "delete /users/42");
+        assert_eq!(normalizer.normalize("Sync Users"), "Sync Users");
+    }
+
+    #[test]
+    fn test_process_transaction() {
+        let transaction = Transaction {
+            name: Some("GET /users/42".into()),
+            ..Default::default()
+        };
+        let transaction = TransactionNameNormalizer::new()
+            .process_transaction(transaction, &ClientOptions::default())
+            .unwrap();
+        assert_eq!(transaction.name.as_deref(), Some("GET /users/{id}"));
+
+        let event = Event {
+            transaction: Some("GET /users/42".into()),
+            ..Default::default()
+        };
+        let event = TransactionNameNormalizer::new()
+            .process_event(event, &ClientOptions::default())
+            .unwrap();
+        assert_eq!(event.transaction.as_deref(), Some("GET /users/{id}"));
+    }
+}
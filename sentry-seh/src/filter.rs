@@ -0,0 +1,137 @@
+//! The unhandled exception filter and the background thread that sends its records.
+//!
+//! The exception filter runs on the crashing thread while the process is in an unknown
+//! state, so it avoids allocations and locks.  All state it needs is prepared when the
+//! filter is installed, and kept in statics.
+
+use std::io;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use sentry_core::Hub;
+use winapi::shared::minwindef::FALSE;
+use winapi::shared::ntdef::{HANDLE, LONG};
+use winapi::um::errhandlingapi::{SetUnhandledExceptionFilter, LPTOP_LEVEL_EXCEPTION_FILTER};
+use winapi::um::synchapi::{CreateEventW, SetEvent, WaitForSingleObject};
+use winapi::um::winbase::{INFINITE, WAIT_OBJECT_0};
+use winapi::um::winnt::EXCEPTION_POINTERS;
+use winapi::vc::excpt::EXCEPTION_CONTINUE_SEARCH;
+
+use crate::record::{event_from_record, module_of, ExceptionRecord, MAX_FRAMES};
+
+/// Set by the first crashing thread, so concurrent crashes are not recorded.
+static HANDLING: AtomicBool = AtomicBool::new(false);
+/// Signaled by the exception filter once the record was written.
+static RECORD_EVENT: AtomicPtr<winapi::ctypes::c_void> = AtomicPtr::new(ptr::null_mut());
+/// Signaled by the background thread once the event was sent.
+static ACK_EVENT: AtomicPtr<winapi::ctypes::c_void> = AtomicPtr::new(ptr::null_mut());
+/// How long to wait for the acknowledgement, in milliseconds.
+static TIMEOUT_MS: AtomicU32 = AtomicU32::new(0);
+
+/// The exception filter that was installed before ours.
+static mut PREVIOUS: LPTOP_LEVEL_EXCEPTION_FILTER = None;
+/// The record filled in by the exception filter.
+static mut RECORD: ExceptionRecord = ExceptionRecord::EMPTY;
+
+/// Installs the exception filter and spawns the background thread.
+pub(crate) fn install(timeout: Duration) -> io::Result<()> {
+    let record_event = create_event()?;
+    let ack_event = create_event()?;
+    RECORD_EVENT.store(record_event, Ordering::SeqCst);
+    ACK_EVENT.store(ack_event, Ordering::SeqCst);
+    TIMEOUT_MS.store(
+        timeout.as_millis().min(u128::from(INFINITE - 1)) as u32,
+        Ordering::SeqCst,
+    );
+
+    // raw handles are not `Send`, so they are passed as integers
+    let (record_event, ack_event) = (record_event as usize, ack_event as usize);
+    thread::Builder::new()
+        .name("sentry-seh-sender".into())
+        .spawn(move || send_records(record_event as HANDLE, ack_event as HANDLE, timeout))?;
+
+    unsafe {
+        PREVIOUS = SetUnhandledExceptionFilter(Some(handle_exception));
+    }
+    Ok(())
+}
+
+/// Creates an auto-reset event object.
+fn create_event() -> io::Result<HANDLE> {
+    let event = unsafe { CreateEventW(ptr::null_mut(), FALSE, FALSE, ptr::null()) };
+    if event.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(event)
+}
+
+/// Captures the records written by the exception filter, and acknowledges them once sent.
+fn send_records(record_event: HANDLE, ack_event: HANDLE, timeout: Duration) {
+    while unsafe { WaitForSingleObject(record_event, INFINITE) } == WAIT_OBJECT_0 {
+        // SAFETY: the exception filter does not touch the record until it is acknowledged
+        let record = unsafe { RECORD };
+
+        let hub = Hub::main();
+        hub.capture_event(event_from_record(&record, module_of));
+        if let Some(client) = hub.client() {
+            client.flush(Some(timeout));
+        }
+
+        unsafe { SetEvent(ack_event) };
+    }
+}
+
+unsafe extern "system" fn handle_exception(info: *mut EXCEPTION_POINTERS) -> LONG {
+    if !HANDLING.swap(true, Ordering::SeqCst) {
+        report(info);
+    }
+    match PREVIOUS {
+        Some(previous) => previous(info),
+        None => EXCEPTION_CONTINUE_SEARCH,
+    }
+}
+
+/// Records the exception, and hands it to the background thread.
+unsafe fn report(info: *mut EXCEPTION_POINTERS) {
+    let record = &mut RECORD;
+    if !info.is_null() && !(*info).ExceptionRecord.is_null() {
+        let exception = &*(*info).ExceptionRecord;
+        record.code = exception.ExceptionCode;
+        record.address = exception.ExceptionAddress as usize;
+        record.num_params = (exception.NumberParameters as usize).min(record.params.len());
+        record.params[..record.num_params]
+            .copy_from_slice(&exception.ExceptionInformation[..record.num_params]);
+    }
+
+    record.len = 0;
+    backtrace::trace_unsynchronized(|frame| {
+        record.frames[record.len] = frame.ip() as usize;
+        record.len += 1;
+        record.len < MAX_FRAMES
+    });
+
+    // the trace starts within the exception dispatcher, so skip to the faulting frame
+    // if it can be found, and otherwise put the faulting address on top
+    let frames = &mut record.frames[..record.len];
+    match frames.iter().position(|ip| *ip == record.address) {
+        Some(start) => {
+            frames.copy_within(start.., 0);
+            record.len -= start;
+        }
+        None if record.len < MAX_FRAMES => {
+            record.frames.copy_within(..record.len, 1);
+            record.frames[0] = record.address;
+            record.len += 1;
+        }
+        None => record.frames[0] = record.address,
+    }
+
+    let record_event = RECORD_EVENT.load(Ordering::SeqCst);
+    let ack_event = ACK_EVENT.load(Ordering::SeqCst);
+    if record_event.is_null() || SetEvent(record_event) == 0 {
+        return;
+    }
+    WaitForSingleObject(ack_event, TIMEOUT_MS.load(Ordering::SeqCst));
+}
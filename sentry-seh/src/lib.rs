@@ -0,0 +1,84 @@
+//! Adds support for capturing unhandled Windows structured exceptions.
+//!
+//! The [`SehIntegration`] installs an unhandled exception filter, which covers crashes
+//! that the panic handler never sees, such as access violations in `unsafe` or foreign
+//! code.  It is the Windows counterpart of the `sentry-signal` integration.
+//!
+//! The exception filter records the exception code, the faulting address and the
+//! instruction addresses of the crashing thread into a preallocated buffer, and hands that
+//! record to a background thread that was spawned when the integration was set up.
+//! The background thread resolves the symbols and the modules the addresses belong to,
+//! captures a fatal event and flushes the client, after which the previously installed
+//! exception filter is invoked.
+//!
+//! # Example
+//!
+//! ```no_run
+//! let _sentry = sentry::init(
+//!     sentry::ClientOptions::new().add_integration(sentry_seh::SehIntegration::new()),
+//! );
+//! ```
+//!
+//! This integration is only functional on Windows.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::sync::Once;
+use std::time::Duration;
+
+use sentry_core::{ClientOptions, Integration};
+
+#[cfg(windows)]
+mod filter;
+#[cfg(windows)]
+mod record;
+
+/// The Sentry Windows structured exception handling Integration.
+#[derive(Clone, Debug)]
+pub struct SehIntegration {
+    timeout: Duration,
+}
+
+static INIT: Once = Once::new();
+
+impl SehIntegration {
+    /// Creates a new SEH Integration.
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(2),
+        }
+    }
+
+    /// Sets how long the exception filter waits for the event to be sent.
+    ///
+    /// The default is two seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for SehIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integration for SehIntegration {
+    fn name(&self) -> &'static str {
+        "seh"
+    }
+
+    fn setup(&self, _cfg: &mut ClientOptions) {
+        INIT.call_once(|| {
+            #[cfg(windows)]
+            {
+                if let Err(err) = filter::install(self.timeout) {
+                    sentry_core::sentry_debug!("failed to install exception filter: {}", err);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,250 @@
+use std::ptr;
+
+use sentry_backtrace::addresses_to_stacktrace;
+use sentry_core::protocol::{Event, Exception, Level, Mechanism};
+
+/// The maximum number of frames recorded by the exception filter.
+pub(crate) const MAX_FRAMES: usize = 128;
+
+/// An unhandled exception, as recorded by the exception filter.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct ExceptionRecord {
+    pub code: u32,
+    pub address: usize,
+    /// The first two exception parameters, which describe access violations.
+    pub params: [usize; 2],
+    pub num_params: usize,
+    pub len: usize,
+    pub frames: [usize; MAX_FRAMES],
+}
+
+impl ExceptionRecord {
+    pub const EMPTY: ExceptionRecord = ExceptionRecord {
+        code: 0,
+        address: 0,
+        params: [0; 2],
+        num_params: 0,
+        len: 0,
+        frames: [0; MAX_FRAMES],
+    };
+
+    /// Returns the recorded instruction addresses.
+    pub fn frames(&self) -> &[usize] {
+        &self.frames[..self.len.min(MAX_FRAMES)]
+    }
+}
+
+/// A loaded module an address belongs to.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Module {
+    pub name: String,
+    pub base: usize,
+}
+
+/// Returns the name of an exception code.
+fn exception_name(code: u32) -> Option<&'static str> {
+    Some(match code {
+        0xC000_0005 => "EXCEPTION_ACCESS_VIOLATION",
+        0xC000_0006 => "EXCEPTION_IN_PAGE_ERROR",
+        0xC000_001D => "EXCEPTION_ILLEGAL_INSTRUCTION",
+        0xC000_0025 => "EXCEPTION_NONCONTINUABLE_EXCEPTION",
+        0xC000_008C => "EXCEPTION_ARRAY_BOUNDS_EXCEEDED",
+        0xC000_008E => "EXCEPTION_FLT_DIVIDE_BY_ZERO",
+        0xC000_0094 => "EXCEPTION_INT_DIVIDE_BY_ZERO",
+        0xC000_0095 => "EXCEPTION_INT_OVERFLOW",
+        0xC000_0096 => "EXCEPTION_PRIV_INSTRUCTION",
+        0xC000_00FD => "EXCEPTION_STACK_OVERFLOW",
+        0xC000_0409 => "STATUS_STACK_BUFFER_OVERRUN",
+        0x8000_0002 => "EXCEPTION_DATATYPE_MISALIGNMENT",
+        0x8000_0003 => "EXCEPTION_BREAKPOINT",
+        _ => return None,
+    })
+}
+
+/// Describes the memory access that caused an access violation.
+fn describe_access(record: &ExceptionRecord) -> Option<String> {
+    let is_access_violation = record.code == 0xC000_0005 || record.code == 0xC000_0006;
+    if !is_access_violation || record.num_params < 2 {
+        return None;
+    }
+    let operation = match record.params[0] {
+        0 => "read from",
+        1 => "write to",
+        8 => "execute",
+        _ => "access",
+    };
+    Some(format!(
+        "attempted to {} address {:#x}",
+        operation, record.params[1]
+    ))
+}
+
+/// Formats an address relative to the module it belongs to.
+fn format_address(address: usize, module: Option<&Module>) -> String {
+    match module {
+        Some(module) => format!(
+            "{:#x} ({}+{:#x})",
+            address,
+            module.name,
+            address.wrapping_sub(module.base)
+        ),
+        None => format!("{:#x}", address),
+    }
+}
+
+/// Creates a fatal event from an exception record.
+///
+/// `module_of` looks up the module an address belongs to.
+pub(crate) fn event_from_record<F>(record: &ExceptionRecord, module_of: F) -> Event<'static>
+where
+    F: Fn(usize) -> Option<Module>,
+{
+    let ty =
+        exception_name(record.code).map_or_else(|| format!("{:#010X}", record.code), Into::into);
+
+    let mut value = format!(
+        "Fatal {} at address {}",
+        ty,
+        format_address(record.address, module_of(record.address).as_ref())
+    );
+    if let Some(access) = describe_access(record) {
+        value.push_str(": ");
+        value.push_str(&access);
+    }
+
+    let mut stacktrace = addresses_to_stacktrace(record.frames());
+    if let Some(stacktrace) = &mut stacktrace {
+        for frame in &mut stacktrace.frames {
+            let address = match frame.instruction_addr {
+                Some(address) => address.0 as usize,
+                None => continue,
+            };
+            if let Some(module) = module_of(address) {
+                frame.image_addr = Some(module.base.into());
+                if frame.package.is_none() {
+                    frame.package = Some(module.name);
+                }
+            }
+        }
+    }
+
+    let mut mechanism = Mechanism {
+        ty: "UnhandledExceptionFilter".into(),
+        handled: Some(false),
+        ..Default::default()
+    };
+    mechanism
+        .data
+        .insert("code".into(), format!("{:#010X}", record.code).into());
+
+    Event {
+        exception: vec![Exception {
+            ty,
+            value: Some(value),
+            mechanism: Some(mechanism),
+            stacktrace,
+            ..Default::default()
+        }]
+        .into(),
+        level: Level::Fatal,
+        ..Default::default()
+    }
+}
+
+/// Returns the module `address` belongs to.
+pub(crate) fn module_of(address: usize) -> Option<Module> {
+    use winapi::shared::minwindef::{HMODULE, MAX_PATH};
+    use winapi::um::libloaderapi::{
+        GetModuleFileNameW, GetModuleHandleExW, GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS,
+        GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+    };
+
+    let mut module: HMODULE = ptr::null_mut();
+    let flags =
+        GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT;
+    if unsafe { GetModuleHandleExW(flags, address as _, &mut module) } == 0 {
+        return None;
+    }
+
+    let mut buf = [0u16; MAX_PATH];
+    let len = unsafe { GetModuleFileNameW(module, buf.as_mut_ptr(), buf.len() as u32) };
+    if len == 0 {
+        return None;
+    }
+    let path = String::from_utf16_lossy(&buf[..len as usize]);
+    let name = path.rsplit('\\').next().unwrap_or(&path).to_owned();
+
+    Some(Module {
+        name,
+        base: module as usize,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ExceptionRecord {
+        let mut record = ExceptionRecord::EMPTY;
+        record.code = 0xC000_0005;
+        record.address = 0x1010;
+        record.params = [1, 0x10];
+        record.num_params = 2;
+        record.len = 1;
+        record.frames[0] = 0x1010;
+        record
+    }
+
+    #[test]
+    fn test_event_from_record() {
+        let event = event_from_record(&record(), |address| {
+            if address >= 0x1000 {
+                Some(Module {
+                    name: "app.exe".into(),
+                    base: 0x1000,
+                })
+            } else {
+                None
+            }
+        });
+
+        assert_eq!(event.level, Level::Fatal);
+        let exception = &event.exception.values[0];
+        assert_eq!(exception.ty, "EXCEPTION_ACCESS_VIOLATION");
+        assert_eq!(
+            exception.value.as_deref(),
+            Some(
+                "Fatal EXCEPTION_ACCESS_VIOLATION at address 0x1010 (app.exe+0x10): \
+                 attempted to write to address 0x10"
+            )
+        );
+        let mechanism = exception.mechanism.as_ref().unwrap();
+        assert_eq!(mechanism.handled, Some(false));
+        assert_eq!(mechanism.data["code"], "0xC0000005");
+
+        let frame = &exception.stacktrace.as_ref().unwrap().frames[0];
+        assert_eq!(frame.image_addr, Some(0x1000usize.into()));
+        assert_eq!(frame.package.as_deref(), Some("app.exe"));
+    }
+
+    #[test]
+    fn test_unknown_exception() {
+        let mut record = record();
+        record.code = 0xE06D_7363;
+        let event = event_from_record(&record, |_| None);
+        let exception = &event.exception.values[0];
+        assert_eq!(exception.ty, "0xE06D7363");
+        assert_eq!(
+            exception.value.as_deref(),
+            Some("Fatal 0xE06D7363 at address 0x1010")
+        );
+    }
+
+    #[test]
+    fn test_module_of() {
+        let module = module_of(test_module_of as fn() as usize).unwrap();
+        assert!(module.name.ends_with(".exe"));
+        assert!(module_of(0).is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! The signal handler and the background thread that sends its records.
+//!
+//! Everything that runs inside the signal handler has to be async-signal-safe, which
+//! means no allocations and no locks.  All state the handler needs is prepared when the
+//! handler is installed, and kept in statics.  The one exception is unwinding the stack
+//! of the crashing thread, see `report`.
+
+use std::cell::UnsafeCell;
+use std::ffi::{c_void, CString};
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use libc::{c_char, c_int, siginfo_t};
+use sentry_core::protocol::SessionStatus;
+use sentry_core::types::Uuid;
+use sentry_core::Hub;
+
+use crate::record::{event_from_record, CrashRecord, MAX_FRAMES, RECORD_EXTENSION};
+
+/// The signals a handler is installed for.
+const SIGNALS: [c_int; 4] = [libc::SIGSEGV, libc::SIGABRT, libc::SIGBUS, libc::SIGILL];
+/// The signals that end the session as abnormal, if session exits are tracked.
+const TERMINATION_SIGNALS: [c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// Set by the first crashing thread, so concurrent crashes are not recorded.
+static HANDLING: AtomicBool = AtomicBool::new(false);
+/// Set by the first termination signal, so the session is only ended once.
+static TERMINATING: AtomicBool = AtomicBool::new(false);
+/// The write end of the pipe to the background thread.
+static RECORD_FD: AtomicI32 = AtomicI32::new(-1);
+/// The read end of the pipe the background thread acknowledges records on.
+static ACK_FD: AtomicI32 = AtomicI32::new(-1);
+/// How long to wait for the acknowledgement, in milliseconds.
+static TIMEOUT_MS: AtomicI32 = AtomicI32::new(0);
+/// The path the record is written to, if a database is configured.
+static RECORD_PATH: AtomicPtr<c_char> = AtomicPtr::new(ptr::null_mut());
+
+/// The signal handlers that were installed before ours.
+static PREVIOUS: SignalCell<Option<[libc::sigaction; SIGNALS.len()]>> = SignalCell::new(None);
+/// The termination signal handlers that were installed before ours.
+static PREVIOUS_TERMINATION: SignalCell<Option<[libc::sigaction; TERMINATION_SIGNALS.len()]>> =
+    SignalCell::new(None);
+/// The record filled in by the signal handler.
+static RECORD: SignalCell<CrashRecord> = SignalCell::new(CrashRecord::EMPTY);
+
+/// A static that the signal handler accesses without locks.
+///
+/// The previous handlers are only written before our handlers are installed, and the
+/// record is only written by the first crashing thread, so accesses never race.
+struct SignalCell<T>(UnsafeCell<T>);
+
+unsafe impl<T> Sync for SignalCell<T> {}
+
+impl<T> SignalCell<T> {
+    const fn new(value: T) -> Self {
+        SignalCell(UnsafeCell::new(value))
+    }
+
+    fn get(&self) -> *mut T {
+        self.0.get()
+    }
+}
+
+/// Installs the signal handlers and spawns the background thread.
+///
+/// With `session_exit`, handlers for the termination signals and an `atexit`
+/// handler that end the session are installed as well.
+pub(crate) fn install(
+    database: Option<&Path>,
+    timeout: Duration,
+    session_exit: bool,
+) -> io::Result<()> {
+    let (record_rx, record_tx) = pipe()?;
+    let (ack_rx, ack_tx) = pipe()?;
+
+    if let Some(database) = database {
+        let path = database.join(format!("{}.{}", Uuid::new_v4(), RECORD_EXTENSION));
+        if let Ok(path) = CString::new(path.as_os_str().as_bytes()) {
+            // leaked on purpose, the signal handler may need it at any time
+            RECORD_PATH.store(path.into_raw(), Ordering::SeqCst);
+        }
+    }
+    RECORD_FD.store(record_tx, Ordering::SeqCst);
+    ACK_FD.store(ack_rx, Ordering::SeqCst);
+    TIMEOUT_MS.store(
+        timeout.as_millis().min(i32::MAX as u128) as i32,
+        Ordering::SeqCst,
+    );
+
+    thread::Builder::new()
+        .name("sentry-signal-sender".into())
+        .spawn(move || send_records(record_rx, ack_tx, timeout))?;
+
+    // initializes the unwinder outside of the signal handler, see `report`
+    backtrace::trace(|_| true);
+
+    unsafe {
+        let mut previous: [libc::sigaction; SIGNALS.len()] = mem::zeroed();
+        save_handlers(&SIGNALS, &mut previous)?;
+        *PREVIOUS.get() = Some(previous);
+        set_handlers(&SIGNALS)?;
+
+        if session_exit {
+            let mut previous: [libc::sigaction; TERMINATION_SIGNALS.len()] = mem::zeroed();
+            save_handlers(&TERMINATION_SIGNALS, &mut previous)?;
+            *PREVIOUS_TERMINATION.get() = Some(previous);
+            // ignored signals do not terminate the process, so they must not end
+            // the session either
+            let signals: Vec<c_int> = TERMINATION_SIGNALS
+                .iter()
+                .zip(previous.iter())
+                .filter(|(_, previous)| previous.sa_sigaction != libc::SIG_IGN)
+                .map(|(signal, _)| *signal)
+                .collect();
+            set_handlers(&signals)?;
+            if libc::atexit(end_session_at_exit) != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "failed to register atexit handler",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Stores the currently installed handlers of the signals in `previous`.
+unsafe fn save_handlers(signals: &[c_int], previous: &mut [libc::sigaction]) -> io::Result<()> {
+    for (signal, previous) in signals.iter().zip(previous.iter_mut()) {
+        if libc::sigaction(*signal, ptr::null(), previous) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Installs our handler for the signals.
+unsafe fn set_handlers(signals: &[c_int]) -> io::Result<()> {
+    let mut action: libc::sigaction = mem::zeroed();
+    action.sa_sigaction = handle_signal as *const () as libc::sighandler_t;
+    action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+    libc::sigemptyset(&mut action.sa_mask);
+
+    for signal in signals {
+        if libc::sigaction(*signal, &action, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Ends the session when the process exits without dropping the client guard,
+/// for example by calling `std::process::exit`.
+extern "C" fn end_session_at_exit() {
+    // unwinding out of an `extern "C"` function aborts the process
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let hub = Hub::main();
+        hub.end_session();
+        if let Some(client) = hub.client() {
+            client.flush(None);
+        }
+    }))
+    .ok();
+}
+
+/// Creates a pipe, returning the read and write ends.
+fn pipe() -> io::Result<(c_int, c_int)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((fds[0], fds[1]))
+}
+
+/// Captures the records sent by the signal handler, and acknowledges them once sent.
+fn send_records(record_fd: c_int, ack_fd: c_int, timeout: Duration) {
+    let mut buf = vec![0u8; CrashRecord::SIZE];
+    while read_exact(record_fd, &mut buf) {
+        let record = match CrashRecord::from_bytes(&buf) {
+            Some(record) => record,
+            None => continue,
+        };
+
+        let hub = Hub::main();
+        if TERMINATION_SIGNALS.contains(&record.signal) {
+            hub.end_session_with_status(SessionStatus::Abnormal);
+        } else {
+            hub.capture_event(event_from_record(&record));
+            hub.end_session_with_status(SessionStatus::Crashed);
+        }
+        let sent = hub
+            .client()
+            .map_or(false, |client| client.flush(Some(timeout)));
+
+        let ack = [sent as u8];
+        unsafe { libc::write(ack_fd, ack.as_ptr() as *const c_void, 1) };
+    }
+}
+
+/// Reads exactly `buf.len()` bytes from the file descriptor.
+fn read_exact(fd: c_int, buf: &mut [u8]) -> bool {
+    let mut read = 0;
+    while read < buf.len() {
+        let rv = unsafe {
+            libc::read(
+                fd,
+                buf[read..].as_mut_ptr() as *mut c_void,
+                buf.len() - read,
+            )
+        };
+        if rv > 0 {
+            read += rv as usize;
+        } else if rv < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+            continue;
+        } else {
+            return false;
+        }
+    }
+    true
+}
+
+extern "C" fn handle_signal(signal: c_int, info: *mut siginfo_t, context: *mut c_void) {
+    if TERMINATION_SIGNALS.contains(&signal) {
+        if !TERMINATING.swap(true, Ordering::SeqCst) {
+            unsafe { report_termination(signal) };
+        }
+    } else if !HANDLING.swap(true, Ordering::SeqCst) {
+        unsafe { report(signal, info) };
+    }
+    unsafe { invoke_previous(signal, info, context) };
+}
+
+/// Hands a termination signal to the background thread, which ends the session.
+///
+/// This must only use async-signal-safe functions.
+unsafe fn report_termination(signal: c_int) {
+    let mut record = CrashRecord::EMPTY;
+    record.signal = signal;
+
+    let record_fd = RECORD_FD.load(Ordering::SeqCst);
+    if record_fd < 0 || !write_all(record_fd, record.as_bytes()) {
+        return;
+    }
+    wait_for_ack();
+}
+
+/// Records the crash, and hands it to the background thread.
+///
+/// This must only use async-signal-safe functions, except for unwinding the stack:
+/// `backtrace::trace_unsynchronized` is not async-signal-safe on all platforms, since
+/// the unwinder may allocate or take locks, for instance to load the unwind tables of
+/// a module for the first time.  This is a best effort, as the process is crashing
+/// anyway, and the stack is unwound once when the handler is installed so that the
+/// unwinder is initialized by then.  Unwinding from the background thread is not an
+/// option, as the stack of the crashing thread is only intact while it is handling
+/// the signal.
+unsafe fn report(signal: c_int, info: *mut siginfo_t) {
+    let record = &mut *RECORD.get();
+    record.signal = signal;
+    if !info.is_null() {
+        record.code = (*info).si_code;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            record.address = (*info).si_addr() as usize;
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        {
+            record.address = (*info).si_addr as usize;
+        }
+    }
+    record.len = 0;
+    backtrace::trace_unsynchronized(|frame| {
+        record.frames[record.len] = frame.ip() as usize;
+        record.len += 1;
+        record.len < MAX_FRAMES
+    });
+
+    // the record is written first, in case the background thread can not send it
+    let path = RECORD_PATH.load(Ordering::SeqCst);
+    if !path.is_null() {
+        let fd = libc::open(path, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, 0o600);
+        if fd >= 0 {
+            write_all(fd, record.as_bytes());
+            libc::close(fd);
+        }
+    }
+
+    let record_fd = RECORD_FD.load(Ordering::SeqCst);
+    if record_fd < 0 || !write_all(record_fd, record.as_bytes()) {
+        return;
+    }
+
+    if wait_for_ack() && !path.is_null() {
+        libc::unlink(path);
+    }
+}
+
+/// Waits for the background thread to acknowledge a record, and returns whether
+/// it was sent.
+///
+/// This must only use async-signal-safe functions.
+unsafe fn wait_for_ack() -> bool {
+    let mut pollfd = libc::pollfd {
+        fd: ACK_FD.load(Ordering::SeqCst),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let mut ack = [0u8; 1];
+    libc::poll(&mut pollfd, 1, TIMEOUT_MS.load(Ordering::SeqCst)) == 1
+        && libc::read(pollfd.fd, ack.as_mut_ptr() as *mut c_void, 1) == 1
+        && ack[0] == 1
+}
+
+/// Writes all bytes to the file descriptor.
+///
+/// This must only use async-signal-safe functions.
+unsafe fn write_all(fd: c_int, mut bytes: &[u8]) -> bool {
+    while !bytes.is_empty() {
+        let rv = libc::write(fd, bytes.as_ptr() as *const c_void, bytes.len());
+        if rv <= 0 {
+            return false;
+        }
+        bytes = &bytes[rv as usize..];
+    }
+    true
+}
+
+/// Restores and invokes the signal handler that was installed before ours.
+unsafe fn invoke_previous(signal: c_int, info: *mut siginfo_t, context: *mut c_void) {
+    let action = if let Some(index) = SIGNALS.iter().position(|s| *s == signal) {
+        match &*PREVIOUS.get() {
+            Some(previous) => &previous[index],
+            None => return,
+        }
+    } else if let Some(index) = TERMINATION_SIGNALS.iter().position(|s| *s == signal) {
+        match &*PREVIOUS_TERMINATION.get() {
+            Some(previous) => &previous[index],
+            None => return,
+        }
+    } else {
+        return;
+    };
+    libc::sigaction(signal, action, ptr::null_mut());
+
+    match action.sa_sigaction {
+        libc::SIG_IGN => {}
+        libc::SIG_DFL => {
+            // returning re-executes the faulting instruction for most signals, but
+            // not for signals that were sent explicitly
+            libc::raise(signal);
+        }
+        handler if action.sa_flags & libc::SA_SIGINFO != 0 => {
+            let handler: extern "C" fn(c_int, *mut siginfo_t, *mut c_void) =
+                mem::transmute(handler);
+            handler(signal, info, context);
+        }
+        handler => {
+            let handler: extern "C" fn(c_int) = mem::transmute(handler);
+            handler(signal);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Adds support for capturing fatal POSIX signals.
+//!
+//! The [`SignalIntegration`] installs a handler for `SIGSEGV`, `SIGABRT`, `SIGBUS` and
+//! `SIGILL`, which covers crashes that the panic handler never sees, such as invalid
+//! memory accesses in `unsafe` or foreign code.
+//!
+//! The signal handler avoids allocations and locks: it records the signal and the
+//! instruction addresses of the crashing thread into a preallocated buffer, and hands that
+//! record to a background thread that was spawned when the integration was set up.
+//! The background thread resolves the symbols, captures a fatal event and flushes the
+//! client, after which the previously installed signal handler is invoked.
+//!
+//! As the state of the process is unknown after a crash, the background thread might
+//! not be able to send the event.  When a database directory is configured, the record is
+//! also written to that directory, and can be uploaded on the next start using
+//! [`upload_pending`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! let _sentry = sentry::init(sentry::ClientOptions::new().add_integration(
+//!     sentry_signal::SignalIntegration::new().database("./crashes"),
+//! ));
+//! // upload the crashes of previous runs that could not be sent
+//! sentry_signal::upload_pending().ok();
+//! ```
+//!
+//! Aborts caused by panics are reported by the panic handler integration as well, so
+//! when building with `panic = "abort"`, a panic results in two events.
+//!
+//! For command line tools that track one release health session per run,
+//! [`SignalIntegration::track_session_exit`] also ends the session as `abnormal` when the
+//! process is terminated by a signal, and as `exited` when it calls [`std::process::exit`].
+//!
+//! This integration is only functional on unix platforms.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::Once;
+use std::time::Duration;
+
+use sentry_core::{ClientOptions, Integration};
+
+#[cfg(unix)]
+mod handler;
+#[cfg(unix)]
+mod record;
+
+/// The Sentry POSIX signal handler Integration.
+#[derive(Clone, Debug)]
+pub struct SignalIntegration {
+    database: Option<PathBuf>,
+    timeout: Duration,
+    session_exit: bool,
+}
+
+static INIT: Once = Once::new();
+
+impl SignalIntegration {
+    /// Creates a new Signal Integration.
+    pub fn new() -> Self {
+        Self {
+            database: None,
+            timeout: Duration::from_secs(2),
+            session_exit: false,
+        }
+    }
+
+    /// Writes the records of crashes into the given directory.
+    ///
+    /// Records are removed once they were sent by the background thread, and
+    /// otherwise uploaded by [`upload_pending`].
+    pub fn database<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.database = Some(path.into());
+        self
+    }
+
+    /// Ends the release health session when the process exits.
+    ///
+    /// This is meant for command line tools that use one session per run.  A
+    /// crash always ends the session as `crashed`.  With this enabled, the
+    /// session is also ended as `abnormal` when the process receives `SIGINT`,
+    /// `SIGTERM` or `SIGHUP`, and as `exited` when the process exits without
+    /// dropping the client guard, for example by calling
+    /// [`std::process::exit`].  Handlers that were installed for these signals
+    /// before are still invoked afterwards, and signals that are ignored, such as
+    /// `SIGHUP` under `nohup`, are left alone.
+    ///
+    /// The default is to not track the exit of the process.
+    pub fn track_session_exit(mut self, val: bool) -> Self {
+        self.session_exit = val;
+        self
+    }
+
+    /// Sets how long the signal handler waits for the event to be sent.
+    ///
+    /// The default is two seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for SignalIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integration for SignalIntegration {
+    fn name(&self) -> &'static str {
+        "signal"
+    }
+
+    fn setup(&self, _cfg: &mut ClientOptions) {
+        INIT.call_once(|| {
+            #[cfg(unix)]
+            {
+                if let Some(database) = &self.database {
+                    std::fs::create_dir_all(database).ok();
+                }
+                let database = self.database.as_deref();
+                if let Err(err) = handler::install(database, self.timeout, self.session_exit) {
+                    sentry_core::sentry_debug!("failed to install signal handlers: {}", err);
+                }
+            }
+        });
+    }
+}
+
+/// Captures the crashes of previous runs that were written to the database.
+///
+/// This needs to be called after the client has been initialized with the
+/// [`SignalIntegration`], and does nothing if no database was configured.
+pub fn upload_pending() -> io::Result<()> {
+    let database = sentry_core::with_integration(|integration: &SignalIntegration, _| {
+        integration.database.clone()
+    });
+    match database {
+        #[cfg(unix)]
+        Some(database) => record::upload_pending(&sentry_core::Hub::current(), &database),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::ptr;
+
+use sentry_backtrace::addresses_to_stacktrace;
+use sentry_core::protocol::{Event, Exception, Level, Mechanism, MechanismMeta, PosixSignal};
+use sentry_core::types::{DateTime, Utc};
+use sentry_core::Hub;
+
+/// The maximum number of frames recorded by the signal handler.
+pub(crate) const MAX_FRAMES: usize = 128;
+
+/// The file extension of records written to the database.
+pub(crate) const RECORD_EXTENSION: &str = "crash";
+
+/// A crash, as recorded by the signal handler.
+///
+/// This is written as raw bytes to the pipe of the background thread and to the
+/// database, so it must only consist of plain integers.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct CrashRecord {
+    pub signal: i32,
+    pub code: i32,
+    pub address: usize,
+    pub len: usize,
+    pub frames: [usize; MAX_FRAMES],
+}
+
+impl CrashRecord {
+    pub const EMPTY: CrashRecord = CrashRecord {
+        signal: 0,
+        code: 0,
+        address: 0,
+        len: 0,
+        frames: [0; MAX_FRAMES],
+    };
+
+    pub const SIZE: usize = mem::size_of::<CrashRecord>();
+
+    /// Reads a record from its raw bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Option<CrashRecord> {
+        if bytes.len() != Self::SIZE {
+            return None;
+        }
+        // SAFETY: the record only consists of integers, so every bit pattern is valid
+        Some(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const CrashRecord) })
+    }
+
+    /// Returns the raw bytes of the record.
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: the record is `repr(C)` and consists of integers only
+        unsafe { std::slice::from_raw_parts(self as *const _ as *const u8, Self::SIZE) }
+    }
+
+    /// Returns the recorded instruction addresses.
+    pub fn frames(&self) -> &[usize] {
+        &self.frames[..self.len.min(MAX_FRAMES)]
+    }
+}
+
+/// Returns the name of a signal.
+fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGILL => "SIGILL",
+        libc::SIGFPE => "SIGFPE",
+        _ => return None,
+    })
+}
+
+/// Creates a fatal event from a crash record.
+pub(crate) fn event_from_record(record: &CrashRecord) -> Event<'static> {
+    let name = signal_name(record.signal);
+    let ty = name.map_or_else(|| format!("signal {}", record.signal), Into::into);
+    Event {
+        exception: vec![Exception {
+            value: Some(format!("Fatal {} at address {:#x}", ty, record.address)),
+            ty,
+            mechanism: Some(Mechanism {
+                ty: "signalhandler".into(),
+                handled: Some(false),
+                meta: MechanismMeta {
+                    signal: Some(PosixSignal {
+                        number: record.signal,
+                        code: Some(record.code),
+                        name: name.map(Into::into),
+                        code_name: None,
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            stacktrace: addresses_to_stacktrace(record.frames()),
+            ..Default::default()
+        }]
+        .into(),
+        level: Level::Fatal,
+        ..Default::default()
+    }
+}
+
+/// Captures the records in the `database` directory, and removes them.
+pub(crate) fn upload_pending(hub: &Hub, database: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(database)? {
+        let path = entry?.path();
+        if path.extension() != Some(RECORD_EXTENSION.as_ref()) {
+            continue;
+        }
+
+        if let Some(record) = CrashRecord::from_bytes(&fs::read(&path)?) {
+            let mut event = event_from_record(&record);
+            if let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                event.timestamp = DateTime::<Utc>::from(modified);
+            }
+            hub.capture_event(event);
+        }
+        fs::remove_file(&path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> CrashRecord {
+        let mut crash = CrashRecord::EMPTY;
+        crash.signal = libc::SIGSEGV;
+        crash.address = 0x10;
+        crash.len = 1;
+        crash.frames[0] = record as fn() -> CrashRecord as usize;
+        crash
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let record = record();
+        let parsed = CrashRecord::from_bytes(record.as_bytes()).unwrap();
+        assert_eq!(parsed.signal, libc::SIGSEGV);
+        assert_eq!(parsed.frames(), record.frames());
+        assert!(CrashRecord::from_bytes(&[0; 4]).is_none());
+    }
+
+    #[test]
+    fn test_upload_pending() {
+        let database = std::env::temp_dir().join(format!(
+            "sentry-signal-{}",
+            sentry_core::types::Uuid::new_v4()
+        ));
+        fs::create_dir_all(&database).unwrap();
+        fs::write(database.join("first.crash"), record().as_bytes()).unwrap();
+
+        let events = sentry::test::with_captured_events(|| {
+            upload_pending(&Hub::current(), &database).unwrap();
+        });
+        fs::remove_dir_all(&database).ok();
+
+        assert_eq!(events.len(), 1);
+        let exception = &events[0].exception.values[0];
+        assert_eq!(events[0].level, Level::Fatal);
+        assert_eq!(exception.ty, "SIGSEGV");
+        assert_eq!(
+            exception.value,
+            Some("Fatal SIGSEGV at address 0x10".into())
+        );
+        let signal = exception.mechanism.as_ref().unwrap().meta.signal.as_ref();
+        assert_eq!(signal.unwrap().number, libc::SIGSEGV);
+        assert!(exception.stacktrace.is_some());
+    }
+}
@@ -4,12 +4,7 @@ use std::fmt;
 
 /// Converts a [`slog::Level`] to a Sentry [`Level`]
 pub fn convert_log_level(level: slog::Level) -> Level {
-    match level {
-        slog::Level::Trace | slog::Level::Debug => Level::Debug,
-        slog::Level::Info => Level::Info,
-        slog::Level::Warning => Level::Warning,
-        slog::Level::Error | slog::Level::Critical => Level::Error,
-    }
+    level.into()
 }
 
 struct MapSerializer<'a>(&'a mut Map<String, Value>);
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use sentry_core::protocol::{Map, Value};
+use sentry_core::{Breadcrumb, Level};
+
+/// The log target that `sqlx` uses for executed statements.
+pub const SQLX_QUERY_TARGET: &str = "sqlx::query";
+
+/// Returns whether the [`log::Metadata`] belongs to a statement logged by `sqlx`.
+pub fn is_sqlx_query(metadata: &log::Metadata<'_>) -> bool {
+    metadata.target() == SQLX_QUERY_TARGET
+}
+
+/// A statement logged by `sqlx`, split into its parts.
+pub(crate) struct Query {
+    pub statement: String,
+    pub data: Map<String, Value>,
+    pub elapsed: Option<Duration>,
+}
+
+/// Parses a statement logged by `sqlx`.
+///
+/// `sqlx` logs a summary line with the number of rows and the elapsed time, followed by
+/// the formatted statement.  The elapsed time is recorded as `duration_ms` in the data.
+pub(crate) fn parse_query_record(record: &log::Record<'_>) -> Query {
+    let message = record.args().to_string();
+    let (head, statement) = match message.find("\n\n") {
+        Some(pos) => (&message[..pos], Some(message[pos + 2..].trim())),
+        None => (&message[..], None),
+    };
+
+    let mut data = Map::new();
+    let mut elapsed = None;
+    let summary = match head.rfind("; rows affected: ") {
+        Some(pos) => {
+            for part in head[pos + 2..].split(", ") {
+                let mut kv = part.splitn(2, ": ");
+                let (key, value) = match (kv.next(), kv.next()) {
+                    (Some(key), Some(value)) => (key.replace(' ', "_"), value),
+                    _ => continue,
+                };
+                if key == "elapsed" {
+                    elapsed = parse_duration(value);
+                    if let Some(elapsed) = elapsed {
+                        data.insert(
+                            "duration_ms".into(),
+                            (elapsed.as_secs_f64() * 1000.0).into(),
+                        );
+                    }
+                    continue;
+                }
+                let value = match value.parse::<u64>() {
+                    Ok(num) => Value::from(num),
+                    Err(_) => Value::from(value),
+                };
+                data.insert(key, value);
+            }
+            &head[..pos]
+        }
+        None => head,
+    };
+
+    Query {
+        statement: statement.unwrap_or(summary).into(),
+        data,
+        elapsed,
+    }
+}
+
+/// Parses a [`Duration`] in the `Debug` format that `sqlx` logs, such as `1.250ms`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let pos = value.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (num, unit) = value.split_at(pos);
+    let num = num.parse::<f64>().ok()?;
+    let secs = match unit {
+        "s" => num,
+        "ms" => num / 1e3,
+        "µs" | "us" => num / 1e6,
+        "ns" => num / 1e9,
+        _ => return None,
+    };
+    Some(Duration::from_secs_f64(secs))
+}
+
+/// Creates a `db.query` [`Breadcrumb`] from a statement logged by `sqlx`.
+///
+/// The statement becomes the message of the breadcrumb, and the number of affected and
+/// returned rows as well as the elapsed time in milliseconds are added to its data.
+pub fn breadcrumb_from_query_record(record: &log::Record<'_>) -> Breadcrumb {
+    let query = parse_query_record(record);
+    Breadcrumb {
+        ty: "query".into(),
+        category: Some("db.query".into()),
+        level: match record.level() {
+            log::Level::Error => Level::Error,
+            log::Level::Warn => Level::Warning,
+            _ => Level::Info,
+        },
+        message: Some(query.statement),
+        data: query.data,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,34 @@
+//! Adds support for recording [`sqlx`](https://docs.rs/sqlx) queries as breadcrumbs.
+//!
+//! `sqlx` logs every statement it executes to the `sqlx::query` target of the `log` crate,
+//! including the number of affected rows and the time it took.  The [`SqlxLogger`] wraps
+//! another [`log::Log`] and turns these records into `db.query` breadcrumbs, so the queries
+//! leading up to an error show up alongside it.
+//!
+//! If the current scope has a span, such as a running transaction, every statement is also
+//! recorded as a `db` child span of it, covering the time the statement took.
+//!
+//! `sqlx` only logs the statement with its placeholders, so the values bound to a query are
+//! never recorded.
+//!
+//! # Examples
+//!
+//! ```
+//! let logger = sentry_sqlx::SqlxLogger::new(sentry_log::SentryLogger::new());
+//!
+//! log::set_boxed_logger(Box::new(logger)).unwrap();
+//! log::set_max_level(log::LevelFilter::Debug);
+//! ```
+//!
+//! By default, `sqlx` logs statements at the `Debug` level, and slow statements at the
+//! `Warn` level, so the maximum log level needs to include `Debug` to record all queries.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+mod converters;
+mod logger;
+
+pub use converters::*;
+pub use logger::*;
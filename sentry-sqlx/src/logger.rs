@@ -0,0 +1,181 @@
+use std::time::SystemTime;
+
+use sentry_core::types::{DateTime, Utc};
+
+use crate::converters::{breadcrumb_from_query_record, is_sqlx_query, parse_query_record};
+
+/// A [`log::Log`] that records `sqlx` statements as breadcrumbs.
+///
+/// If the current scope has a span, such as a running transaction, every statement is also
+/// recorded as a `db` child span of it.  All records, including the statements, are passed
+/// on to the wrapped logger.
+#[derive(Debug, Default)]
+pub struct SqlxLogger<L: log::Log> {
+    dest: L,
+}
+
+impl<L: log::Log> SqlxLogger<L> {
+    /// Create a new SqlxLogger wrapping a destination [`log::Log`].
+    pub fn new(dest: L) -> Self {
+        Self { dest }
+    }
+}
+
+impl<L: log::Log> log::Log for SqlxLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        is_sqlx_query(metadata) || self.dest.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if is_sqlx_query(record.metadata()) {
+            record_query_span(record);
+            sentry_core::add_breadcrumb(|| breadcrumb_from_query_record(record));
+        }
+
+        if self.dest.enabled(record.metadata()) {
+            self.dest.log(record)
+        }
+    }
+
+    fn flush(&self) {
+        self.dest.flush()
+    }
+}
+
+/// Records a statement logged by `sqlx` as a `db` child span of the current span.
+///
+/// `sqlx` logs statements once they have completed, so the span is backdated by the
+/// elapsed time of the statement.
+fn record_query_span(record: &log::Record<'_>) {
+    let parent = match sentry_core::configure_scope(|scope| scope.get_span()) {
+        Some(parent) => parent,
+        None => return,
+    };
+
+    let query = parse_query_record(record);
+    let span = parent.start_child("db", &query.statement);
+    if let Some(start) = query
+        .elapsed
+        .and_then(|elapsed| SystemTime::now().checked_sub(elapsed))
+    {
+        span.set_start_timestamp(DateTime::<Utc>::from(start));
+    }
+    for (key, value) in query.data {
+        span.set_data(&key, value);
+    }
+    span.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Log;
+    use sentry::protocol::Value;
+    use sentry::Level;
+
+    use super::*;
+    use crate::SQLX_QUERY_TARGET;
+
+    struct NoopLogger;
+
+    impl log::Log for NoopLogger {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            false
+        }
+
+        fn log(&self, _: &log::Record<'_>) {}
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_query_breadcrumbs() {
+        let logger = SqlxLogger::new(NoopLogger);
+
+        let events = sentry::test::with_captured_events(|| {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!(
+                        "SELECT * FROM users …; rows affected: 0, rows returned: 2, elapsed: 1.250ms\n\nSELECT\n  *\nFROM\n  users\nWHERE\n  id = $1"
+                    ))
+                    .level(log::Level::Debug)
+                    .target(SQLX_QUERY_TARGET)
+                    .build(),
+            );
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("unrelated"))
+                    .level(log::Level::Info)
+                    .target("app")
+                    .build(),
+            );
+            sentry::capture_message("Message", Level::Warning);
+        });
+
+        assert_eq!(events.len(), 1);
+        let breadcrumbs = &events[0].breadcrumbs;
+        assert_eq!(breadcrumbs.len(), 1);
+        assert_eq!(breadcrumbs[0].category, Some("db.query".into()));
+        assert_eq!(
+            breadcrumbs[0].message,
+            Some("SELECT\n  *\nFROM\n  users\nWHERE\n  id = $1".into())
+        );
+        assert_eq!(breadcrumbs[0].data["rows_returned"], Value::from(2));
+        assert_eq!(breadcrumbs[0].data["duration_ms"], Value::from(1.25));
+    }
+
+    #[test]
+    fn test_query_spans() {
+        let logger = SqlxLogger::new(NoopLogger);
+
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                // not recorded, as there is no transaction yet
+                logger.log(
+                    &log::Record::builder()
+                        .args(format_args!(
+                            "BEGIN; rows affected: 0, rows returned: 0, elapsed: 20.000µs"
+                        ))
+                        .level(log::Level::Debug)
+                        .target(SQLX_QUERY_TARGET)
+                        .build(),
+                );
+
+                let transaction =
+                    sentry::start_transaction(sentry::TransactionContext::new("test", "test"));
+                sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+                logger.log(
+                    &log::Record::builder()
+                        .args(format_args!(
+                            "SELECT * FROM users; rows affected: 0, rows returned: 2, elapsed: 1.500s\n\nSELECT\n  *\nFROM\n  users"
+                        ))
+                        .level(log::Level::Debug)
+                        .target(SQLX_QUERY_TARGET)
+                        .build(),
+                );
+                sentry::configure_scope(|scope| scope.set_span(None));
+                transaction.finish();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(envelopes.len(), 1);
+        let transaction = match envelopes[0].items().next().unwrap() {
+            sentry::protocol::EnvelopeItem::Transaction(transaction) => transaction,
+            item => panic!("unexpected envelope item {:?}", item),
+        };
+        assert_eq!(transaction.spans.len(), 1);
+        let span = &transaction.spans[0];
+        assert_eq!(span.op.as_deref(), Some("db"));
+        assert_eq!(
+            span.description.as_deref(),
+            Some("SELECT\n  *\nFROM\n  users")
+        );
+        assert_eq!(span.data["rows_returned"], Value::from(2));
+        assert_eq!(span.data["duration_ms"], Value::from(1500.0));
+        let duration = span.timestamp.unwrap() - span.start_timestamp;
+        assert!(duration.num_milliseconds() >= 1500);
+    }
+}
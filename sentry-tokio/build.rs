@@ -0,0 +1,5 @@
+fn main() {
+    // runtime metrics are only available with `RUSTFLAGS="--cfg tokio_unstable"`
+    println!("cargo:rustc-check-cfg=cfg(tokio_unstable)");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,172 @@
+//! Adds support for attaching [`tokio`](https://docs.rs/tokio) runtime metrics to
+//! transactions.
+//!
+//! Latency that is caused by a saturated runtime, rather than by the code of a
+//! transaction, is hard to tell apart in traces.  The [`TokioMetricsIntegration`]
+//! periodically samples the metrics of a runtime, and attaches the latest sample to
+//! every transaction as a `tokio` context:
+//!
+//! * `workers`: the number of worker threads.
+//! * `worker_utilization`: the share of the last interval the workers were busy.
+//! * `active_tasks`: the number of tasks alive in the runtime.
+//! * `injection_queue_depth` and `local_queue_depth`: the number of tasks scheduled
+//!   from outside of the runtime, and in the local queues of the workers.
+//! * `blocking_queue_depth`, `blocking_threads` and `idle_blocking_threads`: the state
+//!   of the blocking thread pool used by `spawn_blocking`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! #[tokio::main]
+//! async fn main() {
+//!     let _sentry = sentry::init(sentry::ClientOptions {
+//!         traces_sample_rate: 1.0,
+//!         ..Default::default()
+//!     }
+//!     .add_integration(sentry_tokio::TokioMetricsIntegration::new()));
+//! }
+//! ```
+//!
+//! # Availability
+//!
+//! The runtime metrics of tokio are unstable, and only available when building with
+//! `RUSTFLAGS="--cfg tokio_unstable"`.  Otherwise, the integration does nothing.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use sentry_core::protocol::Transaction;
+use sentry_core::{ClientOptions, Integration};
+use tokio::runtime::Handle;
+
+mod metrics;
+
+use crate::metrics::Snapshot;
+
+/// The Sentry tokio runtime metrics Integration.
+#[derive(Debug)]
+pub struct TokioMetricsIntegration {
+    handle: Option<Handle>,
+    interval: Duration,
+    latest: Arc<Mutex<Option<Snapshot>>>,
+    spawned: AtomicBool,
+}
+
+impl TokioMetricsIntegration {
+    /// Creates a new tokio runtime metrics Integration.
+    pub fn new() -> Self {
+        Self {
+            handle: None,
+            interval: Duration::from_secs(1),
+            latest: Arc::new(Mutex::new(None)),
+            spawned: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets the runtime whose metrics are sampled.
+    ///
+    /// By default, this is the runtime the client is created in.
+    pub fn handle(mut self, handle: Handle) -> Self {
+        self.handle = Some(handle);
+        self
+    }
+
+    /// Sets how often the metrics are sampled.
+    ///
+    /// The default is once per second.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl Default for TokioMetricsIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integration for TokioMetricsIntegration {
+    fn name(&self) -> &'static str {
+        "tokio-metrics"
+    }
+
+    fn setup(&self, _options: &mut ClientOptions) {
+        if self.spawned.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let handle = match self.handle.clone().or_else(|| Handle::try_current().ok()) {
+            Some(handle) => handle,
+            None => {
+                sentry_core::sentry_debug!("no tokio runtime to sample the metrics of");
+                return;
+            }
+        };
+        metrics::spawn_sampler(&handle, self.interval, self.latest.clone());
+    }
+
+    fn process_transaction(
+        &self,
+        mut transaction: Transaction<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Transaction<'static>> {
+        if let Some(ref snapshot) = *self.latest.lock().unwrap() {
+            transaction
+                .contexts
+                .insert("tokio".into(), snapshot.to_context());
+        }
+        Some(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry::protocol::{Context, EnvelopeItem};
+
+    use super::*;
+
+    #[test]
+    fn test_transaction_context() {
+        let integration = TokioMetricsIntegration::new();
+        *integration.latest.lock().unwrap() = Some(Snapshot {
+            workers: 4,
+            worker_utilization: 0.756,
+            active_tasks: 12,
+            ..Default::default()
+        });
+
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let ctx = sentry::TransactionContext::new("job", "queue.task");
+                sentry::start_transaction(ctx).finish();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            }
+            .add_integration(integration),
+        );
+
+        let transaction = envelopes
+            .iter()
+            .flat_map(|envelope| envelope.items())
+            .find_map(|item| match item {
+                EnvelopeItem::Transaction(transaction) => Some(transaction),
+                _ => None,
+            })
+            .unwrap();
+        match transaction.contexts.get("tokio") {
+            Some(Context::Other(context)) => {
+                assert_eq!(context["workers"], 4);
+                assert_eq!(context["worker_utilization"], 0.76);
+                assert_eq!(context["active_tasks"], 12);
+            }
+            context => panic!("expected tokio context, got {:?}", context),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Samples the metrics of a tokio runtime.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use sentry_core::protocol::{Context, Map};
+
+/// The metrics of a runtime, sampled over one interval.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Snapshot {
+    pub workers: usize,
+    /// The share of the interval the workers were busy, between 0 and 1.
+    pub worker_utilization: f64,
+    pub active_tasks: usize,
+    pub injection_queue_depth: usize,
+    pub local_queue_depth: usize,
+    pub blocking_queue_depth: usize,
+    pub blocking_threads: usize,
+    pub idle_blocking_threads: usize,
+}
+
+impl Snapshot {
+    /// Converts the snapshot into a `tokio` context.
+    pub fn to_context(&self) -> Context {
+        let mut map = Map::new();
+        map.insert("workers".into(), self.workers.into());
+        // rounded to whole percents, more precision is meaningless for a sample
+        let utilization = (self.worker_utilization * 100.0).round() / 100.0;
+        map.insert("worker_utilization".into(), utilization.into());
+        map.insert("active_tasks".into(), self.active_tasks.into());
+        map.insert(
+            "injection_queue_depth".into(),
+            self.injection_queue_depth.into(),
+        );
+        map.insert("local_queue_depth".into(), self.local_queue_depth.into());
+        map.insert(
+            "blocking_queue_depth".into(),
+            self.blocking_queue_depth.into(),
+        );
+        map.insert("blocking_threads".into(), self.blocking_threads.into());
+        map.insert(
+            "idle_blocking_threads".into(),
+            self.idle_blocking_threads.into(),
+        );
+        Context::Other(map)
+    }
+}
+
+/// Spawns a task on the runtime that stores a snapshot of its metrics every `interval`.
+#[cfg(tokio_unstable)]
+pub(crate) fn spawn_sampler(
+    handle: &tokio::runtime::Handle,
+    interval: Duration,
+    latest: Arc<Mutex<Option<Snapshot>>>,
+) {
+    use std::time::Instant;
+
+    let metrics = handle.metrics();
+    let total_busy = |metrics: &tokio::runtime::RuntimeMetrics| -> Duration {
+        (0..metrics.num_workers())
+            .map(|worker| metrics.worker_total_busy_duration(worker))
+            .sum()
+    };
+
+    handle.spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut last_busy = total_busy(&metrics);
+        let mut last_tick = Instant::now();
+        loop {
+            ticker.tick().await;
+            let busy = total_busy(&metrics);
+            let now = Instant::now();
+            let workers = metrics.num_workers();
+            let capacity = now.duration_since(last_tick).as_secs_f64() * workers as f64;
+            let utilization = if capacity > 0.0 {
+                (busy.saturating_sub(last_busy).as_secs_f64() / capacity).min(1.0)
+            } else {
+                0.0
+            };
+            last_busy = busy;
+            last_tick = now;
+
+            let snapshot = Snapshot {
+                workers,
+                worker_utilization: utilization,
+                active_tasks: metrics.active_tasks_count(),
+                injection_queue_depth: metrics.injection_queue_depth(),
+                local_queue_depth: (0..workers)
+                    .map(|worker| metrics.worker_local_queue_depth(worker))
+                    .sum(),
+                blocking_queue_depth: metrics.blocking_queue_depth(),
+                blocking_threads: metrics.num_blocking_threads(),
+                idle_blocking_threads: metrics.num_idle_blocking_threads(),
+            };
+            *latest.lock().unwrap() = Some(snapshot);
+        }
+    });
+}
+
+/// Without `tokio_unstable`, the metrics of the runtime are not available.
+#[cfg(not(tokio_unstable))]
+pub(crate) fn spawn_sampler(
+    _handle: &tokio::runtime::Handle,
+    _interval: Duration,
+    _latest: Arc<Mutex<Option<Snapshot>>>,
+) {
+    sentry_core::sentry_debug!("tokio runtime metrics require building with --cfg tokio_unstable");
+}
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use sentry_core::protocol::{Context, SpanId, TraceContext};
+use sentry_core::types::{SentryTrace, Uuid, BAGGAGE_HEADER, SENTRY_TRACE_HEADER};
+use sentry_core::Hub;
+use tonic::metadata::MetadataMap;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// Server interceptor that continues the trace of the calling service.
+///
+/// The trace context of the scope is set to a new span that continues the trace from the
+/// `sentry-trace` metadata of the request, or to a new trace if the metadata is missing.
+/// The `baggage` metadata is kept in the scope, so it can be propagated further by the
+/// [`SentryClientInterceptor`].
+///
+/// This configures the hub that the `SentryHttpLayer` of `sentry-tower` inserted into the
+/// request extensions, or the current hub if there is none.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SentryServerInterceptor;
+
+impl Interceptor for SentryServerInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let metadata = request.metadata();
+        let mut trace_context = metadata_str(metadata, SENTRY_TRACE_HEADER)
+            .and_then(|value| value.parse::<SentryTrace>().ok())
+            .map_or_else(new_trace, |trace| trace.new_child());
+        trace_context.op = Some("grpc.server".into());
+        let baggage = metadata_str(metadata, BAGGAGE_HEADER).map(ToOwned::to_owned);
+        let hub = request
+            .extensions()
+            .get::<Arc<Hub>>()
+            .cloned()
+            .unwrap_or_else(Hub::current);
+
+        hub.configure_scope(|scope| {
+            scope.set_context("trace", trace_context);
+            if let Some(baggage) = baggage {
+                scope.set_baggage(Some(&baggage));
+            }
+        });
+        Ok(request)
+    }
+}
+
+/// Client interceptor that propagates the trace of the current scope.
+///
+/// The `sentry-trace` metadata is derived from the current span, or otherwise from the trace
+/// context of the current scope with a new span id.  If the scope has neither, the call starts
+/// a new trace.  The scope itself is never changed.  Baggage received by the
+/// [`SentryServerInterceptor`] is forwarded as well.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SentryClientInterceptor;
+
+impl Interceptor for SentryClientInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let (trace, baggage) = Hub::current().configure_scope(|scope| {
+            let trace = match (scope.get_span(), scope.get_context("trace")) {
+                (Some(span), _) => span.sentry_trace(),
+                (None, Some(Context::Trace(trace_context))) => SentryTrace {
+                    span_id: SpanId::default(),
+                    ..SentryTrace::from_trace_context(trace_context)
+                },
+                _ => SentryTrace::from_trace_context(&new_trace()),
+            };
+            (Some(trace), scope.get_baggage().map(ToOwned::to_owned))
+        });
+
+        let metadata = request.metadata_mut();
+        if let Some(value) = trace.and_then(|trace| trace.to_string().parse().ok()) {
+            metadata.insert(SENTRY_TRACE_HEADER, value);
+        }
+        if let Some(value) = baggage.and_then(|baggage| baggage.parse().ok()) {
+            metadata.insert(BAGGAGE_HEADER, value);
+        }
+        Ok(request)
+    }
+}
+
+/// Creates the trace context for a new trace
+fn new_trace() -> TraceContext {
+    TraceContext {
+        span_id: SpanId::default(),
+        trace_id: Uuid::new_v4(),
+        ..Default::default()
+    }
+}
+
+fn metadata_str<'a>(metadata: &'a MetadataMap, key: &str) -> Option<&'a str> {
+    metadata.get(key).and_then(|value| value.to_str().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry::Level;
+
+    use super::*;
+
+    #[test]
+    fn test_trace_propagation() {
+        let upstream: SentryTrace = "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a"
+            .parse()
+            .unwrap();
+
+        let mut outgoing = None;
+        let events = sentry::test::with_captured_events(|| {
+            let mut request = Request::new(());
+            let metadata = request.metadata_mut();
+            metadata.insert(SENTRY_TRACE_HEADER, upstream.to_string().parse().unwrap());
+            metadata.insert(BAGGAGE_HEADER, "sentry-release=1.0".parse().unwrap());
+            SentryServerInterceptor.call(request).unwrap();
+
+            sentry::capture_message("Message", Level::Warning);
+
+            let request = SentryClientInterceptor.call(Request::new(())).unwrap();
+            outgoing = Some(request.metadata().clone());
+        });
+
+        assert_eq!(events.len(), 1);
+        let trace_context = match events[0].contexts.get("trace") {
+            Some(Context::Trace(trace_context)) => trace_context,
+            _ => panic!("Trace context should be set."),
+        };
+        assert_eq!(trace_context.trace_id, upstream.trace_id);
+        assert_eq!(
+            trace_context.parent_span_id,
+            Some("8c1f9f3e1d2b4c5a".into())
+        );
+        assert_eq!(trace_context.op, Some("grpc.server".into()));
+
+        let outgoing = outgoing.unwrap();
+        let trace: SentryTrace = metadata_str(&outgoing, SENTRY_TRACE_HEADER)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(trace.trace_id, upstream.trace_id);
+        assert_ne!(trace.span_id, trace_context.span_id);
+        assert_eq!(
+            metadata_str(&outgoing, BAGGAGE_HEADER),
+            Some("sentry-release=1.0")
+        );
+    }
+
+    #[test]
+    fn test_server_hub_from_extensions() {
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        let mut request = Request::new(());
+        request.extensions_mut().insert(hub.clone());
+        SentryServerInterceptor.call(request).unwrap();
+
+        let has_trace =
+            |hub: &Hub| hub.configure_scope(|scope| scope.get_context("trace").is_some());
+        assert!(has_trace(&hub));
+        assert!(!has_trace(&Hub::current()));
+    }
+
+    #[test]
+    fn test_client_new_trace() {
+        let outgoing_trace = || {
+            let request = SentryClientInterceptor.call(Request::new(())).unwrap();
+            metadata_str(request.metadata(), SENTRY_TRACE_HEADER)
+                .unwrap()
+                .parse::<SentryTrace>()
+                .unwrap()
+        };
+
+        Hub::run(Arc::new(Hub::new_from_top(Hub::current())), || {
+            let first = outgoing_trace();
+            let second = outgoing_trace();
+            assert_ne!(first.trace_id, second.trace_id);
+            assert!(Hub::current().configure_scope(|scope| scope.get_context("trace").is_none()));
+        });
+    }
+}
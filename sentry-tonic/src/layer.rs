@@ -0,0 +1,166 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http_::Response;
+use sentry_core::protocol::Context as SentryContext;
+use sentry_core::Hub;
+use tonic::{Code, Status};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{event_from_status, is_server_error, span_status_from_code};
+
+/// Tower layer that reports failed gRPC calls.
+///
+/// The status of every call is set on the trace context of the current scope, and calls
+/// that failed with a server error are captured as events.
+///
+/// Only failures that are sent as a trailers-only response are detected, which is how
+/// `tonic` responds when a unary call returns an error.  Failures of streaming calls that
+/// are only reported in the trailers after the response body are not seen by this layer.
+#[derive(Clone, Debug)]
+pub struct SentryGrpcLayer {
+    capture_server_errors: bool,
+}
+
+impl SentryGrpcLayer {
+    /// Creates a new layer that captures calls that failed with a server error.
+    pub fn new() -> Self {
+        Self {
+            capture_server_errors: true,
+        }
+    }
+
+    /// Enables or disables capturing of calls that failed with a server error.
+    ///
+    /// See [`is_server_error`](crate::is_server_error) for the status codes that are
+    /// considered server errors.
+    pub fn capture_server_errors(mut self, val: bool) -> Self {
+        self.capture_server_errors = val;
+        self
+    }
+}
+
+impl Default for SentryGrpcLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for SentryGrpcLayer {
+    type Service = SentryGrpcService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SentryGrpcService {
+            service,
+            capture_server_errors: self.capture_server_errors,
+        }
+    }
+}
+
+/// Tower service that reports failed gRPC calls.
+#[derive(Clone, Debug)]
+pub struct SentryGrpcService<S> {
+    service: S,
+    capture_server_errors: bool,
+}
+
+impl<S, Request, B> Service<Request> for SentryGrpcService<S>
+where
+    S: Service<Request, Response = Response<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SentryGrpcFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        SentryGrpcFuture {
+            future: self.service.call(request),
+            hub: Hub::current(),
+            capture_server_errors: self.capture_server_errors,
+        }
+    }
+}
+
+/// The future returned by [`SentryGrpcService`].
+#[derive(Debug)]
+pub struct SentryGrpcFuture<F> {
+    future: F,
+    hub: Arc<Hub>,
+    capture_server_errors: bool,
+}
+
+impl<F, B, E> Future for SentryGrpcFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // https://doc.rust-lang.org/std/pin/index.html#pinning-is-structural-for-field
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let poll = future.poll(cx);
+
+        if let Poll::Ready(Ok(response)) = &poll {
+            let status = Status::from_header_map(response.headers());
+            let code = status.as_ref().map_or(Code::Ok, Status::code);
+
+            this.hub.configure_scope(|scope| {
+                if let Some(SentryContext::Trace(trace_context)) = scope.get_context("trace") {
+                    let mut trace_context = (**trace_context).clone();
+                    trace_context.status = Some(span_status_from_code(code).into());
+                    scope.set_context("trace", trace_context);
+                }
+            });
+
+            if let Some(status) = status {
+                if this.capture_server_errors && is_server_error(code) {
+                    this.hub.capture_event(event_from_status(&status));
+                }
+            }
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use futures::executor::block_on;
+    use sentry_tower::SentryLayer;
+    use tower::{service_fn, ServiceBuilder, ServiceExt};
+
+    use super::*;
+
+    #[test]
+    fn test_failed_calls() {
+        let events = sentry::test::with_captured_events(|| {
+            let service = ServiceBuilder::new()
+                .layer(SentryLayer::new_from_top())
+                .layer(SentryGrpcLayer::new())
+                .service(service_fn(|message: &'static str| async move {
+                    let status = match message {
+                        "internal" => Status::internal("Database is gone"),
+                        _ => Status::not_found("No such user"),
+                    };
+                    Ok::<_, Infallible>(status.to_http())
+                }));
+
+            block_on(service.clone().oneshot("internal")).unwrap();
+            block_on(service.oneshot("not found")).unwrap();
+        });
+
+        assert_eq!(events.len(), 1);
+        let exception = &events[0].exception.values[0];
+        assert_eq!(exception.ty, "Internal");
+        assert_eq!(exception.value, Some("Database is gone".into()));
+    }
+}
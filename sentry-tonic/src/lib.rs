@@ -0,0 +1,136 @@
+//! Adds support for the [`tonic`](https://docs.rs/tonic) gRPC framework.
+//!
+//! This crate provides:
+//!
+//! * The [`SentryServerInterceptor`], which continues the trace of the calling service
+//!   from the `sentry-trace` and `baggage` metadata of incoming requests.
+//! * The [`SentryClientInterceptor`], which propagates the trace of the current scope to
+//!   the called service using the same metadata.
+//! * The [`SentryGrpcLayer`], which maps the status of failed calls to a span status and
+//!   captures calls that failed with a server error as events.
+//!
+//! The server side is meant to be used together with the
+//! [`sentry-tower`](https://docs.rs/sentry-tower) layer, which binds a new hub per request,
+//! so that the trace of one call does not leak into another.
+//!
+//! # Server Example
+//!
+//! ```ignore
+//! use sentry_tonic::{SentryGrpcLayer, SentryServerInterceptor};
+//! use tower::ServiceBuilder;
+//!
+//! let layer = ServiceBuilder::new()
+//!     .layer(sentry_tower::SentryLayer::new_from_top())
+//!     .layer(sentry_tower::SentryHttpLayer::new())
+//!     .layer(SentryGrpcLayer::new())
+//!     .into_inner();
+//!
+//! Server::builder()
+//!     .layer(layer)
+//!     .add_service(GreeterServer::with_interceptor(MyGreeter, SentryServerInterceptor))
+//!     .serve(addr)
+//!     .await?;
+//! ```
+//!
+//! # Client Example
+//!
+//! ```ignore
+//! use sentry_tonic::SentryClientInterceptor;
+//!
+//! let channel = Channel::from_static("http://[::1]:50051").connect().await?;
+//! let client = GreeterClient::with_interceptor(channel, SentryClientInterceptor);
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use sentry_core::protocol::{Event, Exception, Level, Mechanism, SpanStatus};
+use sentry_core::types::Uuid;
+use sentry_core::Hub;
+use tonic::{Code, Status};
+
+mod interceptor;
+mod layer;
+
+pub use crate::interceptor::{SentryClientInterceptor, SentryServerInterceptor};
+pub use crate::layer::{SentryGrpcFuture, SentryGrpcLayer, SentryGrpcService};
+
+/// Maps a gRPC status code to the corresponding Sentry span status.
+///
+/// This is a shorthand for [`SpanStatus::from_grpc_status`].
+pub fn span_status_from_code(code: Code) -> &'static str {
+    SpanStatus::from_grpc_status(code as i32).as_str()
+}
+
+/// Returns whether the status code signals a failure of the server.
+///
+/// Codes that are caused by the caller, like `InvalidArgument` or `NotFound`, are not
+/// considered server errors.
+pub fn is_server_error(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Unknown
+            | Code::DeadlineExceeded
+            | Code::Unimplemented
+            | Code::Internal
+            | Code::Unavailable
+            | Code::DataLoss
+    )
+}
+
+/// Creates a Sentry [`Event`] from a gRPC [`Status`].
+pub fn event_from_status(status: &Status) -> Event<'static> {
+    let mut event = Event {
+        exception: vec![Exception {
+            ty: format!("{:?}", status.code()),
+            value: Some(status.message().to_owned()).filter(|msg| !msg.is_empty()),
+            mechanism: Some(Mechanism {
+                ty: "grpc".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }]
+        .into(),
+        level: Level::Error,
+        ..Default::default()
+    };
+    event.tags.insert(
+        "grpc.status_code".into(),
+        span_status_from_code(status.code()).into(),
+    );
+    event
+}
+
+/// Captures a gRPC [`Status`] on the current hub.
+///
+/// This is useful on the client side, to report unexpected failures of a call:
+///
+/// ```
+/// let status = tonic::Status::unavailable("Service is down");
+/// sentry_tonic::capture_status(&status);
+/// ```
+pub fn capture_status(status: &Status) -> Uuid {
+    Hub::with_active(|hub| hub.capture_event(event_from_status(status)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_status() {
+        let events = sentry::test::with_captured_events(|| {
+            capture_status(&Status::internal("Database is gone"));
+        });
+
+        assert_eq!(events.len(), 1);
+        let exception = &events[0].exception.values[0];
+        assert_eq!(exception.ty, "Internal");
+        assert_eq!(exception.value, Some("Database is gone".into()));
+        assert_eq!(
+            events[0].tags.get("grpc.status_code").unwrap(),
+            "internal_error"
+        );
+    }
+}
@@ -0,0 +1,575 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http_::header::{HeaderName, HeaderValue};
+use http_::{Request, Response};
+use sentry_core::protocol::{self, ClientSdkPackage, Event, SpanStatus, User};
+use sentry_core::types::{SentryTrace, Uuid, BAGGAGE_HEADER, SENTRY_TRACE_HEADER};
+use sentry_core::{Hub, Transaction, TransactionContext};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Tower layer that adds request information to the events captured while handling an
+/// [`http::Request`](http_::Request).
+///
+/// An `http.server` transaction named after the request method and path is started for every
+/// request, continuing the trace of an incoming `sentry-trace` header, and finished with the
+/// status of the response.  The transaction is the current span of the scope while the
+/// request is handled, and the [`Request`](protocol::Request) interface is attached to all
+/// captured events.
+///
+/// If an event was captured while handling the request, its id is inserted as a [`Uuid`] into
+/// the extensions of the response, and can optionally be sent to the client in the
+/// `X-Sentry-Event-Id` header.  The hub of the request is inserted as an `Arc<Hub>` into the
+/// extensions of the request, for services that run outside of its future.
+///
+/// With [`send_default_pii`](sentry_core::ClientOptions::send_default_pii) enabled, a
+/// [`UserExtractor`] can set the user of the scope from the request, for example from the
+/// authentication state an earlier layer inserted into its extensions.
+///
+/// A [`RequestFilter`] can exclude requests, like health checks, from being instrumented.
+///
+/// This layer configures the scope of the currently bound hub, and should thus be used
+/// together with a [`SentryLayer`](crate::SentryLayer) that binds a new hub per request.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SentryHttpLayer<U = NoUserExtractor, R = NoRequestFilter> {
+    emit_header: bool,
+    user_extractor: U,
+    request_filter: R,
+}
+
+impl SentryHttpLayer<NoUserExtractor> {
+    /// Creates a new layer that adds request information to captured events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<U, R> SentryHttpLayer<U, R> {
+    /// Sets the user of the scope from each request with the given extractor.
+    ///
+    /// The extractor is only called if `send_default_pii` is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_::Request;
+    /// use sentry_core::protocol::User;
+    ///
+    /// struct UserId(String);
+    ///
+    /// let layer = sentry_tower::SentryHttpLayer::new().user_extractor(|request: &Request<()>| {
+    ///     let user_id = request.extensions().get::<UserId>()?;
+    ///     Some(User {
+    ///         id: Some(user_id.0.clone()),
+    ///         ..Default::default()
+    ///     })
+    /// });
+    /// ```
+    pub fn user_extractor<E>(self, user_extractor: E) -> SentryHttpLayer<E, R> {
+        SentryHttpLayer {
+            emit_header: self.emit_header,
+            user_extractor,
+            request_filter: self.request_filter,
+        }
+    }
+
+    /// Only instruments the requests for which the given filter returns `true`.
+    ///
+    /// Other requests, like health checks, are passed through without starting a
+    /// transaction or setting request data on the scope, and without attaching event ids
+    /// to their responses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_::Request;
+    ///
+    /// let layer = sentry_tower::SentryHttpLayer::new()
+    ///     .request_filter(|request: &Request<()>| request.uri().path() != "/health");
+    /// ```
+    pub fn request_filter<F>(self, request_filter: F) -> SentryHttpLayer<U, F> {
+        SentryHttpLayer {
+            emit_header: self.emit_header,
+            user_extractor: self.user_extractor,
+            request_filter,
+        }
+    }
+
+    /// If enabled, the id of the event captured while handling the request is attached to
+    /// the response in the `X-Sentry-Event-Id` header.
+    ///
+    /// The default is to not emit the header.
+    pub fn emit_header(mut self, val: bool) -> Self {
+        self.emit_header = val;
+        self
+    }
+}
+
+impl<S, U, R> Layer<S> for SentryHttpLayer<U, R>
+where
+    U: Clone,
+    R: Clone,
+{
+    type Service = SentryHttpService<S, U, R>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SentryHttpService {
+            service,
+            emit_header: self.emit_header,
+            user_extractor: self.user_extractor.clone(),
+            request_filter: self.request_filter.clone(),
+        }
+    }
+}
+
+/// Extracts the [`User`] that made a request.
+///
+/// This is implemented for closures taking a reference to the request.
+pub trait UserExtractor<Request> {
+    /// Returns the user that made the request, if it is known.
+    fn extract_user(&self, request: &Request) -> Option<User>;
+}
+
+impl<F, Request> UserExtractor<Request> for F
+where
+    F: Fn(&Request) -> Option<User>,
+{
+    fn extract_user(&self, request: &Request) -> Option<User> {
+        (self)(request)
+    }
+}
+
+/// The default [`UserExtractor`], which does not set a user.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoUserExtractor;
+
+impl<Request> UserExtractor<Request> for NoUserExtractor {
+    fn extract_user(&self, _request: &Request) -> Option<User> {
+        None
+    }
+}
+
+/// Decides which requests are instrumented.
+///
+/// This is implemented for closures taking a reference to the request.
+pub trait RequestFilter<Request> {
+    /// Returns whether the request should be instrumented.
+    fn filter_request(&self, request: &Request) -> bool;
+}
+
+impl<F, Request> RequestFilter<Request> for F
+where
+    F: Fn(&Request) -> bool,
+{
+    fn filter_request(&self, request: &Request) -> bool {
+        (self)(request)
+    }
+}
+
+/// The default [`RequestFilter`], which instruments all requests.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoRequestFilter;
+
+impl<Request> RequestFilter<Request> for NoRequestFilter {
+    fn filter_request(&self, _request: &Request) -> bool {
+        true
+    }
+}
+
+/// Tower service that adds request information to the events captured while handling an
+/// [`http::Request`](http_::Request).
+#[derive(Clone, Debug)]
+pub struct SentryHttpService<S, U = NoUserExtractor, R = NoRequestFilter> {
+    service: S,
+    emit_header: bool,
+    user_extractor: U,
+    request_filter: R,
+}
+
+impl<S, U, R, B, ResBody> Service<Request<B>> for SentryHttpService<S, U, R>
+where
+    S: Service<Request<B>, Response = Response<ResBody>>,
+    U: UserExtractor<Request<B>>,
+    R: RequestFilter<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SentryHttpFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        let hub = Hub::current();
+        request.extensions_mut().insert(hub.clone());
+
+        if !self.request_filter.filter_request(&request) {
+            return SentryHttpFuture {
+                future: self.service.call(request),
+                hub: None,
+                transaction: None,
+                last_event_id: None,
+                emit_header: false,
+            };
+        }
+
+        let with_pii = hub
+            .client()
+            .map_or(false, |client| client.options().send_default_pii);
+
+        let (transaction, sentry_req) = sentry_request_from_http(&request, with_pii);
+        let user = if with_pii {
+            self.user_extractor.extract_user(&request)
+        } else {
+            None
+        };
+        let headers = request.headers();
+        let trace = headers
+            .get(SENTRY_TRACE_HEADER)
+            .and_then(|value| value.to_str().ok()?.parse::<SentryTrace>().ok());
+        let baggage = headers
+            .get(BAGGAGE_HEADER)
+            .and_then(|value| value.to_str().ok());
+        let ctx = match trace {
+            Some(trace) => {
+                TransactionContext::continue_from_sentry_trace(&transaction, "http.server", &trace)
+            }
+            None => TransactionContext::new(&transaction, "http.server"),
+        };
+        let sentry_transaction = sentry_core::start_transaction(ctx);
+        hub.configure_scope(|scope| {
+            scope.set_transaction(Some(&transaction));
+            scope.set_span(Some(sentry_transaction.clone().into()));
+            scope.set_baggage(baggage);
+            if user.is_some() {
+                scope.set_user(user);
+            }
+            scope.add_event_processor(Box::new(move |event| process_event(event, &sentry_req)))
+        });
+
+        SentryHttpFuture {
+            last_event_id: hub.last_event_id(),
+            future: self.service.call(request),
+            hub: Some(hub),
+            transaction: Some(sentry_transaction),
+            emit_header: self.emit_header,
+        }
+    }
+}
+
+/// The name of the header the event id is emitted in.
+const EVENT_ID_HEADER: &str = "x-sentry-event-id";
+
+/// The future returned by [`SentryHttpService`].
+///
+/// If an event was captured while the inner future was running, its id is attached to the
+/// response.  The transaction of the request is finished once the response is ready.
+#[derive(Debug)]
+pub struct SentryHttpFuture<F> {
+    future: F,
+    hub: Option<Arc<Hub>>,
+    transaction: Option<Transaction>,
+    last_event_id: Option<Uuid>,
+    emit_header: bool,
+}
+
+impl<F, ResBody, E> Future for SentryHttpFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // https://doc.rust-lang.org/std/pin/index.html#pinning-is-structural-for-field
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let poll = future.poll(cx);
+        if let Poll::Ready(ref result) = poll {
+            let status = match result {
+                Ok(response) => SpanStatus::from_http_status(response.status().as_u16()),
+                Err(_) => SpanStatus::InternalError,
+            };
+            this.finish_transaction(status);
+        }
+        match poll {
+            Poll::Ready(Ok(mut response)) => {
+                let last_event_id = this.last_event_id;
+                let event_id = this
+                    .hub
+                    .as_ref()
+                    .and_then(|hub| hub.last_event_id())
+                    .filter(|id| !id.is_nil() && Some(*id) != last_event_id);
+                if let Some(event_id) = event_id {
+                    if this.emit_header {
+                        let value = HeaderValue::from_str(&event_id.to_simple_ref().to_string());
+                        if let Ok(value) = value {
+                            response
+                                .headers_mut()
+                                .insert(HeaderName::from_static(EVENT_ID_HEADER), value);
+                        }
+                    }
+                    response.extensions_mut().insert(event_id);
+                }
+                Poll::Ready(Ok(response))
+            }
+            poll => poll,
+        }
+    }
+}
+
+impl<F> SentryHttpFuture<F> {
+    /// Finishes the transaction of the request, and unbinds it from the scope.
+    fn finish_transaction(&mut self, status: SpanStatus) {
+        if let Some(transaction) = self.transaction.take() {
+            transaction.set_status(status.as_str());
+            if let Some(ref hub) = self.hub {
+                hub.configure_scope(|scope| scope.set_span(None));
+            }
+            transaction.finish();
+        }
+    }
+}
+
+/// Build a Sentry request struct from the HTTP request
+fn sentry_request_from_http<B>(
+    request: &Request<B>,
+    with_pii: bool,
+) -> (String, protocol::Request) {
+    let transaction = format!("{} {}", request.method(), request.uri().path());
+    (transaction, protocol::Request::from_http(request, with_pii))
+}
+
+/// Add request data to a Sentry event
+fn process_event(mut event: Event<'static>, request: &protocol::Request) -> Option<Event<'static>> {
+    // Request
+    if event.request.is_none() {
+        event.request = Some(request.clone());
+    }
+
+    // SDK
+    if let Some(sdk) = event.sdk.take() {
+        let mut sdk = sdk.into_owned();
+        sdk.packages.push(ClientSdkPackage {
+            name: "sentry-tower".into(),
+            version: env!("CARGO_PKG_VERSION").into(),
+        });
+        event.sdk = Some(Cow::Owned(sdk));
+    }
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use futures::executor::block_on;
+    use sentry::protocol::EnvelopeItem;
+    use sentry::Level;
+    use tower::{service_fn, ServiceBuilder, ServiceExt};
+
+    use http_::header;
+
+    use super::*;
+    use crate::SentryLayer;
+
+    #[test]
+    fn test_request_interface() {
+        let events = sentry::test::with_captured_events(|| {
+            let service = ServiceBuilder::new()
+                .layer(SentryLayer::new_from_top())
+                .layer(SentryHttpLayer::new())
+                .service(service_fn(|_: Request<()>| async {
+                    sentry::capture_message("Message", Level::Warning);
+                    Ok::<_, Infallible>(Response::new(()))
+                }));
+
+            let request = Request::get("/users/42?page=1")
+                .header(header::HOST, "example.com")
+                .body(())
+                .unwrap();
+            block_on(service.oneshot(request)).unwrap();
+        });
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        let request = event.request.as_ref().expect("Request should be set.");
+        assert_eq!(event.transaction, Some("GET /users/42".into()));
+        assert_eq!(request.method, Some("GET".into()));
+        assert_eq!(request.query_string, Some("page=1".into()));
+        assert_eq!(
+            request.url.as_ref().map(ToString::to_string),
+            Some("http://example.com/users/42?page=1".into())
+        );
+    }
+
+    #[test]
+    fn test_server_transaction() {
+        let trace = "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a-1";
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let service = ServiceBuilder::new()
+                    .layer(SentryLayer::new_from_top())
+                    .layer(SentryHttpLayer::new())
+                    .service(service_fn(|_: Request<()>| async {
+                        sentry::capture_message("Message", Level::Warning);
+                        let mut response = Response::new(());
+                        *response.status_mut() = http_::StatusCode::NOT_FOUND;
+                        Ok::<_, Infallible>(response)
+                    }));
+
+                let request = Request::get("/users/42")
+                    .header(SENTRY_TRACE_HEADER, trace)
+                    .body(())
+                    .unwrap();
+                block_on(service.oneshot(request)).unwrap();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(envelopes.len(), 2);
+        let event = match envelopes[0].items().next() {
+            Some(EnvelopeItem::Event(event)) => event,
+            _ => panic!("expected an event"),
+        };
+        let transaction = match envelopes[1].items().next() {
+            Some(EnvelopeItem::Transaction(transaction)) => transaction,
+            _ => panic!("expected a transaction"),
+        };
+        assert_eq!(transaction.name.as_deref(), Some("GET /users/42"));
+
+        let context = match transaction.contexts.get("trace") {
+            Some(protocol::Context::Trace(context)) => context,
+            _ => panic!("expected a trace context"),
+        };
+        assert_eq!(context.op.as_deref(), Some("http.server"));
+        assert_eq!(context.status.as_deref(), Some("not_found"));
+        assert_eq!(
+            context.trace_id.to_simple_ref().to_string(),
+            "09e04486820349518ac7b5d2adbf6ba5"
+        );
+        assert_eq!(context.parent_span_id.as_deref(), Some("8c1f9f3e1d2b4c5a"));
+
+        // the event captured while handling the request is part of the transaction
+        match event.contexts.get("trace") {
+            Some(protocol::Context::Trace(event_context)) => {
+                assert_eq!(event_context.span_id, context.span_id)
+            }
+            _ => panic!("expected a trace context"),
+        }
+    }
+
+    #[test]
+    fn test_event_id_header() {
+        let mut response = None;
+        let events = sentry::test::with_captured_events(|| {
+            let service = ServiceBuilder::new()
+                .layer(SentryLayer::new_from_top())
+                .layer(SentryHttpLayer::new().emit_header(true))
+                .service(service_fn(|request: Request<()>| async move {
+                    if request.uri().path() == "/error" {
+                        sentry::capture_message("Message", Level::Error);
+                    }
+                    Ok::<_, Infallible>(Response::new(()))
+                }));
+
+            let ok = Request::get("/").body(()).unwrap();
+            let ok = block_on(service.clone().oneshot(ok)).unwrap();
+            assert!(ok.headers().get(EVENT_ID_HEADER).is_none());
+            assert!(ok.extensions().get::<Uuid>().is_none());
+
+            let error = Request::get("/error").body(()).unwrap();
+            response = Some(block_on(service.oneshot(error)).unwrap());
+        });
+
+        assert_eq!(events.len(), 1);
+        let event_id = events[0].event_id;
+        let response = response.unwrap();
+        assert_eq!(response.extensions().get::<Uuid>(), Some(&event_id));
+        assert_eq!(
+            response.headers().get(EVENT_ID_HEADER).unwrap(),
+            &event_id.to_simple_ref().to_string()
+        );
+    }
+
+    #[test]
+    fn test_user_extractor() {
+        struct UserId(&'static str);
+
+        let extractor = |request: &Request<()>| {
+            let user_id = request.extensions().get::<UserId>()?;
+            Some(User {
+                id: Some(user_id.0.into()),
+                ..Default::default()
+            })
+        };
+        let run = |send_default_pii| {
+            let options = sentry::ClientOptions {
+                send_default_pii,
+                ..Default::default()
+            };
+            sentry::test::with_captured_events_options(
+                || {
+                    let service = ServiceBuilder::new()
+                        .layer(SentryLayer::new_from_top())
+                        .layer(SentryHttpLayer::new().user_extractor(extractor))
+                        .service(service_fn(|_: Request<()>| async {
+                            sentry::capture_message("Message", Level::Warning);
+                            Ok::<_, Infallible>(Response::new(()))
+                        }));
+
+                    let mut request = Request::get("/").body(()).unwrap();
+                    request.extensions_mut().insert(UserId("42"));
+                    block_on(service.oneshot(request)).unwrap();
+                },
+                options,
+            )
+        };
+
+        let events = run(true);
+        assert_eq!(events.len(), 1);
+        let user = events[0].user.as_ref().expect("User should be set.");
+        assert_eq!(user.id.as_deref(), Some("42"));
+
+        let events = run(false);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].user.is_none());
+    }
+
+    #[test]
+    fn test_request_filter() {
+        let events = sentry::test::with_captured_events(|| {
+            let service = ServiceBuilder::new()
+                .layer(SentryLayer::new_from_top())
+                .layer(
+                    SentryHttpLayer::new()
+                        .emit_header(true)
+                        .request_filter(|request: &Request<()>| request.uri().path() != "/health"),
+                )
+                .service(service_fn(|request: Request<()>| async move {
+                    sentry::capture_message(request.uri().path(), Level::Warning);
+                    Ok::<_, Infallible>(Response::new(()))
+                }));
+
+            let request = Request::get("/health").body(()).unwrap();
+            let response = block_on(service.clone().oneshot(request)).unwrap();
+            assert!(response.headers().get(EVENT_ID_HEADER).is_none());
+
+            let request = Request::get("/users").body(()).unwrap();
+            let response = block_on(service.oneshot(request)).unwrap();
+            assert!(response.headers().get(EVENT_ID_HEADER).is_some());
+        });
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].transaction, None);
+        assert!(events[0].request.is_none());
+        assert_eq!(events[1].transaction.as_deref(), Some("GET /users"));
+    }
+}
@@ -0,0 +1,356 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use http_::header::HeaderValue;
+use http_::{Request, Response};
+use sentry_core::protocol::{Breadcrumb, Level, SpanStatus};
+use sentry_core::types::{SentryTrace, BAGGAGE_HEADER, SENTRY_TRACE_HEADER};
+use sentry_core::{protocol, Hub, Span};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Tower layer for HTTP clients, like the `hyper` client, that records outgoing requests.
+///
+/// If the current scope has a span, a `http.client` child span is recorded for every
+/// request.  The trace of that span, or the trace context of the scope if there is no
+/// span, is propagated to the called service using the `sentry-trace` and `baggage`
+/// headers.  Additionally, an `http` breadcrumb is recorded for every request.
+///
+/// This is meant for proxies and low-level HTTP code that does not use `reqwest`, which
+/// is supported by the `sentry-reqwest` middleware instead.
+///
+/// # Examples
+///
+/// ```
+/// use http_::{Request, Response};
+/// use tower::{service_fn, ServiceBuilder};
+///
+/// let client = ServiceBuilder::new()
+///     .layer(sentry_tower::SentryHttpClientLayer::new().propagate_traces_to(&["api.example.com"]))
+///     .service(service_fn(|_: Request<()>| async {
+///         // send the request, for example with `hyper::Client::request`
+///         Ok::<_, std::io::Error>(Response::new(()))
+///     }));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SentryHttpClientLayer {
+    breadcrumbs: bool,
+    trace_propagation_targets: Option<Arc<Vec<String>>>,
+}
+
+impl SentryHttpClientLayer {
+    /// Creates a new layer that records breadcrumbs and spans, and propagates traces to all
+    /// hosts.
+    pub fn new() -> Self {
+        Self {
+            breadcrumbs: true,
+            trace_propagation_targets: None,
+        }
+    }
+
+    /// Enables or disables recording of breadcrumbs.
+    pub fn breadcrumbs(mut self, val: bool) -> Self {
+        self.breadcrumbs = val;
+        self
+    }
+
+    /// Only propagates traces to the given hosts.
+    ///
+    /// By default, the trace is propagated with every request.
+    pub fn propagate_traces_to(mut self, hosts: &[&str]) -> Self {
+        let targets = hosts.iter().map(|&host| host.into()).collect();
+        self.trace_propagation_targets = Some(Arc::new(targets));
+        self
+    }
+}
+
+impl Default for SentryHttpClientLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for SentryHttpClientLayer {
+    type Service = SentryHttpClientService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SentryHttpClientService {
+            service,
+            breadcrumbs: self.breadcrumbs,
+            trace_propagation_targets: self.trace_propagation_targets.clone(),
+        }
+    }
+}
+
+/// Tower service for HTTP clients that records outgoing requests and propagates traces.
+#[derive(Clone, Debug)]
+pub struct SentryHttpClientService<S> {
+    service: S,
+    breadcrumbs: bool,
+    trace_propagation_targets: Option<Arc<Vec<String>>>,
+}
+
+impl<S> SentryHttpClientService<S> {
+    fn should_propagate<B>(&self, request: &Request<B>) -> bool {
+        match (&self.trace_propagation_targets, request.uri().host()) {
+            (None, _) => true,
+            (Some(targets), Some(host)) => targets.iter().any(|target| target == host),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+impl<S, B, ResBody> Service<Request<B>> for SentryHttpClientService<S>
+where
+    S: Service<Request<B>, Response = Response<ResBody>>,
+    S::Error: fmt::Display,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SentryHttpClientFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        let hub = Hub::current();
+        let method = request.method().to_string();
+        let url = request.uri().to_string();
+
+        let (span, trace, baggage) = hub.configure_scope(|scope| {
+            let span = scope
+                .get_span()
+                .map(|parent| parent.start_child("http.client", &format!("{} {}", method, url)));
+            let trace = match (&span, scope.get_context("trace")) {
+                (Some(span), _) => Some(span.sentry_trace()),
+                (None, Some(protocol::Context::Trace(trace_context))) => {
+                    Some(SentryTrace::from_trace_context(trace_context))
+                }
+                _ => None,
+            };
+            let baggage = scope.get_baggage().map(ToOwned::to_owned);
+            (span, trace, baggage)
+        });
+        if let Some(ref span) = span {
+            span.set_data("method", method.clone().into());
+            span.set_data("url", url.clone().into());
+        }
+
+        if self.should_propagate(&request) {
+            let headers = request.headers_mut();
+            if let Some(trace) = trace {
+                if let Ok(value) = HeaderValue::from_str(&trace.to_string()) {
+                    headers.insert(SENTRY_TRACE_HEADER, value);
+                }
+                if let Some(Ok(value)) = baggage.map(|baggage| HeaderValue::from_str(&baggage)) {
+                    headers.insert(BAGGAGE_HEADER, value);
+                }
+            }
+        }
+
+        SentryHttpClientFuture {
+            future: self.service.call(request),
+            hub,
+            span,
+            breadcrumb: if self.breadcrumbs {
+                Some((method, url))
+            } else {
+                None
+            },
+            start: Instant::now(),
+        }
+    }
+}
+
+/// The future returned by [`SentryHttpClientService`].
+///
+/// Once the response arrives, the span of the request is finished, and the breadcrumb is
+/// recorded.
+#[derive(Debug)]
+pub struct SentryHttpClientFuture<F> {
+    future: F,
+    hub: Arc<Hub>,
+    span: Option<Span>,
+    breadcrumb: Option<(String, String)>,
+    start: Instant,
+}
+
+impl<F, ResBody, E> Future for SentryHttpClientFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    E: fmt::Display,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // https://doc.rust-lang.org/std/pin/index.html#pinning-is-structural-for-field
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let result = match future.poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let status = result.as_ref().ok().map(Response::status);
+        if let Some(span) = this.span.take() {
+            match status {
+                Some(status) => {
+                    span.set_data("status_code", status.as_u16().into());
+                    span.set_status(SpanStatus::from_http_status(status.as_u16()).as_str());
+                }
+                None => span.set_status(SpanStatus::InternalError.as_str()),
+            }
+            span.finish();
+        }
+
+        if let Some((method, url)) = this.breadcrumb.take() {
+            let duration = this.start.elapsed();
+            let mut breadcrumb = Breadcrumb::http(method, url, status.map(|s| s.as_u16()));
+            breadcrumb
+                .data
+                .insert("duration_ms".into(), (duration.as_millis() as u64).into());
+            if let Some(reason) = status.and_then(|status| status.canonical_reason()) {
+                breadcrumb.data.insert("reason".into(), reason.into());
+            }
+            if let Err(err) = &result {
+                breadcrumb
+                    .data
+                    .insert("error".into(), err.to_string().into());
+                breadcrumb.level = Level::Error;
+            }
+            this.hub.add_breadcrumb(breadcrumb);
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures::executor::block_on;
+    use http_::{HeaderMap, StatusCode};
+    use sentry::protocol::EnvelopeItem;
+    use sentry_core::protocol::{SpanId, TraceContext, Value};
+    use sentry_core::types::Uuid;
+    use sentry_core::TransactionContext;
+    use tower::{service_fn, ServiceBuilder, ServiceExt};
+
+    use super::*;
+
+    /// Sends a request through the layer, and returns the headers the inner service got.
+    fn send(layer: SentryHttpClientLayer, uri: &str, status: StatusCode) -> HeaderMap {
+        let headers = Arc::new(Mutex::new(None));
+        let service = ServiceBuilder::new().layer(layer).service(service_fn({
+            let headers = headers.clone();
+            move |request: Request<()>| {
+                *headers.lock().unwrap() = Some(request.headers().clone());
+                async move {
+                    let mut response = Response::new(());
+                    *response.status_mut() = status;
+                    Ok::<_, std::io::Error>(response)
+                }
+            }
+        }));
+        block_on(service.oneshot(Request::get(uri).body(()).unwrap())).unwrap();
+        let headers = headers.lock().unwrap().take();
+        headers.unwrap()
+    }
+
+    #[test]
+    fn test_breadcrumbs_and_propagation() {
+        let trace_context = TraceContext {
+            span_id: SpanId::default(),
+            trace_id: Uuid::new_v4(),
+            ..Default::default()
+        };
+
+        let mut headers = Vec::new();
+        let events = sentry::test::with_captured_events(|| {
+            sentry::configure_scope(|scope| {
+                scope.set_context("trace", trace_context.clone());
+                scope.set_baggage(Some("sentry-release=1.0"));
+            });
+
+            headers.push(send(
+                SentryHttpClientLayer::new(),
+                "http://api.example.com/users",
+                StatusCode::NOT_FOUND,
+            ));
+            headers.push(send(
+                SentryHttpClientLayer::new().propagate_traces_to(&["api.example.com"]),
+                "http://example.org/",
+                StatusCode::OK,
+            ));
+
+            sentry::capture_message("Message", Level::Warning);
+        });
+
+        let trace = SentryTrace::from_trace_context(&trace_context).to_string();
+        assert_eq!(headers[0].get(SENTRY_TRACE_HEADER).unwrap(), &trace);
+        assert_eq!(
+            headers[0].get(BAGGAGE_HEADER).unwrap(),
+            "sentry-release=1.0"
+        );
+        assert!(headers[1].get(SENTRY_TRACE_HEADER).is_none());
+
+        assert_eq!(events.len(), 1);
+        let breadcrumbs = &events[0].breadcrumbs;
+        assert_eq!(breadcrumbs.len(), 2);
+        assert_eq!(breadcrumbs[0].ty, "http");
+        assert_eq!(
+            breadcrumbs[0].data["url"],
+            Value::from("http://api.example.com/users")
+        );
+        assert_eq!(breadcrumbs[0].data["status_code"], Value::from(404));
+    }
+
+    #[test]
+    fn test_client_span() {
+        let mut trace = None;
+        let envelopes = sentry::test::with_captured_envelopes_options(
+            || {
+                let transaction =
+                    sentry::start_transaction(TransactionContext::new("proxy", "http.server"));
+                sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+
+                let headers = send(
+                    SentryHttpClientLayer::new().breadcrumbs(false),
+                    "http://api.example.com/users",
+                    StatusCode::SERVICE_UNAVAILABLE,
+                );
+                trace = Some(headers[SENTRY_TRACE_HEADER].to_str().unwrap().to_owned());
+
+                sentry::configure_scope(|scope| scope.set_span(None));
+                transaction.finish();
+            },
+            sentry::ClientOptions {
+                traces_sample_rate: 1.0,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(envelopes.len(), 1);
+        let transaction = match envelopes[0].items().next() {
+            Some(EnvelopeItem::Transaction(transaction)) => transaction,
+            _ => panic!("expected a transaction"),
+        };
+        assert_eq!(transaction.spans.len(), 1);
+        let span = &transaction.spans[0];
+        assert_eq!(span.op.as_deref(), Some("http.client"));
+        assert_eq!(
+            span.description.as_deref(),
+            Some("GET http://api.example.com/users")
+        );
+        assert_eq!(span.status.as_deref(), Some("unavailable"));
+
+        let expected = format!("{}-{}-1", span.trace_id.to_simple_ref(), span.span_id);
+        assert_eq!(trace.unwrap(), expected);
+    }
+}
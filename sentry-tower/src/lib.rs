@@ -0,0 +1,384 @@
+//! Adds support for automatic hub binding for each request received by a
+//! [`tower`](https://docs.rs/tower) service.
+//!
+//! The [`SentryLayer`] binds a new (or a given) [`Hub`] to the execution of every request,
+//! and captures errors returned by the wrapped service on that hub.  Because `hyper`, `axum`
+//! and `tonic` are all built on top of `tower`, this layer can be used with all of them.
+//!
+//! With the `http` feature, which is enabled by default, the [`SentryHttpLayer`] can be added
+//! on top of it, which starts an `http.server` transaction named after the request method and
+//! path, continuing the trace of an incoming `sentry-trace` header, and attaches the
+//! [`Request`](sentry_core::protocol::Request) interface to all events captured while
+//! handling the request.  The id of an event captured while handling the request is
+//! inserted into the response extensions, and with [`SentryHttpLayer::emit_header`] also sent
+//! to the client in the `X-Sentry-Event-Id` header.
+//!
+//! # Example
+//!
+//! ```
+//! use tower::ServiceBuilder;
+//!
+//! let layer = ServiceBuilder::new()
+//!     .layer(sentry_tower::SentryLayer::new_from_top())
+//!     .layer(sentry_tower::SentryHttpLayer::new());
+//! ```
+//!
+//! The [`SentryHttpLayer`] configures the scope of the hub that is currently bound, so it
+//! needs to be placed *inside* of the [`SentryLayer`] as shown above.
+//!
+//! # Using a specific Hub
+//!
+//! By default, every request gets a new hub that is created from the top scope of the
+//! current hub.  A [`HubProvider`] can be used to customize this behavior, for example to
+//! derive the hub from a specific parent hub:
+//!
+//! ```
+//! use sentry_core::Hub;
+//!
+//! let parent = Hub::current();
+//! let layer = sentry_tower::SentryLayer::new(parent);
+//! ```
+//!
+//! # Capturing Errors
+//!
+//! Errors returned by the wrapped service are captured if they implement [`ServiceError`].
+//! It is implemented for [`Infallible`](std::convert::Infallible), [`std::io::Error`] and the
+//! boxed errors that are commonly used by `tower` and `tonic`.
+//!
+//! [`SentryLayer::error_filter`] decides which errors are captured, and
+//! [`SentryHttpLayer::request_filter`] excludes requests like health checks from being
+//! instrumented:
+//!
+//! ```
+//! use std::io;
+//!
+//! use http_::Request;
+//! use tower::ServiceBuilder;
+//!
+//! let layer = ServiceBuilder::new()
+//!     .layer(sentry_tower::SentryLayer::new_from_top().error_filter(|error| {
+//!         error
+//!             .downcast_ref::<io::Error>()
+//!             .map_or(true, |error| error.kind() != io::ErrorKind::TimedOut)
+//!     }))
+//!     .layer(
+//!         sentry_tower::SentryHttpLayer::new()
+//!             .request_filter(|request: &Request<()>| request.uri().path() != "/health"),
+//!     );
+//! ```
+//!
+//! # Outgoing Requests
+//!
+//! The [`SentryHttpClientLayer`] wraps HTTP clients, like the `hyper` client.  It records
+//! a `http.client` span and a breadcrumb for every outgoing request, and propagates the
+//! current trace to the called service in the `sentry-trace` and `baggage` headers:
+//!
+//! ```
+//! use tower::ServiceBuilder;
+//!
+//! let layer = ServiceBuilder::new()
+//!     .layer(sentry_tower::SentryHttpClientLayer::new().propagate_traces_to(&["api.example.com"]));
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use sentry_core::{Hub, SentryFuture, SentryFutureExt};
+use tower_layer::Layer;
+use tower_service::Service;
+
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+mod http_client;
+
+#[cfg(feature = "http")]
+pub use crate::http::{
+    NoRequestFilter, NoUserExtractor, RequestFilter, SentryHttpFuture, SentryHttpLayer,
+    SentryHttpService, UserExtractor,
+};
+#[cfg(feature = "http")]
+pub use crate::http_client::{
+    SentryHttpClientFuture, SentryHttpClientLayer, SentryHttpClientService,
+};
+
+/// Provides a hub for each request.
+pub trait HubProvider<Request> {
+    /// Returns the hub to be bound to the request.
+    fn hub(&self, request: &Request) -> Arc<Hub>;
+}
+
+impl<F, Request> HubProvider<Request> for F
+where
+    F: Fn(&Request) -> Arc<Hub>,
+{
+    fn hub(&self, request: &Request) -> Arc<Hub> {
+        (self)(request)
+    }
+}
+
+impl<Request> HubProvider<Request> for Arc<Hub> {
+    fn hub(&self, _request: &Request) -> Arc<Hub> {
+        Arc::new(Hub::new_from_top(self))
+    }
+}
+
+/// Provides a new hub made from the currently active hub for each request.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NewFromTopProvider;
+
+impl<Request> HubProvider<Request> for NewFromTopProvider {
+    fn hub(&self, _request: &Request) -> Arc<Hub> {
+        Arc::new(Hub::new_from_top(Hub::current()))
+    }
+}
+
+/// An error returned by a service that can be captured by Sentry.
+pub trait ServiceError {
+    /// Returns the error that should be captured, if any.
+    fn as_error(&self) -> Option<&(dyn Error + 'static)>;
+}
+
+impl ServiceError for Infallible {
+    fn as_error(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {}
+    }
+}
+
+impl ServiceError for std::io::Error {
+    fn as_error(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self)
+    }
+}
+
+impl ServiceError for Box<dyn Error + Send + Sync> {
+    fn as_error(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&**self)
+    }
+}
+
+impl ServiceError for Box<dyn Error> {
+    fn as_error(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&**self)
+    }
+}
+
+/// A filter deciding which errors are captured.
+#[derive(Clone)]
+struct ErrorFilter(Arc<dyn Fn(&(dyn Error + 'static)) -> bool + Send + Sync>);
+
+impl fmt::Debug for ErrorFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorFilter").finish()
+    }
+}
+
+/// Tower layer that binds a specific Sentry hub for each request made.
+#[derive(Clone, Debug)]
+pub struct SentryLayer<P> {
+    provider: P,
+    capture_errors: bool,
+    error_filter: Option<ErrorFilter>,
+}
+
+impl SentryLayer<NewFromTopProvider> {
+    /// Creates a new layer that binds a new hub made from the current one for each request.
+    pub fn new_from_top() -> Self {
+        Self::new(NewFromTopProvider)
+    }
+}
+
+impl<P> SentryLayer<P> {
+    /// Creates a new layer that binds the hub returned by the provider for each request.
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            capture_errors: true,
+            error_filter: None,
+        }
+    }
+
+    /// Enables or disables capturing errors returned by the service.
+    ///
+    /// The default is to capture all errors.
+    pub fn capture_errors(mut self, val: bool) -> Self {
+        self.capture_errors = val;
+        self
+    }
+
+    /// Only captures the errors returned by the service for which the filter returns `true`.
+    pub fn error_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&(dyn Error + 'static)) -> bool + Send + Sync + 'static,
+    {
+        self.error_filter = Some(ErrorFilter(Arc::new(filter)));
+        self
+    }
+}
+
+impl<S, P> Layer<S> for SentryLayer<P>
+where
+    P: Clone,
+{
+    type Service = SentryService<S, P>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        SentryService {
+            service,
+            provider: self.provider.clone(),
+            capture_errors: self.capture_errors,
+            error_filter: self.error_filter.clone(),
+        }
+    }
+}
+
+/// Tower service that binds a specific Sentry hub for each request made.
+#[derive(Clone, Debug)]
+pub struct SentryService<S, P> {
+    service: S,
+    provider: P,
+    capture_errors: bool,
+    error_filter: Option<ErrorFilter>,
+}
+
+impl<S, P, Request> Service<Request> for SentryService<S, P>
+where
+    S: Service<Request>,
+    S::Error: ServiceError,
+    P: HubProvider<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SentryServiceFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let hub = self.provider.hub(&request);
+        let service = &mut self.service;
+        let future = Hub::run(hub.clone(), || service.call(request));
+        SentryServiceFuture {
+            future: future.bind_hub(hub.clone()),
+            hub,
+            capture_errors: self.capture_errors,
+            error_filter: self.error_filter.clone(),
+        }
+    }
+}
+
+/// The future returned by [`SentryService`].
+///
+/// The inner future is polled with the request's hub bound, and errors it resolves to
+/// are captured on that hub.
+#[derive(Debug)]
+pub struct SentryServiceFuture<F> {
+    future: SentryFuture<F>,
+    hub: Arc<Hub>,
+    capture_errors: bool,
+    error_filter: Option<ErrorFilter>,
+}
+
+impl<F, T, E> Future for SentryServiceFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: ServiceError,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // https://doc.rust-lang.org/std/pin/index.html#pinning-is-structural-for-field
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        match future.poll(cx) {
+            Poll::Ready(Err(err)) => {
+                if this.capture_errors {
+                    if let Some(error) = err.as_error() {
+                        if this.error_filter.as_ref().map_or(true, |f| (f.0)(error)) {
+                            this.hub.capture_error(error);
+                        }
+                    }
+                }
+                Poll::Ready(Err(err))
+            }
+            poll => poll,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use futures::executor::block_on;
+    use sentry::Level;
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    #[test]
+    fn test_hub_per_request() {
+        let events = sentry::test::with_captured_events(|| {
+            let service =
+                SentryLayer::new_from_top().layer(service_fn(|name: &'static str| async move {
+                    sentry::configure_scope(|scope| scope.set_tag("request", name));
+                    sentry::capture_message("Message", Level::Warning);
+                    Ok::<_, Infallible>(())
+                }));
+
+            block_on(service.clone().oneshot("first")).unwrap();
+            block_on(service.oneshot("second")).unwrap();
+            sentry::capture_message("Outside", Level::Info);
+        });
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].tags.get("request").unwrap(), "first");
+        assert_eq!(events[1].tags.get("request").unwrap(), "second");
+        assert!(events[2].tags.get("request").is_none());
+    }
+
+    #[test]
+    fn test_service_errors() {
+        let events = sentry::test::with_captured_events(|| {
+            let service = SentryLayer::new_from_top().layer(service_fn(|_: ()| async {
+                Err::<(), _>(io::Error::new(io::ErrorKind::Other, "Test Error"))
+            }));
+
+            assert!(block_on(service.oneshot(())).is_err());
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].exception.values[0].ty, "Custom");
+        assert_eq!(
+            events[0].exception.values[0].value,
+            Some("Test Error".into())
+        );
+    }
+
+    #[test]
+    fn test_error_filter() {
+        let events = sentry::test::with_captured_events(|| {
+            let service = SentryLayer::new_from_top()
+                .error_filter(|error| error.to_string() != "Ignored")
+                .layer(service_fn(|message: &'static str| async move {
+                    Err::<(), _>(io::Error::new(io::ErrorKind::Other, message))
+                }));
+
+            assert!(block_on(service.clone().oneshot("Ignored")).is_err());
+            assert!(block_on(service.oneshot("Captured")).is_err());
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].exception.values[0].value, Some("Captured".into()));
+    }
+}
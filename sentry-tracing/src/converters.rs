@@ -0,0 +1,113 @@
+use std::error::Error;
+use std::fmt;
+
+use sentry_core::protocol::{Event, Map, Value};
+use sentry_core::{Breadcrumb, Level};
+use tracing_core::field::{Field, Visit};
+
+/// Converts a [`tracing_core::Level`] to a Sentry [`Level`].
+pub fn convert_tracing_level(level: &tracing_core::Level) -> Level {
+    match *level {
+        tracing_core::Level::TRACE | tracing_core::Level::DEBUG => Level::Debug,
+        tracing_core::Level::INFO => Level::Info,
+        tracing_core::Level::WARN => Level::Warning,
+        tracing_core::Level::ERROR => Level::Error,
+    }
+}
+
+/// Collects the message and fields of a [`tracing_core::Event`].
+///
+/// The first field that is recorded as an error is turned into an [`Event`] with its
+/// full exception chain, while it is still borrowed as a `dyn Error`.
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: Map<String, Value>,
+    error_event: Option<Event<'static>>,
+}
+
+impl FieldVisitor {
+    fn visit(event: &tracing_core::Event<'_>) -> Self {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        visitor
+    }
+
+    fn record(&mut self, field: &Field, value: Value) {
+        self.fields.insert(field.name().into(), value);
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.into());
+        } else {
+            self.record(field, value.into());
+        }
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn Error + 'static)) {
+        if self.error_event.is_none() {
+            self.error_event = Some(sentry_core::event_from_enriched_error(value));
+        }
+        self.record(field, value.to_string().into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.record(field, format!("{:?}", value).into());
+        }
+    }
+}
+
+/// Creates a [`Breadcrumb`] from a given [`tracing_core::Event`].
+///
+/// The fields of the event are added to the data of the breadcrumb.
+pub fn breadcrumb_from_event(event: &tracing_core::Event<'_>) -> Breadcrumb {
+    let visitor = FieldVisitor::visit(event);
+    Breadcrumb {
+        ty: "log".into(),
+        level: convert_tracing_level(event.metadata().level()),
+        category: Some(event.metadata().target().into()),
+        message: visitor.message,
+        data: visitor.fields,
+        ..Default::default()
+    }
+}
+
+/// Creates an [`Event`] from a given [`tracing_core::Event`].
+///
+/// The fields of the event are added to its extra data.  If a field holds an error that is
+/// recorded as a `dyn Error`, such as `error = &err as &dyn Error`, the event contains the
+/// exception chain of that error, like one created by
+/// [`event_from_enriched_error`](sentry_core::event_from_enriched_error).  Errors
+/// recorded using `%` or `?` are formatted by `tracing` before they reach the layer, so
+/// they only show up as a string in the extra data.
+pub fn event_from_event(event: &tracing_core::Event<'_>) -> Event<'static> {
+    let visitor = FieldVisitor::visit(event);
+    let mut sentry_event = visitor.error_event.unwrap_or_default();
+    sentry_event.logger = Some(event.metadata().target().into());
+    sentry_event.level = convert_tracing_level(event.metadata().level());
+    sentry_event.message = visitor.message;
+    sentry_event.extra = visitor.fields;
+    sentry_event
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+
+use tracing_core::{Event, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::converters::{breadcrumb_from_event, event_from_event};
+
+/// The action that Sentry should perform for a [`Metadata`].
+#[derive(Debug)]
+pub enum EventFilter {
+    /// Ignore the [`Event`].
+    Ignore,
+    /// Create a [`Breadcrumb`](sentry_core::Breadcrumb) from this [`Event`].
+    Breadcrumb,
+    /// Create a Sentry [`Event`](sentry_core::protocol::Event) from this [`Event`].
+    Event,
+}
+
+/// The default event filter.
+///
+/// By default, an event is captured for `ERROR`, a breadcrumb for `WARN` and `INFO`, and
+/// `DEBUG` and `TRACE` events are ignored.
+pub fn default_event_filter(metadata: &Metadata<'_>) -> EventFilter {
+    match *metadata.level() {
+        tracing_core::Level::ERROR => EventFilter::Event,
+        tracing_core::Level::WARN | tracing_core::Level::INFO => EventFilter::Breadcrumb,
+        tracing_core::Level::DEBUG | tracing_core::Level::TRACE => EventFilter::Ignore,
+    }
+}
+
+/// A [`tracing_subscriber::Layer`] that reports `tracing` events to Sentry.
+///
+/// The [`EventFilter`] returned by the filter function decides what happens to an event,
+/// based on its [`Metadata`], which includes its target and level.  The
+/// [`default_event_filter`] is used unless another one is set with
+/// [`event_filter`](SentryLayer::event_filter).
+pub struct SentryLayer {
+    event_filter: Box<dyn Fn(&Metadata<'_>) -> EventFilter + Send + Sync>,
+}
+
+impl Default for SentryLayer {
+    fn default() -> Self {
+        Self {
+            event_filter: Box::new(default_event_filter),
+        }
+    }
+}
+
+impl fmt::Debug for SentryLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SentryLayer").finish()
+    }
+}
+
+impl SentryLayer {
+    /// Creates a new Sentry layer with the [`default_event_filter`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a custom event filter function.
+    ///
+    /// The filter classifies how sentry should handle [`Event`]s based on their
+    /// [`Metadata`].
+    pub fn event_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Metadata<'_>) -> EventFilter + Send + Sync + 'static,
+    {
+        self.event_filter = Box::new(filter);
+        self
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SentryLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // the diagnostics of the SDK itself are never reported to Sentry
+        if event
+            .metadata()
+            .target()
+            .starts_with(sentry_core::INTERNAL_LOG_TARGET)
+        {
+            return;
+        }
+
+        match (self.event_filter)(event.metadata()) {
+            EventFilter::Ignore => {}
+            EventFilter::Breadcrumb => {
+                sentry_core::add_breadcrumb(|| breadcrumb_from_event(event));
+            }
+            EventFilter::Event => {
+                sentry_core::capture_event(event_from_event(event));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::fmt;
+
+    use sentry::protocol::Value;
+    use sentry::Level;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct ConnectError;
+
+    impl fmt::Display for ConnectError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("connection refused")
+        }
+    }
+
+    impl Error for ConnectError {}
+
+    #[derive(Debug)]
+    struct QueryError(ConnectError);
+
+    impl fmt::Display for QueryError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("query failed")
+        }
+    }
+
+    impl Error for QueryError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    fn with_layer<F: FnOnce()>(layer: SentryLayer, f: F) {
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, f);
+    }
+
+    #[test]
+    fn test_breadcrumbs_and_events() {
+        let events = sentry::test::with_captured_events(|| {
+            with_layer(SentryLayer::new(), || {
+                tracing::debug!("ignored");
+                tracing::info!(user_id = 42, "logged in");
+                tracing::error!(status = "failed", "request failed");
+            });
+        });
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.level, Level::Error);
+        assert_eq!(event.message.as_deref(), Some("request failed"));
+        assert_eq!(event.extra["status"], Value::from("failed"));
+        assert!(event.exception.is_empty());
+
+        assert_eq!(event.breadcrumbs.len(), 1);
+        let breadcrumb = &event.breadcrumbs[0];
+        assert_eq!(breadcrumb.level, Level::Info);
+        assert_eq!(breadcrumb.message.as_deref(), Some("logged in"));
+        assert_eq!(breadcrumb.data["user_id"], Value::from(42));
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let events = sentry::test::with_captured_events(|| {
+            with_layer(SentryLayer::new(), || {
+                let err = QueryError(ConnectError);
+                tracing::error!(error = &err as &dyn Error, "could not load users");
+                tracing::error!(error = %err, "could not load users");
+            });
+        });
+
+        assert_eq!(events.len(), 2);
+        let event = &events[0];
+        assert_eq!(event.message.as_deref(), Some("could not load users"));
+        assert_eq!(event.exception.len(), 2);
+        assert_eq!(event.exception[0].ty, "ConnectError");
+        assert_eq!(
+            event.exception[0].value.as_deref(),
+            Some("connection refused")
+        );
+        assert_eq!(event.exception[1].ty, "QueryError");
+        assert_eq!(event.exception[1].value.as_deref(), Some("query failed"));
+
+        assert!(events[1].exception.is_empty());
+        assert_eq!(events[1].extra["error"], Value::from("query failed"));
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let layer = SentryLayer::new().event_filter(|metadata| {
+            match (metadata.target(), *metadata.level()) {
+                ("noisy", _) => EventFilter::Ignore,
+                ("db", tracing_core::Level::DEBUG) => EventFilter::Breadcrumb,
+                _ => default_event_filter(metadata),
+            }
+        });
+
+        let events = sentry::test::with_captured_events(|| {
+            with_layer(layer, || {
+                tracing::debug!(target: "db", "connected");
+                tracing::debug!(target: "app", "ignored");
+                tracing::error!(target: "noisy", "ignored");
+                tracing::error!(target: "app", "failed");
+            });
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].logger.as_deref(), Some("app"));
+        let breadcrumbs = &events[0].breadcrumbs;
+        assert_eq!(breadcrumbs.len(), 1);
+        assert_eq!(breadcrumbs[0].category.as_deref(), Some("db"));
+        assert_eq!(breadcrumbs[0].level, Level::Debug);
+    }
+}
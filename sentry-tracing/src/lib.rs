@@ -0,0 +1,61 @@
+//! Adds support for automatic Breadcrumb and Event capturing from `tracing` events.
+//!
+//! The [`SentryLayer`] is a [`tracing_subscriber::Layer`] that records `tracing` events as
+//! breadcrumbs, or captures them as events.  By default, `ERROR` events are captured as
+//! events, `WARN` and `INFO` events are recorded as breadcrumbs, and `DEBUG` and `TRACE`
+//! events are ignored.  The fields of a `tracing` event are added to the data of the
+//! breadcrumb, or to the extra data of the event.
+//!
+//! # Examples
+//!
+//! ```
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry()
+//!     .with(sentry_tracing::SentryLayer::new())
+//!     .init();
+//!
+//! let _sentry = sentry::init(());
+//!
+//! tracing::info!("Generates a breadcrumb");
+//! tracing::error!("Generates an event");
+//! ```
+//!
+//! Or one might also set an explicit filter, to decide per target and level how to treat
+//! `tracing` events, for example to keep the debug events of one crate as breadcrumbs
+//! while dropping those of another crate entirely:
+//!
+//! ```
+//! use sentry_tracing::EventFilter;
+//!
+//! let layer = sentry_tracing::SentryLayer::new().event_filter(|md| {
+//!     match (md.target(), *md.level()) {
+//!         ("noisy_crate", _) => EventFilter::Ignore,
+//!         ("my_crate", tracing::Level::DEBUG) => EventFilter::Breadcrumb,
+//!         _ => sentry_tracing::default_event_filter(md),
+//!     }
+//! });
+//! ```
+//!
+//! # Errors
+//!
+//! An error that is recorded as a `dyn Error` field is captured with its full chain of
+//! sources as exceptions, just like [`capture_error`](sentry_core::capture_error):
+//!
+//! ```
+//! # let err = std::io::Error::new(std::io::ErrorKind::Other, "oh no");
+//! tracing::error!(error = &err as &dyn std::error::Error, "Could not load the config");
+//! ```
+//!
+//! `tracing` formats fields recorded with `%` or `?` before they reach the layer, so
+//! errors recorded as `error = %err` or `error = ?err` only show up as a string.
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+mod converters;
+mod layer;
+
+pub use converters::*;
+pub use layer::*;
@@ -43,13 +43,16 @@ mod auth;
 mod dsn;
 mod project_id;
 pub mod protocol;
+mod trace;
 mod utils;
 
 pub use crate::auth::*;
 pub use crate::dsn::*;
 pub use crate::project_id::*;
+pub use crate::trace::*;
 
 // Re-export external types and traits for convenience
 pub use chrono::{DateTime, ParseError as ChronoParseError, TimeZone, Utc};
 pub use debugid::*;
+pub use url::{ParseError as UrlParseError, Url};
 pub use uuid::{Uuid, Variant as UuidVariant, Version as UuidVersion};
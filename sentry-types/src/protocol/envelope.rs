@@ -1,11 +1,78 @@
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 use std::io::Write;
 
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::v7::Attachment;
+use super::v7::ClientSdkInfo;
 use super::v7::Event;
 use super::v7::SessionUpdate;
 use super::v7::Transaction;
 
+thread_local! {
+    /// Set while an item is serialized whose `sdk` is spliced in afterwards.
+    static SPLICE_SDK: Cell<bool> = Cell::new(false);
+    /// The last serialized `sdk`, which is the same for all items of a client.
+    static SDK_CACHE: RefCell<Option<(ClientSdkInfo, Vec<u8>)>> = RefCell::new(None);
+}
+
+/// Whether the `sdk` of events and transactions is skipped during serialization.
+pub(crate) fn skip_sdk(sdk: &Option<Cow<'_, ClientSdkInfo>>) -> bool {
+    sdk.is_none() || SPLICE_SDK.with(Cell::get)
+}
+
+/// Serializes an event or transaction into `buf`.
+///
+/// The `sdk` is identical for every item a client sends, so instead of being
+/// serialized over and over again, it is serialized once and spliced into the
+/// serialized item.
+fn write_item_with_sdk<T: Serialize>(
+    buf: &mut Vec<u8>,
+    item: &T,
+    sdk: Option<&ClientSdkInfo>,
+) -> serde_json::Result<()> {
+    struct SpliceGuard;
+    impl Drop for SpliceGuard {
+        fn drop(&mut self) {
+            SPLICE_SDK.with(|splice| splice.set(false));
+        }
+    }
+
+    let sdk = match sdk {
+        Some(sdk) => sdk,
+        None => return serde_json::to_writer(buf, item),
+    };
+
+    {
+        SPLICE_SDK.with(|splice| splice.set(true));
+        let _guard = SpliceGuard;
+        serde_json::to_writer(&mut *buf, item)?;
+    }
+
+    // replace the closing brace of the serialized object
+    buf.pop();
+    if buf.len() > 1 {
+        buf.push(b',');
+    }
+    buf.extend_from_slice(br#""sdk":"#);
+    SDK_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        match *cache {
+            Some((ref cached, ref bytes)) if cached == sdk => buf.extend_from_slice(bytes),
+            _ => {
+                let bytes = serde_json::to_vec(sdk)?;
+                buf.extend_from_slice(&bytes);
+                *cache = Some((sdk.clone(), bytes));
+            }
+        }
+        Ok::<_, serde_json::Error>(())
+    })?;
+    buf.push(b'}');
+    Ok(())
+}
+
 /// An Envelope Item.
 ///
 /// See the [documentation on Items](https://develop.sentry.dev/sdk/envelopes/#items)
@@ -28,8 +95,17 @@ pub enum EnvelopeItem {
     /// See the [Transaction Item documentation](https://develop.sentry.dev/sdk/envelopes/#transaction)
     /// for more details.
     Transaction(Transaction<'static>),
+    /// An Attachment Item.
+    ///
+    /// See the [Attachment Item documentation](https://develop.sentry.dev/sdk/envelopes/#attachment)
+    /// for more details.
+    Attachment(Attachment),
+    /// A Statsd Item, containing aggregated metrics in the statsd format.
+    ///
+    /// See the [Metrics documentation](https://develop.sentry.dev/sdk/metrics/)
+    /// for more details.
+    Statsd(Vec<u8>),
     // TODO:
-    // * Attachment,
     // etc…
 }
 
@@ -51,6 +127,12 @@ impl From<Transaction<'static>> for EnvelopeItem {
     }
 }
 
+impl From<Attachment> for EnvelopeItem {
+    fn from(attachment: Attachment) -> Self {
+        EnvelopeItem::Attachment(attachment)
+    }
+}
+
 /// An Iterator over the items of an Envelope.
 #[derive(Clone)]
 pub struct EnvelopeItemIter<'s> {
@@ -77,6 +159,7 @@ impl<'s> Iterator for EnvelopeItemIter<'s> {
 pub struct Envelope {
     event_id: Option<Uuid>,
     items: Vec<EnvelopeItem>,
+    raw: Option<Vec<u8>>,
 }
 
 impl Envelope {
@@ -85,7 +168,34 @@ impl Envelope {
         Default::default()
     }
 
+    /// Creates an Envelope from the raw bytes of a serialized Envelope.
+    ///
+    /// Only the Envelope headers are parsed.  The bytes are written out as-is
+    /// by [`to_writer`](#method.to_writer), and the Envelope does not yield
+    /// any [`EnvelopeItem`]s.  This is used to send Envelopes that were
+    /// serialized earlier, for example by a previous run of the application.
+    pub fn from_bytes_raw(bytes: Vec<u8>) -> Result<Envelope, serde_json::Error> {
+        #[derive(Deserialize)]
+        struct Headers {
+            event_id: Option<Uuid>,
+        }
+
+        let header_len = bytes
+            .iter()
+            .position(|b| *b == b'\n')
+            .unwrap_or_else(|| bytes.len());
+        let headers: Headers = serde_json::from_slice(&bytes[..header_len])?;
+        Ok(Envelope {
+            event_id: headers.event_id,
+            items: Vec::new(),
+            raw: Some(bytes),
+        })
+    }
+
     /// Add a new Envelope Item.
+    ///
+    /// Items added to an Envelope created with
+    /// [`from_bytes_raw`](#method.from_bytes_raw) are not serialized.
     pub fn add_item<I>(&mut self, item: I)
     where
         I: Into<EnvelopeItem>,
@@ -126,6 +236,17 @@ impl Envelope {
             .next()
     }
 
+    /// Returns a mutable reference to the Envelopes Event, if any.
+    pub fn event_mut(&mut self) -> Option<&mut Event<'static>> {
+        self.items
+            .iter_mut()
+            .filter_map(|item| match item {
+                EnvelopeItem::Event(event) => Some(event),
+                _ => None,
+            })
+            .next()
+    }
+
     /// Serialize the Envelope into the given [`Write`].
     ///
     /// [`Write`]: https://doc.rust-lang.org/std/io/trait.Write.html
@@ -133,6 +254,10 @@ impl Envelope {
     where
         W: Write,
     {
+        if let Some(ref raw) = self.raw {
+            return writer.write_all(raw);
+        }
+
         let mut item_buf = Vec::new();
 
         // write the headers:
@@ -145,19 +270,29 @@ impl Envelope {
         // write each item:
         for item in &self.items {
             // we write them to a temporary buffer first, since we need their length
-            match item {
-                EnvelopeItem::Event(event) => serde_json::to_writer(&mut item_buf, event)?,
+            let item_type = match item {
+                EnvelopeItem::Event(event) => {
+                    write_item_with_sdk(&mut item_buf, event, event.sdk.as_deref())?;
+                    "event"
+                }
                 EnvelopeItem::SessionUpdate(session) => {
-                    serde_json::to_writer(&mut item_buf, session)?
+                    serde_json::to_writer(&mut item_buf, session)?;
+                    "session"
                 }
                 EnvelopeItem::Transaction(transaction) => {
-                    serde_json::to_writer(&mut item_buf, transaction)?
+                    write_item_with_sdk(&mut item_buf, transaction, transaction.sdk.as_deref())?;
+                    "transaction"
+                }
+                EnvelopeItem::Statsd(payload) => {
+                    item_buf.extend_from_slice(payload);
+                    "statsd"
+                }
+                // attachments carry additional headers and are not JSON
+                EnvelopeItem::Attachment(attachment) => {
+                    attachment.to_writer(&mut writer)?;
+                    writeln!(writer)?;
+                    continue;
                 }
-            }
-            let item_type = match item {
-                EnvelopeItem::Event(_) => "event",
-                EnvelopeItem::SessionUpdate(_) => "session",
-                EnvelopeItem::Transaction(_) => "transaction",
             };
             writeln!(
                 writer,
@@ -195,7 +330,7 @@ mod test {
     use chrono::{DateTime, Utc};
 
     use super::*;
-    use crate::protocol::v7::{SessionAttributes, SessionStatus, Span};
+    use crate::protocol::v7::{AttachmentType, SessionAttributes, SessionStatus, Span, SpanId};
 
     fn to_str(envelope: Envelope) -> String {
         let mut vec = Vec::new();
@@ -203,6 +338,16 @@ mod test {
         String::from_utf8_lossy(&vec).to_string()
     }
 
+    #[test]
+    fn test_statsd() {
+        let mut envelope = Envelope::new();
+        envelope.add_item(EnvelopeItem::Statsd(b"requests:1|c|T1597790835".to_vec()));
+        assert_eq!(
+            to_str(envelope),
+            "{}\n{\"type\":\"statsd\",\"length\":24}\nrequests:1|c|T1597790835\n"
+        );
+    }
+
     #[test]
     fn test_empty() {
         assert_eq!(to_str(Envelope::new()), "{}\n");
@@ -227,6 +372,32 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_event_with_sdk() {
+        let event_id = Uuid::parse_str("22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c").unwrap();
+        let timestamp = "2020-07-20T14:51:14.296Z".parse::<DateTime<Utc>>().unwrap();
+        let event = Event {
+            event_id,
+            timestamp,
+            sdk: Some(Cow::Owned(ClientSdkInfo {
+                name: "sentry.rust".into(),
+                version: "1.0.0".into(),
+                integrations: vec![],
+                packages: vec![],
+            })),
+            ..Default::default()
+        };
+        let expected = r#"{"event_id":"22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c"}
+{"type":"event","length":121}
+{"event_id":"22d00b3fd1b14b5d8d2049d138cd8a9c","timestamp":1595256674.296,"sdk":{"name":"sentry.rust","version":"1.0.0"}}
+"#;
+        // the second time around, the cached sdk is spliced in
+        assert_eq!(to_str(event.clone().into()), expected);
+        assert_eq!(to_str(event.clone().into()), expected);
+        // serializing the event on its own still includes the sdk
+        assert!(serde_json::to_string(&event).unwrap().contains(r#""sdk":"#));
+    }
+
     #[test]
     fn test_session() {
         let session_id = Uuid::parse_str("22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c").unwrap();
@@ -262,7 +433,7 @@ mod test {
     #[test]
     fn test_transaction() {
         let event_id = Uuid::parse_str("22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c").unwrap();
-        let span_id = Uuid::parse_str("d42cee9f-c3e7-4f5c-ada9-47ab601a14d2").unwrap();
+        let span_id = "d42cee9fc3e74f5c".parse::<SpanId>().unwrap();
         let trace_id = Uuid::parse_str("335e53d6-1447-4acc-9f89-e632b776cc28").unwrap();
         let start_timestamp = "2020-07-20T14:51:14.296Z".parse::<DateTime<Utc>>().unwrap();
         let spans = vec![Span {
@@ -281,9 +452,57 @@ mod test {
         assert_eq!(
             to_str(envelope),
             r#"{"event_id":"22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c"}
-{"type":"transaction","length":216}
-{"event_id":"22d00b3fd1b14b5d8d2049d138cd8a9c","start_timestamp":1595256674.296,"spans":[{"span_id":"d42cee9fc3e74f5cada947ab601a14d2","trace_id":"335e53d614474acc9f89e632b776cc28","start_timestamp":1595256674.296}]}
+{"type":"transaction","length":200}
+{"event_id":"22d00b3fd1b14b5d8d2049d138cd8a9c","start_timestamp":1595256674.296,"spans":[{"span_id":"d42cee9fc3e74f5c","trace_id":"335e53d614474acc9f89e632b776cc28","start_timestamp":1595256674.296}]}
+"#
+        )
+    }
+
+    #[test]
+    fn test_event_with_attachment() {
+        let event_id = Uuid::parse_str("22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c").unwrap();
+        let timestamp = "2020-07-20T14:51:14.296Z".parse::<DateTime<Utc>>().unwrap();
+        let mut envelope: Envelope = Event {
+            event_id,
+            timestamp,
+            ..Default::default()
+        }
+        .into();
+        envelope.add_item(Attachment {
+            buffer: b"MDMP".to_vec(),
+            filename: "minidump.dmp".into(),
+            ty: Some(AttachmentType::Minidump),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            to_str(envelope),
+            r#"{"event_id":"22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c"}
+{"type":"event","length":74}
+{"event_id":"22d00b3fd1b14b5d8d2049d138cd8a9c","timestamp":1595256674.296}
+{"type":"attachment","length":4,"filename":"minidump.dmp","attachment_type":"event.minidump","content_type":"application/octet-stream"}
+MDMP
 "#
         )
     }
+
+    #[test]
+    fn test_raw_roundtrip() {
+        let event_id = Uuid::parse_str("22d00b3f-d1b1-4b5d-8d20-49d138cd8a9c").unwrap();
+        let timestamp = "2020-07-20T14:51:14.296Z".parse::<DateTime<Utc>>().unwrap();
+        let envelope: Envelope = Event {
+            event_id,
+            timestamp,
+            ..Default::default()
+        }
+        .into();
+        let serialized = to_str(envelope);
+
+        let raw = Envelope::from_bytes_raw(serialized.clone().into_bytes()).unwrap();
+        assert_eq!(raw.uuid(), Some(&event_id));
+        assert_eq!(raw.items().count(), 0);
+        assert_eq!(to_str(raw), serialized);
+
+        assert!(Envelope::from_bytes_raw(b"not an envelope".to_vec()).is_err());
+    }
 }
@@ -0,0 +1,114 @@
+use http_::header::{self, HeaderMap};
+
+use super::v7::{Map, Request};
+
+/// Headers that may carry credentials or other personal data.
+///
+/// These are only included in the [`Request`] interface if sending default
+/// PII is enabled.
+pub const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+    "x-api-key",
+    "x-forwarded-for",
+    "x-real-ip",
+];
+
+/// Converts HTTP headers into the headers of the [`Request`] interface.
+///
+/// Unless `with_pii` is set, the [`SENSITIVE_HEADERS`] are removed.  Header
+/// values that are not valid strings are dropped.
+pub fn filter_headers(headers: &HeaderMap, with_pii: bool) -> Map<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| with_pii || !SENSITIVE_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+impl Request {
+    /// Creates the `Request` interface from an [`http::Request`](http_::Request).
+    ///
+    /// If the URI of the request is relative, the URL is completed with the
+    /// `Host` header.  Cookies and the [`SENSITIVE_HEADERS`] are only included
+    /// if `with_pii` is set.
+    pub fn from_http<B>(request: &http_::Request<B>, with_pii: bool) -> Self {
+        let uri = request.uri();
+        let url = if uri.host().is_some() {
+            uri.to_string()
+        } else {
+            let host = request
+                .headers()
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .unwrap_or("localhost");
+            format!("{}://{}{}", uri.scheme_str().unwrap_or("http"), host, uri)
+        };
+
+        Request {
+            url: url.parse().ok(),
+            method: Some(request.method().to_string()),
+            query_string: uri.query().map(Into::into),
+            cookies: if with_pii {
+                request
+                    .headers()
+                    .get(header::COOKIE)
+                    .and_then(|cookies| cookies.to_str().ok())
+                    .map(Into::into)
+            } else {
+                None
+            },
+            headers: filter_headers(request.headers(), with_pii),
+            ..Default::default()
+        }
+    }
+}
+
+impl<B> From<&http_::Request<B>> for Request {
+    /// Creates the `Request` interface without any personal data.
+    fn from(request: &http_::Request<B>) -> Self {
+        Request::from_http(request, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> http_::Request<()> {
+        http_::Request::get("/users/42?page=1")
+            .header(header::HOST, "example.com")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .header(header::COOKIE, "session=secret")
+            .header(header::USER_AGENT, "test")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_from_http() {
+        let request = Request::from(&request());
+        assert_eq!(
+            request.url.as_ref().map(ToString::to_string),
+            Some("http://example.com/users/42?page=1".into())
+        );
+        assert_eq!(request.method.as_deref(), Some("GET"));
+        assert_eq!(request.query_string.as_deref(), Some("page=1"));
+        assert_eq!(request.cookies, None);
+        assert_eq!(
+            request.headers.get("user-agent").map(String::as_str),
+            Some("test")
+        );
+        assert!(!request.headers.contains_key("authorization"));
+        assert!(!request.headers.contains_key("cookie"));
+    }
+
+    #[test]
+    fn test_from_http_with_pii() {
+        let request = Request::from_http(&request(), true);
+        assert_eq!(request.cookies.as_deref(), Some("session=secret"));
+        assert!(request.headers.contains_key("authorization"));
+    }
+}
@@ -0,0 +1,52 @@
+//! Conversions from the levels of logging libraries.
+//!
+//! The integrations for these libraries all use these conversions, so that the
+//! same log level always results in the same Sentry level.
+
+#[cfg(any(feature = "log", feature = "slog", feature = "tracing"))]
+use super::v7::Level;
+
+/// Converts a `log::Level`.
+///
+/// `trace` and `debug` are both mapped to `debug`.
+#[cfg(feature = "log")]
+impl From<log_::Level> for Level {
+    fn from(level: log_::Level) -> Level {
+        match level {
+            log_::Level::Error => Level::Error,
+            log_::Level::Warn => Level::Warning,
+            log_::Level::Info => Level::Info,
+            log_::Level::Debug | log_::Level::Trace => Level::Debug,
+        }
+    }
+}
+
+/// Converts a `slog::Level`.
+///
+/// `trace` and `debug` are both mapped to `debug`, and `critical` to `error`.
+#[cfg(feature = "slog")]
+impl From<slog_::Level> for Level {
+    fn from(level: slog_::Level) -> Level {
+        match level {
+            slog_::Level::Trace | slog_::Level::Debug => Level::Debug,
+            slog_::Level::Info => Level::Info,
+            slog_::Level::Warning => Level::Warning,
+            slog_::Level::Error | slog_::Level::Critical => Level::Error,
+        }
+    }
+}
+
+/// Converts a `tracing::Level`.
+///
+/// `TRACE` and `DEBUG` are both mapped to `debug`.
+#[cfg(feature = "tracing")]
+impl From<tracing_core::Level> for Level {
+    fn from(level: tracing_core::Level) -> Level {
+        match level {
+            tracing_core::Level::ERROR => Level::Error,
+            tracing_core::Level::WARN => Level::Warning,
+            tracing_core::Level::INFO => Level::Info,
+            tracing_core::Level::DEBUG | tracing_core::Level::TRACE => Level::Debug,
+        }
+    }
+}
@@ -9,5 +9,11 @@ pub const LATEST: u16 = 7;
 #[cfg(feature = "protocol")]
 pub use v7 as latest;
 
+mod attachment;
 mod envelope;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "protocol")]
+mod level;
 mod session;
+mod span_status;
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::str;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The status of a span or transaction.
+///
+/// The statuses follow the canonical codes of gRPC.  [`SpanStatus::from_http_status`] and
+/// [`SpanStatus::from_grpc_status`] map the status codes of responses, so that all
+/// integrations report the same status for the same outcome.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanStatus {
+    /// The operation completed successfully.
+    Ok,
+    /// The operation was cancelled, typically by the caller.
+    Cancelled,
+    /// An unknown error occurred.
+    UnknownError,
+    /// The client specified an invalid argument.
+    InvalidArgument,
+    /// The deadline expired before the operation could complete.
+    DeadlineExceeded,
+    /// The requested entity was not found.
+    NotFound,
+    /// The entity that was attempted to be created already exists.
+    AlreadyExists,
+    /// The caller does not have permission to execute the operation.
+    PermissionDenied,
+    /// A resource has been exhausted, for example by rate limiting.
+    ResourceExhausted,
+    /// The system is not in a state required for the operation.
+    FailedPrecondition,
+    /// The operation was aborted, typically due to a concurrency issue.
+    Aborted,
+    /// The operation was attempted past the valid range.
+    OutOfRange,
+    /// The operation is not implemented or supported.
+    Unimplemented,
+    /// An internal error occurred.
+    InternalError,
+    /// The service is currently unavailable.
+    Unavailable,
+    /// Unrecoverable data loss or corruption.
+    DataLoss,
+    /// The request does not have valid authentication credentials.
+    Unauthenticated,
+}
+
+impl SpanStatus {
+    /// Maps the status code of an HTTP response to a span status.
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            100..=399 => SpanStatus::Ok,
+            401 => SpanStatus::Unauthenticated,
+            403 => SpanStatus::PermissionDenied,
+            404 => SpanStatus::NotFound,
+            409 => SpanStatus::AlreadyExists,
+            413 => SpanStatus::FailedPrecondition,
+            429 => SpanStatus::ResourceExhausted,
+            499 => SpanStatus::Cancelled,
+            400..=499 => SpanStatus::InvalidArgument,
+            501 => SpanStatus::Unimplemented,
+            503 => SpanStatus::Unavailable,
+            504 => SpanStatus::DeadlineExceeded,
+            500..=599 => SpanStatus::InternalError,
+            _ => SpanStatus::UnknownError,
+        }
+    }
+
+    /// Maps a gRPC status code to a span status.
+    ///
+    /// Codes that are not defined by gRPC map to [`SpanStatus::UnknownError`].
+    pub fn from_grpc_status(code: i32) -> Self {
+        match code {
+            0 => SpanStatus::Ok,
+            1 => SpanStatus::Cancelled,
+            3 => SpanStatus::InvalidArgument,
+            4 => SpanStatus::DeadlineExceeded,
+            5 => SpanStatus::NotFound,
+            6 => SpanStatus::AlreadyExists,
+            7 => SpanStatus::PermissionDenied,
+            8 => SpanStatus::ResourceExhausted,
+            9 => SpanStatus::FailedPrecondition,
+            10 => SpanStatus::Aborted,
+            11 => SpanStatus::OutOfRange,
+            12 => SpanStatus::Unimplemented,
+            13 => SpanStatus::InternalError,
+            14 => SpanStatus::Unavailable,
+            15 => SpanStatus::DataLoss,
+            16 => SpanStatus::Unauthenticated,
+            _ => SpanStatus::UnknownError,
+        }
+    }
+
+    /// Returns the status as it is sent to Sentry, such as `ok` or `internal_error`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpanStatus::Ok => "ok",
+            SpanStatus::Cancelled => "cancelled",
+            SpanStatus::UnknownError => "unknown_error",
+            SpanStatus::InvalidArgument => "invalid_argument",
+            SpanStatus::DeadlineExceeded => "deadline_exceeded",
+            SpanStatus::NotFound => "not_found",
+            SpanStatus::AlreadyExists => "already_exists",
+            SpanStatus::PermissionDenied => "permission_denied",
+            SpanStatus::ResourceExhausted => "resource_exhausted",
+            SpanStatus::FailedPrecondition => "failed_precondition",
+            SpanStatus::Aborted => "aborted",
+            SpanStatus::OutOfRange => "out_of_range",
+            SpanStatus::Unimplemented => "unimplemented",
+            SpanStatus::InternalError => "internal_error",
+            SpanStatus::Unavailable => "unavailable",
+            SpanStatus::DataLoss => "data_loss",
+            SpanStatus::Unauthenticated => "unauthenticated",
+        }
+    }
+}
+
+/// An error used when parsing `SpanStatus`.
+#[derive(Debug, Error)]
+#[error("invalid span status")]
+pub struct ParseSpanStatusError;
+
+impl str::FromStr for SpanStatus {
+    type Err = ParseSpanStatusError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Ok(match string {
+            "ok" => SpanStatus::Ok,
+            "cancelled" => SpanStatus::Cancelled,
+            "unknown_error" => SpanStatus::UnknownError,
+            "invalid_argument" => SpanStatus::InvalidArgument,
+            "deadline_exceeded" => SpanStatus::DeadlineExceeded,
+            "not_found" => SpanStatus::NotFound,
+            "already_exists" => SpanStatus::AlreadyExists,
+            "permission_denied" => SpanStatus::PermissionDenied,
+            "resource_exhausted" => SpanStatus::ResourceExhausted,
+            "failed_precondition" => SpanStatus::FailedPrecondition,
+            "aborted" => SpanStatus::Aborted,
+            "out_of_range" => SpanStatus::OutOfRange,
+            "unimplemented" => SpanStatus::Unimplemented,
+            "internal_error" => SpanStatus::InternalError,
+            "unavailable" => SpanStatus::Unavailable,
+            "data_loss" => SpanStatus::DataLoss,
+            "unauthenticated" => SpanStatus::Unauthenticated,
+            _ => return Err(ParseSpanStatusError),
+        })
+    }
+}
+
+impl fmt::Display for SpanStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -13,8 +13,9 @@ use std::iter::FromIterator;
 use std::net::{AddrParseError, IpAddr};
 use std::ops;
 use std::str;
+use std::time::Duration;
 
-use ::debugid::DebugId;
+use ::debugid::{CodeId, DebugId};
 use chrono::{DateTime, Utc};
 use serde::Serializer;
 use serde::{Deserialize, Serialize};
@@ -24,8 +25,12 @@ use uuid::Uuid;
 
 use crate::utils::ts_seconds_float;
 
+pub use super::attachment::*;
 pub use super::envelope::*;
+#[cfg(feature = "http")]
+pub use super::http::*;
 pub use super::session::*;
+pub use super::span_status::*;
 
 /// An arbitrary (JSON) value.
 pub mod value {
@@ -39,7 +44,7 @@ pub mod map {
 
 /// Represents a debug ID.
 pub mod debugid {
-    pub use debugid::{BreakpadFormat, DebugId, ParseDebugIdError};
+    pub use debugid::{BreakpadFormat, CodeId, DebugId, ParseDebugIdError};
 }
 
 /// An arbitrary (JSON) value.
@@ -639,6 +644,10 @@ pub struct Exception {
 pub struct ParseLevelError;
 
 /// Represents the level of severity of an event or breadcrumb.
+///
+/// Levels are ordered by severity, from `Debug` to `Fatal`.  They are parsed case
+/// insensitively, and also accept the names used by common logging libraries, such
+/// as `warn` and `trace`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Level {
     /// Indicates very spammy debug information.
@@ -663,12 +672,12 @@ impl str::FromStr for Level {
     type Err = ParseLevelError;
 
     fn from_str(string: &str) -> Result<Level, Self::Err> {
-        Ok(match string {
-            "debug" => Level::Debug,
+        Ok(match string.to_ascii_lowercase().as_str() {
+            "debug" | "trace" => Level::Debug,
             "info" | "log" => Level::Info,
-            "warning" => Level::Warning,
+            "warning" | "warn" => Level::Warning,
             "error" => Level::Error,
-            "fatal" => Level::Fatal,
+            "fatal" | "critical" => Level::Fatal,
             _ => return Err(ParseLevelError),
         })
     }
@@ -779,6 +788,75 @@ impl Default for Breadcrumb {
     }
 }
 
+impl Breadcrumb {
+    /// Creates an `http` breadcrumb for a request.
+    ///
+    /// The `method`, `url` and `status_code` are added to the data of the breadcrumb.
+    /// The level is `error` for server errors, and `warning` for client errors.
+    pub fn http<M, U>(method: M, url: U, status_code: Option<u16>) -> Breadcrumb
+    where
+        M: Into<String>,
+        U: Into<String>,
+    {
+        let mut data = Map::new();
+        data.insert("method".into(), method.into().into());
+        data.insert("url".into(), url.into().into());
+        let level = match status_code {
+            Some(status_code) => {
+                data.insert("status_code".into(), status_code.into());
+                match status_code {
+                    500..=599 => Level::Error,
+                    400..=499 => Level::Warning,
+                    _ => Level::Info,
+                }
+            }
+            None => Level::Info,
+        };
+        Breadcrumb {
+            ty: "http".into(),
+            category: Some("http".into()),
+            level,
+            data,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a `navigation` breadcrumb for a change from one location to another.
+    ///
+    /// The locations are added as `from` and `to` to the data of the breadcrumb.
+    pub fn navigation<F, T>(from: F, to: T) -> Breadcrumb
+    where
+        F: Into<String>,
+        T: Into<String>,
+    {
+        let mut data = Map::new();
+        data.insert("from".into(), from.into().into());
+        data.insert("to".into(), to.into().into());
+        Breadcrumb {
+            ty: "navigation".into(),
+            category: Some("navigation".into()),
+            data,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a `query` breadcrumb for a database query.
+    ///
+    /// The statement is the message of the breadcrumb, and the duration is added as
+    /// `duration_ms` to its data.
+    pub fn query<S: Into<String>>(statement: S, duration: Duration) -> Breadcrumb {
+        let mut data = Map::new();
+        data.insert("duration_ms".into(), (duration.as_millis() as u64).into());
+        Breadcrumb {
+            ty: "query".into(),
+            category: Some("query".into()),
+            message: Some(statement.into()),
+            data,
+            ..Default::default()
+        }
+    }
+}
+
 /// An IP address, either IPv4, IPv6 or Auto.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub enum IpAddress {
@@ -975,6 +1053,15 @@ pub struct SymbolicDebugImage {
     pub image_vmaddr: Addr,
     /// The unique debug id of the image.
     pub id: DebugId,
+    /// The platform specific identifier of the image file itself.
+    ///
+    /// This is the timestamp and size of PE files on Windows, and is usually not needed
+    /// on other platforms, where the debug id already identifies the image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_id: Option<CodeId>,
+    /// The name of the file containing the debug information, such as a PDB file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_file: Option<String>,
 }
 
 /// Represents a proguard mapping file reference.
@@ -1050,6 +1137,10 @@ pub enum Context {
     App(Box<AppContext>),
     /// Web browser data.
     Browser(Box<BrowserContext>),
+    /// Graphics device data.
+    Gpu(Box<GpuContext>),
+    /// Locale and formatting data.
+    Culture(Box<CultureContext>),
     /// Tracing data.
     Trace(Box<TraceContext>),
     /// Generic other context data.
@@ -1066,6 +1157,8 @@ impl Context {
             Context::Runtime(..) => "runtime",
             Context::App(..) => "app",
             Context::Browser(..) => "browser",
+            Context::Gpu(..) => "gpu",
+            Context::Culture(..) => "culture",
             Context::Trace(..) => "trace",
             Context::Other(..) => "unknown",
         }
@@ -1221,12 +1314,120 @@ pub struct BrowserContext {
     pub other: Map<String, Value>,
 }
 
+/// Holds information about the graphics device.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GpuContext {
+    /// The name of the graphics device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The PCI identifier of the graphics device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    /// The PCI vendor identifier of the graphics device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_id: Option<u64>,
+    /// The vendor name as reported by the graphics device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_name: Option<String>,
+    /// The total graphics memory available in megabytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_size: Option<u64>,
+    /// The graphics API in use (for instance "Vulkan" or "Metal").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_type: Option<String>,
+    /// The version of the graphics device or driver.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Additional arbitrary fields for forwards compatibility.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Holds information about the locale and formatting preferences.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CultureContext {
+    /// The calendar in use (for instance "GregorianCalendar").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<String>,
+    /// A human readable name of the culture.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// The name of the locale (for instance "en-US").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Whether times are formatted with a 24 hour clock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_24_hour_format: Option<bool>,
+    /// The name of the timezone (for instance "Europe/Vienna").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Additional arbitrary fields for forwards compatibility.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// The ID of a span.
+///
+/// Span IDs are 8 random bytes, which are represented as 16 lowercase hex
+/// characters.
+#[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash)]
+pub struct SpanId([u8; 8]);
+
+impl Default for SpanId {
+    /// Creates a new random span ID.
+    fn default() -> SpanId {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&Uuid::new_v4().as_bytes()[..8]);
+        SpanId(bytes)
+    }
+}
+
+impl From<[u8; 8]> for SpanId {
+    fn from(bytes: [u8; 8]) -> SpanId {
+        SpanId(bytes)
+    }
+}
+
+impl fmt::Display for SpanId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SpanId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SpanId({})", self)
+    }
+}
+
+/// An error used when parsing `SpanId`.
+#[derive(Debug, Error)]
+#[error("invalid span id")]
+pub struct ParseSpanIdError;
+
+impl str::FromStr for SpanId {
+    type Err = ParseSpanIdError;
+
+    fn from_str(s: &str) -> Result<SpanId, ParseSpanIdError> {
+        if s.len() != 16 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseSpanIdError);
+        }
+        let id = u64::from_str_radix(s, 16).map_err(|_| ParseSpanIdError)?;
+        Ok(SpanId(id.to_be_bytes()))
+    }
+}
+
+impl_str_serde!(SpanId);
+
 /// Holds information about a tracing event.
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct TraceContext {
     /// The ID of the trace event
-    #[serde(default = "event::default_id", serialize_with = "event::serialize_id")]
-    pub span_id: Uuid,
+    #[serde(default)]
+    pub span_id: SpanId,
     /// Determines which trace the transaction belongs to.
     #[serde(default = "event::default_id", serialize_with = "event::serialize_id")]
     pub trace_id: Uuid,
@@ -1242,6 +1443,9 @@ pub struct TraceContext {
     /// Describes the status of the span (e.g. `ok`, `cancelled`, etc.)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub status: Option<String>,
+    /// Optional extra information to be sent with the transaction.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub data: Map<String, Value>,
 }
 
 macro_rules! into_context {
@@ -1259,6 +1463,8 @@ into_context!(Device, DeviceContext);
 into_context!(Os, OsContext);
 into_context!(Runtime, RuntimeContext);
 into_context!(Browser, BrowserContext);
+into_context!(Gpu, GpuContext);
+into_context!(Culture, CultureContext);
 into_context!(Trace, TraceContext);
 
 mod event {
@@ -1394,7 +1600,7 @@ pub struct Event<'a> {
     #[serde(default, skip_serializing_if = "DebugMeta::is_empty")]
     pub debug_meta: Cow<'a, DebugMeta>,
     /// SDK metadata
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "crate::protocol::envelope::skip_sdk")]
     pub sdk: Option<Cow<'a, ClientSdkInfo>>,
 }
 
@@ -1483,12 +1689,22 @@ impl<'a> fmt::Display for Event<'a> {
     }
 }
 
+/// A measurement of a transaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Measurement {
+    /// The value of the measurement.
+    pub value: f64,
+    /// The unit of the value, such as `byte` or `millisecond`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
 /// Represents a tracing span.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Span {
     /// The ID of the span
-    #[serde(default = "event::default_id", serialize_with = "event::serialize_id")]
-    pub span_id: Uuid,
+    #[serde(default)]
+    pub span_id: SpanId,
     /// Determines which trace the span belongs to.
     #[serde(default = "event::default_id", serialize_with = "event::serialize_id")]
     pub trace_id: Uuid,
@@ -1525,7 +1741,7 @@ pub struct Span {
 impl Default for Span {
     fn default() -> Self {
         Span {
-            span_id: event::default_id(),
+            span_id: Default::default(),
             trace_id: event::default_id(),
             timestamp: Default::default(),
             tags: Default::default(),
@@ -1571,11 +1787,17 @@ pub struct Transaction<'a> {
     /// The transaction name.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// The release to be associated with the transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<Cow<'a, str>>,
+    /// The environment to be associated with the transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Cow<'a, str>>,
     /// Optional tags to be attached to the event.
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub tags: Map<String, String>,
     /// SDK metadata
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "crate::protocol::envelope::skip_sdk")]
     pub sdk: Option<Cow<'a, ClientSdkInfo>>,
     /// A platform identifier for this event.
     #[serde(
@@ -1594,6 +1816,9 @@ pub struct Transaction<'a> {
     /// Optional contexts.
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub contexts: Map<String, Context>,
+    /// Optional measurements, such as the memory allocated during the transaction.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub measurements: Map<String, Measurement>,
 }
 
 impl<'a> Default for Transaction<'a> {
@@ -1601,6 +1826,8 @@ impl<'a> Default for Transaction<'a> {
         Transaction {
             event_id: event::default_id(),
             name: Default::default(),
+            release: Default::default(),
+            environment: Default::default(),
             tags: Default::default(),
             sdk: Default::default(),
             platform: event::default_platform(),
@@ -1608,6 +1835,7 @@ impl<'a> Default for Transaction<'a> {
             start_timestamp: event::default_timestamp(),
             spans: Default::default(),
             contexts: Default::default(),
+            measurements: Default::default(),
         }
     }
 }
@@ -1623,6 +1851,8 @@ impl<'a> Transaction<'a> {
         Transaction {
             event_id: self.event_id,
             name: self.name,
+            release: self.release.map(|x| Cow::Owned(x.into_owned())),
+            environment: self.environment.map(|x| Cow::Owned(x.into_owned())),
             tags: self.tags,
             sdk: self.sdk.map(|x| Cow::Owned(x.into_owned())),
             platform: Cow::Owned(self.platform.into_owned()),
@@ -1630,6 +1860,7 @@ impl<'a> Transaction<'a> {
             start_timestamp: self.start_timestamp,
             spans: self.spans,
             contexts: self.contexts,
+            measurements: self.measurements,
         }
     }
 
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::protocol::v7::{SpanId, TraceContext};
+
+/// The name of the header that propagates a trace between services.
+pub const SENTRY_TRACE_HEADER: &str = "sentry-trace";
+
+/// The name of the header that propagates baggage between services.
+pub const BAGGAGE_HEADER: &str = "baggage";
+
+/// Raised if a `sentry-trace` header cannot be parsed.
+#[derive(Debug, Error, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ParseSentryTraceError {
+    /// Raised if the trace id is missing or invalid.
+    #[error("invalid trace id")]
+    InvalidTraceId,
+    /// Raised if the span id is missing or invalid.
+    #[error("invalid span id")]
+    InvalidSpanId,
+    /// Raised if the sampled flag is not `0` or `1`.
+    #[error("invalid sampled flag")]
+    InvalidSampled,
+}
+
+/// Represents the value of a `sentry-trace` header.
+///
+/// The header has the form `<trace_id>-<span_id>[-<sampled>]`, where the trace id has 32
+/// and the span id 16 hex characters, and the sampled flag is either `1` or `0`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SentryTrace {
+    /// The id of the trace.
+    pub trace_id: Uuid,
+    /// The id of the span that made the outgoing request.
+    pub span_id: SpanId,
+    /// The sampling decision of the upstream service, if it made one.
+    pub sampled: Option<bool>,
+}
+
+impl SentryTrace {
+    /// Creates the header value that continues the trace of the given context.
+    pub fn from_trace_context(context: &TraceContext) -> Self {
+        SentryTrace {
+            trace_id: context.trace_id,
+            span_id: context.span_id,
+            sampled: None,
+        }
+    }
+
+    /// Creates a new trace context for a span that continues this trace.
+    pub fn new_child(&self) -> TraceContext {
+        TraceContext {
+            span_id: SpanId::default(),
+            trace_id: self.trace_id,
+            parent_span_id: Some(self.span_id.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for SentryTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.trace_id.to_simple_ref(), self.span_id)?;
+        match self.sampled {
+            Some(true) => write!(f, "-1"),
+            Some(false) => write!(f, "-0"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for SentryTrace {
+    type Err = ParseSentryTraceError;
+
+    fn from_str(s: &str) -> Result<SentryTrace, ParseSentryTraceError> {
+        let mut parts = s.trim().splitn(3, '-');
+        let trace_id = parts
+            .next()
+            .and_then(|id| id.parse().ok())
+            .ok_or(ParseSentryTraceError::InvalidTraceId)?;
+        let span_id = parts
+            .next()
+            .and_then(|id| id.parse().ok())
+            .ok_or(ParseSentryTraceError::InvalidSpanId)?;
+        let sampled = match parts.next() {
+            Some("1") => Some(true),
+            Some("0") => Some(false),
+            Some(_) => return Err(ParseSentryTraceError::InvalidSampled),
+            None => None,
+        };
+        Ok(SentryTrace {
+            trace_id,
+            span_id,
+            sampled,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let header = "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a-1";
+        let trace: SentryTrace = header.parse().unwrap();
+        assert_eq!(
+            trace.trace_id,
+            "09e04486820349518ac7b5d2adbf6ba5".parse::<Uuid>().unwrap()
+        );
+        assert_eq!(trace.sampled, Some(true));
+        assert_eq!(trace.to_string(), header);
+
+        let child = trace.new_child();
+        assert_eq!(child.trace_id, trace.trace_id);
+        assert_eq!(child.parent_span_id, Some("8c1f9f3e1d2b4c5a".into()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "".parse::<SentryTrace>(),
+            Err(ParseSentryTraceError::InvalidTraceId)
+        );
+        assert_eq!(
+            "09e04486820349518ac7b5d2adbf6ba5".parse::<SentryTrace>(),
+            Err(ParseSentryTraceError::InvalidSpanId)
+        );
+        assert_eq!(
+            "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a9e8d7c6b5a4f3e2d"
+                .parse::<SentryTrace>(),
+            Err(ParseSentryTraceError::InvalidSpanId)
+        );
+        assert_eq!(
+            "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a-x".parse::<SentryTrace>(),
+            Err(ParseSentryTraceError::InvalidSampled)
+        );
+    }
+}
@@ -431,6 +431,38 @@ mod test_breadcrumbs {
              \"/api/0/organizations/foo\"}}]}}"
         );
     }
+
+    #[test]
+    fn test_breadcrumb_constructors() {
+        let breadcrumb = v7::Breadcrumb::http("GET", "https://example.com/users", Some(503));
+        assert_eq!(breadcrumb.ty, "http");
+        assert_eq!(breadcrumb.category.as_deref(), Some("http"));
+        assert_eq!(breadcrumb.level, v7::Level::Error);
+        assert_eq!(breadcrumb.data["method"], "GET");
+        assert_eq!(breadcrumb.data["url"], "https://example.com/users");
+        assert_eq!(breadcrumb.data["status_code"], 503);
+
+        let breadcrumb = v7::Breadcrumb::http("POST", "/login", Some(401));
+        assert_eq!(breadcrumb.level, v7::Level::Warning);
+        let breadcrumb = v7::Breadcrumb::http("POST", "/login", None);
+        assert_eq!(breadcrumb.level, v7::Level::Info);
+        assert!(!breadcrumb.data.contains_key("status_code"));
+
+        let breadcrumb = v7::Breadcrumb::navigation("/home", "/settings");
+        assert_eq!(breadcrumb.ty, "navigation");
+        assert_eq!(breadcrumb.category.as_deref(), Some("navigation"));
+        assert_eq!(breadcrumb.data["from"], "/home");
+        assert_eq!(breadcrumb.data["to"], "/settings");
+
+        let breadcrumb = v7::Breadcrumb::query(
+            "SELECT * FROM users",
+            std::time::Duration::from_micros(12_500),
+        );
+        assert_eq!(breadcrumb.ty, "query");
+        assert_eq!(breadcrumb.category.as_deref(), Some("query"));
+        assert_eq!(breadcrumb.message.as_deref(), Some("SELECT * FROM users"));
+        assert_eq!(breadcrumb.data["duration_ms"], 12);
+    }
 }
 
 mod test_stacktrace {
@@ -767,6 +799,19 @@ mod test_debug_meta {
                         image_size: 4096,
                         image_vmaddr: 32768.into(),
                         id: "494f3aea-88fa-4296-9644-fa8ef5d139b6-1234".parse().unwrap(),
+                        code_id: None,
+                        debug_file: None,
+                    }
+                    .into(),
+                    v7::SymbolicDebugImage {
+                        name: "C:\\app\\app.exe".into(),
+                        arch: None,
+                        image_addr: 65536.into(),
+                        image_size: 8192,
+                        image_vmaddr: 0.into(),
+                        id: "3249d99d-0c40-4931-8610-f4e4fb0b6936-1".parse().unwrap(),
+                        code_id: Some(v7::debugid::CodeId::new("5ab380779000".into())),
+                        debug_file: Some("app.pdb".into()),
                     }
                     .into(),
                     v7::ProguardDebugImage {
@@ -788,7 +833,10 @@ mod test_debug_meta {
              4096,\"image_vmaddr\":\"0x8000\",\"uuid\":\"494f3aea-88fa-4296-9644-fa8ef5d139b6\"},\
              {\"type\":\"symbolic\",\"name\":\"CoreFoundation\",\"arch\":\"arm64\",\"image_addr\":\
              \"0x0\",\"image_size\":4096,\"image_vmaddr\":\"0x8000\",\"id\":\
-             \"494f3aea-88fa-4296-9644-fa8ef5d139b6-1234\"},{\"type\":\"proguard\",\"uuid\":\
+             \"494f3aea-88fa-4296-9644-fa8ef5d139b6-1234\"},{\"type\":\"symbolic\",\"name\":\
+             \"C:\\\\app\\\\app.exe\",\"arch\":null,\"image_addr\":\"0x10000\",\"image_size\":8192,\
+             \"id\":\"3249d99d-0c40-4931-8610-f4e4fb0b6936-1\",\"code_id\":\"5ab380779000\",\
+             \"debug_file\":\"app.pdb\"},{\"type\":\"proguard\",\"uuid\":\
              \"8c954262-f905-4992-8a61-f60825f4553b\"}]}}"
         );
     }
@@ -1259,6 +1307,69 @@ mod test_contexts {
         );
     }
 
+    #[test]
+    fn test_culture_context() {
+        let event = v7::Event {
+            event_id: event_id(),
+            timestamp: event_time(),
+            contexts: {
+                let mut m = v7::Map::new();
+                m.insert(
+                    "culture".into(),
+                    v7::CultureContext {
+                        locale: Some("de-AT".into()),
+                        is_24_hour_format: Some(true),
+                        timezone: Some("Europe/Vienna".into()),
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+                m
+            },
+            ..Default::default()
+        };
+
+        assert_roundtrip(&event);
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            "{\"event_id\":\"d43e86c96e424a93a4fbda156dd17341\",\"timestamp\":1514103120,\
+             \"contexts\":{\"culture\":{\"type\":\"culture\",\"locale\":\"de-AT\",\
+             \"is_24_hour_format\":true,\"timezone\":\"Europe/Vienna\"}}}"
+        );
+    }
+
+    #[test]
+    fn test_gpu_context() {
+        let event = v7::Event {
+            event_id: event_id(),
+            timestamp: event_time(),
+            contexts: {
+                let mut m = v7::Map::new();
+                m.insert(
+                    "gpu".into(),
+                    v7::GpuContext {
+                        name: Some("GeForce GTX 1080".into()),
+                        vendor_id: Some(0x10de),
+                        vendor_name: Some("NVIDIA".into()),
+                        api_type: Some("Vulkan".into()),
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+                m
+            },
+            ..Default::default()
+        };
+
+        assert_roundtrip(&event);
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            "{\"event_id\":\"d43e86c96e424a93a4fbda156dd17341\",\"timestamp\":1514103120,\
+             \"contexts\":{\"gpu\":{\"type\":\"gpu\",\"name\":\"GeForce GTX 1080\",\
+             \"vendor_id\":4318,\"vendor_name\":\"NVIDIA\",\"api_type\":\"Vulkan\"}}}"
+        );
+    }
+
     #[test]
     fn test_runtime_context() {
         let event = v7::Event {
@@ -1362,6 +1473,26 @@ fn test_level_log() {
     assert_eq!(v7::Level::Info, serde_json::from_str("\"log\"").unwrap());
 }
 
+#[test]
+fn test_level_parse_and_order() {
+    let levels = [
+        v7::Level::Debug,
+        v7::Level::Info,
+        v7::Level::Warning,
+        v7::Level::Error,
+        v7::Level::Fatal,
+    ];
+    for level in &levels {
+        assert_eq!(level.to_string().parse::<v7::Level>().unwrap(), *level);
+    }
+    assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+
+    assert_eq!("WARN".parse::<v7::Level>().unwrap(), v7::Level::Warning);
+    assert_eq!("Trace".parse::<v7::Level>().unwrap(), v7::Level::Debug);
+    assert_eq!("critical".parse::<v7::Level>().unwrap(), v7::Level::Fatal);
+    assert!("verbose".parse::<v7::Level>().is_err());
+}
+
 #[test]
 fn test_addr_format() {
     assert_eq!(serde_json::to_string(&v7::Addr(0)).unwrap(), "\"0x0\"");
@@ -1430,3 +1561,42 @@ fn test_orientation() {
         "\"portrait\""
     );
 }
+
+#[test]
+fn test_span_status() {
+    assert_eq!(v7::SpanStatus::from_http_status(204), v7::SpanStatus::Ok);
+    assert_eq!(
+        v7::SpanStatus::from_http_status(404),
+        v7::SpanStatus::NotFound
+    );
+    assert_eq!(
+        v7::SpanStatus::from_http_status(418),
+        v7::SpanStatus::InvalidArgument
+    );
+    assert_eq!(
+        v7::SpanStatus::from_http_status(502),
+        v7::SpanStatus::InternalError
+    );
+    assert_eq!(
+        v7::SpanStatus::from_grpc_status(14),
+        v7::SpanStatus::Unavailable
+    );
+    assert_eq!(
+        v7::SpanStatus::from_grpc_status(42),
+        v7::SpanStatus::UnknownError
+    );
+
+    assert_eq!(
+        serde_json::to_string(&v7::SpanStatus::DeadlineExceeded).unwrap(),
+        "\"deadline_exceeded\""
+    );
+    assert_eq!(
+        "internal_error".parse::<v7::SpanStatus>().unwrap(),
+        v7::SpanStatus::InternalError
+    );
+    assert_eq!(
+        v7::SpanStatus::Unauthenticated.to_string(),
+        "unauthenticated"
+    );
+    assert!("bogus".parse::<v7::SpanStatus>().is_err());
+}
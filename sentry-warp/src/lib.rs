@@ -0,0 +1,398 @@
+//! Adds support for the [`warp`](https://docs.rs/warp) web framework.
+//!
+//! The [`with_sentry`] combinator wraps a filter, so that its rejections and its replies
+//! whose status is at or above a configurable threshold are captured as events.  By default,
+//! this includes all server errors.  The events get the [`Request`] interface attached, and
+//! the transaction name is set from the request method and path.
+//!
+//! Rejections are passed on unchanged, so the wrapped filter can still be combined with
+//! [`Filter::or`].  A rejection is captured when it leaves the wrapped filter, which is why
+//! the combinator should wrap the complete routes rather than a single alternative.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//!
+//! let hello = warp::path!("hello" / String).map(|name| format!("Hello, {}!", name));
+//! let routes = sentry_warp::with_sentry(hello);
+//! # let _ = routes;
+//! // warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+//! ```
+//!
+//! # Reporting Client Errors
+//!
+//! The threshold for reported statuses can be changed with [`SentryWarp`]:
+//!
+//! ```
+//! use sentry_warp::SentryWarp;
+//! use warp::http::StatusCode;
+//! use warp::Filter;
+//!
+//! let hello = warp::path!("hello" / String).map(|name| format!("Hello, {}!", name));
+//! let routes = SentryWarp::new()
+//!     .capture_status_from(StatusCode::BAD_REQUEST)
+//!     .wrap(hello);
+//! # let _ = routes;
+//! ```
+//!
+//! # Isolating Requests
+//!
+//! Filters cannot bind a [`Hub`] around the filters they wrap, so events are captured on
+//! the hub that is current when the request is handled.  To give every request its own hub,
+//! serve the filter as a service, wrapped in the layers of `sentry-tower`:
+//!
+//! ```
+//! use tower::ServiceBuilder;
+//! use warp::Filter;
+//!
+//! let hello = warp::path!("hello" / String).map(|name| format!("Hello, {}!", name));
+//! let service = ServiceBuilder::new()
+//!     .layer(sentry_tower::SentryLayer::new_from_top())
+//!     .layer(sentry_tower::SentryHttpLayer::new())
+//!     .service(warp::service(sentry_warp::with_sentry(hello)));
+//! # let _ = service;
+//! // hyper::Server::bind(&addr)
+//! //     .serve(hyper::service::make_service_fn(move |_| {
+//! //         let service = service.clone();
+//! //         async move { Ok::<_, std::convert::Infallible>(service) }
+//! //     }))
+//! //     .await;
+//! ```
+
+#![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
+#![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
+#![warn(missing_docs)]
+
+use std::convert::Infallible;
+
+use http_::header::{self, HeaderMap};
+use http_::{Method, StatusCode};
+use sentry_core::protocol::{self, Event, Exception, Level, Request};
+use sentry_core::Hub;
+use warp::filters::path::FullPath;
+use warp::reply::{Reply, Response};
+use warp::{Filter, Rejection};
+
+/// Wraps a filter with the default [`SentryWarp`] configuration.
+///
+/// Rejections and replies with a server error status are captured.
+pub fn with_sentry<F, R>(filter: F) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    SentryWarp::new().wrap(filter)
+}
+
+/// Configures how filters are wrapped by Sentry.
+#[derive(Clone, Debug)]
+pub struct SentryWarp {
+    capture_status_from: Option<StatusCode>,
+}
+
+impl SentryWarp {
+    /// Creates a new configuration that captures server errors.
+    pub fn new() -> Self {
+        Self {
+            capture_status_from: Some(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+
+    /// Captures replies and rejections with a status at or above the given one.
+    ///
+    /// The status of a rejection is the one of warp's default rejection handling, where
+    /// rejections that are not built into warp are server errors.
+    pub fn capture_status_from(mut self, status: StatusCode) -> Self {
+        self.capture_status_from = Some(status);
+        self
+    }
+
+    /// Disables capturing of replies and rejections based on their status.
+    pub fn no_status_capture(mut self) -> Self {
+        self.capture_status_from = None;
+        self
+    }
+
+    /// Wraps the filter, capturing its rejections and replies as configured.
+    pub fn wrap<F, R>(
+        self,
+        filter: F,
+    ) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+    where
+        F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+        R: Reply,
+    {
+        let capture_status_from = self.capture_status_from;
+        let should_capture =
+            move |status: StatusCode| capture_status_from.map_or(false, |from| status >= from);
+
+        let filter = filter
+            .map(|reply: R| Ok(reply.into_response()))
+            .or_else(|rejection| async { Ok::<_, Infallible>((Err(rejection),)) });
+
+        request_info().and(filter).and_then(
+            move |info: RequestInfo, result: Result<Response, Rejection>| {
+                let event = match &result {
+                    Ok(response) if should_capture(response.status()) => {
+                        Some(info.event(response.status(), None))
+                    }
+                    Err(rejection) if should_capture(rejection_status(rejection)) => {
+                        Some(info.event(rejection_status(rejection), Some(rejection)))
+                    }
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    Hub::current().capture_event(event);
+                }
+                async { result }
+            },
+        )
+    }
+}
+
+impl Default for SentryWarp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The parts of the incoming request that are attached to captured events
+struct RequestInfo {
+    method: Method,
+    path: FullPath,
+    query: String,
+    headers: HeaderMap,
+}
+
+fn request_info() -> impl Filter<Extract = (RequestInfo,), Error = Infallible> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::header::headers_cloned())
+        .map(|method, path, query, headers| RequestInfo {
+            method,
+            path,
+            query,
+            headers,
+        })
+}
+
+impl RequestInfo {
+    /// Creates the event for a reply with the given status, or for a rejection
+    fn event(&self, status: StatusCode, rejection: Option<&Rejection>) -> Event<'static> {
+        let with_pii = Hub::current()
+            .client()
+            .map_or(false, |client| client.options().send_default_pii);
+
+        let mut event = Event {
+            level: Level::Error,
+            message: Some(status.to_string()),
+            transaction: Some(format!("{} {}", self.method, self.path.as_str())),
+            request: Some(self.request(with_pii)),
+            ..Default::default()
+        };
+        if let Some(rejection) = rejection {
+            event.exception.values.push(Exception {
+                ty: "Rejection".into(),
+                value: Some(format!("{:?}", rejection)),
+                ..Default::default()
+            });
+        }
+        event
+    }
+
+    fn request(&self, with_pii: bool) -> Request {
+        let path = match self.query.as_str() {
+            "" => self.path.as_str().to_owned(),
+            query => format!("{}?{}", self.path.as_str(), query),
+        };
+        // a `Host` header that does not form a valid URL is replaced, so the URL is
+        // never dropped
+        let url = self
+            .headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .and_then(|host| format!("http://{}{}", host, path).parse().ok())
+            .or_else(|| format!("http://localhost{}", path).parse().ok());
+
+        Request {
+            url,
+            method: Some(self.method.to_string()),
+            query_string: Some(self.query.clone()).filter(|query| !query.is_empty()),
+            cookies: if with_pii {
+                self.headers
+                    .get(header::COOKIE)
+                    .and_then(|cookies| cookies.to_str().ok())
+                    .map(Into::into)
+            } else {
+                None
+            },
+            headers: protocol::filter_headers(&self.headers, with_pii),
+            ..Default::default()
+        }
+    }
+}
+
+/// Determines the status of the response that warp creates for an unhandled rejection
+///
+/// Of several causes, the one with the highest status is preferred, but `404 Not Found`
+/// and `405 Method Not Allowed` only if there is no other cause.
+fn rejection_status(rejection: &Rejection) -> StatusCode {
+    use warp::reject;
+
+    if rejection.is_not_found() {
+        return StatusCode::NOT_FOUND;
+    }
+
+    let known = [
+        (
+            rejection.find::<reject::InvalidHeader>().is_some()
+                || rejection.find::<reject::MissingHeader>().is_some()
+                || rejection.find::<reject::MissingCookie>().is_some()
+                || rejection.find::<reject::InvalidQuery>().is_some()
+                || rejection
+                    .find::<warp::body::BodyDeserializeError>()
+                    .is_some(),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            rejection.find::<reject::LengthRequired>().is_some(),
+            StatusCode::LENGTH_REQUIRED,
+        ),
+        (
+            rejection.find::<reject::PayloadTooLarge>().is_some(),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        ),
+        (
+            rejection.find::<reject::UnsupportedMediaType>().is_some(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ),
+        (
+            rejection.find::<warp::cors::CorsForbidden>().is_some(),
+            StatusCode::FORBIDDEN,
+        ),
+    ];
+    let status = known
+        .iter()
+        .filter(|(found, _)| *found)
+        .map(|(_, status)| *status)
+        .max();
+
+    match status {
+        Some(status) => status,
+        None if rejection.find::<reject::MethodNotAllowed>().is_some() => {
+            StatusCode::METHOD_NOT_ALLOWED
+        }
+        // custom rejections are unhandled, and turned into server errors
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use tower::{ServiceBuilder, ServiceExt};
+    use warp::http::StatusCode;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Unavailable;
+
+    impl warp::reject::Reject for Unavailable {}
+
+    #[test]
+    fn test_hub_per_request() {
+        let events = sentry::test::with_captured_events(|| {
+            let users = warp::path!("users" / String).map(|name: String| {
+                sentry::configure_scope(|scope| scope.set_tag("user", &name));
+                sentry::capture_message("Message", Level::Warning);
+                name
+            });
+            let service = ServiceBuilder::new()
+                .layer(sentry_tower::SentryLayer::new_from_top())
+                .layer(sentry_tower::SentryHttpLayer::new())
+                .service(warp::service(with_sentry(users)));
+
+            for name in &["first", "second"] {
+                let request = http_::Request::builder()
+                    .uri(format!("/users/{}?page=1", name))
+                    .body(hyper::Body::empty())
+                    .unwrap();
+                let response = block_on(service.clone().oneshot(request)).unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+            sentry::capture_message("Outside", Level::Info);
+        });
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].tags.get("user").unwrap(), "first");
+        assert_eq!(events[0].transaction, Some("GET /users/first".into()));
+        let request = events[0].request.as_ref().expect("Request should be set.");
+        assert_eq!(request.query_string, Some("page=1".into()));
+        assert_eq!(events[1].tags.get("user").unwrap(), "second");
+        assert!(events[2].tags.get("user").is_none());
+    }
+
+    #[test]
+    fn test_status_capture() {
+        let events = sentry::test::with_captured_events(|| {
+            let failing = warp::path("fail")
+                .map(|| warp::reply::with_status("Oops", StatusCode::INTERNAL_SERVER_ERROR));
+            let routes = with_sentry(failing.clone());
+
+            let response = block_on(
+                warp::test::request()
+                    .path("/fail?page=1")
+                    .header("host", "example.com")
+                    .reply(&routes),
+            );
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            let response = block_on(warp::test::request().path("/missing").reply(&routes));
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            let routes = SentryWarp::new()
+                .capture_status_from(StatusCode::BAD_REQUEST)
+                .wrap(failing);
+            block_on(warp::test::request().path("/missing").reply(&routes));
+        });
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].message, Some("500 Internal Server Error".into()));
+        assert_eq!(events[0].level, Level::Error);
+        assert_eq!(events[0].transaction, Some("GET /fail".into()));
+        let request = events[0].request.as_ref().expect("Request should be set.");
+        assert_eq!(
+            request.url.as_ref().map(|url| url.as_str()),
+            Some("http://example.com/fail?page=1")
+        );
+        assert_eq!(request.query_string, Some("page=1".into()));
+        assert_eq!(events[1].message, Some("404 Not Found".into()));
+    }
+
+    #[test]
+    fn test_rejections() {
+        let events = sentry::test::with_captured_events(|| {
+            let unavailable = warp::path("unavailable")
+                .and_then(|| async { Err::<String, _>(warp::reject::custom(Unavailable)) });
+            let fallback = warp::any().map(|| "Fallback");
+            let routes = with_sentry(unavailable.clone()).or(fallback);
+
+            let response = block_on(warp::test::request().path("/other").reply(&routes));
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.body(), "Fallback");
+
+            let routes = with_sentry(unavailable);
+            let response = block_on(warp::test::request().path("/unavailable").reply(&routes));
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].message, Some("500 Internal Server Error".into()));
+        assert_eq!(events[0].transaction, Some("GET /unavailable".into()));
+        let exception = &events[0].exception.values[0];
+        assert_eq!(exception.ty, "Rejection");
+        assert!(exception.value.as_ref().unwrap().contains("Unavailable"));
+    }
+}
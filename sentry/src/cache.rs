@@ -0,0 +1,150 @@
+//! State that is persisted in the `cache_dir` across application restarts.
+
+use std::fs;
+#[cfg(unix)]
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sentry_core::protocol::{Event, Level};
+use sentry_core::sentry_debug;
+
+use crate::types::Uuid;
+use crate::Envelope;
+
+/// The prefix of the markers that exist while an application is running,
+/// which is followed by the ID of the process.
+const RUNNING_MARKER_PREFIX: &str = "sentry-running-";
+
+/// The directory in which unsent envelopes are persisted.
+const ENVELOPES_DIR: &str = "envelopes";
+
+/// Writes the running marker of this process, and returns `true` if a marker
+/// of a previous run that is no longer running still existed.
+pub(crate) fn set_running_marker(dir: &Path) -> bool {
+    let crashed = remove_stale_markers(dir);
+    let marker = running_marker(dir);
+    let result = fs::create_dir_all(dir).and_then(|_| fs::write(&marker, ""));
+    if let Err(err) = result {
+        sentry_debug!("failed to write marker {}: {}", marker.display(), err);
+    }
+    crashed
+}
+
+/// Removes the running marker on clean shutdown.
+pub(crate) fn clear_running_marker(dir: &Path) {
+    let _ = fs::remove_file(running_marker(dir));
+}
+
+fn running_marker(dir: &Path) -> PathBuf {
+    dir.join(format!("{}{}", RUNNING_MARKER_PREFIX, process::id()))
+}
+
+/// Removes the running markers of processes that are gone, and returns
+/// whether there were any.
+fn remove_stale_markers(dir: &Path) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    let mut crashed = false;
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name();
+        let pid = match name.to_str() {
+            Some(name) if name.starts_with(RUNNING_MARKER_PREFIX) => {
+                match name[RUNNING_MARKER_PREFIX.len()..].parse::<u32>() {
+                    Ok(pid) => pid,
+                    Err(_) => continue,
+                }
+            }
+            _ => continue,
+        };
+        // a marker with our own ID was left by an earlier process that had it
+        if pid == process::id() || !is_running(pid) {
+            sentry_debug!("removing stale marker {}", entry.path().display());
+            let _ = fs::remove_file(entry.path());
+            crashed = true;
+        }
+    }
+    crashed
+}
+
+/// Returns whether the process with the given ID is still running.
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // signal 0 only checks whether the process exists, and fails with
+    // `EPERM` for processes of other users
+    let rv = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    rv == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Returns whether the process with the given ID is still running.
+///
+/// This can not be checked here, so the process is assumed to be gone.
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+/// The event that is sent when the previous run did not shut down cleanly.
+pub(crate) fn crashed_on_previous_run_event() -> Event<'static> {
+    let mut event = Event {
+        level: Level::Fatal,
+        message: Some("The application crashed on its previous run".into()),
+        ..Default::default()
+    };
+    event
+        .tags
+        .insert("app.crashed_on_previous_run".into(), "true".into());
+    event
+}
+
+/// Writes an envelope that could not be sent to the cache directory.
+#[allow(unused)]
+pub(crate) fn persist_envelope(dir: &Path, envelope: &Envelope) {
+    let dir = dir.join(ENVELOPES_DIR);
+    // prefix the name with the current time so envelopes are resent in order
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let path = dir.join(format!("{:024}-{}.envelope", nanos, Uuid::new_v4()));
+
+    let mut body = Vec::new();
+    let result = envelope
+        .to_writer(&mut body)
+        .and_then(|_| fs::create_dir_all(&dir))
+        .and_then(|_| fs::write(&path, body));
+    match result {
+        Ok(()) => sentry_debug!("persisted unsent envelope to {}", path.display()),
+        Err(err) => sentry_debug!("failed to persist envelope {}: {}", path.display(), err),
+    }
+}
+
+/// Reads and removes the envelopes persisted by a previous run.
+pub(crate) fn take_persisted_envelopes(dir: &Path) -> Vec<Envelope> {
+    let entries = match fs::read_dir(dir.join(ENVELOPES_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "envelope"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let bytes = fs::read(&path).ok();
+            let _ = fs::remove_file(&path);
+            match Envelope::from_bytes_raw(bytes?) {
+                Ok(envelope) => Some(envelope),
+                Err(err) => {
+                    sentry_debug!("discarding invalid envelope {}: {}", path.display(), err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
@@ -16,11 +16,12 @@ use crate::{ClientOptions, Integration};
 /// `true` (the default), the following integrations will be added *before*
 /// any manually defined integrations, depending on enabled feature flags:
 ///
-/// 1. [`AttachStacktraceIntegration`] (`feature = "backtrace"`)
+/// 1. [`AttachStacktraceIntegration`] (`feature = "backtrace"` or `feature = "std-backtrace"`)
 /// 2. [`DebugImagesIntegration`] (`feature = "debug-images"`)
 /// 3. [`ContextIntegration`] (`feature = "contexts"`)
 /// 4. [`PanicIntegration`] (`feature = "panic"`)
-/// 5. [`ProcessStacktraceIntegration`] (`feature = "backtrace"`)
+/// 5. [`ForeignFramesIntegration`] (`feature = "backtrace"` or `feature = "std-backtrace"`)
+/// 6. [`ProcessStacktraceIntegration`] (`feature = "backtrace"` or `feature = "std-backtrace"`)
 ///
 /// Some integrations can be used multiple times, however, the
 /// [`PanicIntegration`] can not, and it will not pick up custom panic
@@ -43,6 +44,7 @@ use crate::{ClientOptions, Integration};
 /// [`DebugImagesIntegration`]: integrations/debug_images/struct.DebugImagesIntegration.html
 /// [`ContextIntegration`]: integrations/contexts/struct.ContextIntegration.html
 /// [`PanicIntegration`]: integrations/panic/struct.PanicIntegration.html
+/// [`ForeignFramesIntegration`]: integrations/backtrace/struct.ForeignFramesIntegration.html
 /// [`ProcessStacktraceIntegration`]: integrations/backtrace/struct.ProcessStacktraceIntegration.html
 pub fn apply_defaults(mut opts: ClientOptions) -> ClientOptions {
     if opts.transport.is_none() {
@@ -52,7 +54,7 @@ pub fn apply_defaults(mut opts: ClientOptions) -> ClientOptions {
         // default integrations need to be ordered *before* custom integrations,
         // since they also process events in order
         let mut integrations: Vec<Arc<dyn Integration>> = vec![];
-        #[cfg(feature = "backtrace")]
+        #[cfg(any(feature = "backtrace", feature = "std-backtrace"))]
         {
             integrations.push(Arc::new(
                 sentry_backtrace::AttachStacktraceIntegration::default(),
@@ -72,8 +74,11 @@ pub fn apply_defaults(mut opts: ClientOptions) -> ClientOptions {
         {
             integrations.push(Arc::new(sentry_panic::PanicIntegration::default()));
         }
-        #[cfg(feature = "backtrace")]
+        #[cfg(any(feature = "backtrace", feature = "std-backtrace"))]
         {
+            integrations.push(Arc::new(
+                sentry_backtrace::ForeignFramesIntegration::default(),
+            ));
             integrations.push(Arc::new(
                 sentry_backtrace::ProcessStacktraceIntegration::default(),
             ));
@@ -1,9 +1,16 @@
-use std::sync::Arc;
+use std::any::Any;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
 
 use sentry_core::sentry_debug;
 
+use crate::cache;
 use crate::defaults::apply_defaults;
-use crate::{Client, ClientOptions, Hub};
+use crate::types::{Dsn, ParseDsnError};
+use crate::{Client, ClientOptions, Hub, Transport, TryIntoClientOptions};
 
 /// Helper struct that is returned from `init`.
 ///
@@ -36,6 +43,9 @@ impl Drop for ClientInitGuard {
         // end any session that might be open before closing the client
         crate::end_session();
         self.0.close(None);
+        if let Some(dir) = self.0.options().cache_dir.as_deref() {
+            cache::clear_running_marker(dir);
+        }
     }
 }
 
@@ -47,7 +57,9 @@ impl Drop for ClientInitGuard {
 /// further events can be sent on it.
 ///
 /// If you don't want (or can) keep the guard around it's permissible to
-/// call `mem::forget` on it.
+/// call `mem::forget` on it.  This does not go together with the `cache_dir`
+/// option though, as every run would then be reported as crashed on the next
+/// start.
 ///
 /// # Examples
 ///
@@ -86,13 +98,153 @@ impl Drop for ClientInitGuard {
 /// # Panics
 ///
 /// This will panic when the provided DSN is invalid.
-/// If you want to handle invalid DSNs you need to parse them manually by
-/// calling `parse` on it and handle the error.
+/// If you want to handle invalid DSNs, use [`try_init`] instead.
+///
+/// [`try_init`]: fn.try_init.html
 pub fn init<C>(opts: C) -> ClientInitGuard
 where
     C: Into<ClientOptions>,
 {
-    let opts = apply_defaults(opts.into());
+    bind_client(apply_defaults(opts.into()))
+}
+
+/// An error returned from [`try_init`].
+///
+/// [`try_init`]: fn.try_init.html
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InitError {
+    /// The DSN, either provided explicitly or via `SENTRY_DSN`, is invalid.
+    InvalidDsn(ParseDsnError),
+    /// One of the configured proxies is not a valid URL.
+    InvalidProxy {
+        /// The option the proxy was configured with, `http_proxy` or `https_proxy`.
+        option: &'static str,
+        /// The invalid proxy value.
+        url: String,
+    },
+    /// The transport could not be created.
+    Transport(String),
+}
+
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitError::InvalidDsn(err) => write!(f, "invalid DSN: {}", err),
+            InitError::InvalidProxy { option, url } => {
+                write!(f, "invalid proxy URL for {}: {}", option, url)
+            }
+            InitError::Transport(msg) => write!(f, "failed to create transport: {}", msg),
+        }
+    }
+}
+
+impl Error for InitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InitError::InvalidDsn(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseDsnError> for InitError {
+    fn from(err: ParseDsnError) -> Self {
+        InitError::InvalidDsn(err)
+    }
+}
+
+/// Creates the Sentry client for a given client config and binds it,
+/// returning an error instead of panicking on invalid configuration.
+///
+/// This behaves like [`init`], but is meant for services that read their
+/// configuration from user input.  An error is returned when:
+///
+/// * the provided DSN, or the one read from `SENTRY_DSN`, is invalid,
+/// * a configured (or environment provided) proxy is not a valid URL,
+/// * the transport panics while being created.
+///
+/// No client is bound when an error is returned.
+///
+/// # Examples
+///
+/// ```
+/// let err = sentry::try_init("not a dsn").err().unwrap();
+/// assert!(matches!(err, sentry::InitError::InvalidDsn(_)));
+///
+/// let _sentry = sentry::try_init("https://key@sentry.io/1234").unwrap();
+/// ```
+///
+/// [`init`]: fn.init.html
+pub fn try_init<C>(opts: C) -> Result<ClientInitGuard, InitError>
+where
+    C: TryIntoClientOptions,
+{
+    let mut opts = opts.try_into_client_options()?;
+    if opts.dsn.is_none() {
+        if let Some(dsn) = env::var("SENTRY_DSN").ok().filter(|dsn| !dsn.is_empty()) {
+            opts.dsn = Some(dsn.parse::<Dsn>()?);
+        }
+    }
+    let mut opts = apply_defaults(opts);
+
+    validate_proxy("http_proxy", opts.http_proxy.as_deref())?;
+    validate_proxy("https_proxy", opts.https_proxy.as_deref())?;
+
+    // Create the transport eagerly so that a failure surfaces here rather
+    // than as a panic inside of `Client::from`.  The client picks up that
+    // transport, while transports for other DSNs still come from the factory.
+    if opts.dsn.is_some() {
+        if let Some(factory) = opts.transport.take() {
+            let transport =
+                panic::catch_unwind(AssertUnwindSafe(|| factory.create_transport(&opts)))
+                    .map_err(|payload| InitError::Transport(panic_message(&*payload)))?;
+            let transport = Arc::new(Mutex::new(Some(transport)));
+            opts.transport = Some(Arc::new(
+                move |opts: &ClientOptions| -> Arc<dyn Transport> {
+                    let created = transport.lock().ok().and_then(|mut t| t.take());
+                    created.unwrap_or_else(|| factory.create_transport(opts))
+                },
+            ));
+        }
+    }
+
+    Ok(bind_client(opts))
+}
+
+fn validate_proxy(option: &'static str, proxy: Option<&str>) -> Result<(), InitError> {
+    let proxy = match proxy {
+        Some(proxy) if !proxy.is_empty() => proxy,
+        _ => return Ok(()),
+    };
+    // Transports accept proxies without a scheme and default to `http`.
+    let valid = match url::Url::parse(proxy) {
+        Ok(url) => url.has_host(),
+        Err(_) => url::Url::parse(&format!("http://{}", proxy))
+            .map(|url| url.has_host())
+            .unwrap_or(false),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(InitError::InvalidProxy {
+            option,
+            url: proxy.to_string(),
+        })
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        (*msg).to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "transport panicked during creation".to_string()
+    }
+}
+
+fn bind_client(opts: ClientOptions) -> ClientInitGuard {
     let auto_session_tracking = opts.auto_session_tracking;
     let client = Arc::new(Client::from(opts));
 
@@ -102,6 +254,17 @@ where
     } else {
         sentry_debug!("initialized disabled sentry client due to disabled or invalid DSN");
     }
+    if let Some(dir) = client.options().cache_dir.as_deref() {
+        if client.is_enabled() && cache::set_running_marker(dir) {
+            sentry_debug!("detected a crash on the previous run");
+            crate::capture_event(cache::crashed_on_previous_run_event());
+        }
+        if client.is_enabled() {
+            for envelope in cache::take_persisted_envelopes(dir) {
+                client.send_envelope(envelope);
+            }
+        }
+    }
     if auto_session_tracking {
         crate::start_session()
     }
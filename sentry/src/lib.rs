@@ -47,7 +47,7 @@
 //!
 //! Default features:
 //!
-//! * `backtrace`: Enables backtrace support.
+//! * `backtrace`: Enables backtrace support, using the `backtrace` crate.
 //! * `contexts`: Enables capturing device, os, and rust contexts.
 //! * `panic`: Enables support for capturing panics.
 //! * `transport`: Enables the default transport, which is currently `reqwest` with `native-tls`.
@@ -55,25 +55,66 @@
 //! Additional features:
 //!
 //! * `anyhow`: Enables support for the `anyhow` crate.
+//! * `std-backtrace`: Enables backtrace support using `std::backtrace::Backtrace`, which can be
+//!   used instead of the `backtrace` feature to avoid depending on the `backtrace` crate.
 //! * `debug-images`: Attaches a list of loaded libraries to events (currently only supported on unix).
 //! * `log`: Enables support for the `log` crate.
+//! * `macros`: Enables the `#[sentry::traced]` and `#[sentry::capture_err]` attribute macros,
+//!   which wrap functions in spans and capture the errors they return.
+//! * `scrubbing`: Enables the `ScrubbingProcessor`, which removes passwords, tokens and other
+//!   sensitive data from events before they are sent, and the `TransactionNameNormalizer`,
+//!   which replaces ids in transaction names.
 //! * `env_logger`: Enables support for the `log` crate with additional `env_logger` support.
 //! * `slog`: Enables support for the `slog` crate.
+//! * `sqlx`: Enables recording `sqlx` queries as breadcrumbs.
+//! * `tracing`: Enables support for the `tracing` crate.
 //! * `test`: Enables testing support.
-//! * `debug-logs`: Uses the `log` crate for internal logging.
+//! * `debug-logs`: Logs internal diagnostics through the `log` crate under the
+//!   `sentry::internals` target.
+//! * `gzip`: Compresses envelopes sent by the HTTP transports with gzip.
 //! * `reqwest`: Enables the `reqwest` transport, which is currently the default.
 //! * `curl`: Enables the curl transport.
 //! * `surf`: Enables the surf transport.
+//! * `fetch`: Enables the transport using the `fetch` API of browsers, which is
+//!   the only transport that works on `wasm32-unknown-unknown`.
+//! * `console-error-panic-hook`: Forwards panics to the `console_error_panic_hook`
+//!   crate after reporting them, so they are also logged to the browser console.
+//! * `sync-transport`: Enables the `SyncHttpTransport`, which sends events on the
+//!   calling thread using a pluggable HTTP client, for targets without threads such as
+//!   `wasm32-wasi`.
 //! * `native-tls`: Uses the `native-tls` crate, which is currently the default.
 //!   This only has an effect on the `reqwest` transport.
 //! * `rustls`: Enables the `rustls` support of the `reqwest` transport.
 //!   Please note that `native-tls` is a default feature, and one needs to use
 //!   `default-features = false` to completely disable building `native-tls` dependencies.
+//!
+//! # WebAssembly
+//!
+//! The client can be compiled to `wasm32-unknown-unknown` for use in browsers.  As the
+//! default transport and contexts do not support that target, the default features need to
+//! be disabled:
+//!
+//! ```toml
+//! sentry = { version = "0.21.0", default-features = false, features = ["fetch", "console-error-panic-hook"] }
+//! ```
+//!
+//! Envelopes are sent in the background on the event loop of the browser, and timestamps
+//! are taken from `js_sys::Date`.
+//!
+//! The `wasm32-wasi` target has no threads and usually no HTTP support in the standard
+//! library either.  The `sync-transport` feature provides a `SyncHttpTransport`, which
+//! sends events synchronously using an HTTP client provided by the application, for
+//! example one implemented on top of the outbound HTTP API of the host:
+//!
+//! ```toml
+//! sentry = { version = "0.21.0", default-features = false, features = ["contexts", "panic", "sync-transport"] }
+//! ```
 
 #![doc(html_favicon_url = "https://sentry-brand.storage.googleapis.com/favicon.ico")]
 #![doc(html_logo_url = "https://sentry-brand.storage.googleapis.com/sentry-glyph-black.png")]
 #![warn(missing_docs)]
 
+mod cache;
 mod defaults;
 mod init;
 mod transport;
@@ -84,7 +125,11 @@ pub use sentry_core::*;
 
 // added public API
 pub use crate::defaults::apply_defaults;
-pub use crate::init::{init, ClientInitGuard};
+pub use crate::init::{init, try_init, ClientInitGuard, InitError};
+#[cfg(feature = "macros")]
+pub use sentry_macros::{capture_err, traced};
+#[cfg(feature = "panic")]
+pub use sentry_panic::capture_unwind;
 
 /// Available Sentry Integrations.
 ///
@@ -139,7 +184,7 @@ pub mod integrations {
     #[cfg(feature = "anyhow")]
     #[doc(inline)]
     pub use sentry_anyhow as anyhow;
-    #[cfg(feature = "backtrace")]
+    #[cfg(any(feature = "backtrace", feature = "std-backtrace"))]
     #[doc(inline)]
     pub use sentry_backtrace as backtrace;
     #[cfg(feature = "contexts")]
@@ -154,9 +199,18 @@ pub mod integrations {
     #[cfg(feature = "panic")]
     #[doc(inline)]
     pub use sentry_panic as panic;
+    #[cfg(feature = "scrubbing")]
+    #[doc(inline)]
+    pub use sentry_scrubbing as scrubbing;
     #[cfg(feature = "slog")]
     #[doc(inline)]
     pub use sentry_slog as slog;
+    #[cfg(feature = "sqlx")]
+    #[doc(inline)]
+    pub use sentry_sqlx as sqlx;
+    #[cfg(feature = "tracing")]
+    #[doc(inline)]
+    pub use sentry_tracing as tracing;
 }
 
 #[doc(inline)]
@@ -165,7 +219,8 @@ pub use sentry_core::types::protocol::latest as protocol;
 /// The provided transports.
 ///
 /// This module exposes all transports that are compiled into the sentry
-/// library.  The `reqwest`, `curl` and `surf` features turn on these transports.
+/// library.  The `reqwest`, `curl`, `surf`, `fetch` and `sync-transport` features turn
+/// on these transports.
 pub mod transports {
     pub use crate::transport::DefaultTransportFactory;
 
@@ -178,6 +233,19 @@ pub mod transports {
     #[cfg(feature = "surf")]
     pub use crate::transport::SurfHttpTransport;
 
-    #[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+    #[cfg(feature = "fetch")]
+    pub use crate::transport::FetchTransport;
+
+    #[cfg(feature = "sync-transport")]
+    pub use crate::transport::{
+        SyncHttpClient, SyncHttpRequest, SyncHttpResponse, SyncHttpTransport,
+    };
+
+    #[cfg(any(
+        feature = "reqwest",
+        feature = "curl",
+        feature = "surf",
+        feature = "fetch"
+    ))]
     pub use crate::transport::HttpTransport;
 }
@@ -1,33 +1,73 @@
 #![allow(unused_imports)]
 
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
 use std::sync::{Arc, Condvar, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, SystemTime};
 
-#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+#[cfg(any(
+    feature = "reqwest",
+    feature = "curl",
+    feature = "surf",
+    feature = "sync-transport"
+))]
 use httpdate::parse_http_date;
 
 #[cfg(feature = "reqwest")]
-use reqwest_::{blocking::Client as ReqwestClient, header::RETRY_AFTER, Proxy};
+use reqwest_::{
+    blocking::{Client as ReqwestClient, RequestBuilder},
+    header::RETRY_AFTER,
+    Proxy,
+};
 
 #[cfg(feature = "curl")]
 use crate::types::Scheme;
 #[cfg(feature = "curl")]
 use curl_ as curl;
 #[cfg(feature = "curl")]
-use std::io::{Cursor, Read};
+use std::io::Read;
+
+#[cfg(feature = "gzip")]
+use flate2::{write::GzEncoder, Compression};
 
 #[cfg(feature = "surf")]
 use futures::executor;
 #[cfg(feature = "surf")]
 use surf_::Client as SurfClient;
 
+#[cfg(feature = "fetch")]
+use wasm_bindgen::{JsCast, JsValue};
+#[cfg(feature = "fetch")]
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+#[cfg(feature = "fetch")]
+use web_sys::{Request as FetchRequest, RequestInit, Response as FetchResponse};
+
 use sentry_core::sentry_debug;
 
 use crate::protocol::Event;
-use crate::{ClientOptions, Envelope, Transport, TransportFactory};
+use crate::{
+    ClientOptions, DeliveryReceipt, DeliveryStatus, Envelope, QueueStats, Transport,
+    TransportFactory,
+};
+
+/// An envelope on the queue of a transport, with an optional receipt.
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+type QueueItem = (Envelope, Option<DeliveryReceipt>);
+
+/// Reports the outcome of sending an envelope, if it has a receipt.
+#[cfg(any(
+    feature = "reqwest",
+    feature = "curl",
+    feature = "surf",
+    feature = "fetch"
+))]
+fn report(receipt: Option<DeliveryReceipt>, status: DeliveryStatus) {
+    if let Some(receipt) = receipt {
+        receipt.report(status);
+    }
+}
 
 /// Creates the default HTTP transport.
 ///
@@ -39,11 +79,21 @@ pub struct DefaultTransportFactory;
 
 impl TransportFactory for DefaultTransportFactory {
     fn create_transport(&self, options: &ClientOptions) -> Arc<dyn Transport> {
-        #[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+        #[cfg(any(
+            feature = "reqwest",
+            feature = "curl",
+            feature = "surf",
+            feature = "fetch"
+        ))]
         {
             Arc::new(HttpTransport::new(options))
         }
-        #[cfg(not(any(feature = "reqwest", feature = "curl", feature = "surf")))]
+        #[cfg(not(any(
+            feature = "reqwest",
+            feature = "curl",
+            feature = "surf",
+            feature = "fetch"
+        )))]
         {
             let _ = options;
             panic!("sentry crate was compiled without transport")
@@ -51,7 +101,12 @@ impl TransportFactory for DefaultTransportFactory {
     }
 }
 
-#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+#[cfg(any(
+    feature = "reqwest",
+    feature = "curl",
+    feature = "surf",
+    feature = "sync-transport"
+))]
 fn parse_retry_after(s: &str) -> Option<SystemTime> {
     if let Ok(value) = s.parse::<f64>() {
         Some(SystemTime::now() + Duration::from_secs(value.ceil() as u64))
@@ -62,7 +117,249 @@ fn parse_retry_after(s: &str) -> Option<SystemTime> {
     }
 }
 
-#[allow(unused)]
+/// Persists the given item and everything left on the queue into the cache
+/// directory, if one is configured.
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+fn persist_queue(
+    cache_dir: Option<&Path>,
+    item: QueueItem,
+    receiver: &Receiver<Option<QueueItem>>,
+) {
+    if let Some(dir) = cache_dir {
+        let items = std::iter::once(item).chain(receiver.try_iter().flatten());
+        for (envelope, receipt) in items {
+            crate::cache::persist_envelope(dir, &envelope);
+            report(receipt, DeliveryStatus::Persisted);
+        }
+    }
+}
+
+/// Resolves the deferred stacktraces of the envelopes event, if
+/// `defer_symbolication` is enabled.
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+fn resolve_deferred(envelope: &mut Envelope, options: Option<&ClientOptions>) {
+    #[cfg(feature = "backtrace")]
+    {
+        if let (Some(options), Some(event)) = (options, envelope.event_mut()) {
+            sentry_backtrace::resolve_event_stacktraces(event, options);
+        }
+    }
+    #[cfg(not(feature = "backtrace"))]
+    {
+        let _ = (envelope, options);
+    }
+}
+
+/// Spills envelopes into the cache directory when the queue is full, and moves
+/// them back into the queue once envelopes can be sent again.
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+struct SpillQueue {
+    cache_dir: Option<PathBuf>,
+    spilled: AtomicBool,
+    sender: Mutex<SyncSender<Option<QueueItem>>>,
+    queue_size: Arc<Mutex<usize>>,
+}
+
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+impl SpillQueue {
+    /// Persists an envelope that did not fit into the queue, and reports its
+    /// receipt as persisted.
+    ///
+    /// Returns `false` if there is no cache directory, and the envelope is lost.
+    fn spill(&self, (envelope, receipt): QueueItem) -> bool {
+        match self.cache_dir {
+            Some(ref dir) => {
+                sentry_debug!("Queue is full, spilling envelope to disk");
+                crate::cache::persist_envelope(dir, &envelope);
+                self.spilled.store(true, Ordering::SeqCst);
+                report(receipt, DeliveryStatus::Persisted);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves spilled envelopes back into the queue, in order, as long as
+    /// there is room for them.
+    fn reload(&self) {
+        let dir = match self.cache_dir {
+            Some(ref dir) if self.spilled.swap(false, Ordering::SeqCst) => dir,
+            _ => return,
+        };
+        let sender = self.sender.lock().unwrap();
+        let mut envelopes = crate::cache::take_persisted_envelopes(dir).into_iter();
+        for envelope in &mut envelopes {
+            *self.queue_size.lock().unwrap() += 1;
+            if let Err(err) = sender.try_send(Some((envelope, None))) {
+                *self.queue_size.lock().unwrap() -= 1;
+                if let TrySendError::Full(Some(item)) = err {
+                    self.spill(item);
+                }
+                break;
+            }
+        }
+        for envelope in envelopes {
+            self.spill((envelope, None));
+        }
+    }
+}
+
+/// The state of a transport worker, which the transport uses for health checks
+/// and queue statistics.
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+#[derive(Default)]
+struct WorkerState {
+    stopped: AtomicBool,
+    disabled: Mutex<Option<SystemTime>>,
+    in_flight: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+impl WorkerState {
+    /// Disables sending until the given time because of rate limits.
+    fn rate_limit(&self, until: SystemTime) {
+        *self.disabled.lock().unwrap() = Some(until);
+    }
+
+    /// Returns how long sending is still disabled because of rate limits.
+    fn rate_limited(&self) -> Option<Duration> {
+        let mut disabled = self.disabled.lock().unwrap();
+        let time_left = disabled.and_then(|ts| ts.duration_since(SystemTime::now()).ok());
+        if time_left.is_none() {
+            *disabled = None;
+        }
+        time_left
+    }
+
+    /// Counts an envelope that is being sent.
+    fn start_sending(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Counts an envelope that was sent, or failed to send.
+    fn finish_sending(&self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Counts an envelope that was dropped without sending it.
+    fn drop_envelope(&self) {
+        self.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Whether the worker is still running and sending is not rate limited.
+    fn is_healthy(&self) -> bool {
+        !self.stopped.load(Ordering::SeqCst) && self.rate_limited().is_none()
+    }
+}
+
+/// Removes an envelope taken from the queue from the queue size, and wakes up
+/// flushes once the queue is empty.
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+fn dequeued(queue_size: &Mutex<usize>, signal: &Condvar) {
+    // the queue size is reset on immediate shutdown while requests
+    // may still be in flight on other senders.
+    let mut size = queue_size.lock().unwrap();
+    *size = size.saturating_sub(1);
+    if *size == 0 {
+        signal.notify_all();
+    }
+}
+
+/// Marks the worker as stopped when dropped, which also happens when the worker
+/// thread panics.
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+struct WorkerGuard(Arc<WorkerState>);
+
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        self.0.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+/// A reusable buffer the transport workers serialize envelopes into.
+///
+/// Reusing the buffer avoids growing a fresh allocation for every envelope.
+/// With the `gzip` feature the body is compressed, and has to be sent with
+/// the [`CONTENT_ENCODING`] header.
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+struct EnvelopeBuffer {
+    buf: Vec<u8>,
+}
+
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+impl EnvelopeBuffer {
+    /// Buffers that grew beyond this capacity are not kept around.
+    const MAX_RETAINED: usize = 1024 * 1024;
+
+    fn new() -> Self {
+        EnvelopeBuffer { buf: Vec::new() }
+    }
+
+    /// Serializes the envelope into the buffer, and returns the request body.
+    fn serialize(&mut self, envelope: &Envelope) -> &[u8] {
+        if self.buf.capacity() > Self::MAX_RETAINED {
+            self.buf = Vec::new();
+        } else {
+            self.buf.clear();
+        }
+        #[cfg(feature = "gzip")]
+        {
+            let mut encoder = GzEncoder::new(&mut self.buf, Compression::fast());
+            envelope.to_writer(&mut encoder).unwrap();
+            encoder.finish().unwrap();
+        }
+        #[cfg(not(feature = "gzip"))]
+        {
+            envelope.to_writer(&mut self.buf).unwrap();
+        }
+        &self.buf
+    }
+
+    /// Serializes the envelope, and moves the request body out of the buffer.
+    ///
+    /// This is for HTTP clients that take ownership of the body, and cannot
+    /// hand it back after sending.  The buffer is replaced by one with the
+    /// capacity of the body, so that the next envelope does not have to grow
+    /// its allocation.
+    #[cfg(any(feature = "reqwest", feature = "surf"))]
+    fn take(&mut self, envelope: &Envelope) -> Vec<u8> {
+        let len = self.serialize(envelope).len();
+        let capacity = len.min(Self::MAX_RETAINED);
+        std::mem::replace(&mut self.buf, Vec::with_capacity(capacity))
+    }
+}
+
+/// The `Content-Encoding` of request bodies produced by [`EnvelopeBuffer`].
+#[cfg(all(
+    feature = "gzip",
+    any(feature = "reqwest", feature = "curl", feature = "surf")
+))]
+const CONTENT_ENCODING: Option<&str> = Some("gzip");
+#[cfg(all(
+    not(feature = "gzip"),
+    any(feature = "reqwest", feature = "curl", feature = "surf")
+))]
+const CONTENT_ENCODING: Option<&str> = None;
+
+/// Maps the status code of a response to the envelope endpoint to a delivery status.
+#[cfg(any(
+    feature = "reqwest",
+    feature = "curl",
+    feature = "surf",
+    feature = "fetch",
+    feature = "sync-transport"
+))]
+fn status_from_response(status: u16) -> DeliveryStatus {
+    match status {
+        200..=299 => DeliveryStatus::Sent,
+        429 => DeliveryStatus::RateLimited,
+        status => DeliveryStatus::Failed(format!("HTTP status {}", status)),
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
 macro_rules! implement_http_transport {
     (
         $(#[$attr:meta])*
@@ -72,10 +369,12 @@ macro_rules! implement_http_transport {
     ) => {
         $(#[$attr])*
         pub struct $typename {
-            sender: Mutex<SyncSender<Option<Envelope>>>,
+            sender: Mutex<SyncSender<Option<QueueItem>>>,
             shutdown_signal: Arc<Condvar>,
             shutdown_immediately: Arc<AtomicBool>,
             queue_size: Arc<Mutex<usize>>,
+            spill: Arc<SpillQueue>,
+            state: Arc<WorkerState>,
             handle: Option<JoinHandle<()>>,
         }
 
@@ -101,6 +400,13 @@ macro_rules! implement_http_transport {
                 let shutdown_immediately = Arc::new(AtomicBool::new(false));
                 #[allow(clippy::mutex_atomic)]
                 let queue_size = Arc::new(Mutex::new(0));
+                let spill = Arc::new(SpillQueue {
+                    cache_dir: options.cache_dir.clone(),
+                    spilled: AtomicBool::new(false),
+                    sender: Mutex::new(sender.clone()),
+                    queue_size: queue_size.clone(),
+                });
+                let state = Arc::new(WorkerState::default());
                 let http_client = http_client(options, $hc_client);
                 let handle = Some(spawn(
                     options,
@@ -108,6 +414,8 @@ macro_rules! implement_http_transport {
                     shutdown_signal.clone(),
                     shutdown_immediately.clone(),
                     queue_size.clone(),
+                    spill.clone(),
+                    state.clone(),
                     http_client,
                 ));
                 $typename {
@@ -115,19 +423,64 @@ macro_rules! implement_http_transport {
                     shutdown_signal,
                     shutdown_immediately,
                     queue_size,
+                    spill,
+                    state,
                     handle,
                 }
             }
-        }
 
-        impl Transport for $typename {
-            fn send_envelope(&self, envelope: Envelope) {
+            fn enqueue(&self, item: QueueItem) {
                 // we count up before we put the item on the queue and in case the
                 // queue is filled with too many items or we shut down, we decrement
-                // the count again as there is nobody that can pick it up.
+                // the count again as there is nobody that can pick it up.  The
+                // receipt of a rejected item is dropped, which reports it as
+                // discarded.  With a cache directory, envelopes that do not fit
+                // into the queue are spilled to disk instead, and reported as
+                // persisted.
                 *self.queue_size.lock().unwrap() += 1;
-                if self.sender.lock().unwrap().try_send(Some(envelope)).is_err() {
+                let result = self.sender.lock().unwrap().try_send(Some(item));
+                if let Err(err) = result {
                     *self.queue_size.lock().unwrap() -= 1;
+                    let spilled = match err {
+                        TrySendError::Full(Some(item)) => self.spill.spill(item),
+                        _ => false,
+                    };
+                    if !spilled {
+                        self.state.drop_envelope();
+                    }
+                }
+            }
+        }
+
+        impl Transport for $typename {
+            fn send_envelope(&self, envelope: Envelope) {
+                self.enqueue((envelope, None))
+            }
+
+            fn send_envelope_with_receipt(&self, envelope: Envelope, receipt: DeliveryReceipt) {
+                self.enqueue((envelope, Some(receipt)))
+            }
+
+            fn flush(&self, timeout: Duration) -> bool {
+                sentry_debug!("flushing http transport");
+                let guard = self.queue_size.lock().unwrap();
+                match self.shutdown_signal.wait_timeout_while(guard, timeout, |size| *size > 0) {
+                    Ok((_, result)) => !result.timed_out(),
+                    Err(_) => false,
+                }
+            }
+
+            fn is_healthy(&self) -> bool {
+                self.state.is_healthy()
+            }
+
+            fn queue_stats(&self) -> QueueStats {
+                let queued = *self.queue_size.lock().unwrap();
+                let in_flight = self.state.in_flight.load(Ordering::SeqCst);
+                QueueStats {
+                    pending: queued.saturating_sub(in_flight),
+                    in_flight,
+                    dropped: self.state.dropped.load(Ordering::SeqCst),
                 }
             }
 
@@ -176,23 +529,101 @@ implement_http_transport! {
 
     fn spawn(
         options: &ClientOptions,
-        receiver: Receiver<Option<Envelope>>,
+        receiver: Receiver<Option<QueueItem>>,
         signal: Arc<Condvar>,
         shutdown_immediately: Arc<AtomicBool>,
         queue_size: Arc<Mutex<usize>>,
+        spill: Arc<SpillQueue>,
+        state: Arc<WorkerState>,
         http_client: Option<ReqwestClient>,
     ) {
         let dsn = options.dsn.clone().unwrap();
         let user_agent = options.user_agent.to_string();
+        let cache_dir = options.cache_dir.clone();
+        let deferred_options = if options.defer_symbolication {
+            Some(options.clone())
+        } else {
+            None
+        };
 
-        let mut disabled = None::<SystemTime>;
+        let senders = options.transport_senders.max(1);
         let http_proxy = options.http_proxy.as_ref().map(ToString::to_string);
         let https_proxy = options.https_proxy.as_ref().map(ToString::to_string);
 
+        let send_request = {
+            let state = state.clone();
+            let cache_dir = cache_dir.clone();
+            let signal = signal.clone();
+            let queue_size = queue_size.clone();
+            let shutdown_immediately = shutdown_immediately.clone();
+            Arc::new(move |request: RequestBuilder, envelope: Envelope, receipt: Option<DeliveryReceipt>| {
+                // requests may wait for a free sender of the pool, during which the
+                // transport can be shut down, or rate limited by another response.
+                if shutdown_immediately.load(Ordering::SeqCst) {
+                    if let Some(dir) = &cache_dir {
+                        crate::cache::persist_envelope(dir, &envelope);
+                        report(receipt, DeliveryStatus::Persisted);
+                    }
+                    state.finish_sending();
+                    dequeued(&queue_size, &signal);
+                    return;
+                }
+                if let Some(time_left) = state.rate_limited() {
+                    sentry_debug!(
+                        "Skipping event send because we're disabled due to rate limits for {}s",
+                        time_left.as_secs()
+                    );
+                    report(receipt, DeliveryStatus::RateLimited);
+                    state.drop_envelope();
+                    state.finish_sending();
+                    dequeued(&queue_size, &signal);
+                    return;
+                }
+
+                sentry_debug!("Sending envelope");
+                let mut sent = false;
+                match request.send() {
+                    Ok(resp) => {
+                        let status = resp.status();
+                        if status == 429 {
+                            if let Some(retry_after) = resp
+                                .headers()
+                                .get(RETRY_AFTER)
+                                .and_then(|x| x.to_str().ok())
+                                .and_then(parse_retry_after)
+                            {
+                                state.rate_limit(retry_after);
+                            }
+                        }
+                        match resp.text() {
+                            Err(err) => { sentry_debug!("Failed to read sentry response: {}", err); },
+                            Ok(text) => { sentry_debug!("Get response: `{}`", text); },
+                        }
+                        sent = status.is_success();
+                        report(receipt, status_from_response(status.as_u16()));
+                    }
+                    Err(err) => {
+                        sentry_debug!("Failed to send envelope: {}", err);
+                        if let Some(dir) = &cache_dir {
+                            crate::cache::persist_envelope(dir, &envelope);
+                        }
+                        report(receipt, DeliveryStatus::Failed(err.to_string()));
+                    }
+                }
+
+                state.finish_sending();
+                dequeued(&queue_size, &signal);
+                if sent {
+                    spill.reload();
+                }
+            })
+        };
+
         thread::Builder::new()
             .name("sentry-transport".to_string())
             .spawn(move || {
                 sentry_debug!("spawning reqwest transport");
+                let _guard = WorkerGuard(state.clone());
                 let http_client = http_client.unwrap_or_else(|| {
                     let mut builder = ReqwestClient::builder();
                     if let Some(url) = http_proxy {
@@ -205,10 +636,35 @@ implement_http_transport! {
                 });
 
                 let url = dsn.envelope_api_url().to_string();
+                let mut buffer = EnvelopeBuffer::new();
 
-                while let Some(envelope) = receiver.recv().unwrap_or(None) {
+                // With more than one sender, requests are sent from a pool of
+                // threads, while this thread keeps preparing the next ones.
+                let pool = if senders > 1 {
+                    let (pool_sender, pool_receiver) = sync_channel(senders);
+                    let pool_receiver = Arc::new(Mutex::new(pool_receiver));
+                    let threads: Vec<_> = (0..senders).map(|i| {
+                        let pool_receiver = pool_receiver.clone();
+                        let send_request = send_request.clone();
+                        thread::Builder::new()
+                            .name(format!("sentry-transport-{}", i))
+                            .spawn(move || loop {
+                                let next = pool_receiver.lock().unwrap().recv();
+                                match next {
+                                    Ok((request, envelope, receipt)) => send_request(request, envelope, receipt),
+                                    Err(_) => break,
+                                }
+                            }).unwrap()
+                    }).collect();
+                    Some((pool_sender, threads))
+                } else {
+                    None
+                };
+
+                while let Some((mut envelope, receipt)) = receiver.recv().unwrap_or(None) {
                     // on drop we want to not continue processing the queue.
                     if shutdown_immediately.load(Ordering::SeqCst) {
+                        persist_queue(cache_dir.as_deref(), (envelope, receipt), &receiver);
                         let mut size = queue_size.lock().unwrap();
                         *size = 0;
                         signal.notify_all();
@@ -216,53 +672,41 @@ implement_http_transport! {
                     }
 
                     // while we are disabled due to rate limits, skip
-                    if let Some(ts) = disabled {
-                        if let Ok(time_left) = ts.duration_since(SystemTime::now()) {
-                            sentry_debug!(
-                                "Skipping event send because we're disabled due to rate limits for {}s",
-                                time_left.as_secs()
-                            );
-                            continue;
-                        } else {
-                            disabled = None;
-                        }
+                    if let Some(time_left) = state.rate_limited() {
+                        sentry_debug!(
+                            "Skipping event send because we're disabled due to rate limits for {}s",
+                            time_left.as_secs()
+                        );
+                        report(receipt, DeliveryStatus::RateLimited);
+                        state.drop_envelope();
+                        dequeued(&queue_size, &signal);
+                        continue;
                     }
 
-                    let mut body = Vec::new();
-                    envelope.to_writer(&mut body).unwrap();
-
-                    sentry_debug!("Sending envelope");
-                    match http_client
+                    state.start_sending();
+                    resolve_deferred(&mut envelope, deferred_options.as_ref());
+                    let mut request = http_client
                         .post(url.as_str())
-                        .body(body)
-                        .header("X-Sentry-Auth", dsn.to_auth(Some(&user_agent)).to_string())
-                        .send()
-                    {
-                        Ok(resp) => {
-                            if resp.status() == 429 {
-                                if let Some(retry_after) = resp
-                                    .headers()
-                                    .get(RETRY_AFTER)
-                                    .and_then(|x| x.to_str().ok())
-                                    .and_then(parse_retry_after)
-                                {
-                                    disabled = Some(retry_after);
-                                }
-                            }
-                            match resp.text() {
-                                Err(err) => { sentry_debug!("Failed to read sentry response: {}", err); },
-                                Ok(text) => { sentry_debug!("Get response: `{}`", text); },
-                            }
-                        }
-                        Err(err) => {
-                            sentry_debug!("Failed to send envelope: {}", err);
+                        .body(buffer.take(&envelope))
+                        .header("X-Sentry-Auth", dsn.to_auth(Some(&user_agent)).to_string());
+                    if let Some(encoding) = CONTENT_ENCODING {
+                        request = request.header("Content-Encoding", encoding);
+                    }
+
+                    match pool {
+                        Some((ref pool_sender, _)) => {
+                            pool_sender.send((request, envelope, receipt)).ok();
                         }
+                        None => send_request(request, envelope, receipt),
                     }
+                }
 
-                    let mut size = queue_size.lock().unwrap();
-                    *size -= 1;
-                    if *size == 0 {
-                        signal.notify_all();
+                // wait for the requests that are still in flight, the pool persists
+                // the queued ones instead of sending them on immediate shutdown
+                if let Some((pool_sender, threads)) = pool {
+                    drop(pool_sender);
+                    for thread in threads {
+                        thread.join().ok();
                     }
                 }
             }).unwrap()
@@ -285,27 +729,37 @@ implement_http_transport! {
 
     fn spawn(
         options: &ClientOptions,
-        receiver: Receiver<Option<Envelope>>,
+        receiver: Receiver<Option<QueueItem>>,
         signal: Arc<Condvar>,
         shutdown_immediately: Arc<AtomicBool>,
         queue_size: Arc<Mutex<usize>>,
+        spill: Arc<SpillQueue>,
+        state: Arc<WorkerState>,
         http_client: curl::easy::Easy,
     ) {
         let dsn = options.dsn.clone().unwrap();
         let user_agent = options.user_agent.to_string();
+        let cache_dir = options.cache_dir.clone();
+        let deferred_options = if options.defer_symbolication {
+            Some(options.clone())
+        } else {
+            None
+        };
         let http_proxy = options.http_proxy.as_ref().map(ToString::to_string);
         let https_proxy = options.https_proxy.as_ref().map(ToString::to_string);
 
-        let mut disabled = None::<SystemTime>;
         let mut handle = http_client;
 
         thread::spawn(move || {
             sentry_debug!("spawning curl transport");
+            let _guard = WorkerGuard(state.clone());
             let url = dsn.envelope_api_url().to_string();
+            let mut buffer = EnvelopeBuffer::new();
 
-            while let Some(envelope) = receiver.recv().unwrap_or(None) {
+            while let Some((mut envelope, receipt)) = receiver.recv().unwrap_or(None) {
                 // on drop we want to not continue processing the queue.
                 if shutdown_immediately.load(Ordering::SeqCst) {
+                    persist_queue(cache_dir.as_deref(), (envelope, receipt), &receiver);
                     let mut size = queue_size.lock().unwrap();
                     *size = 0;
                     signal.notify_all();
@@ -313,18 +767,19 @@ implement_http_transport! {
                 }
 
                 // while we are disabled due to rate limits, skip
-                if let Some(ts) = disabled {
-                    if let Ok(time_left) = ts.duration_since(SystemTime::now()) {
-                        sentry_debug!(
-                            "Skipping event send because we're disabled due to rate limits for {}s",
-                            time_left.as_secs()
-                        );
-                        continue;
-                    } else {
-                        disabled = None;
-                    }
+                if let Some(time_left) = state.rate_limited() {
+                    sentry_debug!(
+                        "Skipping event send because we're disabled due to rate limits for {}s",
+                        time_left.as_secs()
+                    );
+                    report(receipt, DeliveryStatus::RateLimited);
+                    state.drop_envelope();
+                    dequeued(&queue_size, &signal);
+                    continue;
                 }
 
+                state.start_sending();
+
                 handle.reset();
                 handle.url(&url).unwrap();
                 handle.custom_request("POST").unwrap();
@@ -339,19 +794,20 @@ implement_http_transport! {
                     _ => {}
                 }
 
-                let mut body = Vec::new();
-                envelope.to_writer(&mut body).unwrap();
-                let mut body = Cursor::new(body);
+                resolve_deferred(&mut envelope, deferred_options.as_ref());
+                let mut body = buffer.serialize(&envelope);
 
                 let mut retry_after = None;
                 let mut headers = curl::easy::List::new();
                 headers.append(&format!("X-Sentry-Auth: {}", dsn.to_auth(Some(&user_agent)))).unwrap();
                 headers.append("Expect:").unwrap();
                 headers.append("Content-Type: application/json").unwrap();
+                if let Some(encoding) = CONTENT_ENCODING {
+                    headers.append(&format!("Content-Encoding: {}", encoding)).unwrap();
+                }
                 handle.http_headers(headers).unwrap();
                 handle.upload(true).unwrap();
-                handle.in_filesize(body.get_ref().len() as u64).unwrap();
-                handle.read_function(move |buf| Ok(body.read(buf).unwrap_or(0))).unwrap();
+                handle.in_filesize(body.len() as u64).unwrap();
                 handle.verbose(true).unwrap();
                 handle.debug_function(move |info, data| {
                     let prefix = match info {
@@ -365,6 +821,7 @@ implement_http_transport! {
 
                 {
                     let mut handle = handle.transfer();
+                    handle.read_function(|buf| Ok(body.read(buf).unwrap_or(0))).unwrap();
                     let retry_after_setter = &mut retry_after;
                     handle.header_function(move |data| {
                         if let Ok(data) = std::str::from_utf8(data) {
@@ -377,28 +834,42 @@ implement_http_transport! {
                         }
                         true
                     }).unwrap();
-                    handle.perform().ok();
+                    if let Err(err) = handle.perform() {
+                        sentry_debug!("Failed to send event: {}", err);
+                        if let Some(dir) = &cache_dir {
+                            crate::cache::persist_envelope(dir, &envelope);
+                        }
+                    }
                 }
 
-                match handle.response_code() {
+                let status = match handle.response_code() {
                     Ok(429) => {
                         if let Some(retry_after) = retry_after
                             .as_deref()
                             .and_then(parse_retry_after)
                         {
-                            disabled = Some(retry_after);
+                            state.rate_limit(retry_after);
                         }
+                        DeliveryStatus::RateLimited
                     }
-                    Ok(200) | Ok(201) => {}
-                    _ => {
+                    Ok(200) | Ok(201) => DeliveryStatus::Sent,
+                    Ok(0) => DeliveryStatus::Failed("no response".into()),
+                    Ok(code) => {
                         sentry_debug!("Failed to send event");
+                        status_from_response(code as u16)
                     }
-                }
+                    Err(err) => {
+                        sentry_debug!("Failed to send event");
+                        DeliveryStatus::Failed(err.to_string())
+                    }
+                };
+                let sent = status == DeliveryStatus::Sent;
+                report(receipt, status);
 
-                let mut size = queue_size.lock().unwrap();
-                *size -= 1;
-                if *size == 0 {
-                    signal.notify_all();
+                state.finish_sending();
+                dequeued(&queue_size, &signal);
+                if sent {
+                    spill.reload();
                 }
             }
         })
@@ -418,26 +889,35 @@ implement_http_transport! {
 
     fn spawn(
         options: &ClientOptions,
-        receiver: Receiver<Option<Envelope>>,
+        receiver: Receiver<Option<QueueItem>>,
         signal: Arc<Condvar>,
         shutdown_immediately: Arc<AtomicBool>,
         queue_size: Arc<Mutex<usize>>,
+        spill: Arc<SpillQueue>,
+        state: Arc<WorkerState>,
         http_client: SurfClient,
     ) {
         let dsn = options.dsn.clone().unwrap();
         let user_agent = options.user_agent.to_string();
-        let mut disabled = None::<SystemTime>;
-
+        let cache_dir = options.cache_dir.clone();
+        let deferred_options = if options.defer_symbolication {
+            Some(options.clone())
+        } else {
+            None
+        };
         thread::Builder::new()
             .name("sentry-transport".to_string())
             .spawn(move || {
                 sentry_debug!("spawning surf transport");
+                let _guard = WorkerGuard(state.clone());
                 let http_client = http_client;
                 let url = dsn.envelope_api_url().to_string();
+                let mut buffer = EnvelopeBuffer::new();
 
-                while let Some(envelope) = receiver.recv().unwrap_or(None) {
+                while let Some((mut envelope, receipt)) = receiver.recv().unwrap_or(None) {
                     // on drop we want to not continue processing the queue.
                     if shutdown_immediately.load(Ordering::SeqCst) {
+                        persist_queue(cache_dir.as_deref(), (envelope, receipt), &receiver);
                         let mut size = queue_size.lock().unwrap();
                         *size = 0;
                         signal.notify_all();
@@ -445,30 +925,33 @@ implement_http_transport! {
                     }
 
                     // while we are disabled due to rate limits, skip
-                    if let Some(ts) = disabled {
-                        if let Ok(time_left) = ts.duration_since(SystemTime::now()) {
-                            sentry_debug!(
-                                "Skipping event send because we're disabled due to rate limits for {}s",
-                                time_left.as_secs()
-                            );
-                            continue;
-                        } else {
-                            disabled = None;
-                        }
+                    if let Some(time_left) = state.rate_limited() {
+                        sentry_debug!(
+                            "Skipping event send because we're disabled due to rate limits for {}s",
+                            time_left.as_secs()
+                        );
+                        report(receipt, DeliveryStatus::RateLimited);
+                        state.drop_envelope();
+                        dequeued(&queue_size, &signal);
+                        continue;
                     }
 
-                    let mut body = Vec::new();
-                    envelope.to_writer(&mut body).unwrap();
+                    state.start_sending();
 
-                    let fut = http_client
+                    resolve_deferred(&mut envelope, deferred_options.as_ref());
+                    let mut request = http_client
                         .post(url.as_str())
                         .header(
                             "X-Sentry-Auth",
                             dsn.to_auth(Some(&user_agent)).to_string()
                         )
-                        .body(body);
+                        .body(buffer.take(&envelope));
+                    if let Some(encoding) = CONTENT_ENCODING {
+                        request = request.header("Content-Encoding", encoding);
+                    }
 
-                    match executor::block_on(fut) {
+                    let mut sent = false;
+                    match executor::block_on(request) {
                         Ok(resp) => {
                             if resp.status() == 429 {
                                 if let Some(retry_after) = resp
@@ -477,19 +960,25 @@ implement_http_transport! {
                                     .map(|x| x.as_str())
                                     .and_then(parse_retry_after)
                                 {
-                                    disabled = Some(retry_after);
+                                    state.rate_limit(retry_after);
                                 }
                             }
+                            sent = resp.status().is_success();
+                            report(receipt, status_from_response(u16::from(resp.status())));
                         }
                         Err(err) => {
                             sentry_debug!("Failed to send event: {}", err);
+                            if let Some(dir) = &cache_dir {
+                                crate::cache::persist_envelope(dir, &envelope);
+                            }
+                            report(receipt, DeliveryStatus::Failed(err.to_string()));
                         }
                     }
 
-                    let mut size = queue_size.lock().unwrap();
-                    *size -= 1;
-                    if *size == 0 {
-                        signal.notify_all();
+                    state.finish_sending();
+                    dequeued(&queue_size, &signal);
+                    if sent {
+                        spill.reload();
                     }
                 }
             }).unwrap()
@@ -512,6 +1001,321 @@ type DefaultTransport = CurlHttpTransport;
 #[cfg(all(feature = "surf", not(feature = "reqwest"), not(feature = "curl")))]
 type DefaultTransport = SurfHttpTransport;
 
+#[cfg(all(
+    feature = "fetch",
+    not(feature = "reqwest"),
+    not(feature = "curl"),
+    not(feature = "surf")
+))]
+type DefaultTransport = FetchTransport;
+
 /// The default http transport.
-#[cfg(any(feature = "reqwest", feature = "curl", feature = "surf"))]
+#[cfg(any(
+    feature = "reqwest",
+    feature = "curl",
+    feature = "surf",
+    feature = "fetch"
+))]
 pub type HttpTransport = DefaultTransport;
+
+/// A transport can send events via HTTP to sentry via the `fetch` API.
+///
+/// This is enabled by the `fetch` flag, and is meant for the
+/// `wasm32-unknown-unknown` target, where no threads can be spawned.  Envelopes
+/// are sent in the background on the event loop of the browser or web worker,
+/// which means that there is no queue that could be flushed or drained on
+/// shutdown.
+#[cfg(feature = "fetch")]
+pub struct FetchTransport {
+    url: String,
+    auth: String,
+    /// The time until which sending is disabled due to rate limits, in
+    /// milliseconds since the epoch as returned by `Date.now()`.
+    disabled_until: Arc<Mutex<f64>>,
+}
+
+#[cfg(feature = "fetch")]
+impl FetchTransport {
+    /// Creates a new transport.
+    pub fn new(options: &ClientOptions) -> Self {
+        let dsn = options.dsn.clone().unwrap();
+        let user_agent = options.user_agent.to_string();
+        FetchTransport {
+            url: dsn.envelope_api_url().to_string(),
+            auth: dsn.to_auth(Some(&user_agent)).to_string(),
+            disabled_until: Arc::new(Mutex::new(0.0)),
+        }
+    }
+
+    fn create_request(&self, body: &[u8]) -> Result<FetchRequest, JsValue> {
+        let body = js_sys::Uint8Array::from(body);
+        let mut init = RequestInit::new();
+        init.method("POST").body(Some(&body));
+
+        let request = FetchRequest::new_with_str_and_init(&self.url, &init)?;
+        request.headers().set("X-Sentry-Auth", &self.auth)?;
+        request.headers().set("Content-Type", "application/json")?;
+        Ok(request)
+    }
+}
+
+#[cfg(feature = "fetch")]
+impl Transport for FetchTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        self.send(envelope, None)
+    }
+
+    fn send_envelope_with_receipt(&self, envelope: Envelope, receipt: DeliveryReceipt) {
+        self.send(envelope, Some(receipt))
+    }
+
+    fn is_healthy(&self) -> bool {
+        js_sys::Date::now() >= *self.disabled_until.lock().unwrap()
+    }
+}
+
+#[cfg(feature = "fetch")]
+impl FetchTransport {
+    fn send(&self, envelope: Envelope, receipt: Option<DeliveryReceipt>) {
+        // while we are disabled due to rate limits, skip
+        let now = js_sys::Date::now();
+        let disabled_until = *self.disabled_until.lock().unwrap();
+        if now < disabled_until {
+            sentry_debug!(
+                "Skipping event send because we're disabled due to rate limits for {}s",
+                ((disabled_until - now) / 1000.0).ceil()
+            );
+            report(receipt, DeliveryStatus::RateLimited);
+            return;
+        }
+
+        let mut body = Vec::new();
+        envelope.to_writer(&mut body).unwrap();
+        let request = match self.create_request(&body) {
+            Ok(request) => request,
+            Err(err) => {
+                sentry_debug!("Failed to create request: {:?}", err);
+                report(receipt, DeliveryStatus::Failed(format!("{:?}", err)));
+                return;
+            }
+        };
+
+        let disabled_until = self.disabled_until.clone();
+        spawn_local(async move {
+            match fetch(&request).await {
+                Ok(response) => {
+                    if response.status() == 429 {
+                        if let Some(retry_after) = response
+                            .headers()
+                            .get("Retry-After")
+                            .ok()
+                            .flatten()
+                            .as_deref()
+                            .and_then(parse_retry_after_ms)
+                        {
+                            *disabled_until.lock().unwrap() = retry_after;
+                        }
+                    }
+                    report(receipt, status_from_response(response.status()));
+                }
+                Err(err) => {
+                    sentry_debug!("Failed to send event: {:?}", err);
+                    report(receipt, DeliveryStatus::Failed(format!("{:?}", err)));
+                }
+            }
+        });
+    }
+}
+
+/// Calls the global `fetch` function of either a window or a web worker.
+#[cfg(feature = "fetch")]
+async fn fetch(request: &FetchRequest) -> Result<FetchResponse, JsValue> {
+    let global = js_sys::global();
+    let promise = if let Some(window) = global.dyn_ref::<web_sys::Window>() {
+        window.fetch_with_request(request)
+    } else if let Some(worker) = global.dyn_ref::<web_sys::WorkerGlobalScope>() {
+        worker.fetch_with_request(request)
+    } else {
+        return Err(JsValue::from_str("no global `fetch` function"));
+    };
+    JsFuture::from(promise).await?.dyn_into()
+}
+
+/// Parses a `Retry-After` header into milliseconds since the epoch.
+#[cfg(feature = "fetch")]
+fn parse_retry_after_ms(s: &str) -> Option<f64> {
+    if let Ok(value) = s.parse::<f64>() {
+        Some(js_sys::Date::now() + value.ceil() * 1000.0)
+    } else {
+        Some(js_sys::Date::parse(s)).filter(|value| !value.is_nan())
+    }
+}
+
+/// An HTTP request sent by the [`SyncHttpTransport`].
+#[cfg(feature = "sync-transport")]
+#[derive(Clone, Debug)]
+pub struct SyncHttpRequest {
+    /// The URL of the envelope endpoint.
+    pub url: String,
+    /// The headers of the request, including the `X-Sentry-Auth` header.
+    pub headers: Vec<(String, String)>,
+    /// The serialized envelope.
+    pub body: Vec<u8>,
+}
+
+/// The response to a [`SyncHttpRequest`].
+#[cfg(feature = "sync-transport")]
+#[derive(Clone, Debug, Default)]
+pub struct SyncHttpResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The value of the `Retry-After` header, if present.
+    pub retry_after: Option<String>,
+}
+
+/// An HTTP client that sends requests synchronously.
+///
+/// This is implemented for all closures taking a [`SyncHttpRequest`].
+#[cfg(feature = "sync-transport")]
+pub trait SyncHttpClient: Send + Sync + 'static {
+    /// Sends the request and waits for its response.
+    fn send(
+        &self,
+        request: SyncHttpRequest,
+    ) -> Result<SyncHttpResponse, Box<dyn std::error::Error>>;
+}
+
+#[cfg(feature = "sync-transport")]
+impl<F> SyncHttpClient for F
+where
+    F: Fn(SyncHttpRequest) -> Result<SyncHttpResponse, Box<dyn std::error::Error>>
+        + Send
+        + Sync
+        + 'static,
+{
+    fn send(
+        &self,
+        request: SyncHttpRequest,
+    ) -> Result<SyncHttpResponse, Box<dyn std::error::Error>> {
+        (*self)(request)
+    }
+}
+
+/// A transport that sends events synchronously via a pluggable HTTP client.
+///
+/// This is enabled by the `sync-transport` flag.  Unlike the other transports,
+/// it does not spawn a background thread, and sends every envelope on the
+/// thread that captured it.  This makes it usable on targets without thread
+/// support such as `wasm32-wasi`, where the HTTP client can be implemented on
+/// top of the sockets or outbound HTTP API of the host.
+///
+/// ```
+/// use std::error::Error;
+/// use std::sync::Arc;
+///
+/// use sentry::transports::{SyncHttpRequest, SyncHttpResponse, SyncHttpTransport};
+///
+/// let options = sentry::ClientOptions {
+///     dsn: "https://public@example.com/1".parse().ok(),
+///     transport: Some(Arc::new(|options: &sentry::ClientOptions| {
+///         Arc::new(SyncHttpTransport::new(options, send)) as Arc<dyn sentry::Transport>
+///     })),
+///     ..Default::default()
+/// };
+///
+/// fn send(request: SyncHttpRequest) -> Result<SyncHttpResponse, Box<dyn Error>> {
+///     // send `request.body` to `request.url` using the host API
+///     Ok(SyncHttpResponse {
+///         status: 200,
+///         ..Default::default()
+///     })
+/// }
+/// ```
+#[cfg(feature = "sync-transport")]
+pub struct SyncHttpTransport {
+    client: Box<dyn SyncHttpClient>,
+    url: String,
+    auth: String,
+    disabled: Mutex<Option<SystemTime>>,
+}
+
+#[cfg(feature = "sync-transport")]
+impl SyncHttpTransport {
+    /// Creates a new transport that sends envelopes using the given client.
+    pub fn new<C: SyncHttpClient>(options: &ClientOptions, client: C) -> Self {
+        let dsn = options.dsn.clone().unwrap();
+        let user_agent = options.user_agent.to_string();
+        SyncHttpTransport {
+            client: Box::new(client),
+            url: dsn.envelope_api_url().to_string(),
+            auth: dsn.to_auth(Some(&user_agent)).to_string(),
+            disabled: Mutex::new(None),
+        }
+    }
+}
+
+#[cfg(feature = "sync-transport")]
+impl Transport for SyncHttpTransport {
+    fn send_envelope(&self, envelope: Envelope) {
+        self.send(envelope);
+    }
+
+    fn send_envelope_with_receipt(&self, envelope: Envelope, receipt: DeliveryReceipt) {
+        receipt.report(self.send(envelope));
+    }
+
+    fn is_healthy(&self) -> bool {
+        match *self.disabled.lock().unwrap() {
+            Some(ts) => ts <= SystemTime::now(),
+            None => true,
+        }
+    }
+}
+
+#[cfg(feature = "sync-transport")]
+impl SyncHttpTransport {
+    fn send(&self, envelope: Envelope) -> DeliveryStatus {
+        let mut disabled = self.disabled.lock().unwrap();
+
+        // while we are disabled due to rate limits, skip
+        if let Some(ts) = *disabled {
+            if let Ok(time_left) = ts.duration_since(SystemTime::now()) {
+                sentry_debug!(
+                    "Skipping event send because we're disabled due to rate limits for {}s",
+                    time_left.as_secs()
+                );
+                return DeliveryStatus::RateLimited;
+            } else {
+                *disabled = None;
+            }
+        }
+
+        let mut body = Vec::new();
+        envelope.to_writer(&mut body).unwrap();
+        let request = SyncHttpRequest {
+            url: self.url.clone(),
+            headers: vec![
+                ("X-Sentry-Auth".into(), self.auth.clone()),
+                ("Content-Type".into(), "application/json".into()),
+            ],
+            body,
+        };
+
+        match self.client.send(request) {
+            Ok(response) => {
+                if response.status == 429 {
+                    if let Some(retry_after) =
+                        response.retry_after.as_deref().and_then(parse_retry_after)
+                    {
+                        *disabled = Some(retry_after);
+                    }
+                }
+                status_from_response(response.status)
+            }
+            Err(err) => {
+                sentry_debug!("Failed to send event: {}", err);
+                DeliveryStatus::Failed(err.to_string())
+            }
+        }
+    }
+}
@@ -3,3 +3,14 @@
 async fn test_nested_async_runtimes() {
     let _guard = sentry::init("https://public@example.com/42");
 }
+
+#[cfg(feature = "test")]
+#[tokio::test]
+async fn test_await_delivery() {
+    let mut handle = None;
+    let events = sentry::test::with_captured_events(|| {
+        handle = Some(sentry::Hub::current().capture_event_with_receipt(Default::default()));
+    });
+    assert_eq!(events.len(), 1);
+    assert_eq!(handle.unwrap().await, sentry::DeliveryStatus::Sent);
+}
@@ -82,6 +82,33 @@ fn test_breadcrumbs() {
     );
 }
 
+#[test]
+fn test_console_writer() {
+    use std::io::Write;
+
+    let mut output = Vec::new();
+    let events = sentry::test::with_captured_events(|| {
+        let mut writer = sentry::ConsoleWriter::new(&mut output, "stdout").max_length(10);
+        write!(writer, "first line\r\n\nsecond ").unwrap();
+        writeln!(writer, "line is longer").unwrap();
+        write!(writer, "partial").unwrap();
+        drop(writer);
+        sentry::capture_message("Hello World!", sentry::Level::Warning);
+    });
+    assert_eq!(output, b"first line\r\n\nsecond line is longer\npartial");
+
+    assert_eq!(events.len(), 1);
+    let messages: Vec<_> = events[0]
+        .breadcrumbs
+        .iter()
+        .map(|x| x.message.as_deref().unwrap())
+        .collect();
+    assert_eq!(messages, vec!["first line", "second lin…", "partial"]);
+    let breadcrumb = &events[0].breadcrumbs[0];
+    assert_eq!(breadcrumb.category.as_deref(), Some("console"));
+    assert_eq!(breadcrumb.data["stream"], "stdout");
+}
+
 #[test]
 fn test_factory() {
     struct TestTransport(Arc<AtomicUsize>);
@@ -139,3 +166,332 @@ fn test_reentrant_configure_scope() {
     // well, the "outer" `configure_scope` wins
     assert_eq!(events[0].tags["which_scope"], "scope1");
 }
+
+#[test]
+fn test_dropped_event_id() {
+    let options = sentry::ClientOptions {
+        before_send: Some(Arc::new(|event| {
+            if event.level == sentry::Level::Debug {
+                None
+            } else {
+                Some(event)
+            }
+        })),
+        ..Default::default()
+    };
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            let kept = sentry::capture_message("kept", sentry::Level::Info);
+            assert!(!kept.is_nil());
+            assert_eq!(sentry::last_event_id(), Some(kept));
+
+            let dropped = sentry::capture_message("dropped", sentry::Level::Debug);
+            assert!(dropped.is_nil());
+            assert_eq!(sentry::last_event_id(), Some(kept));
+        },
+        options,
+    );
+
+    assert_eq!(events.len(), 1);
+}
+
+#[test]
+fn test_try_init_errors() {
+    let err = sentry::try_init("not a dsn").err().unwrap();
+    assert!(matches!(err, sentry::InitError::InvalidDsn(_)));
+
+    let err = sentry::try_init(sentry::ClientOptions {
+        dsn: "https://public@example.com/1".parse().ok(),
+        http_proxy: Some("::not a proxy::".into()),
+        default_integrations: false,
+        ..Default::default()
+    })
+    .err()
+    .unwrap();
+    assert!(matches!(
+        err,
+        sentry::InitError::InvalidProxy {
+            option: "http_proxy",
+            ..
+        }
+    ));
+
+    let err = sentry::try_init(sentry::ClientOptions {
+        dsn: "https://public@example.com/1".parse().ok(),
+        transport: Some(Arc::new(
+            |_: &sentry::ClientOptions| -> Arc<dyn sentry::Transport> {
+                panic!("no transport for you")
+            },
+        )),
+        default_integrations: false,
+        ..Default::default()
+    })
+    .err()
+    .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "failed to create transport: no transport for you"
+    );
+}
+
+#[test]
+fn test_try_init_ok() {
+    let transport = sentry::test::TestTransport::new();
+    let guard = sentry::try_init(sentry::ClientOptions {
+        dsn: "https://public@example.com/1".parse().ok(),
+        transport: Some(Arc::new(transport.clone())),
+        default_integrations: false,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(guard.is_enabled());
+
+    sentry::capture_message("Hello World!", sentry::Level::Info);
+    drop(guard);
+    assert_eq!(transport.fetch_and_clear_envelopes().len(), 1);
+}
+
+#[test]
+fn test_try_init_dsn_override() {
+    let default = sentry::test::TestTransport::new();
+    let tenant = sentry::test::TestTransport::new();
+    let factory = {
+        let default = default.clone();
+        let tenant = tenant.clone();
+        move |options: &sentry::ClientOptions| -> Arc<dyn sentry::Transport> {
+            match options.dsn.as_ref().map(|dsn| dsn.project_id().value()) {
+                Some(2) => tenant.clone(),
+                _ => default.clone(),
+            }
+        }
+    };
+    let guard = sentry::try_init(sentry::ClientOptions {
+        dsn: "https://public@example.com/1".parse().ok(),
+        transport: Some(Arc::new(factory)),
+        default_integrations: false,
+        ..Default::default()
+    })
+    .unwrap();
+
+    sentry::capture_message("default", sentry::Level::Info);
+    sentry::with_scope(
+        |scope| scope.set_dsn(Some("https://public@example.com/2".parse().unwrap())),
+        || sentry::capture_message("tenant", sentry::Level::Info),
+    );
+    drop(guard);
+
+    let events = default.fetch_and_clear_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message.as_deref(), Some("default"));
+
+    let events = tenant.fetch_and_clear_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message.as_deref(), Some("tenant"));
+}
+
+#[test]
+fn test_crashed_on_previous_run() {
+    let dir = std::env::temp_dir().join(format!("sentry-test-marker-{}", std::process::id()));
+    let marker = dir.join(format!("sentry-running-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // left behind by an earlier process with the same ID
+    std::fs::write(&marker, "").unwrap();
+
+    let transport = sentry::test::TestTransport::new();
+    let guard = sentry::init(sentry::ClientOptions {
+        dsn: "https://public@example.com/1".parse().ok(),
+        transport: Some(Arc::new(transport.clone())),
+        default_integrations: false,
+        cache_dir: Some(dir.clone()),
+        ..Default::default()
+    });
+    assert!(marker.exists());
+    drop(guard);
+    assert!(!marker.exists());
+
+    let events = transport.fetch_and_clear_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].level, sentry::Level::Fatal);
+    assert_eq!(events[0].tags["app.crashed_on_previous_run"], "true");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_running_marker_of_other_process() {
+    let dir = std::env::temp_dir().join(format!("sentry-test-other-{}", std::process::id()));
+    // the init process is always running
+    let marker = dir.join("sentry-running-1");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&marker, "").unwrap();
+
+    let transport = sentry::test::TestTransport::new();
+    let guard = sentry::init(sentry::ClientOptions {
+        dsn: "https://public@example.com/1".parse().ok(),
+        transport: Some(Arc::new(transport.clone())),
+        default_integrations: false,
+        cache_dir: Some(dir.clone()),
+        ..Default::default()
+    });
+    drop(guard);
+    assert!(marker.exists());
+    assert!(transport.fetch_and_clear_events().is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_resend_persisted_envelopes() {
+    let dir = std::env::temp_dir().join(format!("sentry-test-envelopes-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("envelopes")).unwrap();
+
+    let event_id = Uuid::new_v4();
+    let envelope: sentry::Envelope = sentry::protocol::Event {
+        event_id,
+        ..Default::default()
+    }
+    .into();
+    let mut body = Vec::new();
+    envelope.to_writer(&mut body).unwrap();
+    std::fs::write(dir.join("envelopes").join("0-test.envelope"), &body).unwrap();
+
+    let transport = sentry::test::TestTransport::new();
+    let guard = sentry::init(sentry::ClientOptions {
+        dsn: "https://public@example.com/1".parse().ok(),
+        transport: Some(Arc::new(transport.clone())),
+        default_integrations: false,
+        cache_dir: Some(dir.clone()),
+        ..Default::default()
+    });
+    drop(guard);
+
+    let envelopes = transport.fetch_and_clear_envelopes();
+    assert_eq!(envelopes.len(), 1);
+    assert_eq!(envelopes[0].uuid(), Some(&event_id));
+    let mut resent = Vec::new();
+    envelopes[0].to_writer(&mut resent).unwrap();
+    assert_eq!(resent, body);
+    assert_eq!(std::fs::read_dir(dir.join("envelopes")).unwrap().count(), 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_assertion_macros() {
+    let events = sentry::test::with_captured_events(|| {
+        sentry::configure_scope(|scope| scope.set_tag("worker", "worker1"));
+        sentry::add_breadcrumb(sentry::Breadcrumb {
+            ty: "http".into(),
+            category: Some("request".into()),
+            message: Some("GET /users".into()),
+            level: sentry::Level::Warning,
+            ..Default::default()
+        });
+        let error = std::io::Error::new(std::io::ErrorKind::Other, "disk is full");
+        sentry::capture_error(&error);
+        sentry::capture_message("Hello World!", sentry::Level::Error);
+    });
+    assert_eq!(events.len(), 2);
+
+    sentry::assert_event!(
+        events[0],
+        level: Error,
+        exception: "Custom",
+        exception contains "disk",
+        tag "worker": "worker1",
+    );
+    sentry::assert_event!(&events[1], message: "Hello World!", message contains "World");
+    sentry::assert_breadcrumb!(
+        events[1].breadcrumbs[0],
+        ty: "http",
+        category: "request",
+        level: Warning,
+        message contains "/users"
+    );
+}
+
+#[test]
+#[should_panic(expected = "expected `message` to contain \"boom\"")]
+fn test_assertion_macros_failure() {
+    let events = sentry::test::with_captured_events(|| {
+        sentry::capture_message("Hello World!", sentry::Level::Error);
+    });
+    sentry::assert_event!(events[0], level: Error, message contains "boom");
+}
+
+#[test]
+fn test_isolated_scope() {
+    let events = sentry::test::with_captured_events(|| {
+        sentry::configure_scope(|scope| scope.set_tag("tool", "cli"));
+        for command in &["build", "deploy"] {
+            sentry::with_isolated_scope(|| {
+                sentry::configure_scope(|scope| scope.set_tag("command", command));
+                sentry::add_breadcrumb(sentry::Breadcrumb {
+                    message: Some(format!("running {}", command)),
+                    ..Default::default()
+                });
+                // a leaked scope guard does not escape the isolated scope either
+                std::mem::forget(sentry::Hub::current().push_scope());
+                sentry::capture_message(command, sentry::Level::Info);
+            });
+        }
+        sentry::capture_message("done", sentry::Level::Info);
+    });
+
+    assert_eq!(events.len(), 3);
+    sentry::assert_event!(events[0], message: "build", tag "command": "build");
+    assert_eq!(events[0].breadcrumbs.len(), 1);
+    sentry::assert_event!(events[1], message: "deploy", tag "command": "deploy");
+    assert_eq!(events[1].breadcrumbs.len(), 1);
+    sentry::assert_breadcrumb!(events[1].breadcrumbs[0], message: "running deploy");
+    sentry::assert_event!(events[2], message: "done", tag "tool": "cli");
+    assert!(!events[2].tags.contains_key("command"));
+    assert!(events[2].breadcrumbs.is_empty());
+}
+
+#[test]
+fn test_set_tags() {
+    let tags = sentry::tags! {};
+    assert!(tags.is_empty());
+
+    let events = sentry::test::with_captured_events(|| {
+        sentry::configure_scope(|scope| {
+            scope.set_tag("region", "us");
+            scope.set_tags(sentry::tags! {
+                "region" => "eu",
+                "shard" => 7,
+            });
+            scope.set_tags(vec![(String::from("ready"), true)]);
+        });
+        sentry::capture_message("tagged", sentry::Level::Info);
+    });
+
+    assert_eq!(events.len(), 1);
+    sentry::assert_event!(
+        events[0],
+        tag "region": "eu",
+        tag "shard": "7",
+        tag "ready": "true"
+    );
+}
+
+#[test]
+fn test_tag_scope() {
+    let events = sentry::test::with_captured_events(|| {
+        sentry::configure_scope(|scope| scope.set_tag("stage", "load"));
+        {
+            let _stage = sentry::tag_scope("stage", "transform");
+            let _job = sentry::tag_scope("job_id", 42);
+            sentry::capture_message("inside", sentry::Level::Info);
+        }
+        sentry::capture_message("outside", sentry::Level::Info);
+    });
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].tags["stage"], "transform");
+    assert_eq!(events[0].tags["job_id"], "42");
+    assert_eq!(events[1].tags["stage"], "load");
+    assert!(!events[1].tags.contains_key("job_id"));
+}
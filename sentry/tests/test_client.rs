@@ -1,7 +1,7 @@
 #![cfg(feature = "test")]
 
 use std::panic;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn test_into_client() {
@@ -56,3 +56,525 @@ fn test_unwind_safe() {
 
     assert_eq!(events.len(), 1);
 }
+
+#[test]
+fn test_fingerprint_fn() {
+    let options = sentry::ClientOptions {
+        fingerprint_fn: Some(Arc::new(|event| {
+            sentry::fingerprint::from_exception_types(event)
+        })),
+        ..Default::default()
+    };
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            let err = "NaN".parse::<usize>().unwrap_err();
+            sentry::capture_error(&err);
+            sentry::with_scope(
+                |scope| scope.set_fingerprint(Some(&["custom"])),
+                || sentry::capture_error(&err),
+            );
+            sentry::capture_message("no exceptions", sentry::Level::Info);
+        },
+        options,
+    );
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].fingerprint.as_ref(), ["ParseIntError"]);
+    assert_eq!(events[1].fingerprint.as_ref(), ["custom"]);
+    assert_eq!(events[2].fingerprint.as_ref(), ["{{ default }}"]);
+}
+
+#[test]
+fn test_capture_event_with_receipt() {
+    let options = sentry::ClientOptions {
+        before_send: Some(Arc::new(|event| {
+            if event.level == sentry::Level::Debug {
+                None
+            } else {
+                Some(event)
+            }
+        })),
+        ..Default::default()
+    };
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            let hub = sentry::Hub::current();
+            let sent = hub.capture_event_with_receipt(sentry::protocol::Event {
+                level: sentry::Level::Fatal,
+                ..Default::default()
+            });
+            assert!(!sent.event_id().is_nil());
+            assert_eq!(sent.status(), Some(sentry::DeliveryStatus::Sent));
+
+            let discarded = hub.capture_event_with_receipt(sentry::protocol::Event {
+                level: sentry::Level::Debug,
+                ..Default::default()
+            });
+            assert!(discarded.event_id().is_nil());
+            assert_eq!(discarded.status(), Some(sentry::DeliveryStatus::Discarded));
+        },
+        options,
+    );
+
+    assert_eq!(events.len(), 1);
+}
+
+#[test]
+fn test_dry_run() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let transport = sentry::test::TestTransport::new();
+    let before_send_called = Arc::new(AtomicBool::new(false));
+    let called = before_send_called.clone();
+    let client = sentry::Client::from(sentry::ClientOptions {
+        dry_run: true,
+        transport: Some(Arc::new(transport.clone())),
+        before_send: Some(Arc::new(move |event| {
+            called.store(true, Ordering::SeqCst);
+            Some(event)
+        })),
+        ..Default::default()
+    });
+    assert!(client.dsn().is_none());
+    assert!(client.is_enabled());
+
+    let event_id = client.capture_event(Default::default(), None);
+    assert!(!event_id.is_nil());
+    assert!(before_send_called.load(Ordering::SeqCst));
+    assert!(transport.fetch_and_clear_envelopes().is_empty());
+}
+
+#[test]
+fn test_dsn_override() {
+    let default = sentry::test::TestTransport::new();
+    let tenant = sentry::test::TestTransport::new();
+    let factory = {
+        let default = default.clone();
+        let tenant = tenant.clone();
+        move |options: &sentry::ClientOptions| -> Arc<dyn sentry::Transport> {
+            match options.dsn.as_ref().map(|dsn| dsn.project_id().value()) {
+                Some(2) => tenant.clone(),
+                _ => default.clone(),
+            }
+        }
+    };
+    let options = sentry::ClientOptions {
+        dsn: Some("https://public@example.com/1".parse().unwrap()),
+        transport: Some(Arc::new(factory)),
+        ..Default::default()
+    };
+
+    let hub = Arc::new(sentry::Hub::new(
+        Some(Arc::new(options.into())),
+        Default::default(),
+    ));
+    sentry::Hub::run(hub, || {
+        sentry::capture_message("default", sentry::Level::Info);
+        sentry::with_scope(
+            |scope| scope.set_dsn(Some("https://public@example.com/2".parse().unwrap())),
+            || sentry::capture_message("tenant", sentry::Level::Info),
+        );
+    });
+
+    let events = default.fetch_and_clear_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message.as_deref(), Some("default"));
+
+    let events = tenant.fetch_and_clear_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message.as_deref(), Some("tenant"));
+}
+
+#[test]
+fn test_screenshot_fn() {
+    let options = sentry::ClientOptions {
+        screenshot_fn: Some(Arc::new(|| Some(b"\x89PNG".to_vec()))),
+        ..Default::default()
+    };
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            sentry::capture_message("failed", sentry::Level::Error);
+            sentry::capture_message("no screenshot", sentry::Level::Info);
+        },
+        options,
+    );
+
+    assert_eq!(envelopes.len(), 2);
+    let attachments: Vec<_> = envelopes[0]
+        .items()
+        .filter_map(|item| match item {
+            sentry::protocol::EnvelopeItem::Attachment(attachment) => Some(attachment),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].filename, "screenshot.png");
+    assert_eq!(attachments[0].content_type.as_deref(), Some("image/png"));
+    assert_eq!(attachments[0].buffer, b"\x89PNG");
+    assert_eq!(envelopes[1].items().count(), 1);
+}
+
+#[test]
+fn test_log_file_tail() {
+    let path = std::env::temp_dir().join(format!("sentry-log-tail-{}.log", std::process::id()));
+    std::fs::write(&path, "first line\nsecond line\nthird line\n").unwrap();
+    let options = sentry::ClientOptions {
+        log_file: Some(path.clone()),
+        log_file_tail_bytes: 20,
+        ..Default::default()
+    };
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            sentry::capture_message("crashed", sentry::Level::Fatal);
+            sentry::capture_message("no log file", sentry::Level::Error);
+        },
+        options,
+    );
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(envelopes.len(), 2);
+    let attachment = envelopes[0]
+        .items()
+        .find_map(|item| match item {
+            sentry::protocol::EnvelopeItem::Attachment(attachment) => Some(attachment),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(
+        attachment.filename,
+        path.file_name().unwrap().to_str().unwrap()
+    );
+    assert_eq!(attachment.buffer, b"third line\n");
+    assert_eq!(envelopes[1].items().count(), 1);
+}
+
+#[test]
+fn test_clock() {
+    use sentry::types::{TimeZone, Utc};
+
+    let time = Utc.ymd(2021, 3, 14).and_hms(15, 9, 26);
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::add_breadcrumb(sentry::Breadcrumb::default());
+            sentry::capture_message("Hello World!", sentry::Level::Info);
+        },
+        sentry::ClientOptions {
+            clock: Some(Arc::new(move || time)),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].timestamp, time);
+    assert_eq!(events[0].breadcrumbs[0].timestamp, time);
+}
+
+#[test]
+fn test_clock_monotonic_fallback() {
+    use sentry::types::{DateTime, Utc};
+    use std::time::{Duration, SystemTime};
+
+    // a breadcrumb recorded before the system clock jumped back by an hour
+    let before_jump: DateTime<Utc> = (SystemTime::now() + Duration::from_secs(3600)).into();
+    let events = sentry::test::with_captured_events(|| {
+        sentry::add_breadcrumb(sentry::Breadcrumb {
+            timestamp: before_jump,
+            ..Default::default()
+        });
+        sentry::add_breadcrumb(sentry::Breadcrumb::default());
+        sentry::capture_message("Hello World!", sentry::Level::Info);
+    });
+
+    assert_eq!(events.len(), 1);
+    let breadcrumbs = &events[0].breadcrumbs;
+    assert_eq!(breadcrumbs[0].timestamp, before_jump);
+    assert!(breadcrumbs[1].timestamp >= before_jump);
+    assert!(events[0].timestamp >= breadcrumbs[1].timestamp);
+}
+
+#[test]
+fn test_explicit_timestamps() {
+    use sentry::types::{TimeZone, Utc};
+
+    let clock = Utc.ymd(2021, 3, 14).and_hms(15, 9, 26);
+    let explicit = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let capture = || {
+        sentry::add_breadcrumb(sentry::Breadcrumb::default());
+        sentry::add_breadcrumb(sentry::Breadcrumb {
+            timestamp: explicit,
+            ..Default::default()
+        });
+        sentry::capture_event(sentry::protocol::Event {
+            timestamp: explicit,
+            ..Default::default()
+        });
+    };
+
+    let events = sentry::test::with_captured_events(capture);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].timestamp, explicit);
+    assert!(events[0].breadcrumbs[0].timestamp > explicit);
+    assert_eq!(events[0].breadcrumbs[1].timestamp, explicit);
+
+    let events = sentry::test::with_captured_events_options(
+        capture,
+        sentry::ClientOptions {
+            clock: Some(Arc::new(move || clock)),
+            ..Default::default()
+        },
+    );
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].timestamp, explicit);
+    assert_eq!(events[0].breadcrumbs[0].timestamp, clock);
+    assert_eq!(events[0].breadcrumbs[1].timestamp, explicit);
+}
+
+#[test]
+fn test_value_limits() {
+    use sentry::protocol::Value;
+
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::add_breadcrumb(sentry::Breadcrumb {
+                data: {
+                    let mut data = sentry::protocol::Map::new();
+                    data.insert("query".into(), "SELECT 1234567890".into());
+                    data
+                },
+                ..Default::default()
+            });
+            sentry::configure_scope(|scope| {
+                // {"a": {"b": {"c": 1}}}
+                let nested = vec![("c", 1)].into_iter().collect::<Value>();
+                let nested = vec![("b", nested)].into_iter().collect::<Value>();
+                let nested = vec![("a", nested)].into_iter().collect::<Value>();
+                scope.set_extra("nested", nested);
+            });
+            sentry::capture_message("Hello World!", sentry::Level::Info);
+        },
+        sentry::ClientOptions {
+            max_value_depth: 2,
+            max_value_length: 8,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].extra["nested"]["a"]["b"], "[Object]");
+    assert_eq!(events[0].breadcrumbs[0].data["query"], "SELECT 1…");
+}
+
+#[test]
+fn test_large_values_as_attachments() {
+    let options = sentry::ClientOptions {
+        max_inline_value_size: Some(16),
+        ..Default::default()
+    };
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            sentry::configure_scope(|scope| {
+                scope.set_extra("small", "fits".into());
+                scope.set_extra("sql", "SELECT * FROM users WHERE id = 1".into());
+            });
+            sentry::capture_message("a message that is too long", sentry::Level::Error);
+        },
+        options,
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    let event = envelopes[0].event().unwrap();
+    assert_eq!(event.extra["small"], "fits");
+    assert_eq!(event.extra["sql"], "[attachment: extra.sql.json]");
+    assert_eq!(
+        event.message.as_deref(),
+        Some("a message that i… [attachment: message.txt]")
+    );
+
+    let attachments: Vec<_> = envelopes[0]
+        .items()
+        .filter_map(|item| match item {
+            sentry::protocol::EnvelopeItem::Attachment(attachment) => Some(attachment),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(attachments.len(), 2);
+    assert_eq!(attachments[0].filename, "extra.sql.json");
+    assert_eq!(
+        attachments[0].content_type.as_deref(),
+        Some("application/json")
+    );
+    assert_eq!(
+        attachments[0].buffer,
+        &b"\"SELECT * FROM users WHERE id = 1\""[..]
+    );
+    assert_eq!(attachments[1].filename, "message.txt");
+    assert_eq!(attachments[1].buffer, b"a message that is too long");
+}
+
+#[test]
+fn test_on_transport_error() {
+    struct FailingTransport;
+
+    impl sentry::Transport for FailingTransport {
+        fn send_envelope(&self, _envelope: sentry::Envelope) {}
+
+        fn send_envelope_with_receipt(
+            &self,
+            _envelope: sentry::Envelope,
+            receipt: sentry::DeliveryReceipt,
+        ) {
+            receipt.report(sentry::DeliveryStatus::Failed("connection refused".into()));
+        }
+    }
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let options = sentry::ClientOptions {
+        dsn: Some("https://public@example.com/1".parse().unwrap()),
+        transport: Some(Arc::new(|_: &sentry::ClientOptions| {
+            Arc::new(FailingTransport) as Arc<dyn sentry::Transport>
+        })),
+        on_transport_error: Some({
+            let errors = errors.clone();
+            Arc::new(move |error: &sentry::TransportError| {
+                errors.lock().unwrap().push(error.clone());
+            })
+        }),
+        ..Default::default()
+    };
+
+    let hub = Arc::new(sentry::Hub::new(
+        Some(Arc::new(options.into())),
+        Default::default(),
+    ));
+    let event_id = sentry::Hub::run(hub.clone(), || {
+        sentry::capture_message("lost", sentry::Level::Error)
+    });
+    let handle = hub.capture_event_with_receipt(Default::default());
+    assert_eq!(
+        handle.status(),
+        Some(sentry::DeliveryStatus::Failed("connection refused".into()))
+    );
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].event_id, Some(event_id));
+    assert_eq!(errors[0].message, "connection refused");
+    assert_eq!(
+        errors[0].to_string(),
+        format!("failed to send event {}: connection refused", event_id)
+    );
+    assert_eq!(errors[1].event_id, Some(handle.event_id()));
+}
+
+#[test]
+fn test_is_healthy() {
+    struct StoppedTransport;
+
+    impl sentry::Transport for StoppedTransport {
+        fn send_envelope(&self, _envelope: sentry::Envelope) {}
+
+        fn is_healthy(&self) -> bool {
+            false
+        }
+    }
+
+    let client = sentry::Client::from(sentry::ClientOptions::default());
+    assert!(!client.is_healthy());
+
+    let transport = sentry::test::TestTransport::new();
+    let client = Arc::new(sentry::Client::from(sentry::ClientOptions {
+        dsn: Some("https://public@example.com/1".parse().unwrap()),
+        transport: Some(Arc::new(transport)),
+        ..Default::default()
+    }));
+    assert!(client.is_healthy());
+    let hub = Arc::new(sentry::Hub::new(Some(client), Default::default()));
+    sentry::Hub::run(hub, || assert!(sentry::is_enabled()));
+
+    let client = sentry::Client::from(sentry::ClientOptions {
+        dsn: Some("https://public@example.com/1".parse().unwrap()),
+        transport: Some(Arc::new(|_: &sentry::ClientOptions| {
+            Arc::new(StoppedTransport) as Arc<dyn sentry::Transport>
+        })),
+        ..Default::default()
+    });
+    assert!(client.is_enabled());
+    assert!(!client.is_healthy());
+}
+
+#[test]
+fn test_queue_stats() {
+    struct BusyTransport;
+
+    impl sentry::Transport for BusyTransport {
+        fn send_envelope(&self, _envelope: sentry::Envelope) {}
+
+        fn queue_stats(&self) -> sentry::QueueStats {
+            sentry::QueueStats {
+                pending: 3,
+                in_flight: 1,
+                dropped: 2,
+            }
+        }
+    }
+
+    let client = sentry::Client::from(sentry::ClientOptions::default());
+    assert_eq!(client.pending_events(), 0);
+    assert_eq!(client.in_flight(), 0);
+    assert_eq!(client.dropped_events(), 0);
+
+    let client = sentry::Client::from(sentry::ClientOptions {
+        dsn: Some("https://public@example.com/1".parse().unwrap()),
+        transport: Some(Arc::new(|_: &sentry::ClientOptions| {
+            Arc::new(BusyTransport) as Arc<dyn sentry::Transport>
+        })),
+        ..Default::default()
+    });
+    assert_eq!(client.pending_events(), 3);
+    assert_eq!(client.in_flight(), 1);
+    assert_eq!(client.dropped_events(), 2);
+}
+
+#[test]
+fn test_capture_events() {
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            sentry::configure_scope(|scope| scope.set_tag("job", "import"));
+            let events = (1..=3).map(|row| sentry::protocol::Event {
+                message: Some(format!("row {}: invalid date", row)),
+                level: sentry::Level::Warning,
+                ..Default::default()
+            });
+            let event_ids = sentry::Hub::current().capture_events(events);
+            assert_eq!(event_ids.len(), 3);
+            assert!(event_ids[..2].iter().all(|id| !id.is_nil()));
+            assert!(event_ids[2].is_nil());
+            assert_eq!(sentry::last_event_id(), Some(event_ids[1]));
+        },
+        sentry::ClientOptions {
+            before_send: Some(Arc::new(|event| {
+                if event.message.as_deref() == Some("row 3: invalid date") {
+                    None
+                } else {
+                    Some(event)
+                }
+            })),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(envelopes.len(), 2);
+    for (row, envelope) in envelopes.iter().enumerate() {
+        let event = envelope.event().unwrap();
+        assert_eq!(
+            event.message.as_deref(),
+            Some(format!("row {}: invalid date", row + 1).as_str())
+        );
+        assert_eq!(event.tags["job"], "import");
+    }
+}
@@ -17,11 +17,14 @@ fn test_log() {
         });
 
         log::info!("Hello World!");
+        log::info!(target: sentry::INTERNAL_LOG_TARGET, "internal diagnostic");
+        log::error!(target: sentry::INTERNAL_LOG_TARGET, "internal error");
         log::error!("Shit's on fire yo");
     });
 
     assert_eq!(events.len(), 1);
     let event = events.into_iter().next().unwrap();
+    assert_eq!(event.breadcrumbs.len(), 1);
 
     assert_eq!(event.tags["worker"], "worker1");
     assert_eq!(event.level, sentry::Level::Error);
@@ -0,0 +1,82 @@
+#![cfg(all(feature = "test", feature = "macros"))]
+
+use sentry::protocol::{Context, EnvelopeItem};
+use sentry::ClientOptions;
+
+#[sentry::traced]
+fn inner(#[trace] id: u32, _payload: &str) -> u32 {
+    id * 2
+}
+
+#[sentry::traced(op = "task")]
+fn outer(#[trace] name: &str) -> Result<u32, std::num::ParseIntError> {
+    let value: u32 = name.parse()?;
+    Ok(inner(value, "ignored"))
+}
+
+#[test]
+fn test_traced() {
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            assert_eq!(outer("21"), Ok(42));
+        },
+        ClientOptions {
+            traces_sample_rate: 1.0,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(envelopes.len(), 1);
+    let transaction = match envelopes[0].items().next() {
+        Some(EnvelopeItem::Transaction(transaction)) => transaction,
+        item => panic!("expected transaction, got {:?}", item),
+    };
+    assert_eq!(transaction.name.as_deref(), Some("test_macros::outer"));
+    match transaction.contexts.get("trace") {
+        Some(Context::Trace(trace)) => {
+            assert_eq!(trace.op.as_deref(), Some("task"));
+            assert_eq!(trace.data["name"], "\"21\"");
+        }
+        context => panic!("expected trace context, got {:?}", context),
+    }
+
+    assert_eq!(transaction.spans.len(), 1);
+    let span = &transaction.spans[0];
+    assert_eq!(span.op.as_deref(), Some("function"));
+    assert_eq!(span.description.as_deref(), Some("test_macros::inner"));
+    assert_eq!(span.data["id"], "21");
+    assert!(!span.data.contains_key("_payload"));
+}
+
+#[sentry::capture_err]
+fn parse(value: &str) -> Result<u32, std::num::ParseIntError> {
+    let parsed = value.parse()?;
+    Ok(parsed)
+}
+
+#[sentry::capture_err(level = "warning", tags(component = "config", attempt = 1))]
+fn parse_config(value: &str) -> Result<u32, std::num::ParseIntError> {
+    if value.is_empty() {
+        return Ok(0);
+    }
+    value.parse()
+}
+
+#[test]
+fn test_capture_err() {
+    let events = sentry::test::with_captured_events(|| {
+        assert_eq!(parse("1"), Ok(1));
+        assert!(parse("x").is_err());
+        assert_eq!(parse_config(""), Ok(0));
+        assert!(parse_config("y").is_err());
+    });
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].level, sentry::Level::Error);
+    assert_eq!(events[0].exception[0].ty, "ParseIntError");
+    assert!(events[0].tags.is_empty());
+
+    assert_eq!(events[1].level, sentry::Level::Warning);
+    assert_eq!(events[1].tags["component"], "config");
+    assert_eq!(events[1].tags["attempt"], "1");
+}
@@ -0,0 +1,13 @@
+#![cfg(feature = "test")]
+
+// This lives in its own test binary, as no client must ever be bound in the
+// process for the fast path to apply.
+
+#[test]
+fn test_noop_without_client() {
+    sentry::add_breadcrumb(|| -> sentry::Breadcrumb { panic!("breadcrumb was created") });
+    sentry::configure_scope(|_| -> () { panic!("scope was configured") });
+    sentry::with_scope(|_| -> () { panic!("scope was configured") }, || {});
+    assert!(sentry::capture_message("message", sentry::Level::Info).is_nil());
+    assert_eq!(sentry::Hub::with_active(|_| 42), 0);
+}
@@ -0,0 +1,283 @@
+#![cfg(feature = "test")]
+
+use std::sync::Arc;
+
+use sentry::protocol::{Context, EnvelopeItem, Transaction};
+use sentry::types::SentryTrace;
+use sentry::{ClientOptions, Envelope, TransactionContext};
+
+fn options() -> ClientOptions {
+    ClientOptions {
+        traces_sample_rate: 1.0,
+        ..Default::default()
+    }
+}
+
+fn transactions(envelopes: Vec<Envelope>) -> Vec<Transaction<'static>> {
+    envelopes
+        .iter()
+        .flat_map(|envelope| envelope.items())
+        .filter_map(|item| match item {
+            EnvelopeItem::Transaction(transaction) => Some(transaction.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_transaction_with_spans() {
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let transaction = sentry::start_transaction(TransactionContext::new("job", "task"));
+            transaction.set_data("attempt", 2.into());
+            let span = transaction.start_child("db.query", "SELECT 1");
+            span.start_child("db.fetch", "rows").finish();
+            span.finish();
+            transaction.finish();
+        },
+        options(),
+    );
+
+    let transactions = transactions(envelopes);
+    assert_eq!(transactions.len(), 1);
+    let transaction = &transactions[0];
+    assert_eq!(transaction.name.as_deref(), Some("job"));
+    assert!(transaction.timestamp.is_some());
+    assert!(transaction.sdk.is_some());
+
+    let trace = match transaction.contexts.get("trace") {
+        Some(Context::Trace(trace)) => trace,
+        context => panic!("expected trace context, got {:?}", context),
+    };
+    assert_eq!(trace.op.as_deref(), Some("task"));
+    assert_eq!(trace.data["attempt"], 2);
+
+    assert_eq!(transaction.spans.len(), 2);
+    let (child, span) = (&transaction.spans[0], &transaction.spans[1]);
+    assert_eq!(span.op.as_deref(), Some("db.query"));
+    assert_eq!(span.trace_id, trace.trace_id);
+    assert_eq!(
+        span.parent_span_id,
+        Some(trace.span_id.to_string())
+    );
+    assert_eq!(
+        child.parent_span_id,
+        Some(span.span_id.to_string())
+    );
+}
+
+#[test]
+fn test_unsampled_transaction() {
+    let envelopes = sentry::test::with_captured_envelopes(|| {
+        let transaction = sentry::start_transaction(TransactionContext::new("job", "task"));
+        assert!(!transaction.is_sampled());
+        transaction.start_child("db.query", "SELECT 1").finish();
+        transaction.finish();
+    });
+    assert!(transactions(envelopes).is_empty());
+}
+
+#[test]
+fn test_continue_trace() {
+    let upstream: SentryTrace =
+        "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a-1"
+            .parse()
+            .unwrap();
+    let envelopes = sentry::test::with_captured_envelopes(|| {
+        let ctx = TransactionContext::continue_from_sentry_trace("request", "http", &upstream);
+        let transaction = sentry::start_transaction(ctx);
+        assert!(transaction.is_sampled());
+        assert_eq!(transaction.sentry_trace().trace_id, upstream.trace_id);
+        transaction.finish();
+    });
+
+    let transactions = transactions(envelopes);
+    match transactions[0].contexts.get("trace") {
+        Some(Context::Trace(trace)) => {
+            assert_eq!(trace.trace_id, upstream.trace_id);
+            assert_eq!(
+                trace.parent_span_id,
+                Some("8c1f9f3e1d2b4c5a".into())
+            );
+        }
+        context => panic!("expected trace context, got {:?}", context),
+    }
+}
+
+#[test]
+fn test_traces_sampler() {
+    let sampled: SentryTrace =
+        "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a-1"
+            .parse()
+            .unwrap();
+    let unsampled: SentryTrace =
+        "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a-0"
+            .parse()
+            .unwrap();
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let ctx = TransactionContext::continue_from_sentry_trace("parent", "http", &sampled);
+            assert_eq!(ctx.parent_sampled(), Some(true));
+            sentry::start_transaction(ctx).finish();
+
+            let ctx = TransactionContext::continue_from_sentry_trace("parent", "http", &unsampled);
+            assert!(!sentry::start_transaction(ctx).is_sampled());
+
+            let ctx = TransactionContext::continue_from_sentry_trace("health", "http", &sampled);
+            assert!(!sentry::start_transaction(ctx).is_sampled());
+
+            let mut ctx = TransactionContext::new("health", "http");
+            ctx.set_sampled(Some(true));
+            sentry::start_transaction(ctx).finish();
+
+            assert!(
+                !sentry::start_transaction(TransactionContext::new("root", "http")).is_sampled()
+            );
+        },
+        ClientOptions {
+            traces_sampler: Some(Arc::new(|ctx: &TransactionContext| {
+                if ctx.name() == "health" {
+                    0.0
+                } else {
+                    match ctx.parent_sampled() {
+                        Some(true) => 1.0,
+                        _ => 0.0,
+                    }
+                }
+            })),
+            ..Default::default()
+        },
+    );
+
+    let transactions = transactions(envelopes);
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[0].name.as_deref(), Some("parent"));
+    assert_eq!(transactions[1].name.as_deref(), Some("health"));
+}
+
+#[test]
+fn test_inherit_parent_sampled() {
+    let unsampled: SentryTrace =
+        "09e04486820349518ac7b5d2adbf6ba5-8c1f9f3e1d2b4c5a-0"
+            .parse()
+            .unwrap();
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let ctx = TransactionContext::continue_from_sentry_trace("request", "http", &unsampled);
+            let transaction = sentry::start_transaction(ctx);
+            assert!(!transaction.is_sampled());
+            transaction.finish();
+        },
+        options(),
+    );
+    assert!(transactions(envelopes).is_empty());
+}
+
+#[test]
+fn test_entered_span_on_events() {
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let guard = sentry::start_child_or_transaction("task", "job").enter();
+            let span_id = guard.span().get_trace_context().span_id;
+            {
+                let _child = sentry::start_child_or_transaction("db.query", "SELECT 1").enter();
+                sentry::capture_message("inside", sentry::Level::Info);
+            }
+            sentry::configure_scope(|scope| {
+                assert_eq!(
+                    scope.get_span().unwrap().get_trace_context().span_id,
+                    span_id
+                );
+            });
+            drop(guard);
+            sentry::configure_scope(|scope| assert!(scope.get_span().is_none()));
+        },
+        options(),
+    );
+
+    let event = envelopes
+        .iter()
+        .find_map(|envelope| envelope.event().cloned())
+        .unwrap();
+    let transactions = transactions(envelopes);
+    assert_eq!(transactions.len(), 1);
+    let span = &transactions[0].spans[0];
+    match event.contexts.get("trace") {
+        Some(Context::Trace(trace)) => assert_eq!(trace.span_id, span.span_id),
+        context => panic!("expected trace context, got {:?}", context),
+    }
+    assert_eq!(event.transaction.as_deref(), Some("job"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_process_command() {
+    use sentry::process::Command;
+
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let transaction = sentry::start_transaction(TransactionContext::new("job", "cli"));
+            sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+            let output = Command::new("sh")
+                .args(&["-c", "printf %s \"$SENTRY_TRACE\"; exit 3"])
+                .output()
+                .unwrap();
+            assert_eq!(output.status.code(), Some(3));
+            assert_eq!(
+                String::from_utf8(output.stdout).unwrap(),
+                transaction.sentry_trace().to_string()
+            );
+            sentry::capture_message("Hello World!", sentry::Level::Warning);
+            transaction.finish();
+        },
+        options(),
+    );
+
+    let event = envelopes
+        .iter()
+        .find_map(|envelope| envelope.event().cloned())
+        .unwrap();
+    let breadcrumb = &event.breadcrumbs[0];
+    assert_eq!(breadcrumb.category.as_deref(), Some("process"));
+    assert_eq!(
+        breadcrumb.message.as_deref(),
+        Some("sh -c printf %s \"$SENTRY_TRACE\"; exit 3")
+    );
+    assert_eq!(breadcrumb.level, sentry::Level::Warning);
+    assert_eq!(breadcrumb.data["program"], "sh");
+    assert_eq!(breadcrumb.data["exit_code"], 3);
+}
+
+#[test]
+fn test_queue_spans() {
+    let envelopes = sentry::test::with_captured_envelopes_options(
+        || {
+            let span = sentry::spans::queue_publish("nats", "orders");
+            let headers = sentry::spans::trace_headers(&span);
+            assert_eq!(
+                headers["sentry-trace"],
+                span.sentry_trace().to_string().into_bytes()
+            );
+            span.finish();
+
+            let transaction = sentry::spans::queue_consume("nats", "orders", &headers);
+            transaction.finish();
+        },
+        options(),
+    );
+
+    let transactions = transactions(envelopes);
+    assert_eq!(transactions.len(), 2);
+    let traces: Vec<_> = transactions
+        .iter()
+        .map(|transaction| match transaction.contexts.get("trace") {
+            Some(Context::Trace(trace)) => (trace.op.clone().unwrap(), trace.trace_id),
+            context => panic!("expected trace context, got {:?}", context),
+        })
+        .collect();
+    assert_eq!(traces[0].0, "queue.publish");
+    assert_eq!(traces[1].0, "queue.process");
+    assert_eq!(traces[0].1, traces[1].1);
+    assert_eq!(transactions[1].name.as_deref(), Some("orders"));
+}
@@ -120,3 +120,51 @@ fn test_before_breadcrumb_callback_drop() {
     assert_eq!(event.message.as_ref().unwrap(), "Hello World!");
     assert_eq!(event.breadcrumbs.len(), 0);
 }
+
+#[test]
+fn test_breadcrumb_sampling_and_limits() {
+    let events = sentry::test::with_captured_events_options(
+        || {
+            sentry::add_breadcrumb(sentry::Breadcrumb {
+                category: Some("auth".into()),
+                ..Default::default()
+            });
+            for i in 0..10 {
+                sentry::add_breadcrumb(sentry::Breadcrumb {
+                    category: Some("db.query".into()),
+                    message: Some(i.to_string()),
+                    ..Default::default()
+                });
+                sentry::add_breadcrumb(sentry::Breadcrumb {
+                    category: Some("http".into()),
+                    message: Some(i.to_string()),
+                    ..Default::default()
+                });
+            }
+            sentry::capture_message("Hello World!", sentry::Level::Warning);
+        },
+        sentry::ClientOptions {
+            max_breadcrumbs: 6,
+            breadcrumb_sample_rates: vec![("db.query", 5)],
+            max_breadcrumbs_per_category: vec![("http", 2)],
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(events.len(), 1);
+    let crumbs: Vec<_> = events[0]
+        .breadcrumbs
+        .iter()
+        .map(|crumb| {
+            format!(
+                "{}:{}",
+                crumb.category.as_deref().unwrap(),
+                crumb.message.as_deref().unwrap_or("")
+            )
+        })
+        .collect();
+    assert_eq!(
+        crumbs,
+        vec!["auth:", "db.query:0", "db.query:5", "http:8", "http:9"]
+    );
+}
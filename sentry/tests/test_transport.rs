@@ -0,0 +1,52 @@
+#![cfg(feature = "sync-transport")]
+
+use std::sync::{Arc, Mutex};
+
+use sentry::transports::{SyncHttpRequest, SyncHttpResponse, SyncHttpTransport};
+
+#[test]
+fn test_sync_transport() {
+    let requests = Arc::new(Mutex::new(Vec::<SyncHttpRequest>::new()));
+    let options = sentry::ClientOptions {
+        dsn: Some("https://public@example.com/1".parse().unwrap()),
+        ..Default::default()
+    };
+
+    let recorded = requests.clone();
+    let transport = Arc::new(SyncHttpTransport::new(
+        &options,
+        move |request: SyncHttpRequest| {
+            recorded.lock().unwrap().push(request);
+            Ok::<_, Box<dyn std::error::Error>>(SyncHttpResponse {
+                status: 429,
+                retry_after: Some("60".into()),
+            })
+        },
+    ));
+    let options = sentry::ClientOptions {
+        transport: Some(Arc::new(move |_: &sentry::ClientOptions| {
+            transport.clone() as Arc<dyn sentry::Transport>
+        })),
+        ..options
+    };
+
+    sentry::Hub::run(
+        Arc::new(sentry::Hub::new(
+            Some(Arc::new(options.into())),
+            Arc::new(Default::default()),
+        )),
+        || {
+            sentry::capture_message("First", sentry::Level::Warning);
+            sentry::capture_message("Rate Limited", sentry::Level::Warning);
+        },
+    );
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].url, "https://example.com/api/1/envelope/");
+    assert!(requests[0]
+        .headers
+        .iter()
+        .any(|(name, value)| name == "X-Sentry-Auth" && value.contains("sentry_key=public")));
+    assert!(String::from_utf8_lossy(&requests[0].body).contains("First"));
+}